@@ -1,13 +1,13 @@
 use self::atoms::{AllFacts, AtomMaps, Origin, Output, SubPoint};
-use crate::context::{AnalysisCtxt, PermissionSet};
+use crate::context::{AnalysisCtxt, FlagSet, PermissionSet, PointerId};
 use crate::dataflow::DataflowConstraints;
 use crate::labeled_ty::{LabeledTy, LabeledTyCtxt};
-use crate::pointer_id::PointerTableMut;
+use crate::pointer_id::{PointerTable, PointerTableMut};
 use crate::util::{describe_rvalue, RvalueDesc};
 use crate::AdtMetadataTable;
 use indexmap::{IndexMap, IndexSet};
 use rustc_hir::def_id::DefId;
-use rustc_middle::mir::{Body, LocalKind, Place, StatementKind, START_BLOCK};
+use rustc_middle::mir::{Body, LocalKind, Location, Place, StatementKind, START_BLOCK};
 use rustc_middle::ty::{EarlyBoundRegion, List, Region, Ty, TyKind};
 use rustc_type_ir::RegionKind::ReEarlyBound;
 use std::collections::HashMap;
@@ -113,15 +113,31 @@ impl std::fmt::Debug for OriginParam {
     }
 }
 
+/// A borrowck error that caused `UNIQUE` to be removed from a pointer.
+#[derive(Clone, Copy, Debug)]
+pub struct BorrowConflict {
+    /// The pointer that lost `UNIQUE`.
+    pub ptr: PointerId,
+    /// The statement that issued the conflicting loan.
+    pub issued_at: Location,
+    /// The point where polonius reported the loan as conflicting with another access.
+    pub error_at: Location,
+}
+
+/// Run polonius on `mir`, removing `UNIQUE` from pointers whose loans cause borrowck errors until
+/// no more errors can be fixed this way.  Returns the errors that caused `UNIQUE` to be removed.
+#[allow(clippy::too_many_arguments)]
 pub fn borrowck_mir<'tcx>(
     acx: &AnalysisCtxt<'_, 'tcx>,
     dataflow: &DataflowConstraints,
     hypothesis: &mut PointerTableMut<PermissionSet>,
+    flags: &PointerTable<FlagSet>,
     name: &str,
     mir: &Body<'tcx>,
     adt_metadata: &AdtMetadataTable<'tcx>,
     field_tys: HashMap<DefId, crate::LTy<'tcx>>,
-) {
+) -> Vec<BorrowConflict> {
+    let mut conflicts = Vec::new();
     let mut i = 0;
     loop {
         eprintln!("run polonius");
@@ -143,7 +159,7 @@ pub fn borrowck_mir<'tcx>(
         }
 
         let mut changed = false;
-        for loans in output.errors.values() {
+        for (&error_point, loans) in output.errors.iter() {
             for &loan in loans {
                 let issued_point = facts
                     .loan_issued_at
@@ -175,12 +191,17 @@ pub fn borrowck_mir<'tcx>(
                 if hypothesis[ptr].contains(PermissionSet::UNIQUE) {
                     hypothesis[ptr].remove(PermissionSet::UNIQUE);
                     changed = true;
+                    conflicts.push(BorrowConflict {
+                        ptr,
+                        issued_at: issued_loc,
+                        error_at: maps.get_point_location(error_point),
+                    });
                 }
             }
         }
 
         eprintln!("propagate");
-        changed |= dataflow.propagate(hypothesis);
+        changed |= dataflow.propagate(hypothesis, flags);
         eprintln!("done propagating");
 
         if !changed {
@@ -193,6 +214,8 @@ pub fn borrowck_mir<'tcx>(
             break;
         }
     }
+
+    conflicts
 }
 
 fn run_polonius<'tcx>(
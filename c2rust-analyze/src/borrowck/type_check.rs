@@ -13,6 +13,7 @@ use rustc_middle::mir::{
     Place, Rvalue, Statement, StatementKind, Terminator, TerminatorKind,
 };
 use rustc_middle::ty::{AdtDef, FieldDef, TyCtxt, TyKind};
+use rustc_span::sym;
 use std::collections::HashMap;
 
 struct TypeChecker<'tcx, 'a> {
@@ -338,6 +339,17 @@ impl<'tcx> TypeChecker<'tcx, '_> {
                     }
                     expect_ty
                 }
+                AggregateKind::Adt(adt_did, ..)
+                    if self.tcx.is_diagnostic_item(sym::Option, adt_did) =>
+                {
+                    // `Some(x)` stores `x` as the only type argument of the `Option`.
+                    let inner_lty = assert_matches!(expect_ty.args, [lty] => lty);
+                    for op in ops {
+                        let op_lty = self.visit_operand(op);
+                        self.do_assign(inner_lty, op_lty);
+                    }
+                    expect_ty
+                }
                 AggregateKind::Adt(adt_did, ..) => {
                     /*
                         Generic types are not yet supported because of situations such as the
@@ -502,6 +514,23 @@ impl<'tcx> TypeChecker<'tcx, '_> {
                             self.visit_operand(p)
                         });
                     }
+                    Callee::Null => {
+                        let _pl_lty = self.visit_place(destination);
+                    }
+                    Callee::Strlen | Callee::Strcmp => {
+                        let _pl_lty = self.visit_place(destination);
+                        for op in args {
+                            let _rv_lty = self.visit_operand(op);
+                        }
+                    }
+                    Callee::Strchr => {
+                        // The result borrows from the string argument.
+                        let pl_lty = self.visit_place(destination);
+                        let rv_lty = assert_matches!(&args[..], [p, _] => {
+                            self.visit_operand(p)
+                        });
+                        self.do_assign(pl_lty, rv_lty);
+                    }
                 }
             }
             // TODO(spernsteiner): handle other `TerminatorKind`s
@@ -187,6 +187,11 @@ impl<'tcx> CVoidCastsUniDirectional<'tcx> {
             .unwrap_or(&place)
     }
 
+    /// Determine if the [`Statement`] at a [`Location`] is a [`CVoidCast`].
+    pub fn contains_cast(&self, loc: Location) -> bool {
+        self.casts.contains(&loc)
+    }
+
     /// Tracks the [Location] of the use of a casted pointer in a [TerminatorKind::Call]
     pub fn insert_call(&mut self, loc: Location, cast: CVoidCast<'tcx>) {
         assert!(!self.calls.contains_key(&loc));
@@ -7,7 +7,7 @@ use crate::pointer_id::{
 use crate::util::{self, describe_rvalue, PhantomLifetime, RvalueDesc};
 use crate::AssignPointerIds;
 use bitflags::bitflags;
-use rustc_hir::def_id::DefId;
+use rustc_hir::def_id::{DefId, LocalDefId};
 use rustc_index::vec::IndexVec;
 use rustc_middle::mir::interpret::{self, AllocId, ConstValue, GlobalAlloc};
 use rustc_middle::mir::{
@@ -15,7 +15,7 @@ use rustc_middle::mir::{
     Place, PlaceElem, PlaceRef, Rvalue,
 };
 use rustc_middle::ty::{AdtDef, FieldDef, Ty, TyCtxt, TyKind};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::ops::Index;
 
 bitflags! {
@@ -76,6 +76,9 @@ bitflags! {
         /// * [`core::ptr::null_mut`]
         /// * [`core::ptr::from_exposed_addr`] with the constant `0`
         /// * `0 as * {const,mut} _`, a cast from the constant `0` to a pointer type
+        /// * raw pointer function arguments, which callers outside the crate may pass as null
+        /// * the results of calls to functions we don't analyze, other than allocators
+        /// * pointers that are checked against null with `_.is_null()` or `==`/`!=`
         /// * anything else
         ///
         /// Non-zero but invalid pointers, such as those produced by:
@@ -95,7 +98,7 @@ bitflags! {
         /// Casts/transitions from non-[`NON_NULL`] to [`NON_NULL`] will become [`_.unwrap()`].
         ///
         /// [`_.is_null()`] on a [`NON_NULL`] pointer will become [`false`].\
-        /// [`_.is_null()`] on a non-[`NON_NULL`] pointer will become [`_.is_some()`].
+        /// [`_.is_null()`] on a non-[`NON_NULL`] pointer will become [`_.is_none()`].
         ///
         /// Constant null pointers, like those produced by:
         /// * [`core::ptr::null`]
@@ -113,16 +116,28 @@ bitflags! {
         /// [`Some(_)`]: Some
         /// [`_.unwrap()`]: Option::unwrap
         /// [`_.is_null()`]: core::ptr::is_null
-        /// [`_.is_some()`]: Option::is_some
+        /// [`_.is_none()`]: Option::is_none
         const NON_NULL = 0x0080;
+
+        /// This pointer is used as a NUL-terminated string: it's passed to a function like
+        /// `strlen` that reads up to and including the first NUL, or a character read through it
+        /// is compared against NUL.
+        ///
+        /// A read-only pointer that is used only in this way, and is never dereferenced or offset
+        /// directly, becomes a `&CStr`.
+        const NUL_TERMINATED = 0x0100;
     }
 }
 
 impl PermissionSet {
-    /// The permissions for a (byte-)string literal.
+    /// The permissions for a (byte-)string literal.  Translated C string literals always include
+    /// their NUL terminator, so they can be used as `NUL_TERMINATED` strings.
     //
     // `.union` is used here since it's a `const fn`, unlike `BitOr::bitor`.
-    pub const STRING_LITERAL: Self = Self::READ.union(Self::OFFSET_ADD);
+    pub const STRING_LITERAL: Self = Self::READ
+        .union(Self::OFFSET_ADD)
+        .union(Self::NON_NULL)
+        .union(Self::NUL_TERMINATED);
 }
 
 bitflags! {
@@ -135,6 +150,11 @@ bitflags! {
         /// way, and it can't be freely discarded (or its inverse freely added) as is the case for
         /// everything in `PermissionSet`.
         const CELL = 0x0001;
+
+        /// This pointer's type is fixed: it keeps its original raw pointer type, and its
+        /// permissions are not updated by the analysis.  This is used for pointers in functions
+        /// that couldn't be analyzed.
+        const FIXED = 0x0002;
     }
 }
 
@@ -159,6 +179,15 @@ pub struct GlobalAnalysisCtxt<'tcx> {
 
     pub static_tys: HashMap<DefId, LTy<'tcx>>,
 
+    /// The local functions each indirect call may reach, keyed by the function containing the call
+    /// and the location of its terminator, or `None` if it may also reach a function we don't
+    /// analyze.  See `crate::fn_ptr`.
+    pub indirect_call_targets: HashMap<(LocalDefId, Location), Option<Vec<DefId>>>,
+
+    /// Local functions stored in function pointers whose types can't be rewritten to match their
+    /// signatures.  See `crate::fn_ptr`.
+    pub escaping_fns: HashSet<DefId>,
+
     next_ptr_id: NextGlobalPointerId,
 }
 
@@ -224,6 +253,8 @@ impl<'tcx> GlobalAnalysisCtxt<'tcx> {
             fn_sigs: HashMap::new(),
             field_tys: HashMap::new(),
             static_tys: HashMap::new(),
+            indirect_call_targets: HashMap::new(),
+            escaping_fns: HashSet::new(),
             next_ptr_id: NextGlobalPointerId::new(),
         }
     }
@@ -262,6 +293,8 @@ impl<'tcx> GlobalAnalysisCtxt<'tcx> {
             ref mut fn_sigs,
             ref mut field_tys,
             ref mut static_tys,
+            indirect_call_targets: _,
+            escaping_fns: _,
             ref mut next_ptr_id,
         } = *self;
 
@@ -333,11 +366,12 @@ impl<'a, 'tcx> AnalysisCtxt<'a, 'tcx> {
             string_literal_locs,
             next_ptr_id,
         } = data;
+        let tcx = gacx.tcx;
         AnalysisCtxt {
             gacx,
             local_decls: &mir.local_decls,
             local_tys,
-            c_void_casts: CVoidCasts::default(),
+            c_void_casts: CVoidCasts::new(mir, tcx),
             addr_of_local,
             rvalue_tys,
             string_literal_locs,
@@ -367,10 +401,6 @@ impl<'a, 'tcx> AnalysisCtxt<'a, 'tcx> {
         self.next_ptr_id.next()
     }
 
-    pub fn num_pointers(&self) -> usize {
-        self.next_ptr_id.num_pointers()
-    }
-
     pub fn type_of<T: TypeOf<'tcx>>(&self, x: T) -> LTy<'tcx> {
         x.type_of(self)
     }
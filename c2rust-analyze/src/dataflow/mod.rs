@@ -1,15 +1,20 @@
+use std::collections::HashMap;
 use std::mem;
 
 use crate::context::{AnalysisCtxt, Assignment, FlagSet, PermissionSet, PointerId};
 use crate::pointer_id::{OwnedPointerTable, PointerTable, PointerTableMut};
-use rustc_middle::mir::Body;
+use rustc_middle::mir::{Body, Location};
 
 mod type_check;
 
 #[derive(Clone, Debug)]
-enum Constraint {
+pub enum Constraint {
     /// Pointer `.0` must have a subset of the permissions of pointer `.1`.
     Subset(PointerId, PointerId),
+    /// Pointer `.0` is the result of offsetting pointer `.1`.  This is the same as `Subset`,
+    /// except that `.0` stays `NON_NULL` even if `.1` may be null, since offsetting a null pointer
+    /// is undefined behavior.
+    Offset(PointerId, PointerId),
     /// Pointer `.0` must have all the permissions in `.1`.
     AllPerms(PointerId, PermissionSet),
     /// Pointer `.0` must not have any of the permissions in `.1`.
@@ -19,24 +24,57 @@ enum Constraint {
 #[derive(Clone, Debug, Default)]
 pub struct DataflowConstraints {
     constraints: Vec<Constraint>,
+    /// The MIR location whose statement or terminator generated each entry of `constraints`, or
+    /// `None` for constraints that don't come from a particular location.
+    locs: Vec<Option<Location>>,
+    /// Permissions that are removed from (`.0`) or added to (`.1`) a pointer whenever propagation
+    /// updates it, overriding what the constraints would give it.  These come from dynamic
+    /// evidence; see `crate::pdg`.
+    overrides: HashMap<PointerId, (PermissionSet, PermissionSet)>,
 }
 
 impl DataflowConstraints {
-    fn add_subset(&mut self, a: PointerId, b: PointerId) {
+    fn add_subset(&mut self, a: PointerId, b: PointerId, loc: Option<Location>) {
         self.constraints.push(Constraint::Subset(a, b));
+        self.locs.push(loc);
     }
 
-    fn add_all_perms(&mut self, ptr: PointerId, perms: PermissionSet) {
+    fn add_offset(&mut self, a: PointerId, b: PointerId, loc: Option<Location>) {
+        self.constraints.push(Constraint::Offset(a, b));
+        self.locs.push(loc);
+    }
+
+    fn add_all_perms(&mut self, ptr: PointerId, perms: PermissionSet, loc: Option<Location>) {
         self.constraints.push(Constraint::AllPerms(ptr, perms));
+        self.locs.push(loc);
     }
 
-    #[allow(dead_code)]
-    fn _add_no_perms(&mut self, ptr: PointerId, perms: PermissionSet) {
+    fn add_no_perms(&mut self, ptr: PointerId, perms: PermissionSet, loc: Option<Location>) {
         self.constraints.push(Constraint::NoPerms(ptr, perms));
+        self.locs.push(loc);
     }
 
-    /// Update the pointer permissions in `hypothesis` to satisfy these constraints.
-    pub fn propagate(&self, hypothesis: &mut PointerTableMut<PermissionSet>) -> bool {
+    /// Make propagation remove `remove` from and add `add` to the permissions of `ptr`, on top of
+    /// any override `ptr` already has.
+    pub fn add_override(&mut self, ptr: PointerId, remove: PermissionSet, add: PermissionSet) {
+        let entry = self.overrides.entry(ptr).or_default();
+        entry.0 |= remove;
+        entry.1 |= add;
+    }
+
+    /// Iterate over the constraints, along with the location that generated each one.
+    pub fn iter(&self) -> impl Iterator<Item = (&Constraint, Option<Location>)> {
+        self.constraints.iter().zip(self.locs.iter().copied())
+    }
+
+    /// Update the pointer permissions in `hypothesis` to satisfy these constraints.  Pointers
+    /// whose `flags` include `FIXED` keep their current permissions, and pointers with an
+    /// override always have it applied.
+    pub fn propagate(
+        &self,
+        hypothesis: &mut PointerTableMut<PermissionSet>,
+        flags: &PointerTable<FlagSet>,
+    ) -> bool {
         eprintln!("=== propagating ===");
         eprintln!("constraints:");
         for c in &self.constraints {
@@ -47,13 +85,34 @@ impl DataflowConstraints {
             eprintln!("  {}: {:?}", id, p);
         }
 
-        struct PropagatePerms;
-        impl PropagateRules<PermissionSet> for PropagatePerms {
+        struct PropagatePerms<'a> {
+            flags: PointerTable<'a, FlagSet>,
+            overrides: &'a HashMap<PointerId, (PermissionSet, PermissionSet)>,
+        }
+        impl PropagatePerms<'_> {
+            /// Return `new` with the override for `ptr` applied, or `old` if `ptr` is `FIXED` and
+            /// must not be updated.
+            fn update(
+                &self,
+                ptr: PointerId,
+                old: PermissionSet,
+                new: PermissionSet,
+            ) -> PermissionSet {
+                if self.flags[ptr].contains(FlagSet::FIXED) {
+                    old
+                } else if let Some(&(remove, add)) = self.overrides.get(&ptr) {
+                    (new & !remove) | add
+                } else {
+                    new
+                }
+            }
+        }
+        impl PropagateRules<PermissionSet> for PropagatePerms<'_> {
             fn subset(
                 &mut self,
-                _a_ptr: PointerId,
+                a_ptr: PointerId,
                 a_val: &PermissionSet,
-                _b_ptr: PointerId,
+                b_ptr: PointerId,
                 b_val: &PermissionSet,
             ) -> (PermissionSet, PermissionSet) {
                 let old_a = *a_val;
@@ -63,9 +122,11 @@ impl DataflowConstraints {
                 // non-const operator in constants`.
 
                 // Permissions that should be propagated "down": if the superset (`b`)
-                // doesn't have it, then the subset (`a`) should have it removed.
+                // doesn't have it, then the subset (`a`) should have it removed.  For
+                // `NON_NULL`, this means a pointer that may be null makes every pointer copied
+                // from it possibly null as well.
                 #[allow(bad_style)]
-                let PROPAGATE_DOWN = PermissionSet::UNIQUE;
+                let PROPAGATE_DOWN = PermissionSet::UNIQUE | PermissionSet::NON_NULL;
                 // Permissions that should be propagated "up": if the subset (`a`) has it,
                 // then the superset (`b`) should be given it.
                 #[allow(bad_style)]
@@ -73,34 +134,56 @@ impl DataflowConstraints {
                     | PermissionSet::WRITE
                     | PermissionSet::OFFSET_ADD
                     | PermissionSet::OFFSET_SUB
-                    | PermissionSet::FREE;
+                    | PermissionSet::LINEAR
+                    | PermissionSet::FREE
+                    | PermissionSet::NUL_TERMINATED;
 
                 (
-                    old_a & !(!old_b & PROPAGATE_DOWN),
-                    old_b | (old_a & PROPAGATE_UP),
+                    self.update(a_ptr, old_a, old_a & !(!old_b & PROPAGATE_DOWN)),
+                    self.update(b_ptr, old_b, old_b | (old_a & PROPAGATE_UP)),
                 )
             }
 
+            fn offset(
+                &mut self,
+                a_ptr: PointerId,
+                a_val: &PermissionSet,
+                b_ptr: PointerId,
+                b_val: &PermissionSet,
+            ) -> (PermissionSet, PermissionSet) {
+                // The result of the offset is non-null whether or not the base pointer is.
+                let non_null_b_val = *b_val | PermissionSet::NON_NULL;
+                let (new_a, _) = self.subset(a_ptr, a_val, b_ptr, &non_null_b_val);
+                let (_, new_b) = self.subset(a_ptr, a_val, b_ptr, b_val);
+                (new_a, new_b)
+            }
+
             fn all_perms(
                 &mut self,
-                _ptr: PointerId,
+                ptr: PointerId,
                 perms: PermissionSet,
                 val: &PermissionSet,
             ) -> PermissionSet {
-                *val | perms
+                self.update(ptr, *val, *val | perms)
             }
 
             fn no_perms(
                 &mut self,
-                _ptr: PointerId,
+                ptr: PointerId,
                 perms: PermissionSet,
                 val: &PermissionSet,
             ) -> PermissionSet {
-                *val & !perms
+                self.update(ptr, *val, *val & !perms)
             }
         }
 
-        match self.propagate_inner(hypothesis, &mut PropagatePerms) {
+        match self.propagate_inner(
+            hypothesis,
+            &mut PropagatePerms {
+                flags: flags.borrow(),
+                overrides: &self.overrides,
+            },
+        ) {
             Ok(changed) => changed,
             Err(msg) => {
                 panic!("{}", msg);
@@ -141,6 +224,18 @@ impl DataflowConstraints {
                         xs.set(b, new_b);
                     }
 
+                    Constraint::Offset(a, b) => {
+                        if !xs.dirty(a) && !xs.dirty(b) {
+                            continue;
+                        }
+
+                        let old_a = xs.get(a);
+                        let old_b = xs.get(b);
+                        let (new_a, new_b) = rules.offset(a, old_a, b, old_b);
+                        xs.set(a, new_a);
+                        xs.set(b, new_b);
+                    }
+
                     Constraint::AllPerms(ptr, perms) => {
                         if !xs.dirty(ptr) {
                             continue;
@@ -248,6 +343,7 @@ impl Constraint {
     pub fn remap_pointers(&mut self, map: PointerTable<PointerId>) {
         *self = match *self {
             Constraint::Subset(a, b) => Constraint::Subset(map[a], map[b]),
+            Constraint::Offset(a, b) => Constraint::Offset(map[a], map[b]),
             Constraint::AllPerms(ptr, perms) => Constraint::AllPerms(map[ptr], perms),
             Constraint::NoPerms(ptr, perms) => Constraint::NoPerms(map[ptr], perms),
         };
@@ -259,6 +355,9 @@ impl DataflowConstraints {
         for c in &mut self.constraints {
             c.remap_pointers(map.borrow());
         }
+        for (ptr, (remove, add)) in mem::take(&mut self.overrides) {
+            self.add_override(map[ptr], remove, add);
+        }
     }
 }
 
@@ -316,6 +415,9 @@ impl<'a, T: PartialEq> TrackedPointerTable<'a, T> {
 
 trait PropagateRules<T> {
     fn subset(&mut self, a_ptr: PointerId, a_val: &T, b_ptr: PointerId, b_val: &T) -> (T, T);
+    fn offset(&mut self, a_ptr: PointerId, a_val: &T, b_ptr: PointerId, b_val: &T) -> (T, T) {
+        self.subset(a_ptr, a_val, b_ptr, b_val)
+    }
     fn all_perms(&mut self, ptr: PointerId, perms: PermissionSet, val: &T) -> T;
     fn no_perms(&mut self, ptr: PointerId, perms: PermissionSet, val: &T) -> T;
 }
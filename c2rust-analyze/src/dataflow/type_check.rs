@@ -1,15 +1,18 @@
 use super::DataflowConstraints;
 use crate::c_void_casts::CVoidCastDirection;
 use crate::context::{AnalysisCtxt, LTy, PermissionSet, PointerId};
-use crate::util::{describe_rvalue, is_null_const, ty_callee, Callee, RvalueDesc};
+use crate::fn_ptr;
+use crate::util::{describe_rvalue, is_null_const, null_locals, ty_callee, Callee, RvalueDesc};
 use assert_matches::assert_matches;
 use rustc_hir::def_id::DefId;
 use rustc_middle::mir::{
-    AggregateKind, BinOp, Body, CastKind, Location, Mutability, Operand, Place, PlaceRef,
-    ProjectionElem, Rvalue, Statement, StatementKind, Terminator, TerminatorKind,
+    AggregateKind, BinOp, Body, CastKind, Local, LocalKind, Location, Mutability, Operand, Place,
+    PlaceRef, ProjectionElem, Rvalue, Statement, StatementKind, Terminator, TerminatorKind,
 };
 use rustc_middle::ty::adjustment::PointerCast;
-use rustc_middle::ty::{SubstsRef, Ty, TyKind};
+use rustc_middle::ty::{IntTy, List, SubstsRef, TyKind, UintTy};
+use rustc_span::sym;
+use std::collections::{HashMap, HashSet};
 
 /// Visitor that walks over the MIR, computing types of rvalues/operands/places and generating
 /// constraints as a side effect.
@@ -40,6 +43,15 @@ struct TypeChecker<'tcx, 'a> {
     /// structure defined in `crate::equiv`, so adding a constraint here has the effect of unifying
     /// the equivalence classes of the two `PointerId`s.
     equiv_constraints: Vec<(PointerId, PointerId)>,
+    /// The location of the statement or terminator currently being visited.  Recorded alongside
+    /// each new dataflow constraint, so we can later explain where permissions came from.
+    loc: Option<Location>,
+    /// Locals that hold a null pointer constant.  See `util::null_locals`.
+    null_locals: HashSet<Local>,
+    /// The place each temporary was copied from, for temporaries that are assigned only once by
+    /// a plain copy or move.  Used to find the variable behind the operand of a null check, and
+    /// the pointer behind a character compared against NUL.
+    temp_sources: HashMap<Local, Place<'tcx>>,
 }
 
 fn is_castable_to<'tcx>(_from_lty: LTy<'tcx>, _to_lty: LTy<'tcx>) -> bool {
@@ -50,7 +62,73 @@ fn is_castable_to<'tcx>(_from_lty: LTy<'tcx>, _to_lty: LTy<'tcx>) -> bool {
 impl<'tcx> TypeChecker<'tcx, '_> {
     fn add_edge(&mut self, src: PointerId, dest: PointerId) {
         // Copying `src` to `dest` can discard permissions, but can't add new ones.
-        self.constraints.add_subset(dest, src);
+        self.constraints.add_subset(dest, src, self.loc);
+    }
+
+    fn add_all_perms(&mut self, ptr: PointerId, perms: PermissionSet) {
+        self.constraints.add_all_perms(ptr, perms, self.loc);
+    }
+
+    fn add_no_perms(&mut self, ptr: PointerId, perms: PermissionSet) {
+        self.constraints.add_no_perms(ptr, perms, self.loc);
+    }
+
+    /// Remove `NON_NULL` from the pointer `op` holds, which is checked against null.  If `op` is a
+    /// temporary copy of another place, that place is checked as well, and so on.
+    fn add_null_checked(&mut self, op: &Operand<'tcx>) {
+        let mut pl = match op.place() {
+            Some(x) => x,
+            None => return,
+        };
+        loop {
+            let ptr = self.acx.type_of(pl).label;
+            if ptr != PointerId::NONE {
+                self.add_no_perms(ptr, PermissionSet::NON_NULL);
+            }
+            pl = match pl
+                .as_local()
+                .and_then(|local| self.temp_sources.get(&local))
+            {
+                Some(&src) => src,
+                None => break,
+            };
+        }
+    }
+
+    /// Add `NUL_TERMINATED` to the pointer that the character `op` was read through, if there is
+    /// one.  `op` is compared against NUL, as in a loop like `while *p != 0 { ... }` that reads
+    /// up to the end of a string.
+    fn add_nul_checked(&mut self, op: &Operand<'tcx>) {
+        let mut pl = match op.place() {
+            Some(x) => x,
+            None => return,
+        };
+        if let Some(&src) = pl
+            .as_local()
+            .and_then(|local| self.temp_sources.get(&local))
+        {
+            pl = src;
+        }
+        let base = match pl.as_ref().last_projection() {
+            Some((base, ProjectionElem::Deref)) => base,
+            _ => return,
+        };
+        if !matches!(
+            self.acx.type_of(pl).ty.kind(),
+            TyKind::Int(IntTy::I8) | TyKind::Uint(UintTy::U8)
+        ) {
+            return;
+        }
+        let ptr = self.acx.type_of(base).label;
+        if ptr != PointerId::NONE {
+            self.add_all_perms(ptr, PermissionSet::NUL_TERMINATED);
+        }
+    }
+
+    fn is_null_operand(&self, op: &Operand<'tcx>) -> bool {
+        op.place()
+            .and_then(|pl| pl.as_local())
+            .map_or(false, |local| self.null_locals.contains(&local))
     }
 
     fn add_equiv(&mut self, a: PointerId, b: PointerId) {
@@ -64,11 +142,10 @@ impl<'tcx> TypeChecker<'tcx, '_> {
         }
         match mutbl {
             Mutability::Mut => {
-                self.constraints
-                    .add_all_perms(ptr, PermissionSet::READ | PermissionSet::WRITE);
+                self.add_all_perms(ptr, PermissionSet::READ | PermissionSet::WRITE);
             }
             Mutability::Not => {
-                self.constraints.add_all_perms(ptr, PermissionSet::READ);
+                self.add_all_perms(ptr, PermissionSet::READ);
             }
         }
     }
@@ -112,6 +189,7 @@ impl<'tcx> TypeChecker<'tcx, '_> {
                 if !op.constant().copied().map(is_null_const).unwrap_or(false) {
                     panic!("Creating non-null pointers from exposed addresses not supported");
                 }
+                self.add_no_perms(to_lty.label, PermissionSet::NON_NULL);
             }
             CastKind::Pointer(PointerCast::Unsize) => {
                 let pointee_to_ty = to_ty
@@ -129,6 +207,27 @@ impl<'tcx> TypeChecker<'tcx, '_> {
                 assert_eq!(pointee_from_lty.label, PointerId::NONE);
                 self.do_assign_pointer_ids(to_lty.label, from_lty.label);
             }
+            CastKind::Pointer(PointerCast::MutToConstPointer) => {
+                // `*mut T` to `*const T` is a copy of the pointer, as far as permissions go.
+                self.do_assign_pointer_ids(to_lty.label, from_lty.label);
+                self.do_unify(to_lty.args[0], from_lty.args[0]);
+            }
+            CastKind::Pointer(PointerCast::ReifyFnPointer) => {
+                // The function pointer type is rewritten to match the signature of the function,
+                // unless the function escapes to code we don't analyze (see `crate::fn_ptr`).
+                if let TyKind::FnDef(def_id, _) = *from_lty.ty.kind() {
+                    let gacx = &self.acx.gacx;
+                    if gacx.fn_sigs.contains_key(&def_id) && !gacx.escaping_fns.contains(&def_id) {
+                        self.unify_fn_ptr_sig(to_lty, def_id);
+                    }
+                }
+            }
+            CastKind::Pointer(PointerCast::UnsafeFnPointer) => {
+                // `fn` to `unsafe fn` keeps the same signature.
+                for (&to_arg_lty, &from_arg_lty) in to_lty.args.iter().zip(from_lty.args.iter()) {
+                    self.do_unify(to_arg_lty, from_arg_lty);
+                }
+            }
             CastKind::Pointer(..) => {
                 // The source and target types are both pointers, and they have identical pointee types.
                 // TODO: remove or move check to `is_castable_to`
@@ -196,9 +295,25 @@ impl<'tcx> TypeChecker<'tcx, '_> {
                 self.visit_cast(cast_kind, op, rvalue_lty);
             }
             Rvalue::BinaryOp(BinOp::Offset, _) => todo!("visit_rvalue BinOp::Offset"),
-            Rvalue::BinaryOp(_, ref ops) => {
+            Rvalue::BinaryOp(bop, ref ops) => {
                 self.visit_operand(&ops.0);
                 self.visit_operand(&ops.1);
+                if matches!(bop, BinOp::Eq | BinOp::Ne) {
+                    // A pointer that's compared against null may be null.
+                    if self.is_null_operand(&ops.1) {
+                        self.add_null_checked(&ops.0);
+                    } else if self.is_null_operand(&ops.0) {
+                        self.add_null_checked(&ops.1);
+                    }
+                    // A character that's compared against NUL ends a string.
+                    let is_zero =
+                        |op: &Operand| op.constant().copied().map_or(false, is_null_const);
+                    if is_zero(&ops.1) {
+                        self.add_nul_checked(&ops.0);
+                    } else if is_zero(&ops.0) {
+                        self.add_nul_checked(&ops.1);
+                    }
+                }
             }
             Rvalue::CheckedBinaryOp(BinOp::Offset, _) => todo!("visit_rvalue BinOp::Offset"),
             Rvalue::CheckedBinaryOp(_, ref ops) => {
@@ -226,6 +341,16 @@ impl<'tcx> TypeChecker<'tcx, '_> {
                             self.do_assign(elem_lty, op_lty);
                         }
                     }
+                    AggregateKind::Adt(adt_did, ..)
+                        if self.acx.tcx().is_diagnostic_item(sym::Option, adt_did) =>
+                    {
+                        // `Some(x)` stores `x` as the only type argument of the `Option`.
+                        let inner_lty = assert_matches!(rvalue_lty.args, [lty] => lty);
+                        for op in ops {
+                            let op_lty = self.acx.type_of(op);
+                            self.do_assign(inner_lty, op_lty);
+                        }
+                    }
                     AggregateKind::Adt(adt_did, ..) => {
                         let base_adt_def = self.acx.tcx().adt_def(adt_did);
                         let fields = &base_adt_def.non_enum_variant().fields;
@@ -317,6 +442,7 @@ impl<'tcx> TypeChecker<'tcx, '_> {
 
     pub fn visit_statement(&mut self, stmt: &Statement<'tcx>, loc: Location) {
         eprintln!("visit_statement({:?})", stmt);
+        self.loc = Some(loc);
 
         if self.acx.c_void_casts.should_skip_stmt(loc) {
             return;
@@ -332,7 +458,17 @@ impl<'tcx> TypeChecker<'tcx, '_> {
 
                 let rv_lty = self.acx.type_of_rvalue(rv, loc);
                 self.visit_rvalue(rv, rv_lty);
-                self.do_assign(pl_lty, rv_lty);
+                let is_addr_of_deref = matches!(rv, Rvalue::Ref(..) | Rvalue::AddressOf(..))
+                    && matches!(describe_rvalue(rv), Some(RvalueDesc::Project { .. }));
+                if is_addr_of_deref {
+                    // `&(*p).x` is never null, even if `p` may be, since dereferencing a null `p`
+                    // is undefined behavior.
+                    self.constraints
+                        .add_offset(pl_lty.label, rv_lty.label, self.loc);
+                    self.do_equivalence_nested(pl_lty, rv_lty);
+                } else {
+                    self.do_assign(pl_lty, rv_lty);
+                }
             }
             // TODO(spernsteiner): handle other `StatementKind`s
             _ => (),
@@ -341,7 +477,7 @@ impl<'tcx> TypeChecker<'tcx, '_> {
 
     pub fn visit_terminator(&mut self, term: &Terminator<'tcx>, loc: Location) {
         eprintln!("visit_terminator({:?})", term.kind);
-        let tcx = self.acx.tcx();
+        self.loc = Some(loc);
         // TODO(spernsteiner): other `TerminatorKind`s will be handled in the future
         #[allow(clippy::single_match)]
         match term.kind {
@@ -352,7 +488,6 @@ impl<'tcx> TypeChecker<'tcx, '_> {
                 target: _,
                 ..
             } => {
-                let func = func.ty(self.mir, tcx);
                 self.visit_call(loc, func, args, destination);
             }
             // TODO(spernsteiner): handle other `TerminatorKind`s
@@ -363,17 +498,49 @@ impl<'tcx> TypeChecker<'tcx, '_> {
     pub fn visit_call(
         &mut self,
         loc: Location,
-        func: Ty<'tcx>,
+        func: &Operand<'tcx>,
         args: &[Operand<'tcx>],
         destination: Place<'tcx>,
     ) {
         let tcx = self.acx.tcx();
-        let callee = ty_callee(tcx, func);
+        let func_ty = func.ty(self.mir, tcx);
+        let callee = ty_callee(tcx, func_ty);
         eprintln!("callee = {callee:?}");
         match callee {
             Callee::Trivial => {}
             Callee::UnknownDef { .. } => {
-                log::error!("TODO: visit Callee::{callee:?}");
+                // `Option::unwrap` and the like pass a function pointer through unchanged.
+                if fn_ptr::is_option_method(tcx, func_ty) {
+                    let dest_lty = fn_ptr::fn_ptr_lty(tcx, self.acx.type_of(destination));
+                    let arg_lty = args
+                        .get(0)
+                        .and_then(|op| fn_ptr::fn_ptr_lty(tcx, self.acx.type_of(op)));
+                    if let (Some(dest_lty), Some(arg_lty)) = (dest_lty, arg_lty) {
+                        self.do_unify(dest_lty, arg_lty);
+                    }
+                }
+
+                // A call through a function pointer is treated as a call to each local function
+                // the pointer may hold, unless it may also hold a function we don't analyze.
+                let ldid = self.mir.source.def_id().expect_local();
+                match self.acx.gacx.indirect_call_targets.get(&(ldid, loc)) {
+                    Some(Some(targets)) if !targets.is_empty() => {
+                        let func_lty = self.acx.type_of(func);
+                        for &def_id in targets {
+                            self.unify_fn_ptr_sig(func_lty, def_id);
+                            self.visit_local_call(def_id, List::empty(), args, destination);
+                        }
+                    }
+                    _ => {
+                        log::error!("TODO: visit Callee::{callee:?}");
+                        // We don't know what the callee returns, so it may be null.
+                        self.visit_place(destination, Mutability::Mut);
+                        let pl_lty = self.acx.type_of(destination);
+                        if pl_lty.label != PointerId::NONE && pl_lty.ty.is_unsafe_ptr() {
+                            self.add_no_perms(pl_lty.label, PermissionSet::NON_NULL);
+                        }
+                    }
+                }
             }
 
             Callee::LocalDef { def_id, substs } => {
@@ -381,15 +548,17 @@ impl<'tcx> TypeChecker<'tcx, '_> {
             }
 
             Callee::PtrOffset { .. } => {
-                // We handle this like a pointer assignment.
+                // We handle this like a pointer assignment, except that the result is non-null.
                 self.visit_place(destination, Mutability::Mut);
                 let pl_lty = self.acx.type_of(destination);
                 assert!(args.len() == 2);
                 self.visit_operand(&args[0]);
                 let rv_lty = self.acx.type_of(&args[0]);
-                self.do_assign(pl_lty, rv_lty);
+                self.constraints
+                    .add_offset(pl_lty.label, rv_lty.label, self.loc);
+                self.do_equivalence_nested(pl_lty, rv_lty);
                 let perms = PermissionSet::OFFSET_ADD | PermissionSet::OFFSET_SUB;
-                self.constraints.add_all_perms(rv_lty.label, perms);
+                self.add_all_perms(rv_lty.label, perms);
             }
 
             Callee::SliceAsPtr { elem_ty, .. } => {
@@ -417,6 +586,8 @@ impl<'tcx> TypeChecker<'tcx, '_> {
             }
 
             Callee::Malloc | Callee::Calloc => {
+                // Unlike other foreign calls, the result stays `NON_NULL` unless it's checked
+                // against null, since a `Box` allocation never returns null.
                 let out_ptr = self.acx.c_void_casts.get_adjusted_place_or_default_to(
                     loc,
                     CVoidCastDirection::From,
@@ -444,12 +615,15 @@ impl<'tcx> TypeChecker<'tcx, '_> {
                 self.visit_place(in_ptr, Mutability::Not);
                 let rv_lty = self.acx.type_of(in_ptr);
 
-                // input needs FREE permission
-                let perms = PermissionSet::FREE;
-                self.constraints.add_all_perms(rv_lty.label, perms);
+                // input needs FREE permission, and is consumed by the call.  `linearity` checks that it's
+                // actually used linearly.
+                let perms = PermissionSet::FREE | PermissionSet::LINEAR;
+                self.add_all_perms(rv_lty.label, perms);
 
-                // unify inner-most pointer types
-                self.do_equivalence_nested(pl_lty, rv_lty);
+                // The allocation moves from the input to the output, so the input must support
+                // everything the output is used for, including offsetting when the output is a
+                // slice.
+                self.do_assign(pl_lty, rv_lty);
             }
             Callee::Free => {
                 let in_ptr = args[0]
@@ -464,17 +638,58 @@ impl<'tcx> TypeChecker<'tcx, '_> {
                 assert!(args.len() == 1);
 
                 let rv_lty = self.acx.type_of(in_ptr);
-                let perms = PermissionSet::FREE;
-                self.constraints.add_all_perms(rv_lty.label, perms);
+                let perms = PermissionSet::FREE | PermissionSet::LINEAR;
+                self.add_all_perms(rv_lty.label, perms);
             }
 
             Callee::IsNull => {
                 assert!(args.len() == 1);
                 self.visit_operand(&args[0]);
+                self.add_null_checked(&args[0]);
+            }
+
+            Callee::Null => {
+                self.visit_place(destination, Mutability::Mut);
+                let pl_lty = self.acx.type_of(destination);
+                self.add_no_perms(pl_lty.label, PermissionSet::NON_NULL);
+            }
+
+            Callee::Strlen | Callee::Strcmp | Callee::Strchr => {
+                // The string arguments are read only up to their NUL terminators.
+                self.visit_place(destination, Mutability::Mut);
+                let num_strs = match callee {
+                    Callee::Strcmp => 2,
+                    _ => 1,
+                };
+                for op in &args[..num_strs] {
+                    self.visit_operand(op);
+                    let rv_lty = self.acx.type_of(op);
+                    self.add_all_perms(rv_lty.label, PermissionSet::NUL_TERMINATED);
+                }
+
+                if matches!(callee, Callee::Strchr) {
+                    // The result points into the string argument, or is null if the character
+                    // wasn't found.
+                    let pl_lty = self.acx.type_of(destination);
+                    let rv_lty = self.acx.type_of(&args[0]);
+                    self.do_assign_pointer_ids(pl_lty.label, rv_lty.label);
+                    self.add_no_perms(pl_lty.label, PermissionSet::NON_NULL);
+                }
             }
         }
     }
 
+    /// Unify the pointers in the function pointer type `fn_ptr_lty` with those in the signature of
+    /// the local function `def_id`, so the function pointer type is rewritten to match it.
+    fn unify_fn_ptr_sig(&mut self, fn_ptr_lty: LTy<'tcx>, def_id: DefId) {
+        let sig = self.acx.gacx.fn_sigs[&def_id];
+        let (&output_lty, input_ltys) = fn_ptr_lty.args.split_last().unwrap();
+        for (&input_lty, &sig_input_lty) in input_ltys.iter().zip(sig.inputs.iter()) {
+            self.do_unify(input_lty, sig_input_lty);
+        }
+        self.do_unify(output_lty, sig.output);
+    }
+
     /// Visit a local call, where local means
     /// local to the current crate with a static, known definition.
     ///
@@ -497,6 +712,12 @@ impl<'tcx> TypeChecker<'tcx, '_> {
             self.visit_operand(arg_op);
             let arg_lty = self.acx.type_of(arg_op);
             self.do_assign(input_lty, arg_lty);
+            // The argument is a temporary that's only used for the call, so it gets exactly the
+            // parameter's permissions.  In particular, it must be nullable if the parameter is,
+            // since there's no implicit conversion to `Option` at the call.
+            if input_lty.label != PointerId::NONE {
+                self.add_edge(input_lty.label, arg_lty.label);
+            }
         }
 
         // Process a pseudo-assignment from the return type declared in `sig` to `dest`.
@@ -516,10 +737,22 @@ pub fn visit<'tcx>(
         mir,
         constraints: DataflowConstraints::default(),
         equiv_constraints: Vec::new(),
+        loc: None,
+        null_locals: null_locals(acx.tcx(), mir),
+        temp_sources: temp_sources(mir),
     };
 
+    // Raw pointer arguments may be null, since callers outside the crate can pass anything.
+    for local in mir.args_iter() {
+        let ptr = acx.local_tys[local].label;
+        if ptr != PointerId::NONE && mir.local_decls[local].ty.is_unsafe_ptr() {
+            tc.constraints
+                .add_no_perms(ptr, PermissionSet::NON_NULL, None);
+        }
+    }
+
     for (ptr, perms) in acx.string_literal_perms() {
-        tc.constraints.add_all_perms(ptr, perms);
+        tc.constraints.add_all_perms(ptr, perms, None);
     }
 
     for (bb, bb_data) in mir.basic_blocks().iter_enumerated() {
@@ -543,3 +776,37 @@ pub fn visit<'tcx>(
 
     (tc.constraints, tc.equiv_constraints)
 }
+
+/// Find the temporaries of `mir` that are assigned exactly once, by a copy or move of another
+/// place, and map each one to that place.
+fn temp_sources<'tcx>(mir: &Body<'tcx>) -> HashMap<Local, Place<'tcx>> {
+    let mut sources = HashMap::new();
+    let mut assigned = HashSet::new();
+    let mut visit_assign = |pl: Place<'tcx>, src: Option<Place<'tcx>>| {
+        let local = match pl.as_local() {
+            Some(local) if mir.local_kind(local) == LocalKind::Temp => local,
+            _ => return,
+        };
+        if !assigned.insert(local) {
+            sources.remove(&local);
+        } else if let Some(src) = src {
+            sources.insert(local, src);
+        }
+    };
+    for bb in mir.basic_blocks().iter() {
+        for stmt in &bb.statements {
+            if let StatementKind::Assign(ref x) = stmt.kind {
+                let (pl, ref rv) = **x;
+                let src = match *rv {
+                    Rvalue::Use(Operand::Copy(src) | Operand::Move(src)) => Some(src),
+                    _ => None,
+                };
+                visit_assign(pl, src);
+            }
+        }
+        if let TerminatorKind::Call { destination, .. } = bb.terminator().kind {
+            visit_assign(destination, None);
+        }
+    }
+    sources
+}
@@ -0,0 +1,369 @@
+//! Explanations of why a pointer ended up with its permissions, requested with
+//! `--explain <fn>:<local or line>`.
+//!
+//! For each permission among `WRITE`, `OFFSET_ADD`, `OFFSET_SUB`, `FREE`, and `NUL_TERMINATED` that
+//! the pointer has, we search backward along the dataflow constraints for the shortest path to the
+//! statement that required it.  If the pointer lacks `UNIQUE` or `NON_NULL`, we search along the
+//! constraints that propagate those permissions downward for the borrowck error or null pointer
+//! constant that removed it.  Each step of the path is printed with the source location of the
+//! statement that generated it.
+
+use crate::borrowck::BorrowConflict;
+use crate::context::{FlagSet, PermissionSet, PointerId};
+use crate::dataflow::{Constraint, DataflowConstraints};
+use crate::pointer_id::PointerTable;
+use indexmap::IndexMap;
+use rustc_hir::def_id::LocalDefId;
+use rustc_middle::mir::{Body, Local, LocalKind, Location, VarDebugInfoContents};
+use rustc_middle::ty::{TyCtxt, WithOptConstParam};
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+use std::str::FromStr;
+
+/// A query given with `--explain <fn>:<target>`.
+#[derive(Clone, Debug)]
+pub struct ExplainQuery {
+    /// The name of the function containing the pointer.
+    pub fn_name: String,
+    target: ExplainTarget,
+}
+
+#[derive(Clone, Debug)]
+enum ExplainTarget {
+    /// A MIR local, written `_3`.
+    Local(Local),
+    /// A user variable, by name.
+    Var(String),
+    /// The user variables and arguments declared on a source line.
+    Line(usize),
+}
+
+impl FromStr for ExplainQuery {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<ExplainQuery, String> {
+        let (fn_name, target) = s
+            .split_once(':')
+            .ok_or_else(|| format!("expected <fn>:<local or line>, but got {:?}", s))?;
+        let target = if let Some(i) = target.strip_prefix('_').and_then(|i| i.parse().ok()) {
+            ExplainTarget::Local(Local::from_u32(i))
+        } else if let Ok(line) = target.parse() {
+            ExplainTarget::Line(line)
+        } else {
+            ExplainTarget::Var(target.to_owned())
+        };
+        Ok(ExplainQuery {
+            fn_name: fn_name.to_owned(),
+            target,
+        })
+    }
+}
+
+impl fmt::Display for ExplainQuery {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.target {
+            ExplainTarget::Local(local) => write!(f, "{}:{:?}", self.fn_name, local),
+            ExplainTarget::Var(ref name) => write!(f, "{}:{}", self.fn_name, name),
+            ExplainTarget::Line(line) => write!(f, "{}:{}", self.fn_name, line),
+        }
+    }
+}
+
+impl ExplainQuery {
+    /// Find the locals of `mir` that this query refers to.
+    pub fn find_locals(&self, tcx: TyCtxt, mir: &Body) -> Vec<Local> {
+        match self.target {
+            ExplainTarget::Local(local) => {
+                if local.as_usize() < mir.local_decls.len() {
+                    vec![local]
+                } else {
+                    vec![]
+                }
+            }
+            ExplainTarget::Var(ref name) => mir
+                .var_debug_info
+                .iter()
+                .filter(|vdi| vdi.name.as_str() == name)
+                .filter_map(|vdi| match vdi.value {
+                    VarDebugInfoContents::Place(pl) => pl.as_local(),
+                    _ => None,
+                })
+                .collect(),
+            ExplainTarget::Line(line) => mir
+                .local_decls
+                .iter_enumerated()
+                .filter(|&(local, decl)| {
+                    matches!(mir.local_kind(local), LocalKind::Var | LocalKind::Arg)
+                        && decl.is_user_variable()
+                        && tcx
+                            .sess
+                            .source_map()
+                            .lookup_char_pos(decl.source_info.span.lo())
+                            .line
+                            == line
+                })
+                .map(|(local, _)| local)
+                .collect(),
+        }
+    }
+}
+
+/// The analysis results for one function that are needed to explain permissions.
+pub struct FnExplainData<'a> {
+    pub dataflow: &'a DataflowConstraints,
+    pub borrow_conflicts: &'a [BorrowConflict],
+    pub perms: PointerTable<'a, PermissionSet>,
+    pub flags: PointerTable<'a, FlagSet>,
+}
+
+/// A pointer in a particular function.  Global pointers are shared by all functions, so their
+/// function is `None`.
+type Node = (Option<LocalDefId>, PointerId);
+
+fn mk_node(ldid: LocalDefId, ptr: PointerId) -> Node {
+    if ptr.is_global() {
+        (None, ptr)
+    } else {
+        (Some(ldid), ptr)
+    }
+}
+
+/// A single step along a path of dataflow constraints.
+struct Step {
+    from: PointerId,
+    to: PointerId,
+    ldid: LocalDefId,
+    loc: Option<Location>,
+}
+
+/// The reason a pointer has (or lacks) a permission, found at the end of a path of [`Step`]s.
+enum Cause {
+    /// A constraint requires the pointer to have all of these permissions.
+    AllPerms(PointerId, PermissionSet, LocalDefId, Option<Location>),
+    /// A constraint forbids the pointer from having any of these permissions.
+    NoPerms(PointerId, PermissionSet, LocalDefId, Option<Location>),
+    /// A borrowck error removed `UNIQUE` from the pointer.
+    Borrow(LocalDefId, BorrowConflict),
+    /// The pointer is `FIXED`, so its permissions were never updated.
+    Fixed(PointerId),
+}
+
+/// Whether to trace a permission the pointer has back to the statement that requires it, or a
+/// permission it lacks back to the statement that removed it.
+#[derive(Clone, Copy)]
+enum Trace {
+    Present(PermissionSet),
+    Absent(PermissionSet),
+}
+
+/// Search for the shortest path from `start` to a [`Cause`] for the permission described by
+/// `trace`.
+fn trace_cause(
+    fns: &IndexMap<LocalDefId, FnExplainData>,
+    start_ldid: LocalDefId,
+    start: PointerId,
+    trace: Trace,
+) -> Option<(Vec<Step>, Cause)> {
+    let mut parent: HashMap<Node, Option<(Node, Step)>> = HashMap::new();
+    let mut queue = VecDeque::new();
+    parent.insert(mk_node(start_ldid, start), None);
+    queue.push_back((start_ldid, start));
+
+    let cause = 'search: loop {
+        let (cur_ldid, cur) = queue.pop_front()?;
+        // Local pointers can only appear in the constraints of their own function, but global
+        // pointers can appear in any function.
+        let ldids: Vec<LocalDefId> = if cur.is_global() {
+            fns.keys().copied().collect()
+        } else {
+            vec![cur_ldid]
+        };
+
+        for ldid in ldids {
+            let fd = &fns[&ldid];
+            if fd.flags[cur].contains(FlagSet::FIXED) {
+                break 'search (cur_ldid, cur, Cause::Fixed(cur));
+            }
+
+            if let Trace::Absent(perm) = trace {
+                if perm.contains(PermissionSet::UNIQUE) {
+                    if let Some(&bc) = fd.borrow_conflicts.iter().find(|bc| bc.ptr == cur) {
+                        break 'search (cur_ldid, cur, Cause::Borrow(ldid, bc));
+                    }
+                }
+            }
+
+            for (c, loc) in fd.dataflow.iter() {
+                let next = match (trace, c) {
+                    (Trace::Present(perm), &Constraint::AllPerms(ptr, perms))
+                        if ptr == cur && perms.contains(perm) =>
+                    {
+                        break 'search (cur_ldid, cur, Cause::AllPerms(ptr, perms, ldid, loc));
+                    }
+                    (Trace::Absent(perm), &Constraint::NoPerms(ptr, perms))
+                        if ptr == cur && perms.contains(perm) =>
+                    {
+                        break 'search (cur_ldid, cur, Cause::NoPerms(ptr, perms, ldid, loc));
+                    }
+                    // `PROPAGATE_UP`: `a` flows from `cur`, and `cur` gets `a`'s permissions.
+                    (
+                        Trace::Present(perm),
+                        &(Constraint::Subset(a, b) | Constraint::Offset(a, b)),
+                    ) if b == cur && fd.perms[a].contains(perm) => a,
+                    // `PROPAGATE_DOWN`: `cur` flows from `b`, and loses what `b` lacks.  An offset
+                    // doesn't pass on a missing `NON_NULL`.
+                    (Trace::Absent(perm), &Constraint::Subset(a, b))
+                        if a == cur && !fd.perms[b].contains(perm) =>
+                    {
+                        b
+                    }
+                    (Trace::Absent(perm), &Constraint::Offset(a, b))
+                        if a == cur
+                            && perm != PermissionSet::NON_NULL
+                            && !fd.perms[b].contains(perm) =>
+                    {
+                        b
+                    }
+                    _ => continue,
+                };
+                let next_node = mk_node(ldid, next);
+                if parent.contains_key(&next_node) {
+                    continue;
+                }
+                let step = Step {
+                    from: cur,
+                    to: next,
+                    ldid,
+                    loc,
+                };
+                parent.insert(next_node, Some((mk_node(cur_ldid, cur), step)));
+                queue.push_back((ldid, next));
+            }
+        }
+    };
+
+    let (end_ldid, end, cause) = cause;
+    let mut steps = Vec::new();
+    let mut node = mk_node(end_ldid, end);
+    while let Some((prev, step)) = parent.remove(&node).flatten() {
+        steps.push(step);
+        node = prev;
+    }
+    steps.reverse();
+    Some((steps, cause))
+}
+
+/// Describe `loc` in the function `ldid`, naming the function if it isn't `cur_ldid`.
+pub fn describe_loc(
+    tcx: TyCtxt,
+    cur_ldid: LocalDefId,
+    ldid: LocalDefId,
+    loc: Option<Location>,
+) -> String {
+    let loc = match loc {
+        Some(x) => x,
+        None => return "(no location)".to_owned(),
+    };
+    let mir = tcx.mir_built(WithOptConstParam::unknown(ldid));
+    let mir = mir.borrow();
+    // Report the line of the macro call, not a line inside the macro's definition.
+    let span = mir.source_info(loc).span.source_callsite();
+    let span = crate::describe_span(tcx, span);
+    if ldid == cur_ldid {
+        format!("at {}", span)
+    } else {
+        format!("in {:?} at {}", tcx.item_name(ldid.to_def_id()), span)
+    }
+}
+
+/// Print the explanation for each of the `targets` in the function `ldid`.  Each target is a local,
+/// its description, and the `PointerId` of its outermost pointer type.
+pub fn explain(
+    tcx: TyCtxt,
+    query: &ExplainQuery,
+    ldid: LocalDefId,
+    targets: &[(Local, String, PointerId)],
+    fns: &IndexMap<LocalDefId, FnExplainData>,
+) {
+    eprintln!("\nexplain {}:", query);
+    if targets.is_empty() {
+        eprintln!("  no matching locals");
+    }
+
+    let fd = &fns[&ldid];
+    for &(local, ref desc, ptr) in targets {
+        if ptr.is_none() {
+            eprintln!("  {:?} ({}): not a pointer", local, desc);
+            continue;
+        }
+        let perms = fd.perms[ptr];
+        let flags = fd.flags[ptr];
+        eprintln!(
+            "  {:?} ({}): {}, perms = {:?}, flags = {:?}",
+            local, desc, ptr, perms, flags
+        );
+
+        let mut traces = [
+            PermissionSet::WRITE,
+            PermissionSet::OFFSET_ADD,
+            PermissionSet::OFFSET_SUB,
+            PermissionSet::FREE,
+            PermissionSet::NUL_TERMINATED,
+        ]
+        .into_iter()
+        .filter(|&perm| perms.contains(perm))
+        .map(Trace::Present)
+        .collect::<Vec<_>>();
+        for perm in [PermissionSet::UNIQUE, PermissionSet::NON_NULL] {
+            if !perms.contains(perm) {
+                traces.push(Trace::Absent(perm));
+            }
+        }
+
+        for trace in traces {
+            match trace {
+                Trace::Present(perm) => eprintln!("    {:?}:", perm),
+                Trace::Absent(perm) => eprintln!("    not {:?}:", perm),
+            }
+            let (steps, cause) = match trace_cause(fns, ldid, ptr, trace) {
+                Some(x) => x,
+                None => {
+                    eprintln!("      (no cause found)");
+                    continue;
+                }
+            };
+            for step in steps {
+                let loc = describe_loc(tcx, ldid, step.ldid, step.loc);
+                match trace {
+                    Trace::Present(_) => {
+                        eprintln!("      {} flows into {} ({})", step.from, step.to, loc)
+                    }
+                    Trace::Absent(_) => {
+                        eprintln!("      {} is assigned from {} ({})", step.from, step.to, loc)
+                    }
+                }
+            }
+            match cause {
+                Cause::AllPerms(ptr, perms, cause_ldid, loc) => eprintln!(
+                    "      {} needs {:?} ({})",
+                    ptr,
+                    perms,
+                    describe_loc(tcx, ldid, cause_ldid, loc)
+                ),
+                Cause::NoPerms(ptr, perms, cause_ldid, loc) => eprintln!(
+                    "      {} must not have {:?} ({})",
+                    ptr,
+                    perms,
+                    describe_loc(tcx, ldid, cause_ldid, loc)
+                ),
+                Cause::Borrow(cause_ldid, bc) => eprintln!(
+                    "      {} was borrowed ({}), and the loan conflicts with another access ({})",
+                    bc.ptr,
+                    describe_loc(tcx, ldid, cause_ldid, Some(bc.issued_at)),
+                    describe_loc(tcx, ldid, cause_ldid, Some(bc.error_at)),
+                ),
+                Cause::Fixed(ptr) => eprintln!("      {} is FIXED", ptr),
+            }
+        }
+    }
+}
@@ -0,0 +1,461 @@
+//! Detection of functions whose signatures are part of the crate's C ABI.
+//!
+//! Retyping the arguments of a `#[no_mangle]` function, or of an `extern "C"` function whose
+//! address is handed to C as a callback, would break its foreign callers.  Instead, each such
+//! function keeps its inferred safe types, and `rewrite::gen_ffi_wrapper_rewrites` adds a thin
+//! wrapper with the original raw pointer signature, which checks and converts the arguments before
+//! calling it.  The wrapper takes over the exported symbol, and it also replaces the function
+//! wherever that is converted to a function pointer C may call (see `crate::fn_ptr`).
+//!
+//! The wrapper only converts the outermost pointer of each argument and return type, so pointers
+//! nested inside those types are pinned as `FIXED` before the analysis runs.  If the wrapper can't
+//! be generated at all, as when a pointer argument is offset by amounts that no length parameter
+//! bounds, the whole signature is pinned instead.
+
+use crate::util::{self, Callee};
+use indexmap::IndexMap;
+use rustc_hir as hir;
+use rustc_hir::def_id::{DefId, LocalDefId};
+use rustc_hir::intravisit::{self, Visitor};
+use rustc_hir::{Expr, ExprKind, HirId, ItemKind, Node, PatKind, QPath};
+use rustc_index::vec::{Idx, IndexVec};
+use rustc_middle::middle::codegen_fn_attrs::CodegenFnAttrFlags;
+use rustc_middle::mir::{
+    BinOp, Body, Local, Operand, PlaceElem, Rvalue, StatementKind, TerminatorKind,
+};
+use rustc_middle::ty::{self, TyCtxt, TypeckResults, WithOptConstParam};
+use rustc_span::{BytePos, Span, Symbol};
+use rustc_target::spec::abi::Abi;
+use std::collections::HashSet;
+
+/// How a function is reachable from foreign code.
+#[derive(Clone, Debug, Default)]
+pub struct FfiFn {
+    /// The symbol the function is exported under, if it has `#[no_mangle]` or `#[export_name]`.
+    pub export_symbol: Option<Symbol>,
+    /// Spans of the path segments naming the function where it's converted to a function pointer,
+    /// if that pointer may be called by code we don't analyze.
+    pub addr_taken_spans: Vec<Span>,
+}
+
+/// The parts of an FFI function's definition that are needed to generate its wrapper.
+pub struct FfiFnDef<'tcx> {
+    pub item: &'tcx hir::Item<'tcx>,
+    pub sig: &'tcx hir::FnSig<'tcx>,
+    pub param_names: Vec<Symbol>,
+    /// The source of the signature up to the function name, like `pub unsafe extern "C" fn `.
+    pub header: String,
+    /// The source of the signature after the function name, like `(p: *const i32) -> i32`.
+    pub params_and_ret: String,
+    /// The `extern "C"` in the signature, which is removed from the original function once the
+    /// wrapper provides the C ABI.
+    pub abi_span: Option<Span>,
+    /// For each parameter that's a pointer offset in the body, the index of the integer parameter
+    /// that bounds the offsets, and so gives the length of the slice it can be converted to.
+    pub len_params: Vec<Option<usize>>,
+}
+
+/// The symbol `did` is exported under, if it has `#[no_mangle]` or `#[export_name]`.
+pub fn export_symbol(tcx: TyCtxt, did: DefId) -> Option<Symbol> {
+    let attrs = tcx.codegen_fn_attrs(did);
+    if let Some(name) = attrs.export_name {
+        Some(name)
+    } else if attrs.flags.contains(CodegenFnAttrFlags::NO_MANGLE) {
+        Some(tcx.item_name(did))
+    } else {
+        None
+    }
+}
+
+/// Find the functions among `all_fn_ldids` that are exported to C, or have a foreign ABI and are
+/// among the `escaping` functions of `fn_ptr::indirect_call_targets`, whose function pointers may
+/// be called by code we don't analyze.  The result is in the same order as `all_fn_ldids`.
+pub fn find_ffi_fns(
+    tcx: TyCtxt,
+    all_fn_ldids: &[LocalDefId],
+    escaping: &[LocalDefId],
+) -> IndexMap<LocalDefId, FfiFn> {
+    let mut addr_taken = Vec::new();
+    for ldid in tcx.hir().body_owners() {
+        let body = tcx.hir().body(tcx.hir().body_owned_by(ldid));
+        let mut v = AddrTakenVisitor {
+            typeck: tcx.typeck(ldid),
+            callees: HashSet::new(),
+            found: &mut addr_taken,
+        };
+        v.visit_body(body);
+    }
+
+    let mut ffi_fns = IndexMap::new();
+    for &ldid in all_fn_ldids {
+        let did = ldid.to_def_id();
+        let export_symbol = export_symbol(tcx, did);
+
+        let addr_taken_spans = if tcx.fn_sig(did).abi() != Abi::Rust && escaping.contains(&ldid) {
+            addr_taken
+                .iter()
+                .filter(|&&(callee, _)| callee == ldid)
+                .map(|&(_, span)| span)
+                .collect()
+        } else {
+            Vec::new()
+        };
+
+        if export_symbol.is_some() || !addr_taken_spans.is_empty() {
+            ffi_fns.insert(
+                ldid,
+                FfiFn {
+                    export_symbol,
+                    addr_taken_spans,
+                },
+            );
+        }
+    }
+    ffi_fns
+}
+
+/// Look up the definition of the FFI function `ldid`, or explain why we can't generate a wrapper
+/// for it.
+pub fn ffi_fn_def(tcx: TyCtxt, ldid: LocalDefId) -> Result<FfiFnDef, String> {
+    let hir_id = tcx.hir().local_def_id_to_hir_id(ldid);
+    let item = match tcx.hir().get(hir_id) {
+        Node::Item(item) => item,
+        _ => return Err("not a free function".into()),
+    };
+    let (sig, generics, body_id) = match item.kind {
+        ItemKind::Fn(ref sig, generics, body_id) => (sig, generics, body_id),
+        _ => return Err("not a free function".into()),
+    };
+    if !generics.params.is_empty() {
+        return Err("generic functions are not supported".into());
+    }
+    if sig.decl.c_variadic {
+        return Err("variadic functions are not supported".into());
+    }
+
+    let body = tcx.hir().body(body_id);
+    let param_names = body
+        .params
+        .iter()
+        .map(|param| match param.pat.kind {
+            PatKind::Binding(_, _, ident, None) => Ok(ident.name),
+            _ => Err(format!(
+                "unsupported parameter pattern `{}`",
+                tcx.hir().node_to_string(param.pat.hir_id)
+            )),
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    // The wrapper converts each argument and the return value from a raw pointer.
+    let fn_sig = tcx.fn_sig(ldid.to_def_id()).skip_binder();
+    for (name, &ty) in param_names.iter().zip(fn_sig.inputs()) {
+        if ty.is_ref() {
+            return Err(format!("argument `{}` is a reference", name));
+        }
+    }
+    if fn_sig.output().is_ref() {
+        return Err("return value is a reference".into());
+    }
+
+    // The wrapper is built from the original signature, which is left intact in the source.
+    let source_map = tcx.sess.source_map();
+    let snippet = |span: Span| {
+        source_map
+            .span_to_snippet(span)
+            .map_err(|e| format!("failed to get source of {:?}: {:?}", span, e))
+    };
+    let ident_span = item.ident.span;
+    // `sig.span` starts at the visibility, which the wrapper gets from `tcx.visibility` instead.
+    let header_lo = sig.span.lo().max(item.vis_span.hi());
+    let header = snippet(sig.span.with_lo(header_lo).with_hi(ident_span.lo()))?;
+    let header_lo = header_lo + BytePos((header.len() - header.trim_start().len()) as u32);
+    let header = header.trim_start().to_owned();
+    let params_and_ret = snippet(sig.span.with_lo(ident_span.hi()))?;
+    let abi_span = abi_span(sig.span.with_lo(header_lo), &header);
+
+    let mir = tcx.mir_built(WithOptConstParam::unknown(ldid));
+    let len_params = find_len_params(tcx, &mir.borrow(), &param_names)?;
+
+    Ok(FfiFnDef {
+        item,
+        sig,
+        param_names,
+        header,
+        params_and_ret,
+        abi_span,
+        len_params,
+    })
+}
+
+/// Find the `extern` keyword and ABI string in `header`, the source of the signature starting at
+/// `sig_span`, and return their span including the following whitespace.
+fn abi_span(sig_span: Span, header: &str) -> Option<Span> {
+    let start = header
+        .match_indices("extern")
+        .map(|(i, _)| i)
+        .find(|&i| i == 0 || header[..i].ends_with(char::is_whitespace))?;
+    let mut rest = header[start + "extern".len()..].trim_start();
+    if rest.starts_with('"') {
+        let close = rest[1..].find('"')?;
+        rest = rest[close + 2..].trim_start();
+    }
+    let end = header.len() - rest.len();
+    let lo = sig_span.lo() + BytePos(start as u32);
+    let hi = sig_span.lo() + BytePos(end as u32);
+    Some(sig_span.with_lo(lo).with_hi(hi))
+}
+
+/// For each pointer parameter of `mir` that's offset in the body, find the integer parameter that
+/// bounds it.  A parameter `n` bounds the pointer if every offset of the pointer is by a variable
+/// that the body compares `< n`, as in `while i < n { ... *p.offset(i as isize) ... }`, or by a
+/// value that's only ever copied from or decremented from such variables, like `j` in
+/// `j = i; while j > 0 { ... *p.offset(j - 1) ...; j -= 1 }`.  Returns an error if some pointer
+/// parameter is offset without such a bound, since the wrapper can't know how long a slice to
+/// build for it, unless the body reads it up to a NUL, as in `while *p.offset(i) != 0 { ... }`.
+/// The wrapper can find the length of such a string itself, if the analysis agrees that it's
+/// `NUL_TERMINATED`.
+fn find_len_params<'tcx>(
+    tcx: TyCtxt<'tcx>,
+    mir: &Body<'tcx>,
+    param_names: &[Symbol],
+) -> Result<Vec<Option<usize>>, String> {
+    // Each temporary that's assigned once, by copying or casting another local, is traced back to
+    // that local.
+    let mut num_assigns = IndexVec::<Local, usize>::from_elem_n(0, mir.local_decls.len());
+    let mut copied_from =
+        IndexVec::<Local, Option<Local>>::from_elem_n(None, mir.local_decls.len());
+    // Every assignment to a local, with its `Rvalue`, or `None` for the destination of a call.
+    let mut assigns = Vec::new();
+    let mut bounds = Vec::new();
+    let mut nul_checks = Vec::new();
+    let mut offsets = Vec::new();
+    for bb_data in mir.basic_blocks().iter() {
+        for stmt in &bb_data.statements {
+            let (pl, rv) = match stmt.kind {
+                StatementKind::Assign(ref x) => (x.0, &x.1),
+                _ => continue,
+            };
+            if let Some(local) = pl.as_local() {
+                num_assigns[local] += 1;
+                if let Rvalue::Use(ref op) | Rvalue::Cast(_, ref op, _) = *rv {
+                    copied_from[local] = op.place().and_then(|pl| pl.as_local());
+                }
+                assigns.push((local, Some(rv)));
+            }
+            if let Rvalue::BinaryOp(bin_op, ref ops) = *rv {
+                let (ref a, ref b) = **ops;
+                match bin_op {
+                    BinOp::Lt => bounds.push((a, b)),
+                    BinOp::Gt => bounds.push((b, a)),
+                    BinOp::Eq | BinOp::Ne => nul_checks.extend([(a, b), (b, a)]),
+                    _ => {}
+                }
+            }
+        }
+        if let TerminatorKind::Call {
+            ref func,
+            ref args,
+            destination,
+            ..
+        } = bb_data.terminator().kind
+        {
+            if let Some(local) = destination.as_local() {
+                num_assigns[local] += 1;
+                assigns.push((local, None));
+            }
+            if let Callee::PtrOffset { .. } = util::ty_callee(tcx, func.ty(mir, tcx)) {
+                offsets.push((&args[0], &args[1], destination.as_local()));
+            }
+        }
+    }
+    let resolve_local = |mut local: Local| -> Local {
+        for _ in 0..mir.local_decls.len() {
+            match copied_from[local] {
+                Some(src) if num_assigns[local] == 1 => local = src,
+                _ => break,
+            }
+        }
+        local
+    };
+    let resolve = |op: &Operand| -> Option<Local> { Some(resolve_local(op.place()?.as_local()?)) };
+    // For each assignment to a local, the local its new value is copied, cast, or decremented by a
+    // constant from, or `None` if it's computed some other way.
+    let is_const = |op: &Operand| match *op {
+        Operand::Constant(..) => true,
+        _ => op.place().and_then(|pl| pl.as_local()).map_or(false, |l| {
+            num_assigns[l] == 1
+                && assigns.iter().any(|&(al, rv)| {
+                    al == l && matches!(rv, Some(Rvalue::Use(Operand::Constant(..))))
+                })
+        }),
+    };
+    let mut derived_from =
+        IndexVec::<Local, Vec<Option<Local>>>::from_elem_n(Vec::new(), mir.local_decls.len());
+    for &(local, rv) in &assigns {
+        derived_from[local].push(rv.and_then(|rv| derived_source(rv, is_const)));
+    }
+
+    let bounds = bounds
+        .into_iter()
+        .filter_map(|(a, b)| Some((resolve(a)?, resolve(b)?)))
+        .collect::<Vec<_>>();
+
+    // Parameter `i` is local `i + 1`.
+    let is_int_param = |i: usize| mir.local_decls[Local::new(i + 1)].ty.is_integral();
+    // The locals whose values are all less than parameter `i`: those compared `< n`, plus the
+    // largest set of other locals that are only ever assigned from locals in the set.
+    let find_bounded_locals = |i: usize| -> HashSet<Local> {
+        let n = Local::new(i + 1);
+        let compared = bounds
+            .iter()
+            .filter(|&&(_, b)| b == n)
+            .map(|&(a, _)| a)
+            .collect::<HashSet<_>>();
+        let mut set = derived_from
+            .indices()
+            .filter(|&l| compared.contains(&l) || !derived_from[l].is_empty())
+            .collect::<HashSet<_>>();
+        loop {
+            let removed = set
+                .iter()
+                .copied()
+                .filter(|l| !compared.contains(l))
+                .filter(|&l| {
+                    !derived_from[l]
+                        .iter()
+                        .all(|src| src.map_or(false, |src| set.contains(&src)))
+                })
+                .collect::<Vec<_>>();
+            if removed.is_empty() {
+                return set;
+            }
+            for l in removed {
+                set.remove(&l);
+            }
+        }
+    };
+    let bounded_locals = (0..param_names.len())
+        .map(|i| {
+            if is_int_param(i) {
+                find_bounded_locals(i)
+            } else {
+                HashSet::new()
+            }
+        })
+        .collect::<Vec<_>>();
+
+    // The locals that a C character compared against zero was read through, traced back through
+    // copies and offsets.
+    let nul_checked = nul_checks
+        .into_iter()
+        .filter(|&(_, zero)| zero.constant().copied().map_or(false, util::is_null_const))
+        .filter_map(|(c, _)| {
+            let c = resolve(c)?;
+            if num_assigns[c] != 1
+                || !matches!(
+                    mir.local_decls[c].ty.kind(),
+                    ty::Int(ty::IntTy::I8) | ty::Uint(ty::UintTy::U8)
+                )
+            {
+                return None;
+            }
+            let pl = assigns.iter().find_map(|&(l, rv)| match rv {
+                Some(&Rvalue::Use(Operand::Copy(pl) | Operand::Move(pl))) if l == c => Some(pl),
+                _ => None,
+            })?;
+            let ptr = match pl.projection[..] {
+                [PlaceElem::Deref] => resolve_local(pl.local),
+                _ => return None,
+            };
+            let base = offsets.iter().find(|&&(_, _, dest)| dest == Some(ptr));
+            match base {
+                Some(&(base, _, _)) => resolve(base),
+                None => Some(ptr),
+            }
+        })
+        .collect::<HashSet<_>>();
+
+    let mut candidates = vec![None::<HashSet<usize>>; param_names.len()];
+    for (base, amount, _) in offsets {
+        let param = match resolve(base) {
+            Some(local) if (1..=param_names.len()).contains(&local.as_usize()) => {
+                local.as_usize() - 1
+            }
+            _ => continue,
+        };
+        let amount = amount.place().and_then(|pl| pl.as_local());
+        let bounded_by = (0..param_names.len())
+            .filter(|&i| amount.map_or(false, |a| bounded_locals[i].contains(&a)))
+            .collect::<HashSet<_>>();
+        let c = candidates[param].get_or_insert_with(|| bounded_by.clone());
+        c.retain(|i| bounded_by.contains(i));
+    }
+
+    candidates
+        .into_iter()
+        .enumerate()
+        .map(|(i, c)| match c {
+            None => Ok(None),
+            Some(c) if c.is_empty() && nul_checked.contains(&Local::new(i + 1)) => Ok(None),
+            Some(c) => match c.into_iter().min() {
+                Some(len) => Ok(Some(len)),
+                None => Err(format!(
+                    "argument `{}` is offset, but no parameter bounds the offsets",
+                    param_names[i]
+                )),
+            },
+        })
+        .collect()
+}
+
+/// Return the local that `rv` copies, casts, or decrements by a constant.  The result of a checked
+/// subtraction is a tuple whose first field is the difference, so a copy of a field of a local
+/// counts as a copy of the local.
+fn derived_source(rv: &Rvalue, is_const: impl Fn(&Operand) -> bool) -> Option<Local> {
+    match *rv {
+        Rvalue::Use(ref op) | Rvalue::Cast(_, ref op, _) => {
+            let pl = op.place()?;
+            match pl.projection[..] {
+                [] | [PlaceElem::Field(..)] => Some(pl.local),
+                _ => None,
+            }
+        }
+        Rvalue::BinaryOp(BinOp::Sub, ref ops) | Rvalue::CheckedBinaryOp(BinOp::Sub, ref ops) => {
+            let (ref a, ref b) = **ops;
+            if is_const(b) {
+                a.place()?.as_local()
+            } else {
+                None
+            }
+        }
+        _ => None,
+    }
+}
+
+/// Collects paths to local functions that are used as values rather than called directly.  Such
+/// a path is always coerced or cast to a function pointer.
+struct AddrTakenVisitor<'a, 'tcx> {
+    typeck: &'tcx TypeckResults<'tcx>,
+    /// `HirId`s of expressions in the callee position of a call.
+    callees: HashSet<HirId>,
+    found: &'a mut Vec<(LocalDefId, Span)>,
+}
+
+impl<'a, 'tcx> Visitor<'tcx> for AddrTakenVisitor<'a, 'tcx> {
+    fn visit_expr(&mut self, ex: &'tcx Expr<'tcx>) {
+        match ex.kind {
+            ExprKind::Call(func, _) => {
+                self.callees.insert(func.hir_id);
+            }
+            ExprKind::Path(QPath::Resolved(None, path)) if !self.callees.contains(&ex.hir_id) => {
+                if let ty::FnDef(did, _) = *self.typeck.expr_ty(ex).kind() {
+                    if let (Some(ldid), Some(seg)) = (did.as_local(), path.segments.last()) {
+                        self.found.push((ldid, seg.ident.span));
+                    }
+                }
+            }
+            _ => {}
+        }
+        intravisit::walk_expr(self, ex);
+    }
+}
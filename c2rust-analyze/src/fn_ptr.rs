@@ -0,0 +1,644 @@
+//! Flow-insensitive points-to analysis for function pointers.
+//!
+//! Translated C code often calls functions through pointers stored in locals, struct fields, and
+//! tables of callbacks.  `util::ty_callee` can only report these calls as `Callee::UnknownDef`, so
+//! we compute which local functions each indirect call may reach, and the dataflow analysis then
+//! treats the call like a direct call to each of them.
+//!
+//! This is a unification-based analysis over *slots* that can hold a function pointer: locals,
+//! struct fields, and, for every other place (array elements, places behind raw pointers, statics,
+//! and values exchanged with foreign code), one slot per function pointer type.  Each assignment
+//! between slots unifies them, and each conversion of a local function item to a function pointer
+//! adds that function to the targets of the destination slot.  `Option` is transparent, so an
+//! `Option<fn()>` is tracked like the `fn()` inside it.
+//!
+//! A slot may also hold functions we can't analyze: non-local functions like `libc::free`, and any
+//! function that foreign code stores into a pointer that escaped to it.  Such slots are marked as
+//! having an unknown target, and calls through them are treated like any other unknown call.
+//!
+//! The dataflow analysis unifies the pointers in each function pointer type with the signatures of
+//! the functions it may hold, so the type is rewritten along with them.  A function pointer that
+//! may hold a function we don't analyze keeps its raw pointers instead (see
+//! [`FnPtrTargets::fixed_ptrs`]), and so do the signatures of the local functions stored in it,
+//! unless an FFI wrapper with the original signature takes their place.  Calls through such a
+//! pointer keep passing raw pointers too (see [`find_unknown_call_ptrs`]).  Exported functions are
+//! always replaced by their wrappers when converted to function pointers, so a function pointer
+//! that may hold one is treated the same way.
+
+use crate::context::{AnalysisCtxt, Assignment, FlagSet, LTy, PointerId};
+use crate::dataflow::{Constraint, DataflowConstraints};
+use crate::ffi;
+use crate::util::{ty_callee, Callee};
+use rustc_hir::def::{CtorOf, DefKind, Res};
+use rustc_hir::def_id::{DefId, LocalDefId};
+use rustc_hir::intravisit::{self, Visitor};
+use rustc_hir::{Expr, ExprKind, QPath};
+use rustc_index::vec::Idx;
+use rustc_middle::mir::{
+    AggregateKind, Body, CastKind, Local, Location, Operand, Place, ProjectionElem, Rvalue,
+    StatementKind, TerminatorKind, RETURN_PLACE,
+};
+use rustc_middle::ty::adjustment::PointerCast;
+use rustc_middle::ty::{self, DefIdTree, Ty, TyCtxt, TypeckResults, WithOptConstParam};
+use rustc_span::sym;
+use std::collections::{HashMap, HashSet};
+
+/// A place that can hold a function pointer.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum Slot<'tcx> {
+    Local(LocalDefId, Local),
+    Field(DefId),
+    /// Any other place that holds a function pointer of this type.
+    Type(Ty<'tcx>),
+}
+
+struct FnPtrAnalysis<'tcx> {
+    tcx: TyCtxt<'tcx>,
+    /// Functions that can be targets of indirect calls: those in `all_fn_ldids`.
+    local_fns: HashSet<DefId>,
+    slots: HashMap<Slot<'tcx>, usize>,
+    /// Union-find parent of each slot.
+    parent: Vec<usize>,
+    /// Functions that may be stored in each slot.  Only meaningful for root slots.
+    targets: Vec<Vec<DefId>>,
+    /// Whether each slot may hold a function that isn't in `local_fns`.  Only meaningful for root
+    /// slots.
+    unknown: Vec<bool>,
+    /// Indirect call sites, with the slot of the called function pointer.
+    calls: Vec<(LocalDefId, Location, usize)>,
+}
+
+impl<'tcx> FnPtrAnalysis<'tcx> {
+    fn slot(&mut self, slot: Slot<'tcx>) -> usize {
+        let next = self.parent.len();
+        let id = *self.slots.entry(slot).or_insert(next);
+        if id == next {
+            self.parent.push(id);
+            self.targets.push(Vec::new());
+            self.unknown.push(false);
+        }
+        id
+    }
+
+    fn find(&mut self, id: usize) -> usize {
+        let parent = self.parent[id];
+        if parent == id {
+            return id;
+        }
+        let root = self.find(parent);
+        self.parent[id] = root;
+        root
+    }
+
+    fn unify(&mut self, a: Slot<'tcx>, b: Slot<'tcx>) {
+        let a = self.slot(a);
+        let b = self.slot(b);
+        let (a, b) = (self.find(a), self.find(b));
+        if a != b {
+            self.parent[b] = a;
+            let targets = std::mem::take(&mut self.targets[b]);
+            self.targets[a].extend(targets);
+            self.unknown[a] |= self.unknown[b];
+        }
+    }
+
+    /// Record that `slot` may hold the function `did`, which is an unknown target if it's not one
+    /// of `local_fns`.
+    fn add_target(&mut self, slot: Slot<'tcx>, did: DefId) {
+        let id = self.slot(slot);
+        let root = self.find(id);
+        if self.local_fns.contains(&did) {
+            self.targets[root].push(did);
+        } else {
+            self.unknown[root] = true;
+        }
+    }
+
+    /// The slot for `place`, or `None` if it doesn't hold a function pointer.
+    fn place_slot(
+        &self,
+        ldid: LocalDefId,
+        mir: &Body<'tcx>,
+        place: Place<'tcx>,
+    ) -> Option<Slot<'tcx>> {
+        let tcx = self.tcx;
+        let fn_ty = fn_ptr_ty(tcx, place.ty(mir, tcx).ty)?;
+        let mut slot = Slot::Local(ldid, place.local);
+        for (base, elem) in place.iter_projections() {
+            slot = match elem {
+                ProjectionElem::Downcast(..) => slot,
+                ProjectionElem::Field(field, _) => match *base.ty(mir, tcx).ty.kind() {
+                    ty::Adt(adt, _) if is_option(tcx, adt.did()) => slot,
+                    ty::Adt(adt, _) if adt.is_struct() || adt.is_union() => {
+                        Slot::Field(adt.non_enum_variant().fields[field.index()].did)
+                    }
+                    _ => Slot::Type(fn_ty),
+                },
+                _ => Slot::Type(fn_ty),
+            };
+        }
+        Some(slot)
+    }
+
+    fn operand_slot(
+        &self,
+        ldid: LocalDefId,
+        mir: &Body<'tcx>,
+        op: &Operand<'tcx>,
+    ) -> Option<Slot<'tcx>> {
+        self.place_slot(ldid, mir, op.place()?)
+    }
+
+    /// The function named by `op`, if any.
+    fn fn_item(&self, mir: &Body<'tcx>, op: &Operand<'tcx>) -> Option<DefId> {
+        match *op.ty(mir, self.tcx).kind() {
+            ty::FnDef(did, _) => Some(did),
+            _ => None,
+        }
+    }
+
+    /// Unify `slot` with the slot for all function pointers of its type, because the value
+    /// escapes to or comes from code we don't analyze.  That code may store any function there.
+    fn escape(&mut self, slot: Option<Slot<'tcx>>, ty: Ty<'tcx>) {
+        if let (Some(slot), Some(fn_ty)) = (slot, fn_ptr_ty(self.tcx, ty)) {
+            self.unify(slot, Slot::Type(fn_ty));
+            let id = self.slot(slot);
+            let root = self.find(id);
+            self.unknown[root] = true;
+        }
+    }
+
+    fn visit_body(&mut self, ldid: LocalDefId, mir: &Body<'tcx>) {
+        let tcx = self.tcx;
+        for (bb, bb_data) in mir.basic_blocks().iter_enumerated() {
+            for stmt in &bb_data.statements {
+                let (pl, rv) = match stmt.kind {
+                    StatementKind::Assign(ref x) => (x.0, &x.1),
+                    _ => continue,
+                };
+                match *rv {
+                    Rvalue::Cast(CastKind::Pointer(PointerCast::ReifyFnPointer), ref op, _) => {
+                        if let (Some(dest), Some(did)) =
+                            (self.place_slot(ldid, mir, pl), self.fn_item(mir, op))
+                        {
+                            self.add_target(dest, did);
+                        }
+                    }
+                    Rvalue::Use(ref op) | Rvalue::Cast(_, ref op, _) => {
+                        if let (Some(dest), Some(src)) = (
+                            self.place_slot(ldid, mir, pl),
+                            self.operand_slot(ldid, mir, op),
+                        ) {
+                            self.unify(dest, src);
+                        }
+                    }
+                    Rvalue::Aggregate(ref kind, ref ops) => {
+                        for (i, op) in ops.iter().enumerate() {
+                            let src = match self.operand_slot(ldid, mir, op) {
+                                Some(x) => x,
+                                None => continue,
+                            };
+                            let dest = match **kind {
+                                AggregateKind::Adt(did, _, _, _, _) if is_option(tcx, did) => {
+                                    match self.place_slot(ldid, mir, pl) {
+                                        Some(x) => x,
+                                        None => continue,
+                                    }
+                                }
+                                AggregateKind::Adt(did, variant, _, _, active_field) => {
+                                    let adt = tcx.adt_def(did);
+                                    if adt.is_enum() {
+                                        self.escape(Some(src), op.ty(mir, tcx));
+                                        continue;
+                                    }
+                                    let field = active_field.unwrap_or(i);
+                                    Slot::Field(adt.variant(variant).fields[field].did)
+                                }
+                                _ => {
+                                    self.escape(Some(src), op.ty(mir, tcx));
+                                    continue;
+                                }
+                            };
+                            self.unify(dest, src);
+                        }
+                    }
+                    _ => {}
+                }
+            }
+
+            let term = bb_data.terminator();
+            let (func, args, destination) = match term.kind {
+                TerminatorKind::Call {
+                    ref func,
+                    ref args,
+                    destination,
+                    ..
+                } => (func, args, destination),
+                _ => continue,
+            };
+            let func_ty = func.ty(mir, tcx);
+            let dest_slot = self.place_slot(ldid, mir, destination);
+            if is_option_method(tcx, func_ty) {
+                // `Option::unwrap`, `Option::expect`, and so on pass the function pointer through.
+                let src_slot = args.get(0).and_then(|op| self.operand_slot(ldid, mir, op));
+                if let (Some(dest), Some(src)) = (dest_slot, src_slot) {
+                    self.unify(dest, src);
+                }
+                continue;
+            }
+            match ty_callee(tcx, func_ty) {
+                Callee::LocalDef { def_id, .. } if self.local_fns.contains(&def_id) => {
+                    let callee = def_id.expect_local();
+                    let num_inputs = tcx.fn_sig(def_id).skip_binder().inputs().len();
+                    for (i, op) in args.iter().enumerate().take(num_inputs) {
+                        if let Some(src) = self.operand_slot(ldid, mir, op) {
+                            self.unify(src, Slot::Local(callee, Local::new(i + 1)));
+                        }
+                    }
+                    if let Some(dest) = dest_slot {
+                        self.unify(dest, Slot::Local(callee, RETURN_PLACE));
+                    }
+                }
+                callee => {
+                    // Foreign functions and indirect calls may store away or return any function
+                    // pointer.
+                    for op in args {
+                        self.escape(self.operand_slot(ldid, mir, op), op.ty(mir, tcx));
+                    }
+                    self.escape(dest_slot, destination.ty(mir, tcx).ty);
+
+                    if matches!(callee, Callee::UnknownDef { .. })
+                        && matches!(func_ty.kind(), ty::FnPtr(..))
+                    {
+                        if let Some(slot) = self.operand_slot(ldid, mir, func) {
+                            let id = self.slot(slot);
+                            let loc = Location {
+                                block: bb,
+                                statement_index: bb_data.statements.len(),
+                            };
+                            self.calls.push((ldid, loc, id));
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Collects local functions converted to function pointers in the initializers of statics and
+/// consts, which have no MIR we can safely inspect.
+struct InitVisitor<'a, 'tcx> {
+    analysis: &'a mut FnPtrAnalysis<'tcx>,
+    typeck: &'tcx TypeckResults<'tcx>,
+}
+
+impl<'a, 'tcx> InitVisitor<'a, 'tcx> {
+    /// If `ex` names a function, return the function and the function pointer type it's converted
+    /// to.
+    fn reified_fn(&self, ex: &'tcx Expr<'tcx>) -> Option<(DefId, Ty<'tcx>)> {
+        let tcx = self.analysis.tcx;
+        let did = match *self.typeck.expr_ty(ex).kind() {
+            ty::FnDef(did, _) => did,
+            _ => return None,
+        };
+        let fn_ty = fn_ptr_ty(tcx, self.typeck.expr_ty_adjusted(ex))
+            .unwrap_or_else(|| tcx.erase_regions(tcx.mk_fn_ptr(tcx.fn_sig(did))));
+        Some((did, fn_ty))
+    }
+
+    /// Visit `ex`, whose value is stored in `slot`.
+    fn visit_value(&mut self, ex: &'tcx Expr<'tcx>, slot: Slot<'tcx>) {
+        match ex.kind {
+            ExprKind::Call(func, [arg]) if self.is_some_ctor(func) => self.visit_value(arg, slot),
+            ExprKind::Cast(inner, _) => self.visit_value(inner, slot),
+            _ => match self.reified_fn(ex) {
+                Some((did, _)) => self.analysis.add_target(slot, did),
+                None => self.visit_expr(ex),
+            },
+        }
+    }
+
+    fn is_some_ctor(&self, func: &Expr) -> bool {
+        let tcx = self.analysis.tcx;
+        match func.kind {
+            ExprKind::Path(QPath::Resolved(_, path)) => match path.res {
+                Res::Def(DefKind::Ctor(CtorOf::Variant, _), ctor_did) => {
+                    tcx.lang_items().option_some_variant() == Some(tcx.parent(ctor_did))
+                }
+                _ => false,
+            },
+            _ => false,
+        }
+    }
+}
+
+impl<'a, 'tcx> Visitor<'tcx> for InitVisitor<'a, 'tcx> {
+    fn visit_expr(&mut self, ex: &'tcx Expr<'tcx>) {
+        match ex.kind {
+            ExprKind::Struct(_, fields, base) => {
+                if let ty::Adt(adt, _) = *self.typeck.expr_ty(ex).kind() {
+                    if !adt.is_enum() {
+                        let variant = adt.non_enum_variant();
+                        for field in fields {
+                            let idx = self.typeck.field_indices()[field.hir_id];
+                            self.visit_value(field.expr, Slot::Field(variant.fields[idx].did));
+                        }
+                        if let Some(base) = base {
+                            self.visit_expr(base);
+                        }
+                        return;
+                    }
+                }
+            }
+            ExprKind::Call(func, args) => {
+                // Don't mistake the callee for a function pointer value.
+                if !matches!(func.kind, ExprKind::Path(..)) {
+                    self.visit_expr(func);
+                }
+                for arg in args {
+                    self.visit_expr(arg);
+                }
+                return;
+            }
+            _ => {
+                if let Some((did, fn_ty)) = self.reified_fn(ex) {
+                    self.analysis.add_target(Slot::Type(fn_ty), did);
+                    return;
+                }
+            }
+        }
+        intravisit::walk_expr(self, ex);
+    }
+}
+
+/// The function pointer type held by a value of type `ty`, looking through `Option`.
+fn fn_ptr_ty<'tcx>(tcx: TyCtxt<'tcx>, ty: Ty<'tcx>) -> Option<Ty<'tcx>> {
+    match *ty.kind() {
+        ty::FnPtr(..) => Some(tcx.erase_regions(ty)),
+        ty::Adt(adt, substs) if is_option(tcx, adt.did()) => fn_ptr_ty(tcx, substs.type_at(0)),
+        _ => None,
+    }
+}
+
+fn is_option(tcx: TyCtxt, did: DefId) -> bool {
+    tcx.is_diagnostic_item(sym::Option, did)
+}
+
+/// Whether `func_ty` is a method of `Option`, like `unwrap`, which may pass a function pointer
+/// through.
+pub fn is_option_method<'tcx>(tcx: TyCtxt<'tcx>, func_ty: Ty<'tcx>) -> bool {
+    let did = match *func_ty.kind() {
+        ty::FnDef(did, _) => did,
+        _ => return false,
+    };
+    tcx.impl_of_method(did).map_or(false, |impl_did| {
+        matches!(*tcx.type_of(impl_did).kind(), ty::Adt(adt, _) if is_option(tcx, adt.did()))
+    })
+}
+
+/// The results of the function pointer analysis.
+pub struct FnPtrTargets<'tcx> {
+    tcx: TyCtxt<'tcx>,
+    /// The local functions each indirect call may reach, in the order of `all_fn_ldids`, or `None`
+    /// if it may also reach a function we don't analyze.  Keyed by the function containing the call
+    /// and the location of its terminator.  Calls through a function pointer that's never assigned
+    /// any function are mapped to an empty list.
+    pub calls: HashMap<(LocalDefId, Location), Option<Vec<DefId>>>,
+    /// The local functions that may be stored in a function pointer along with a function we don't
+    /// analyze, in the order of `all_fn_ldids`.  These must keep their signatures, or be replaced
+    /// by an FFI wrapper that does.
+    pub escaping: Vec<LocalDefId>,
+    /// Slots that hold only local functions that don't escape, and at least one of them.  The
+    /// function pointer types of all other slots are never rewritten.
+    closed: HashSet<Slot<'tcx>>,
+}
+
+impl<'tcx> FnPtrTargets<'tcx> {
+    /// Call `f` on each pointer in a function pointer type within `lty` that must keep its raw
+    /// pointer type.  `slot` is the slot of a value of type `lty`, or `None` for places that aren't
+    /// locals or fields.
+    pub fn fixed_ptrs(
+        &self,
+        lty: LTy<'tcx>,
+        slot: Option<Slot<'tcx>>,
+        f: &mut impl FnMut(PointerId),
+    ) {
+        match *lty.ty.kind() {
+            ty::FnPtr(..) => {
+                let slot = slot.unwrap_or_else(|| Slot::Type(self.tcx.erase_regions(lty.ty)));
+                if !self.closed.contains(&slot) {
+                    lty.for_each_label(&mut |ptr| {
+                        if !ptr.is_none() {
+                            f(ptr);
+                        }
+                    });
+                    return;
+                }
+                for &arg in lty.args {
+                    self.fixed_ptrs(arg, None, f);
+                }
+            }
+            ty::Adt(adt, _) if is_option(self.tcx, adt.did()) => {
+                self.fixed_ptrs(lty.args[0], slot, f)
+            }
+            _ => {
+                for &arg in lty.args {
+                    self.fixed_ptrs(arg, None, f);
+                }
+            }
+        }
+    }
+}
+
+/// Compute the possible targets of each indirect call in the functions `all_fn_ldids`, and which
+/// function pointer types can be rewritten.
+pub fn indirect_call_targets<'tcx>(
+    tcx: TyCtxt<'tcx>,
+    all_fn_ldids: &[LocalDefId],
+) -> FnPtrTargets<'tcx> {
+    let mut analysis = FnPtrAnalysis {
+        tcx,
+        local_fns: all_fn_ldids.iter().map(|ldid| ldid.to_def_id()).collect(),
+        slots: HashMap::new(),
+        parent: Vec::new(),
+        targets: Vec::new(),
+        unknown: Vec::new(),
+        calls: Vec::new(),
+    };
+
+    for &ldid in all_fn_ldids {
+        let mir = tcx.mir_built(WithOptConstParam::unknown(ldid));
+        let mir = mir.borrow();
+        analysis.visit_body(ldid, &mir);
+    }
+
+    for ldid in tcx.hir().body_owners() {
+        if analysis.local_fns.contains(&ldid.to_def_id()) {
+            continue;
+        }
+        let body = tcx.hir().body(tcx.hir().body_owned_by(ldid));
+        let mut v = InitVisitor {
+            analysis: &mut analysis,
+            typeck: tcx.typeck(ldid),
+        };
+        v.visit_body(body);
+    }
+
+    // A function called through a pointer may receive function pointers from, or return them to,
+    // code that only knows the function pointer type.
+    let address_taken = analysis
+        .targets
+        .iter()
+        .flatten()
+        .copied()
+        .collect::<HashSet<_>>();
+    for &ldid in all_fn_ldids {
+        let did = ldid.to_def_id();
+        if !address_taken.contains(&did) {
+            continue;
+        }
+        let sig = tcx.fn_sig(did).skip_binder();
+        for (i, &ty) in sig.inputs().iter().enumerate() {
+            analysis.escape(Some(Slot::Local(ldid, Local::new(i + 1))), ty);
+        }
+        analysis.escape(Some(Slot::Local(ldid, RETURN_PLACE)), sig.output());
+    }
+
+    // A function escapes if it's exported or stored along with a function we don't analyze, and
+    // then every slot that may hold it has a target we don't analyze, too.
+    let mut escaping = address_taken
+        .iter()
+        .copied()
+        .filter(|&did| ffi::export_symbol(tcx, did).is_some())
+        .collect::<HashSet<_>>();
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for id in 0..analysis.parent.len() {
+            if analysis.find(id) != id {
+                continue;
+            }
+            if analysis.unknown[id] {
+                for &did in &analysis.targets[id] {
+                    changed |= escaping.insert(did);
+                }
+            } else if analysis.targets[id]
+                .iter()
+                .any(|did| escaping.contains(did))
+            {
+                analysis.unknown[id] = true;
+                changed = true;
+            }
+        }
+    }
+
+    let calls = std::mem::take(&mut analysis.calls);
+    let calls = calls
+        .into_iter()
+        .map(|(ldid, loc, id)| {
+            let root = analysis.find(id);
+            if analysis.unknown[root] {
+                return ((ldid, loc), None);
+            }
+            let targets = &analysis.targets[root];
+            let targets = all_fn_ldids
+                .iter()
+                .map(|ldid| ldid.to_def_id())
+                .filter(|did| targets.contains(did))
+                .collect();
+            ((ldid, loc), Some(targets))
+        })
+        .collect();
+
+    let slots = analysis.slots.clone();
+    let closed = slots
+        .into_iter()
+        .filter(|&(_, id)| {
+            let root = analysis.find(id);
+            !analysis.unknown[root] && !analysis.targets[root].is_empty()
+        })
+        .map(|(slot, _)| slot)
+        .collect();
+
+    FnPtrTargets {
+        tcx,
+        calls,
+        escaping: all_fn_ldids
+            .iter()
+            .copied()
+            .filter(|ldid| escaping.contains(&ldid.to_def_id()))
+            .collect(),
+        closed,
+    }
+}
+
+/// The function pointer type held by a value of type `lty`, looking through `Option`.
+pub fn fn_ptr_lty<'tcx>(tcx: TyCtxt<'tcx>, lty: LTy<'tcx>) -> Option<LTy<'tcx>> {
+    match *lty.ty.kind() {
+        ty::FnPtr(..) => Some(lty),
+        ty::Adt(adt, _) if is_option(tcx, adt.did()) => fn_ptr_lty(tcx, lty.args[0]),
+        _ => None,
+    }
+}
+
+/// Find the pointers of `mir` that must keep their raw pointer types because they're passed to or
+/// returned from a call through a function pointer that may hold a function we don't analyze.
+/// This includes the pointers that the arguments are copied from, since a reference can't be cast
+/// back to a raw pointer.  Pointers that are already `FIXED` are skipped.
+pub fn find_unknown_call_ptrs<'tcx>(
+    acx: &AnalysisCtxt<'_, 'tcx>,
+    asn: &Assignment,
+    mir: &Body<'tcx>,
+    dataflow: &DataflowConstraints,
+) -> Vec<PointerId> {
+    let ldid = mir.source.def_id().expect_local();
+    let mut ptrs = HashSet::new();
+    for (bb, bb_data) in mir.basic_blocks().iter_enumerated() {
+        let (args, destination) = match bb_data.terminator().kind {
+            TerminatorKind::Call {
+                ref args,
+                destination,
+                ..
+            } => (args, destination),
+            _ => continue,
+        };
+        let loc = Location {
+            block: bb,
+            statement_index: bb_data.statements.len(),
+        };
+        if !matches!(acx.gacx.indirect_call_targets.get(&(ldid, loc)), Some(None)) {
+            continue;
+        }
+        let mut add = |ptr: PointerId| {
+            if !ptr.is_none() {
+                ptrs.insert(ptr);
+            }
+        };
+        for op in args {
+            acx.type_of(op).for_each_label(&mut add);
+        }
+        acx.type_of(destination).for_each_label(&mut add);
+    }
+
+    // Add the sources of the pinned pointers.  Pointers derived from them are pinned later by
+    // `linearity::spread_pins`.
+    let mut changed = !ptrs.is_empty();
+    while changed {
+        changed = false;
+        for (constraint, _) in dataflow.iter() {
+            if let Constraint::Subset(a, b) | Constraint::Offset(a, b) = *constraint {
+                if ptrs.contains(&a) {
+                    changed |= ptrs.insert(b);
+                }
+            }
+        }
+    }
+
+    let flags = asn.flags();
+    let mut ptrs = ptrs
+        .into_iter()
+        .filter(|&ptr| !flags[ptr].contains(FlagSet::FIXED))
+        .collect::<Vec<_>>();
+    ptrs.sort();
+    ptrs
+}
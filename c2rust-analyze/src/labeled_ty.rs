@@ -367,9 +367,17 @@ impl<'tcx, L: Copy> LabeledTyCtxt<'tcx, L> {
                 assert!(it.next().is_none());
                 self.tcx.mk_fn_def(def_id, substs)
             }
-            FnPtr(ref _sig) => {
-                // TODO: replace all the types under the binder
-                todo!()
+            FnPtr(sig) => {
+                let (&output, inputs) = args.split_last().unwrap();
+                self.tcx.mk_fn_ptr(sig.map_bound(|sig| {
+                    self.tcx.mk_fn_sig(
+                        inputs.iter().copied(),
+                        output,
+                        sig.c_variadic,
+                        sig.unsafety,
+                        sig.abi,
+                    )
+                }))
             }
             Tuple(_) => self.tcx.mk_tup(args.iter().cloned()),
 
@@ -0,0 +1,383 @@
+//! Checks on pointers inferred to be `LINEAR`, which are rewritten to `Box`es.
+//!
+//! Dataflow gives `LINEAR` (along with `FREE`) to every pointer that flows into `free` or
+//! `realloc`, but nothing there checks that these pointers are actually used linearly.  Once the
+//! permissions reach a fixpoint, [`check_linearity`] looks for `LINEAR` pointers that can't become
+//! `Box`es:
+//!
+//! * A pointer moved out of a place that isn't a plain local, like the `(*s).buf` in
+//!   `free((*s).buf as *mut c_void)`.  The `Box` would have to be moved out of a borrow.
+//! * A pointer moved out of a local that's used again before being reassigned, as in
+//!   `q = p; free(q); (*p).x`.  The `Box` would be used after it was moved.
+//! * The result of `malloc`, `calloc`, or `realloc` whose pointee type doesn't implement
+//!   `Default` (and `Clone`, for slices), which the safe allocation is built from.
+//!
+//! Moving a `LINEAR` pointer means copying it into another `LINEAR` location, or into a cast to
+//! `*mut c_void` that's passed to `free` or `realloc`.  Copying it anywhere else is a borrow.
+//!
+//! The pointers found here are pinned to their raw pointer types, together with the rest of their
+//! allocation group (see [`spread_pins`]), and the analysis is run to a fixpoint again.
+
+use crate::c_void_casts::CVoidCastDirection;
+use crate::context::{AnalysisCtxt, Assignment, FlagSet, PermissionSet, PointerId};
+use crate::dataflow::{Constraint, DataflowConstraints};
+use crate::pointer_id::PointerTable;
+use crate::type_desc::{self, Quantity};
+use crate::util::{ty_callee, Callee};
+use rustc_hir::def_id::{DefId, LocalDefId};
+use rustc_infer::infer::TyCtxtInferExt;
+use rustc_middle::mir::visit::{PlaceContext, Visitor};
+use rustc_middle::mir::{
+    BasicBlock, BasicBlockData, Body, Local, Location, Operand, Place, Rvalue, StatementKind,
+    TerminatorKind,
+};
+use rustc_middle::ty::{List, ParamEnv, Ty, TyCtxt};
+use rustc_span::sym;
+use rustc_trait_selection::infer::InferCtxtExt;
+use std::collections::{HashMap, HashSet};
+
+/// Find the `LINEAR` pointers of `mir` that can't be rewritten to `Box`es.
+pub fn check_linearity<'tcx>(
+    acx: &AnalysisCtxt<'_, 'tcx>,
+    asn: &Assignment,
+    mir: &Body<'tcx>,
+) -> Vec<PointerId> {
+    let tcx = acx.tcx();
+    let perms = asn.perms();
+    let flags = asn.flags();
+    let is_linear = |ptr: PointerId| {
+        !ptr.is_none()
+            && perms[ptr].contains(PermissionSet::LINEAR)
+            && !flags[ptr].contains(FlagSet::FIXED)
+    };
+
+    // Check a move out of `src` at `loc`, returning the moved pointer if it's not allowed.
+    let check_move = |src: Place<'tcx>, loc: Location| {
+        let ptr = acx.type_of(src).label;
+        let reason = if !src.projection.is_empty() {
+            "moved out of a place that isn't a local"
+        } else if used_after_move(mir, src.local, loc) {
+            "used after being moved"
+        } else {
+            return None;
+        };
+        eprintln!(
+            "{:?} can't be a Box: {:?} is {} at {:?}",
+            ptr, src, reason, loc
+        );
+        Some(ptr)
+    };
+
+    let mut bad = Vec::new();
+
+    for (bb, bb_data) in mir.basic_blocks().iter_enumerated() {
+        for (i, stmt) in bb_data.statements.iter().enumerate() {
+            let loc = Location {
+                block: bb,
+                statement_index: i,
+            };
+            let (pl, rv) = match stmt.kind {
+                StatementKind::Assign(ref x) => (x.0, &x.1),
+                _ => continue,
+            };
+            let (ops, into_linear) = match *rv {
+                Rvalue::Use(ref op) | Rvalue::Cast(_, ref op, _) => {
+                    let into_linear = is_linear(acx.type_of(pl).label)
+                        || acx
+                            .c_void_casts
+                            .direction(CVoidCastDirection::To)
+                            .contains_cast(loc);
+                    (std::slice::from_ref(op), into_linear)
+                }
+                // A pointer stored into a struct or array is moved into it.
+                Rvalue::Aggregate(_, ref ops) => (&ops[..], true),
+                _ => continue,
+            };
+            if !into_linear {
+                continue;
+            }
+            for src in ops.iter().filter_map(Operand::place) {
+                if is_linear(acx.type_of(src).label) {
+                    bad.extend(check_move(src, loc));
+                }
+            }
+        }
+
+        let loc = Location {
+            block: bb,
+            statement_index: bb_data.statements.len(),
+        };
+        let (func, args, destination) = match bb_data.terminator().kind {
+            TerminatorKind::Call {
+                ref func,
+                ref args,
+                destination,
+                ..
+            } => (func, args, destination),
+            _ => continue,
+        };
+        match ty_callee(tcx, func.ty(mir, tcx)) {
+            Callee::LocalDef { def_id, .. } => {
+                let lsig = match acx.gacx.fn_sigs.get(&def_id) {
+                    Some(x) => x,
+                    None => continue,
+                };
+                for (op, &param_lty) in args.iter().zip(lsig.inputs.iter()) {
+                    let src = match op.place() {
+                        Some(x) => x,
+                        None => continue,
+                    };
+                    if is_linear(param_lty.label) && is_linear(acx.type_of(src).label) {
+                        bad.extend(check_move(src, loc));
+                    }
+                }
+            }
+            callee @ (Callee::Malloc | Callee::Calloc | Callee::Realloc) => {
+                let out_ptr = acx.c_void_casts.get_adjusted_place_or_default_to(
+                    loc,
+                    CVoidCastDirection::From,
+                    destination,
+                );
+                let out_lty = acx.type_of(out_ptr);
+                if !is_linear(out_lty.label) || out_lty.args.is_empty() {
+                    continue;
+                }
+                let (_, qty) = type_desc::perms_to_desc(perms[out_lty.label], flags[out_lty.label]);
+                let single = qty == Quantity::Single;
+                if single && matches!(callee, Callee::Realloc) {
+                    // Resizing a single `Box` leaves it unchanged, so no new values are needed.
+                    continue;
+                }
+                let pointee_ty = out_lty.args[0].ty;
+                let param_env = tcx.param_env(mir.source.def_id());
+                let default_trait = tcx.get_diagnostic_item(sym::Default);
+                let clone_trait = tcx.lang_items().clone_trait();
+                let ok = implements_trait(tcx, param_env, pointee_ty, default_trait)
+                    && (single || implements_trait(tcx, param_env, pointee_ty, clone_trait));
+                if !ok {
+                    eprintln!(
+                        "{:?} can't be a Box: {:?} must implement Default{} at {:?}",
+                        out_lty.label,
+                        pointee_ty,
+                        if single { "" } else { " and Clone" },
+                        loc
+                    );
+                    bad.push(out_lty.label);
+                }
+            }
+            _ => {}
+        }
+    }
+    bad
+}
+
+/// Check whether `ty` implements the trait `trait_did`.
+fn implements_trait<'tcx>(
+    tcx: TyCtxt<'tcx>,
+    param_env: ParamEnv<'tcx>,
+    ty: Ty<'tcx>,
+    trait_did: Option<DefId>,
+) -> bool {
+    let trait_did = match trait_did {
+        Some(x) => x,
+        None => return false,
+    };
+    tcx.infer_ctxt().enter(|infcx| {
+        infcx
+            .type_implements_trait(trait_did, ty, List::empty(), param_env)
+            .must_apply_modulo_regions()
+    })
+}
+
+/// The outcome of scanning part of a basic block for uses of a local.
+enum Scan {
+    /// The local is used.
+    Used,
+    /// The local is reassigned or goes out of scope before any use.
+    Killed,
+    /// The local isn't mentioned, so the scan continues with the successors of the block.
+    NotMentioned,
+}
+
+/// Check whether `local` may be used after the pointer in it is moved out at `loc`, before it's
+/// reassigned.  Cleanup blocks are ignored.
+fn used_after_move(mir: &Body, local: Local, loc: Location) -> bool {
+    let mut seen = HashSet::new();
+    let mut work = Vec::new();
+    if loc.statement_index < mir[loc.block].statements.len() {
+        work.push(loc.successor_within_block());
+    } else {
+        push_successors(mir, loc.block, &mut seen, &mut work);
+    }
+
+    while let Some(start) = work.pop() {
+        match scan_block(local, &mir[start.block], start) {
+            Scan::Used => return true,
+            Scan::Killed => {}
+            Scan::NotMentioned => push_successors(mir, start.block, &mut seen, &mut work),
+        }
+    }
+    false
+}
+
+fn push_successors(
+    mir: &Body,
+    bb: BasicBlock,
+    seen: &mut HashSet<BasicBlock>,
+    work: &mut Vec<Location>,
+) {
+    for succ in mir[bb].terminator().successors() {
+        if !mir[succ].is_cleanup && seen.insert(succ) {
+            work.push(succ.start_location());
+        }
+    }
+}
+
+/// Scan `bb_data` for mentions of `local`, starting at the statement of `start`.
+fn scan_block(local: Local, bb_data: &BasicBlockData, start: Location) -> Scan {
+    let mut v = MentionsLocal {
+        local,
+        found: false,
+    };
+    for (i, stmt) in bb_data
+        .statements
+        .iter()
+        .enumerate()
+        .skip(start.statement_index)
+    {
+        let loc = Location {
+            block: start.block,
+            statement_index: i,
+        };
+        match stmt.kind {
+            StatementKind::StorageLive(l) | StatementKind::StorageDead(l) if l == local => {
+                return Scan::Killed;
+            }
+            // `FakeRead`s only exist for the benefit of borrowck.
+            StatementKind::FakeRead(..) => {}
+            StatementKind::Assign(ref x) if x.0.as_local() == Some(local) => {
+                v.visit_rvalue(&x.1, loc);
+                return if v.found { Scan::Used } else { Scan::Killed };
+            }
+            _ => {
+                v.visit_statement(stmt, loc);
+                if v.found {
+                    return Scan::Used;
+                }
+            }
+        }
+    }
+
+    let loc = Location {
+        block: start.block,
+        statement_index: bb_data.statements.len(),
+    };
+    let term = bb_data.terminator();
+    if let TerminatorKind::Call {
+        ref func,
+        ref args,
+        destination,
+        ..
+    } = term.kind
+    {
+        if destination.as_local() == Some(local) {
+            v.visit_operand(func, loc);
+            for arg in args {
+                v.visit_operand(arg, loc);
+            }
+            return if v.found { Scan::Used } else { Scan::Killed };
+        }
+    }
+    v.visit_terminator(term, loc);
+    if v.found {
+        Scan::Used
+    } else {
+        Scan::NotMentioned
+    }
+}
+
+struct MentionsLocal {
+    local: Local,
+    found: bool,
+}
+
+impl<'tcx> Visitor<'tcx> for MentionsLocal {
+    fn visit_local(&mut self, local: Local, _context: PlaceContext, _location: Location) {
+        if local == self.local {
+            self.found = true;
+        }
+    }
+}
+
+/// Pointers to be pinned to their original raw pointer types, because of a failed check in
+/// [`check_linearity`].
+#[derive(Default)]
+pub struct Pins {
+    global: HashSet<PointerId>,
+    local: HashMap<LocalDefId, HashSet<PointerId>>,
+}
+
+impl Pins {
+    pub fn is_empty(&self) -> bool {
+        self.global.is_empty() && self.local.values().all(|ptrs| ptrs.is_empty())
+    }
+
+    fn contains(&self, ldid: LocalDefId, ptr: PointerId) -> bool {
+        if ptr.is_global() {
+            self.global.contains(&ptr)
+        } else {
+            self.local
+                .get(&ldid)
+                .map_or(false, |ptrs| ptrs.contains(&ptr))
+        }
+    }
+
+    /// Add `ptr`, a pointer that appears in the function `ldid`.  Returns whether it was new.
+    pub fn insert(&mut self, ldid: LocalDefId, ptr: PointerId) -> bool {
+        if ptr.is_global() {
+            self.global.insert(ptr)
+        } else {
+            self.local.entry(ldid).or_default().insert(ptr)
+        }
+    }
+
+    /// Mark the pinned pointers of function `ldid` as `FIXED`, with no permissions.  Global
+    /// pointers are updated regardless of `ldid`.
+    pub fn apply(&self, ldid: LocalDefId, asn: &mut Assignment) {
+        let ptrs = self
+            .global
+            .iter()
+            .chain(self.local.get(&ldid).into_iter().flatten());
+        let (mut perms, mut flags) = asn.all_mut();
+        for &ptr in ptrs {
+            perms[ptr] = PermissionSet::empty();
+            flags[ptr].insert(FlagSet::FIXED);
+        }
+    }
+}
+
+/// Extend `pins` across the dataflow `constraints` of the function `ldid`, so each allocation
+/// group is pinned as a whole.  Pointers derived from a pinned pointer are pinned, since they
+/// can't safely borrow from a raw pointer.  The `LINEAR` sources of a pinned pointer are pinned
+/// too, since a `Box` can't be turned back into a raw pointer.  Returns whether anything was
+/// added.
+pub fn spread_pins(
+    constraints: &DataflowConstraints,
+    perms: &PointerTable<PermissionSet>,
+    ldid: LocalDefId,
+    pins: &mut Pins,
+) -> bool {
+    let mut changed = false;
+    for (constraint, _) in constraints.iter() {
+        let (a, b) = match *constraint {
+            Constraint::Subset(a, b) | Constraint::Offset(a, b) => (a, b),
+            _ => continue,
+        };
+        if pins.contains(ldid, b) {
+            changed |= pins.insert(ldid, a);
+        } else if pins.contains(ldid, a) && perms[b].contains(PermissionSet::LINEAR) {
+            changed |= pins.insert(ldid, b);
+        }
+    }
+    changed
+}
@@ -7,26 +7,33 @@ extern crate rustc_data_structures;
 extern crate rustc_driver;
 extern crate rustc_hir;
 extern crate rustc_index;
+extern crate rustc_infer;
 extern crate rustc_interface;
 extern crate rustc_middle;
 extern crate rustc_mir_build;
 extern crate rustc_session;
 extern crate rustc_span;
 extern crate rustc_target;
+extern crate rustc_trait_selection;
 extern crate rustc_type_ir;
 
-use crate::borrowck::{AdtMetadata, FieldMetadata, OriginArg, OriginParam};
+use crate::borrowck::{AdtMetadata, BorrowConflict, FieldMetadata, OriginArg, OriginParam};
 use crate::context::{
     AnalysisCtxt, AnalysisCtxtData, FlagSet, GlobalAnalysisCtxt, GlobalAssignment, LFnSig, LTy,
     LTyCtxt, LocalAssignment, PermissionSet, PointerId,
 };
 use crate::dataflow::DataflowConstraints;
 use crate::equiv::{GlobalEquivSet, LocalEquivSet};
+use crate::explain::{ExplainQuery, FnExplainData};
 use crate::labeled_ty::LabeledTyCtxt;
 use crate::log::init_logger;
+use crate::panic_detail::PanicDetail;
+use crate::pdg::PdgFacts;
+use crate::pointer_id::PointerTable;
+use crate::rewrite::OutputMode;
 use crate::util::Callee;
 use assert_matches::assert_matches;
-use indexmap::IndexSet;
+use indexmap::{IndexMap, IndexSet};
 use labeled_ty::LabeledTy;
 use rustc_ast::Mutability;
 use rustc_hir::def::DefKind;
@@ -35,7 +42,7 @@ use rustc_index::vec::IndexVec;
 use rustc_middle::mir::visit::Visitor;
 use rustc_middle::mir::{
     AggregateKind, BindingForm, Body, Constant, LocalDecl, LocalInfo, LocalKind, Location, Operand,
-    Rvalue, StatementKind,
+    Rvalue, StatementKind, TerminatorKind,
 };
 use rustc_middle::ty::tls;
 use rustc_middle::ty::{GenericArgKind, Ty, TyCtxt, TyKind, WithOptConstParam};
@@ -44,15 +51,23 @@ use rustc_type_ir::RegionKind::{ReEarlyBound, ReStatic};
 use std::collections::{HashMap, HashSet};
 use std::env;
 use std::fmt::Debug;
+use std::mem;
 use std::ops::{Deref, DerefMut};
+use std::path::Path;
 
 mod borrowck;
 mod c_void_casts;
 mod context;
 mod dataflow;
 mod equiv;
+mod explain;
+mod ffi;
+mod fn_ptr;
 mod labeled_ty;
+mod linearity;
 mod log;
+mod panic_detail;
+mod pdg;
 mod pointer_id;
 mod rewrite;
 mod trivial;
@@ -456,10 +471,21 @@ fn label_rvalue_tys<'tcx>(acx: &mut AnalysisCtxt<'_, 'tcx>, mir: &Body<'tcx>) {
     }
 }
 
-fn run(tcx: TyCtxt) {
+fn run(
+    tcx: TyCtxt,
+    pdg_facts: Option<&PdgFacts>,
+    explain_queries: &[ExplainQuery],
+    rewrite_mode: OutputMode,
+) {
     let mut gacx = GlobalAnalysisCtxt::new(tcx);
     let mut func_info = HashMap::new();
 
+    // Functions whose analysis panicked, along with the reason.  Each per-function step below is
+    // run under `panic_detail::catch_unwind`; when one fails, the function is recorded here and
+    // skipped by all later steps, and the pointers in its signature are marked `FIXED`.
+    let mut fns_failed: IndexMap<LocalDefId, PanicDetail> = IndexMap::new();
+    panic_detail::set_hook();
+
     /// Local information, specific to a single function.  Many of the data structures we use for
     /// the pointer analysis have a "global" part that's shared between all functions and a "local"
     /// part that's specific to the function being analyzed; this struct contains only the local
@@ -480,6 +506,9 @@ fn run(tcx: TyCtxt) {
         /// get a complete [`Assignment`] for this function, which maps every [`PointerId`] in this
         /// function to a [`PermissionSet`] and [`FlagSet`].
         lasn: MaybeUnset<LocalAssignment>,
+        /// Borrowck errors that caused `UNIQUE` to be removed from pointers in this function.
+        /// Used to answer `--explain` queries.
+        borrow_conflicts: Vec<BorrowConflict>,
     }
 
     // Follow a postorder traversal, so that callers are visited after their callees.  This means
@@ -490,6 +519,40 @@ fn run(tcx: TyCtxt) {
         eprintln!("  {:?}", ldid);
     }
 
+    // Find the possible targets of calls through function pointers, so constraint generation can
+    // treat them like direct calls.
+    let mut fn_ptrs = fn_ptr::indirect_call_targets(tcx, &all_fn_ldids);
+    gacx.indirect_call_targets = mem::take(&mut fn_ptrs.calls);
+    gacx.escaping_fns = fn_ptrs
+        .escaping
+        .iter()
+        .map(|ldid| ldid.to_def_id())
+        .collect();
+    eprintln!("indirect call targets:");
+    for &ldid in &all_fn_ldids {
+        let mut calls = gacx
+            .indirect_call_targets
+            .iter()
+            .filter(|&(&(caller, _), _)| caller == ldid)
+            .collect::<Vec<_>>();
+        calls.sort_by_key(|&(&(_, loc), _)| (loc.block, loc.statement_index));
+        for (&(_, loc), targets) in calls {
+            let name = tcx.item_name(ldid.to_def_id());
+            match targets {
+                Some(targets) => {
+                    let names = targets
+                        .iter()
+                        .map(|&did| tcx.item_name(did))
+                        .collect::<Vec<_>>();
+                    eprintln!("  {:?} {:?}: {:?}", name, loc, names);
+                }
+                None => eprintln!("  {:?} {:?}: unknown", name, loc),
+            }
+        }
+    }
+
+    let ffi_fns = ffi::find_ffi_fns(tcx, &all_fn_ldids, &fn_ptrs.escaping);
+
     // Assign global `PointerId`s for all pointers that appear in function signatures.
     for &ldid in &all_fn_ldids {
         let sig = tcx.fn_sig(ldid.to_def_id());
@@ -541,40 +604,51 @@ fn run(tcx: TyCtxt) {
         let mir = mir.borrow();
         let lsig = *gacx.fn_sigs.get(&ldid.to_def_id()).unwrap();
 
-        let mut acx = gacx.function_context(&mir);
-
-        // Assign PointerIds to local types
-        assert!(acx.local_tys.is_empty());
-        acx.local_tys = IndexVec::with_capacity(mir.local_decls.len());
-        for (local, decl) in mir.local_decls.iter_enumerated() {
-            let lty = match mir.local_kind(local) {
-                LocalKind::Var | LocalKind::Temp => acx.assign_pointer_ids(decl.ty),
-                LocalKind::Arg => {
-                    debug_assert!(local.as_usize() >= 1 && local.as_usize() <= mir.arg_count);
-                    lsig.inputs[local.as_usize() - 1]
-                }
-                LocalKind::ReturnPointer => lsig.output,
-            };
-            let l = acx.local_tys.push(lty);
-            assert_eq!(local, l);
+        let r = panic_detail::catch_unwind(|| {
+            let mut acx = gacx.function_context(&mir);
+
+            // Assign PointerIds to local types
+            assert!(acx.local_tys.is_empty());
+            acx.local_tys = IndexVec::with_capacity(mir.local_decls.len());
+            for (local, decl) in mir.local_decls.iter_enumerated() {
+                let lty = match mir.local_kind(local) {
+                    LocalKind::Var | LocalKind::Temp => acx.assign_pointer_ids(decl.ty),
+                    LocalKind::Arg => {
+                        debug_assert!(local.as_usize() >= 1 && local.as_usize() <= mir.arg_count);
+                        lsig.inputs[local.as_usize() - 1]
+                    }
+                    LocalKind::ReturnPointer => lsig.output,
+                };
+                let l = acx.local_tys.push(lty);
+                assert_eq!(local, l);
+
+                let ptr = acx.new_pointer();
+                let l = acx.addr_of_local.push(ptr);
+                assert_eq!(local, l);
+            }
 
-            let ptr = acx.new_pointer();
-            let l = acx.addr_of_local.push(ptr);
-            assert_eq!(local, l);
-        }
+            label_rvalue_tys(&mut acx, &mir);
 
-        label_rvalue_tys(&mut acx, &mir);
+            // Compute dataflow constraints and the constraints for local equivalence classes.
+            let (dataflow, equiv_constraints) = dataflow::generate_constraints(&acx, &mir);
+            (acx.into_data(), dataflow, equiv_constraints)
+        });
+        let (acx_data, dataflow, equiv_constraints) = match r {
+            Ok(x) => x,
+            Err(pd) => {
+                mark_fn_failed(tcx, &mut fns_failed, ldid, pd);
+                continue;
+            }
+        };
 
-        // Compute local equivalence classes and dataflow constraints.
-        let (dataflow, equiv_constraints) = dataflow::generate_constraints(&acx, &mir);
-        let mut local_equiv = LocalEquivSet::new(acx.num_pointers());
+        let mut local_equiv = LocalEquivSet::new(acx_data.num_pointers());
         let mut equiv = global_equiv.and_mut(&mut local_equiv);
         for (a, b) in equiv_constraints {
             equiv.unify(a, b);
         }
 
         let mut info = FuncInfo::default();
-        info.acx_data.set(acx.into_data());
+        info.acx_data.set(acx_data);
         info.dataflow.set(dataflow);
         info.local_equiv.set(local_equiv);
         func_info.insert(ldid, info);
@@ -587,7 +661,10 @@ fn run(tcx: TyCtxt) {
     gacx.remap_pointers(&global_equiv_map, global_counter);
 
     for &ldid in &all_fn_ldids {
-        let info = func_info.get_mut(&ldid).unwrap();
+        let info = match func_info.get_mut(&ldid) {
+            Some(x) => x,
+            None => continue,
+        };
         let (local_counter, local_equiv_map) = info.local_equiv.renumber(&global_equiv_map);
         eprintln!("local_equiv_map = {local_equiv_map:?}");
         info.acx_data.remap_pointers(
@@ -600,20 +677,151 @@ fn run(tcx: TyCtxt) {
         info.local_equiv.clear();
     }
 
-    // Compute permission and flag assignments.
+    // Compute permission and flag assignments.  `UNIQUE` and `NON_NULL` start out set on every
+    // pointer and are removed where they can't hold; all other permissions start out unset.  For
+    // `NON_NULL`, `dataflow::type_check` removes it from every source of a possibly-null pointer,
+    // so in the end only pointers derived from `&`, `&raw`, `offset`, and the like keep it.
 
-    let mut gasn =
-        GlobalAssignment::new(gacx.num_pointers(), PermissionSet::UNIQUE, FlagSet::empty());
+    let initial_perms = PermissionSet::UNIQUE | PermissionSet::NON_NULL;
+    let mut gasn = GlobalAssignment::new(gacx.num_pointers(), initial_perms, FlagSet::empty());
     for info in func_info.values_mut() {
         let num_pointers = info.acx_data.num_pointers();
-        let lasn = LocalAssignment::new(num_pointers, PermissionSet::UNIQUE, FlagSet::empty());
+        let lasn = LocalAssignment::new(num_pointers, initial_perms, FlagSet::empty());
         info.lasn.set(lasn);
     }
 
+    // Pin the signatures of functions that already failed during constraint generation.
+    for &ldid in fns_failed.keys() {
+        make_failed_fn_fixed(&gacx, &mut gasn, ldid);
+    }
+
+    // Struct fields declared through a type alias or similar can't be rewritten, so pin them.
+    rewrite::pin_unrewritable_field_tys(&gacx, &mut gasn);
+
+    // FFI functions get wrappers that convert only the outermost pointers in their signatures, so
+    // pin any nested pointers.  If no wrapper can be generated, pin the whole signature instead.
+    let mut ffi_defs = HashMap::new();
+    for &ldid in ffi_fns.keys() {
+        match ffi::ffi_fn_def(tcx, ldid) {
+            Ok(def) => {
+                make_sig_nested_fixed(&gacx, &mut gasn, ldid);
+                ffi_defs.insert(ldid, def);
+            }
+            Err(reason) => {
+                eprintln!(
+                    "keeping the C ABI signature of {:?}: {}",
+                    tcx.item_name(ldid.to_def_id()),
+                    reason
+                );
+                make_sig_fixed(&gacx, &mut gasn, ldid);
+            }
+        }
+    }
+
+    // A function pointer that may hold a function we don't analyze keeps its type, and so the local
+    // functions stored in it keep their signatures, unless an FFI wrapper takes their place.
+    for &ldid in &fn_ptrs.escaping {
+        if ffi_fns
+            .get(&ldid)
+            .map_or(false, |ffi_fn| !ffi_fn.addr_taken_spans.is_empty())
+        {
+            continue;
+        }
+        eprintln!(
+            "keeping the signature of {:?}: it's stored in a function pointer that may hold a \
+             function we don't analyze",
+            tcx.item_name(ldid.to_def_id())
+        );
+        make_sig_fixed(&gacx, &mut gasn, ldid);
+    }
+    // Pin the types of those function pointers themselves, wherever they appear.
+    for (&did, &lty) in &gacx.field_tys {
+        fn_ptrs.fixed_ptrs(lty, Some(fn_ptr::Slot::Field(did)), &mut |ptr| {
+            gasn.perms[ptr] = PermissionSet::empty();
+            gasn.flags[ptr].insert(FlagSet::FIXED);
+        });
+    }
+    for &lty in gacx.static_tys.values() {
+        fn_ptrs.fixed_ptrs(lty, None, &mut |ptr| {
+            gasn.perms[ptr] = PermissionSet::empty();
+            gasn.flags[ptr].insert(FlagSet::FIXED);
+        });
+    }
+    for &ldid in &all_fn_ldids {
+        let info = match func_info.get_mut(&ldid) {
+            Some(x) => x,
+            None => continue,
+        };
+        let mir = tcx.mir_built(WithOptConstParam::unknown(ldid));
+        let mir = mir.borrow();
+        let acx = gacx.function_context_with_data(&mir, info.acx_data.take());
+        let mut asn = gasn.and(&mut info.lasn);
+        let (mut perms, mut flags) = asn.all_mut();
+        for (local, &lty) in acx.local_tys.iter_enumerated() {
+            fn_ptrs.fixed_ptrs(lty, Some(fn_ptr::Slot::Local(ldid, local)), &mut |ptr| {
+                perms[ptr] = PermissionSet::empty();
+                flags[ptr].insert(FlagSet::FIXED);
+            });
+        }
+        info.acx_data.set(acx.into_data());
+    }
+
+    // Apply dynamic evidence from the PDG, if one was provided.  The refined permissions are
+    // seeded into the initial assignment and registered as overrides, so propagation keeps them
+    // refined and they also shape the permissions of the pointers they flow to and from.
+    if let Some(pdg_facts) = pdg_facts {
+        // Evidence is checked against the dataflow constraints of every function, so take them all
+        // out while it's applied.
+        let mut dataflow = all_fn_ldids
+            .iter()
+            .filter(|&ldid| !fns_failed.contains_key(ldid))
+            .map(|&ldid| (ldid, func_info.get_mut(&ldid).unwrap().dataflow.take()))
+            .collect::<IndexMap<_, _>>();
+
+        let mut global_overrides = Vec::new();
+        for &ldid in &all_fn_ldids {
+            if fns_failed.contains_key(&ldid) {
+                continue;
+            }
+            let info = func_info.get_mut(&ldid).unwrap();
+            let ldid_const = WithOptConstParam::unknown(ldid);
+            let name = tcx.item_name(ldid.to_def_id());
+            let mir = tcx.mir_built(ldid_const);
+            let mir = mir.borrow();
+            let acx = gacx.function_context_with_data(&mir, info.acx_data.take());
+            let mut asn = gasn.and(&mut info.lasn);
+
+            eprintln!("\ndynamic permission evidence for {:?}:", name);
+            let overrides =
+                pdg::perm_overrides(&acx, &asn, &mir, ldid, pdg_facts, &dataflow, describe_local);
+
+            info.acx_data.set(acx.into_data());
+
+            for (ptr, remove, add) in overrides {
+                let mut perms = asn.perms_mut();
+                perms[ptr] = (perms[ptr] & !remove) | add;
+                if ptr.is_global() {
+                    global_overrides.push((ptr, remove, add));
+                } else {
+                    dataflow[&ldid].add_override(ptr, remove, add);
+                }
+            }
+        }
+        // Global pointers are shared with every function, so their overrides apply everywhere.
+        for (ldid, mut fn_dataflow) in dataflow {
+            for &(ptr, remove, add) in &global_overrides {
+                fn_dataflow.add_override(ptr, remove, add);
+            }
+            func_info.get_mut(&ldid).unwrap().dataflow.set(fn_dataflow);
+        }
+    }
+
     let adt_metadata = construct_adt_metadata(tcx);
     eprintln!("=== ADT Metadata ===");
     eprintln!("{adt_metadata:?}");
 
+    // The rewrites generated for each function once the assignment is final.
+    let mut func_rewrites = HashMap::new();
     let mut loop_count = 0;
     loop {
         // Loop until the global assignment reaches a fixpoint.  The inner loop also runs until a
@@ -623,6 +831,9 @@ fn run(tcx: TyCtxt) {
         loop_count += 1;
         let old_gasn = gasn.clone();
         for &ldid in &all_fn_ldids {
+            if fns_failed.contains_key(&ldid) {
+                continue;
+            }
             let info = func_info.get_mut(&ldid).unwrap();
             let ldid_const = WithOptConstParam::unknown(ldid);
             let name = tcx.item_name(ldid.to_def_id());
@@ -633,29 +844,238 @@ fn run(tcx: TyCtxt) {
             let acx = gacx.function_context_with_data(&mir, info.acx_data.take());
             let mut asn = gasn.and(&mut info.lasn);
 
-            // `dataflow.propagate` and `borrowck_mir` both run until the assignment converges on a
-            // fixpoint, so there's no need to do multiple iterations here.
-            info.dataflow.propagate(&mut asn.perms_mut());
-
-            borrowck::borrowck_mir(
-                &acx,
-                &info.dataflow,
-                &mut asn.perms_mut(),
-                name.as_str(),
-                &mir,
-                &adt_metadata,
-                field_tys,
-            );
+            let r = panic_detail::catch_unwind(|| {
+                let (mut perms, flags) = asn.all_mut();
+                let flags = flags.borrow();
+
+                // `dataflow.propagate` and `borrowck_mir` both run until the assignment converges
+                // on a fixpoint, so there's no need to do multiple iterations here.
+                info.dataflow.propagate(&mut perms, &flags);
+
+                borrowck::borrowck_mir(
+                    &acx,
+                    &info.dataflow,
+                    &mut perms,
+                    &flags,
+                    name.as_str(),
+                    &mir,
+                    &adt_metadata,
+                    field_tys,
+                )
+            });
 
             info.acx_data.set(acx.into_data());
+
+            match r {
+                Ok(new_conflicts) => {
+                    // Rebuild the list rather than appending to it.  Polonius stops reporting a
+                    // conflict once the pointer has lost `UNIQUE`, so keep the earlier conflicts
+                    // that still explain a missing `UNIQUE`, unless this iteration found a newer
+                    // one for the same pointer.
+                    let perms = asn.perms();
+                    let mut conflicts = mem::take(&mut info.borrow_conflicts);
+                    conflicts.retain(|bc| {
+                        !perms[bc.ptr].contains(PermissionSet::UNIQUE)
+                            && !new_conflicts.iter().any(|nc| nc.ptr == bc.ptr)
+                    });
+                    conflicts.extend(new_conflicts);
+                    info.borrow_conflicts = conflicts;
+                }
+                Err(pd) => {
+                    mark_fn_failed(tcx, &mut fns_failed, ldid, pd);
+                    make_failed_fn_fixed(&gacx, &mut gasn, ldid);
+                }
+            }
         }
 
-        if gasn == old_gasn {
-            break;
+        if gasn != old_gasn {
+            continue;
+        }
+
+        // The assignment has converged.  Check that the pointers that will become `Box`es can
+        // be used that way, that the wrappers of FFI functions can convert their arguments and
+        // return values, and that calls through function pointers that may hold a function we
+        // don't analyze still pass raw pointers.  If not, pin the offending pointers along with
+        // their whole allocation groups and run to a fixpoint again.
+        let mut pins = linearity::Pins::default();
+        for &ldid in &all_fn_ldids {
+            if fns_failed.contains_key(&ldid) {
+                continue;
+            }
+            let info = func_info.get_mut(&ldid).unwrap();
+            let ldid_const = WithOptConstParam::unknown(ldid);
+            let mir = tcx.mir_built(ldid_const);
+            let mir = mir.borrow();
+            let acx = gacx.function_context_with_data(&mir, info.acx_data.take());
+            let asn = gasn.and(&mut info.lasn);
+
+            let r = panic_detail::catch_unwind(|| {
+                let mut bad = linearity::check_linearity(&acx, &asn, &mir);
+                bad.extend(fn_ptr::find_unknown_call_ptrs(
+                    &acx,
+                    &asn,
+                    &mir,
+                    &info.dataflow,
+                ));
+                if let Some(def) = ffi_defs.get(&ldid) {
+                    for (ptr, reason) in rewrite::find_unconvertible_ptrs(&acx, &asn, ldid, def) {
+                        eprintln!(
+                            "keeping a raw pointer in the signature of {:?}: {}",
+                            tcx.item_name(ldid.to_def_id()),
+                            reason
+                        );
+                        bad.push(ptr);
+                    }
+                }
+                bad
+            });
+
+            info.acx_data.set(acx.into_data());
+
+            match r {
+                Ok(bad) => {
+                    for ptr in bad {
+                        pins.insert(ldid, ptr);
+                    }
+                }
+                Err(pd) => {
+                    mark_fn_failed(tcx, &mut fns_failed, ldid, pd);
+                    make_failed_fn_fixed(&gacx, &mut gasn, ldid);
+                }
+            }
+        }
+        if pins.is_empty() {
+            if gasn != old_gasn {
+                continue;
+            }
+
+            // The assignment is final unless generating the rewrites for some function fails.
+            // That function is left as it is, so pin it like any other failed function and run to
+            // a fixpoint again.
+            func_rewrites.clear();
+            let mut any_failed = false;
+            for &ldid in &all_fn_ldids {
+                if fns_failed.contains_key(&ldid) {
+                    continue;
+                }
+                let info = func_info.get_mut(&ldid).unwrap();
+                let ldid_const = WithOptConstParam::unknown(ldid);
+                let mir = tcx.mir_built(ldid_const);
+                let mir = mir.borrow();
+                let acx = gacx.function_context_with_data(&mir, info.acx_data.take());
+                let mut asn = gasn.and(&mut info.lasn);
+
+                let r = panic_detail::catch_unwind(|| {
+                    info.dataflow.propagate_cell(&mut asn);
+
+                    acx.check_string_literal_perms(&asn);
+
+                    let hir_body_id = tcx.hir().body_owned_by(ldid);
+                    let expr_rewrites = rewrite::gen_expr_rewrites(&acx, &asn, &mir, hir_body_id);
+                    let ty_rewrites = rewrite::gen_ty_rewrites(&acx, &asn, &mir, ldid);
+                    let ffi_rewrites = match (ffi_fns.get(&ldid), ffi_defs.get(&ldid)) {
+                        (Some(ffi_fn), Some(def)) => {
+                            rewrite::gen_ffi_wrapper_rewrites(&acx, &asn, ldid, ffi_fn, def)
+                        }
+                        _ => Vec::new(),
+                    };
+                    (expr_rewrites, ty_rewrites, ffi_rewrites)
+                });
+
+                info.acx_data.set(acx.into_data());
+
+                match r {
+                    Ok(x) => {
+                        func_rewrites.insert(ldid, x);
+                    }
+                    Err(pd) => {
+                        mark_fn_failed(tcx, &mut fns_failed, ldid, pd);
+                        make_failed_fn_fixed(&gacx, &mut gasn, ldid);
+                        any_failed = true;
+                    }
+                }
+            }
+            if !any_failed {
+                break;
+            }
+            continue;
+        }
+        loop {
+            let mut changed = false;
+            for &ldid in &all_fn_ldids {
+                if fns_failed.contains_key(&ldid) {
+                    continue;
+                }
+                let info = func_info.get_mut(&ldid).unwrap();
+                let asn = gasn.and(&mut info.lasn);
+                changed |= linearity::spread_pins(&info.dataflow, &asn.perms(), ldid, &mut pins);
+            }
+            if !changed {
+                break;
+            }
+        }
+        for &ldid in &all_fn_ldids {
+            if fns_failed.contains_key(&ldid) {
+                continue;
+            }
+            let info = func_info.get_mut(&ldid).unwrap();
+            pins.apply(ldid, &mut gasn.and(&mut info.lasn));
         }
     }
     eprintln!("reached fixpoint in {} iterations", loop_count);
 
+    // Answer `--explain` queries, now that all permissions are final.
+    for query in explain_queries {
+        let ldid = match all_fn_ldids
+            .iter()
+            .copied()
+            .find(|&ldid| tcx.item_name(ldid.to_def_id()).as_str() == query.fn_name)
+        {
+            Some(x) => x,
+            None => {
+                eprintln!("\nexplain {}: no function named {:?}", query, query.fn_name);
+                continue;
+            }
+        };
+        if let Some(detail) = fns_failed.get(&ldid) {
+            eprintln!("\nexplain {}: analysis failed: {}", query, detail.short());
+            continue;
+        }
+
+        let targets = {
+            let info = func_info.get_mut(&ldid).unwrap();
+            let mir = tcx.mir_built(WithOptConstParam::unknown(ldid));
+            let mir = mir.borrow();
+            let acx = gacx.function_context_with_data(&mir, info.acx_data.take());
+            let targets = query
+                .find_locals(tcx, &mir)
+                .into_iter()
+                .map(|local| {
+                    let desc = describe_local(tcx, &mir.local_decls[local]);
+                    (local, desc, acx.local_tys[local].label)
+                })
+                .collect::<Vec<_>>();
+            info.acx_data.set(acx.into_data());
+            targets
+        };
+
+        let fns = all_fn_ldids
+            .iter()
+            .filter(|&ldid| !fns_failed.contains_key(ldid))
+            .map(|ldid| {
+                let info = &func_info[ldid];
+                let data = FnExplainData {
+                    dataflow: &info.dataflow,
+                    borrow_conflicts: &info.borrow_conflicts,
+                    perms: PointerTable::new(&gasn.perms, &info.lasn.perms),
+                    flags: PointerTable::new(&gasn.flags, &info.lasn.flags),
+                };
+                (*ldid, data)
+            })
+            .collect::<IndexMap<_, _>>();
+        explain::explain(tcx, query, ldid, &targets, &fns);
+    }
+
     // Print results for each function in `all_fn_ldids`, going in declaration order.  Concretely,
     // we iterate over `body_owners()`, which is a superset of `all_fn_ldids`, and filter based on
     // membership in `func_info`, which contains an entry for each ID in `all_fn_ldids`.
@@ -666,72 +1086,79 @@ fn run(tcx: TyCtxt) {
             Some(x) => x,
             None => continue,
         };
+        if fns_failed.contains_key(&ldid) {
+            continue;
+        }
         let ldid_const = WithOptConstParam::unknown(ldid);
         let name = tcx.item_name(ldid.to_def_id());
         let mir = tcx.mir_built(ldid_const);
         let mir = mir.borrow();
         let acx = gacx.function_context_with_data(&mir, info.acx_data.take());
-        let mut asn = gasn.and(&mut info.lasn);
-        info.dataflow.propagate_cell(&mut asn);
-
-        acx.check_string_literal_perms(&asn);
-
-        // Print labeling and rewrites for the current function.
-
-        eprintln!("\nfinal labeling for {:?}:", name);
-        let lcx1 = crate::labeled_ty::LabeledTyCtxt::new(tcx);
-        let lcx2 = crate::labeled_ty::LabeledTyCtxt::new(tcx);
-        for (local, decl) in mir.local_decls.iter_enumerated() {
-            let addr_of1 = asn.perms()[acx.addr_of_local[local]];
-            let ty1 = lcx1.relabel(acx.local_tys[local], &mut |lty| {
-                if lty.label == PointerId::NONE {
-                    PermissionSet::empty()
-                } else {
-                    asn.perms()[lty.label]
-                }
-            });
-            eprintln!(
-                "{:?} ({}): addr_of = {:?}, type = {:?}",
-                local,
-                describe_local(tcx, decl),
-                addr_of1,
-                ty1,
-            );
-
-            let addr_of2 = asn.flags()[acx.addr_of_local[local]];
-            let ty2 = lcx2.relabel(acx.local_tys[local], &mut |lty| {
-                if lty.label == PointerId::NONE {
-                    FlagSet::empty()
-                } else {
-                    asn.flags()[lty.label]
-                }
-            });
-            eprintln!(
-                "{:?} ({}): addr_of flags = {:?}, type flags = {:?}",
-                local,
-                describe_local(tcx, decl),
-                addr_of2,
-                ty2,
-            );
+        let asn = gasn.and(&mut info.lasn);
+
+        let r = panic_detail::catch_unwind(|| {
+            // Print labeling and rewrites for the current function.
+
+            eprintln!("\nfinal labeling for {:?}:", name);
+            let lcx1 = crate::labeled_ty::LabeledTyCtxt::new(tcx);
+            let lcx2 = crate::labeled_ty::LabeledTyCtxt::new(tcx);
+            for (local, decl) in mir.local_decls.iter_enumerated() {
+                let addr_of1 = asn.perms()[acx.addr_of_local[local]];
+                let ty1 = lcx1.relabel(acx.local_tys[local], &mut |lty| {
+                    if lty.label == PointerId::NONE {
+                        PermissionSet::empty()
+                    } else {
+                        asn.perms()[lty.label]
+                    }
+                });
+                eprintln!(
+                    "{:?} ({}): addr_of = {:?}, type = {:?}",
+                    local,
+                    describe_local(tcx, decl),
+                    addr_of1,
+                    ty1,
+                );
+
+                let addr_of2 = asn.flags()[acx.addr_of_local[local]];
+                let ty2 = lcx2.relabel(acx.local_tys[local], &mut |lty| {
+                    if lty.label == PointerId::NONE {
+                        FlagSet::empty()
+                    } else {
+                        asn.flags()[lty.label]
+                    }
+                });
+                eprintln!(
+                    "{:?} ({}): addr_of flags = {:?}, type flags = {:?}",
+                    local,
+                    describe_local(tcx, decl),
+                    addr_of2,
+                    ty2,
+                );
+
+                let addr_of3 = acx.addr_of_local[local];
+                let ty3 = acx.local_tys[local];
+                eprintln!(
+                    "{:?} ({}): addr_of = {:?}, type = {:?}",
+                    local,
+                    describe_local(tcx, decl),
+                    addr_of3,
+                    ty3,
+                );
+            }
 
-            let addr_of3 = acx.addr_of_local[local];
-            let ty3 = acx.local_tys[local];
-            eprintln!(
-                "{:?} ({}): addr_of = {:?}, type = {:?}",
-                local,
-                describe_local(tcx, decl),
-                addr_of3,
-                ty3,
-            );
+            eprintln!("\ntype assignment for {:?}:", name);
+            rewrite::dump_rewritten_local_tys(&acx, &asn, &mir, describe_local);
+        });
+        info.acx_data.set(acx.into_data());
+        // The rewrites were already generated successfully, so a failure here only affects what
+        // gets printed.
+        if let Err(pd) = r {
+            eprintln!("failed to print the results for {:?}: {}", name, pd);
         }
 
-        eprintln!("\ntype assignment for {:?}:", name);
-        rewrite::dump_rewritten_local_tys(&acx, &asn, &mir, describe_local);
-
         eprintln!();
-        let hir_body_id = tcx.hir().body_owned_by(ldid);
-        let expr_rewrites = rewrite::gen_expr_rewrites(&acx, &asn, &mir, hir_body_id);
-        let ty_rewrites = rewrite::gen_ty_rewrites(&acx, &asn, &mir, ldid);
+        let (expr_rewrites, ty_rewrites, ffi_rewrites) = func_rewrites.remove(&ldid).unwrap();
+
         // Print rewrites
         eprintln!(
             "\ngenerated {} expr rewrites + {} ty rewrites for {:?}:",
@@ -744,6 +1171,18 @@ fn run(tcx: TyCtxt) {
         }
         all_rewrites.extend(expr_rewrites);
         all_rewrites.extend(ty_rewrites);
+
+        if !ffi_rewrites.is_empty() {
+            eprintln!(
+                "generated {} FFI wrapper rewrites for {:?}:",
+                ffi_rewrites.len(),
+                name
+            );
+            for &(span, ref rw) in &ffi_rewrites {
+                eprintln!("  {}: {}", describe_span(tcx, span), rw);
+            }
+        }
+        all_rewrites.extend(ffi_rewrites);
     }
 
     // Print results for `static` items.
@@ -762,8 +1201,110 @@ fn run(tcx: TyCtxt) {
     }
     all_rewrites.extend(static_rewrites);
 
+    let adt_rewrites = rewrite::gen_adt_ty_rewrites(&gacx, &gasn, &adt_metadata);
+    eprintln!("generated {} ADT type rewrites:", adt_rewrites.len());
+    for &(span, ref rw) in &adt_rewrites {
+        eprintln!("    {}: {}", describe_span(gacx.tcx, span), rw);
+    }
+    all_rewrites.extend(adt_rewrites);
+
     // Apply rewrite to all functions at once.
-    rewrite::apply_rewrites(tcx, all_rewrites);
+    rewrite::apply_rewrites(tcx, all_rewrites, rewrite_mode);
+
+    // Report functions that couldn't be analyzed.
+    eprintln!("\n{} functions failed analysis:", fns_failed.len());
+    for (&ldid, detail) in &fns_failed {
+        eprintln!(
+            "  {:?}: {}",
+            tcx.item_name(ldid.to_def_id()),
+            detail.short()
+        );
+    }
+}
+
+/// Record that analysis of `ldid` failed for the reason described in `detail`.
+fn mark_fn_failed(
+    tcx: TyCtxt,
+    fns_failed: &mut IndexMap<LocalDefId, PanicDetail>,
+    ldid: LocalDefId,
+    detail: PanicDetail,
+) {
+    eprintln!(
+        "analysis of {:?} failed: {}",
+        tcx.item_name(ldid.to_def_id()),
+        detail
+    );
+    fns_failed.insert(ldid, detail);
+}
+
+/// Mark all pointers in the signature of `ldid` as `FIXED`, with no permissions.  This pins them to
+/// their original raw pointer types and stops the analysis of other functions from relying on any
+/// particular use of those pointers inside `ldid`.
+fn make_sig_fixed(gacx: &GlobalAnalysisCtxt, gasn: &mut GlobalAssignment, ldid: LocalDefId) {
+    let lsig = &gacx.fn_sigs[&ldid.to_def_id()];
+    for &lty in lsig.inputs.iter().chain(std::iter::once(&lsig.output)) {
+        lty.for_each_label(&mut |ptr| {
+            if !ptr.is_none() {
+                gasn.perms[ptr] = PermissionSet::empty();
+                gasn.flags[ptr].insert(FlagSet::FIXED);
+            }
+        });
+    }
+}
+
+/// Pin the signature of the failed function `ldid` with `make_sig_fixed`, along with the signatures
+/// of the local functions it calls, directly or through a function pointer.  `ldid` won't be
+/// rewritten, so its call sites keep passing and receiving raw pointers.
+fn make_failed_fn_fixed(gacx: &GlobalAnalysisCtxt, gasn: &mut GlobalAssignment, ldid: LocalDefId) {
+    make_sig_fixed(gacx, gasn, ldid);
+
+    let tcx = gacx.tcx;
+    let mir = tcx.mir_built(WithOptConstParam::unknown(ldid));
+    let mir = mir.borrow();
+    for (bb, bb_data) in mir.basic_blocks().iter_enumerated() {
+        let func = match bb_data.terminator().kind {
+            TerminatorKind::Call { ref func, .. } => func,
+            _ => continue,
+        };
+        let callees = match util::ty_callee(tcx, func.ty(&*mir, tcx)) {
+            Callee::LocalDef { def_id, .. } => vec![def_id],
+            Callee::UnknownDef { .. } => {
+                let loc = Location {
+                    block: bb,
+                    statement_index: bb_data.statements.len(),
+                };
+                gacx.indirect_call_targets
+                    .get(&(ldid, loc))
+                    .cloned()
+                    .flatten()
+                    .unwrap_or_default()
+            }
+            _ => continue,
+        };
+        for did in callees {
+            if let Some(callee_ldid) = did.as_local() {
+                if gacx.fn_sigs.contains_key(&did) {
+                    make_sig_fixed(gacx, gasn, callee_ldid);
+                }
+            }
+        }
+    }
+}
+
+/// Mark the pointers nested inside the argument and return types of `ldid` as `FIXED`, with no
+/// permissions, leaving only the outermost pointer of each type free to be rewritten.
+fn make_sig_nested_fixed(gacx: &GlobalAnalysisCtxt, gasn: &mut GlobalAssignment, ldid: LocalDefId) {
+    let lsig = &gacx.fn_sigs[&ldid.to_def_id()];
+    for &lty in lsig.inputs.iter().chain(std::iter::once(&lsig.output)) {
+        for &arg in lty.args {
+            arg.for_each_label(&mut |ptr| {
+                if !ptr.is_none() {
+                    gasn.perms[ptr] = PermissionSet::empty();
+                    gasn.flags[ptr].insert(FlagSet::FIXED);
+                }
+            });
+        }
+    }
 }
 
 trait AssignPointerIds<'tcx> {
@@ -939,7 +1480,14 @@ fn for_each_callee(tcx: TyCtxt, ldid: LocalDefId, f: impl FnMut(LocalDefId)) {
     CalleeVisitor { tcx, mir, f }.visit_body(mir);
 }
 
-struct AnalysisCallbacks;
+struct AnalysisCallbacks {
+    /// Dynamic facts from a PDG, given with `--pdg <file>`.
+    pdg_facts: Option<PdgFacts>,
+    /// Queries given with `--explain <fn>:<local or line>`.
+    explain_queries: Vec<ExplainQuery>,
+    /// What to do with the rewritten code, given with `--rewrite-mode <mode>`.
+    rewrite_mode: OutputMode,
+}
 
 impl rustc_driver::Callbacks for AnalysisCallbacks {
     fn after_expansion<'tcx>(
@@ -948,7 +1496,12 @@ impl rustc_driver::Callbacks for AnalysisCallbacks {
         queries: &'tcx rustc_interface::Queries<'tcx>,
     ) -> rustc_driver::Compilation {
         queries.global_ctxt().unwrap().peek_mut().enter(|tcx| {
-            run(tcx);
+            run(
+                tcx,
+                self.pdg_facts.as_ref(),
+                &self.explain_queries,
+                self.rewrite_mode,
+            );
         });
         rustc_driver::Compilation::Continue
     }
@@ -956,6 +1509,52 @@ impl rustc_driver::Callbacks for AnalysisCallbacks {
 
 fn main() -> rustc_interface::interface::Result<()> {
     init_logger();
-    let args = env::args().collect::<Vec<_>>();
-    rustc_driver::RunCompiler::new(&args, &mut AnalysisCallbacks).run()
+    let mut args = env::args().collect::<Vec<_>>();
+    let pdg_facts = take_args(&mut args, "--pdg").pop().map(|path| {
+        PdgFacts::read(Path::new(&path))
+            .unwrap_or_else(|e| panic!("failed to read PDG facts from {:?}: {}", path, e))
+    });
+    let explain_queries = take_args(&mut args, "--explain")
+        .iter()
+        .map(|query| {
+            query
+                .parse::<ExplainQuery>()
+                .unwrap_or_else(|e| panic!("bad --explain query: {}", e))
+        })
+        .collect();
+    let rewrite_mode = match take_args(&mut args, "--rewrite-mode").pop() {
+        Some(mode) => mode
+            .parse()
+            .unwrap_or_else(|e| panic!("bad --rewrite-mode: {}", e)),
+        None => OutputMode::Print,
+    };
+    let mut callbacks = AnalysisCallbacks {
+        pdg_facts,
+        explain_queries,
+        rewrite_mode,
+    };
+    rustc_driver::RunCompiler::new(&args, &mut callbacks).run()
+}
+
+/// Remove each `<flag> <value>` or `<flag>=<value>` argument from `args`, which are otherwise passed
+/// through to rustc, and return the values.
+fn take_args(args: &mut Vec<String>, flag: &str) -> Vec<String> {
+    let mut values = Vec::new();
+    let mut i = 0;
+    while i < args.len() {
+        if args[i] == flag {
+            assert!(i + 1 < args.len(), "{} requires an argument", flag);
+            values.push(args.remove(i + 1));
+            args.remove(i);
+        } else if let Some(value) = args[i]
+            .strip_prefix(flag)
+            .and_then(|rest| rest.strip_prefix('='))
+        {
+            values.push(value.to_owned());
+            args.remove(i);
+        } else {
+            i += 1;
+        }
+    }
+    values
 }
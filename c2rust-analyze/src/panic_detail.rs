@@ -0,0 +1,85 @@
+//! Recovery from panics raised while analyzing a single function.
+//!
+//! Many parts of the analysis `panic!` or `todo!` on MIR constructs they don't support yet.
+//! Instead of letting one such function abort the analysis of the entire crate, we run each
+//! per-function step under [`catch_unwind`], record a [`PanicDetail`] describing the failure, and
+//! treat the function as unanalyzable.
+
+use std::any::Any;
+use std::cell::{Cell, RefCell};
+use std::fmt;
+use std::panic::{self, AssertUnwindSafe};
+
+/// A description of a panic caught by [`catch_unwind`].
+#[derive(Clone, Debug)]
+pub struct PanicDetail {
+    /// The panic message.
+    msg: String,
+    /// The source location where the panic was raised, if known.
+    loc: Option<String>,
+}
+
+impl PanicDetail {
+    /// A one-line description of the panic, for use in summaries.
+    pub fn short(&self) -> String {
+        let msg = self.msg.lines().next().unwrap_or("");
+        match self.loc {
+            Some(ref loc) => format!("{} (at {})", msg, loc),
+            None => msg.to_owned(),
+        }
+    }
+}
+
+impl fmt::Display for PanicDetail {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.msg)?;
+        if let Some(ref loc) = self.loc {
+            write!(f, " (at {})", loc)?;
+        }
+        Ok(())
+    }
+}
+
+thread_local! {
+    /// Set while running inside [`catch_unwind`], so the panic hook knows to record the panic
+    /// rather than passing it on to the previous hook.
+    static CATCHING: Cell<bool> = Cell::new(false);
+    /// The location of the last panic recorded by the hook installed in [`set_hook`].
+    static LAST_LOCATION: RefCell<Option<String>> = RefCell::new(None);
+}
+
+/// Install a panic hook that records the location of panics raised inside [`catch_unwind`].  Panics
+/// raised elsewhere are passed on to the previously installed hook.
+pub fn set_hook() {
+    let prev = panic::take_hook();
+    panic::set_hook(Box::new(move |info| {
+        if CATCHING.with(|c| c.get()) {
+            eprintln!("caught {}", info);
+            let loc = info.location().map(|loc| loc.to_string());
+            LAST_LOCATION.with(|l| *l.borrow_mut() = loc);
+        } else {
+            prev(info);
+        }
+    }));
+}
+
+/// Run `f`, converting any panic it raises into a [`PanicDetail`].
+pub fn catch_unwind<R>(f: impl FnOnce() -> R) -> Result<R, PanicDetail> {
+    let was_catching = CATCHING.with(|c| c.replace(true));
+    let r = panic::catch_unwind(AssertUnwindSafe(f));
+    CATCHING.with(|c| c.set(was_catching));
+    r.map_err(|payload| PanicDetail {
+        msg: payload_message(&*payload),
+        loc: LAST_LOCATION.with(|l| l.borrow_mut().take()),
+    })
+}
+
+fn payload_message(payload: &(dyn Any + Send)) -> String {
+    if let Some(&s) = payload.downcast_ref::<&str>() {
+        s.to_owned()
+    } else if let Some(s) = payload.downcast_ref::<String>() {
+        s.clone()
+    } else {
+        "(non-string panic payload)".to_owned()
+    }
+}
@@ -0,0 +1,254 @@
+//! Permission evidence from dynamic analysis.
+//!
+//! `c2rust-pdg --facts <file>` records what was observed about each node of the pointer derivation
+//! graph (PDG) built from a run of the instrumented program.  Each node is attributed to the MIR
+//! location and local it came from using the instrumentation metadata, and since the
+//! instrumentation runs on the same `mir_built` bodies we analyze, those locals line up with ours.
+//! Passing the file to `c2rust-analyze --pdg <file>` refines the statically inferred permissions of
+//! each pointer-typed local that was assigned during the run:
+//!
+//! * If no pointer derived from it was ever written through, it loses `WRITE`.
+//! * If no pointer derived from it was ever offset forward (backward), it loses `OFFSET_ADD`
+//!   (`OFFSET_SUB`).
+//! * If it was never null, it gains `NON_NULL`.
+//!
+//! The refined permissions are seeded into the initial assignment before the static analysis runs,
+//! and propagation keeps applying them, so they also shape the permissions of the pointers each
+//! refined local flows to and from.  These are observations of particular runs rather than proofs,
+//! so the evidence behind each refinement is reported.  Evidence that would remove a permission
+//! some statement statically requires is rejected, since it only means the run never reached that
+//! statement.
+
+use crate::context::{AnalysisCtxt, Assignment, FlagSet, PermissionSet, PointerId};
+use crate::dataflow::{Constraint, DataflowConstraints};
+use crate::explain;
+use indexmap::IndexMap;
+use rustc_hir::def_id::LocalDefId;
+use rustc_middle::mir::{Body, Local, LocalDecl, Location};
+use rustc_middle::ty::TyCtxt;
+use serde_json::Value;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// Facts observed about all the pointers assigned to one MIR local.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct LocalFacts {
+    /// Some pointer derived from one stored in the local was written through.
+    pub store: bool,
+    /// Some pointer derived from one stored in the local was offset forward.
+    pub pos_offset: bool,
+    /// Some pointer derived from one stored in the local was offset backward.
+    pub neg_offset: bool,
+    /// The local was assigned a null pointer.
+    pub null: bool,
+}
+
+impl LocalFacts {
+    fn merge(&mut self, other: LocalFacts) {
+        self.store |= other.store;
+        self.pos_offset |= other.pos_offset;
+        self.neg_offset |= other.neg_offset;
+        self.null |= other.null;
+    }
+
+    /// The permissions these facts rule out, and the ones they guarantee.
+    pub fn overrides(&self) -> (PermissionSet, PermissionSet) {
+        let mut remove = PermissionSet::empty();
+        if !self.store {
+            remove.insert(PermissionSet::WRITE);
+        }
+        if !self.pos_offset {
+            remove.insert(PermissionSet::OFFSET_ADD);
+        }
+        if !self.neg_offset {
+            remove.insert(PermissionSet::OFFSET_SUB);
+        }
+        let add = if self.null {
+            PermissionSet::empty()
+        } else {
+            PermissionSet::NON_NULL
+        };
+        (remove, add)
+    }
+}
+
+/// The dynamic facts for every MIR local that was assigned a pointer during the instrumented run.
+#[derive(Debug, Default)]
+pub struct PdgFacts {
+    /// Facts for each local, keyed by the `DefPathHash` of the function and the index of the
+    /// local within it.
+    locals: HashMap<((u64, u64), u32), LocalFacts>,
+}
+
+impl PdgFacts {
+    pub fn read(path: &Path) -> io::Result<PdgFacts> {
+        let json: Value = serde_json::from_slice(&fs::read(path)?)?;
+        Self::from_json(&json)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "malformed PDG facts"))
+    }
+
+    fn from_json(json: &Value) -> Option<PdgFacts> {
+        let mut facts = PdgFacts::default();
+        for node in json.get("nodes")?.as_array()? {
+            // Nodes that don't assign to a whole local can't be mapped to a `PointerId`.
+            let dest = match *node.get("dest")? {
+                Value::Null => continue,
+                ref v => u32::try_from(v.as_u64()?).ok()?,
+            };
+            let function = match *node.get("function")?.as_array()?.as_slice() {
+                [ref a, ref b] => (a.as_u64()?, b.as_u64()?),
+                _ => return None,
+            };
+            let flag = |name: &str| node.get(name)?.as_bool();
+            let local_facts = LocalFacts {
+                store: flag("store")?,
+                pos_offset: flag("pos_offset")?,
+                neg_offset: flag("neg_offset")?,
+                null: flag("null")?,
+            };
+            facts
+                .locals
+                .entry((function, dest))
+                .or_default()
+                .merge(local_facts);
+        }
+        Some(facts)
+    }
+
+    /// Get the facts observed for `local` in the function `ldid`, or `None` if `local` was never
+    /// assigned a pointer during the instrumented run.
+    pub fn local_facts(&self, tcx: TyCtxt, ldid: LocalDefId, local: Local) -> Option<LocalFacts> {
+        let hash = tcx.def_path_hash(ldid.to_def_id()).0.as_value();
+        self.locals.get(&(hash, local.as_u32())).copied()
+    }
+}
+
+/// Compute the permission overrides for the pointer-typed locals of `mir` from the dynamic
+/// `facts`, and print the evidence behind each one.  Each entry gives a pointer along with the
+/// permissions to remove from it and the ones to add.  `FIXED` pointers are left alone, and so are
+/// pointers where the evidence would remove a permission that some statement requires, as found
+/// in the `dataflow` constraints of every analyzed function.
+pub fn perm_overrides<'tcx>(
+    acx: &AnalysisCtxt<'_, 'tcx>,
+    asn: &Assignment,
+    mir: &Body<'tcx>,
+    ldid: LocalDefId,
+    facts: &PdgFacts,
+    dataflow: &IndexMap<LocalDefId, DataflowConstraints>,
+    mut describe_local: impl FnMut(TyCtxt<'tcx>, &LocalDecl) -> String,
+) -> Vec<(PointerId, PermissionSet, PermissionSet)> {
+    // Locals that share a `PointerId` must end up with the same permissions, so merge their facts
+    // first.
+    let mut ptr_facts = HashMap::<PointerId, LocalFacts>::new();
+    for local in mir.local_decls.indices() {
+        let ptr = acx.local_tys[local].label;
+        if ptr.is_none() || asn.flags()[ptr].contains(FlagSet::FIXED) {
+            continue;
+        }
+        if let Some(local_facts) = facts.local_facts(acx.tcx(), ldid, local) {
+            ptr_facts.entry(ptr).or_default().merge(local_facts);
+        }
+    }
+
+    // The run may not have reached every statement, so the evidence can't take away a permission
+    // that one of them requires.
+    let static_uses = ptr_facts
+        .iter()
+        .filter_map(|(&ptr, local_facts)| {
+            let (remove, _) = local_facts.overrides();
+            Some((ptr, find_static_use(dataflow, ldid, ptr, remove)?))
+        })
+        .collect::<HashMap<_, _>>();
+
+    for (local, decl) in mir.local_decls.iter_enumerated() {
+        let ptr = acx.local_tys[local].label;
+        let (remove, add) = match ptr_facts.get(&ptr) {
+            Some(local_facts) => local_facts.overrides(),
+            None => continue,
+        };
+        eprintln!(
+            "{:?} ({}): remove = {:?}, add = {:?}",
+            local,
+            describe_local(acx.tcx(), decl),
+            remove,
+            add,
+        );
+        if let Some(static_use) = static_uses.get(&ptr) {
+            eprintln!(
+                "  rejected: {:?} is needed ({})",
+                static_use.perms,
+                explain::describe_loc(acx.tcx(), ldid, static_use.ldid, static_use.loc),
+            );
+        }
+    }
+
+    ptr_facts
+        .into_iter()
+        .filter(|(ptr, _)| !static_uses.contains_key(ptr))
+        .map(|(ptr, local_facts)| {
+            let (remove, add) = local_facts.overrides();
+            (ptr, remove, add)
+        })
+        .collect()
+}
+
+/// A statement that statically requires a pointer to have some permissions.
+struct StaticUse {
+    /// The function containing the statement.
+    ldid: LocalDefId,
+    /// The location of the statement, if the requirement came from one.
+    loc: Option<Location>,
+    /// The required permissions.
+    perms: PermissionSet,
+}
+
+/// Find a statement that requires `ptr` in the function `ldid` to have some of `perms`, either
+/// directly or through a pointer that flows into `ptr`.  `dataflow` holds the constraints of each
+/// function that was analyzed.
+fn find_static_use(
+    dataflow: &IndexMap<LocalDefId, DataflowConstraints>,
+    ldid: LocalDefId,
+    ptr: PointerId,
+    perms: PermissionSet,
+) -> Option<StaticUse> {
+    // Global pointers are shared by all functions, so they're identified by the pointer alone.
+    let node = |ldid, ptr: PointerId| (if ptr.is_global() { None } else { Some(ldid) }, ptr);
+
+    // Search backward along `Subset` constraints, which propagate these permissions up from the
+    // pointers that flow into `ptr`.
+    let mut seen = HashSet::new();
+    let mut queue = VecDeque::new();
+    seen.insert(node(ldid, ptr));
+    queue.push_back((ldid, ptr));
+    while let Some((cur_ldid, cur)) = queue.pop_front() {
+        // Local pointers can only appear in the constraints of their own function, but global
+        // pointers can appear in any function.
+        let ldids = if cur.is_global() {
+            dataflow.keys().copied().collect()
+        } else {
+            vec![cur_ldid]
+        };
+        for ldid in ldids {
+            for (c, loc) in dataflow[&ldid].iter() {
+                match *c {
+                    Constraint::AllPerms(p, required) if p == cur && required.intersects(perms) => {
+                        return Some(StaticUse {
+                            ldid,
+                            loc,
+                            perms: required & perms,
+                        });
+                    }
+                    Constraint::Subset(a, b) | Constraint::Offset(a, b) if b == cur => {
+                        if seen.insert(node(ldid, a)) {
+                            queue.push_back((ldid, a));
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        }
+    }
+    None
+}
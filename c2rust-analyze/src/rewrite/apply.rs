@@ -1,9 +1,9 @@
-use crate::rewrite::Rewrite;
+use crate::rewrite::{Rewrite, CSTR_CHR_BODY};
 use rustc_hir::Mutability;
 use rustc_span::source_map::{FileName, SourceMap};
-use rustc_span::{BytePos, SourceFile, Span, SyntaxContext};
+use rustc_span::{BytePos, SourceFile, Span};
 use std::cmp::Reverse;
-use std::collections::HashMap;
+use std::ops::Range;
 
 #[derive(Clone, PartialEq, Eq, Debug)]
 pub enum RewriteError<S = Span> {
@@ -241,12 +241,12 @@ impl<'a, F: FnMut(&str)> Emitter<'a, F> {
                 emit_subexpr(slf, span);
             }),
 
-            Rewrite::Ref(ref rw, mutbl) => self.emit_parenthesized(prec > 2, |slf| {
+            Rewrite::Ref(ref rw, mutbl) => self.emit_parenthesized(prec > 3, |slf| {
                 match mutbl {
                     Mutability::Not => slf.emit_str("&"),
                     Mutability::Mut => slf.emit_str("&mut "),
                 }
-                slf.emit_rewrite(rw, 2, emit_expr, emit_subexpr);
+                slf.emit_rewrite(rw, 3, emit_expr, emit_subexpr);
             }),
             Rewrite::AddrOf(ref rw, mutbl) => {
                 match mutbl {
@@ -257,32 +257,45 @@ impl<'a, F: FnMut(&str)> Emitter<'a, F> {
                     slf.emit_rewrite(rw, 0, emit_expr, emit_subexpr);
                 });
             }
-            Rewrite::Deref(ref rw) => self.emit_parenthesized(prec > 2, |slf| {
+            Rewrite::Deref(ref rw) => self.emit_parenthesized(prec > 3, |slf| {
                 slf.emit_str("*");
-                slf.emit_rewrite(rw, 2, emit_expr, emit_subexpr);
+                slf.emit_rewrite(rw, 3, emit_expr, emit_subexpr);
             }),
-            Rewrite::Index(ref arr, ref idx) => self.emit_parenthesized(prec > 3, |slf| {
-                slf.emit_rewrite(arr, 3, emit_expr, emit_subexpr);
+            Rewrite::Index(ref arr, ref idx) => self.emit_parenthesized(prec > 4, |slf| {
+                slf.emit_rewrite(arr, 4, emit_expr, emit_subexpr);
                 slf.emit_str("[");
                 slf.emit_rewrite(idx, 0, emit_expr, emit_subexpr);
                 slf.emit_str("]");
             }),
-            Rewrite::SliceTail(ref arr, ref idx) => self.emit_parenthesized(prec > 3, |slf| {
-                slf.emit_rewrite(arr, 3, emit_expr, emit_subexpr);
+            Rewrite::SliceTail(ref arr, ref idx) => self.emit_parenthesized(prec > 4, |slf| {
+                slf.emit_rewrite(arr, 4, emit_expr, emit_subexpr);
                 slf.emit_str("[");
                 // Rather than figure out the right precedence for `..`, just force
                 // parenthesization in this position.
                 slf.emit_rewrite(idx, 999, emit_expr, emit_subexpr);
                 slf.emit_str(" ..]");
             }),
-            Rewrite::Cast(ref rw, ref ty) => self.emit_parenthesized(prec > 1, |slf| {
-                slf.emit_rewrite(rw, 1, emit_expr, emit_subexpr);
+            Rewrite::Cast(ref rw, ref ty) => self.emit_parenthesized(prec > 2, |slf| {
+                slf.emit_rewrite(rw, 2, emit_expr, emit_subexpr);
                 slf.emit_str(" as ");
                 slf.emit_str(ty);
             }),
+            Rewrite::Div(ref x, ref y) => self.emit_parenthesized(prec > 0, |slf| {
+                slf.emit_rewrite(x, 1, emit_expr, emit_subexpr);
+                slf.emit_str(" / ");
+                slf.emit_rewrite(y, 2, emit_expr, emit_subexpr);
+            }),
+            Rewrite::Assign(ref lhs, ref rhs) => self.emit_parenthesized(prec > 0, |slf| {
+                slf.emit_rewrite(lhs, 1, emit_expr, emit_subexpr);
+                slf.emit_str(" = ");
+                slf.emit_rewrite(rhs, 0, emit_expr, emit_subexpr);
+            }),
             Rewrite::LitZero => {
                 self.emit_str("0");
             }
+            Rewrite::Text(ref s) => {
+                self.emit_str(s);
+            }
 
             Rewrite::PrintTy(ref s) => {
                 self.emit_str(s);
@@ -299,7 +312,7 @@ impl<'a, F: FnMut(&str)> Emitter<'a, F> {
                 });
             }
             Rewrite::MethodCall(ref method, ref receiver_rw, ref arg_rws) => {
-                self.emit_rewrite(receiver_rw, 0, emit_expr, emit_subexpr);
+                self.emit_rewrite(receiver_rw, 4, emit_expr, emit_subexpr);
                 self.emit_str(".");
                 self.emit_str(method);
                 self.emit_parenthesized(true, |slf| {
@@ -311,6 +324,55 @@ impl<'a, F: FnMut(&str)> Emitter<'a, F> {
                     }
                 })
             }
+            Rewrite::VecRepeat(ref elem, ref len) => {
+                self.emit_str("vec![");
+                self.emit_rewrite(elem, 0, emit_expr, emit_subexpr);
+                self.emit_str("; ");
+                self.emit_rewrite(len, 0, emit_expr, emit_subexpr);
+                self.emit_str("]");
+            }
+            Rewrite::VecResize(ref vec, ref len, ref fill) => {
+                // Parenthesize in any non-trivial context, so the block is never mistaken for a
+                // block statement.
+                self.emit_parenthesized(prec > 0, |slf| {
+                    slf.emit_str("{ let (mut v, n) = (");
+                    slf.emit_rewrite(vec, 0, emit_expr, emit_subexpr);
+                    slf.emit_str(", ");
+                    slf.emit_rewrite(len, 0, emit_expr, emit_subexpr);
+                    slf.emit_str("); v.resize(n, ");
+                    slf.emit_rewrite(fill, 0, emit_expr, emit_subexpr);
+                    slf.emit_str("); v }");
+                })
+            }
+            Rewrite::LetSome(ref name, ref rw) => {
+                self.emit_str("let Some(");
+                self.emit_str(name);
+                self.emit_str(") = ");
+                self.emit_rewrite(rw, 0, emit_expr, emit_subexpr);
+            }
+            Rewrite::LetSomeElse(ref name, ref rw) => {
+                self.emit_str("let Some(");
+                self.emit_str(name);
+                self.emit_str(") = ");
+                self.emit_rewrite(rw, 0, emit_expr, emit_subexpr);
+                self.emit_str(" else");
+            }
+            Rewrite::CStrChr(ref s, ref c) => {
+                // Parenthesized for the same reason as `VecResize`.
+                self.emit_parenthesized(prec > 0, |slf| {
+                    slf.emit_str("{ let (s, c) = (");
+                    slf.emit_rewrite(s, 0, emit_expr, emit_subexpr);
+                    slf.emit_str(", ");
+                    slf.emit_rewrite(c, 0, emit_expr, emit_subexpr);
+                    slf.emit_str("); ");
+                    slf.emit_str(CSTR_CHR_BODY);
+                    slf.emit_str(" }");
+                })
+            }
+            Rewrite::Append(ref s) => {
+                emit_expr(self);
+                self.emit_str(s);
+            }
             Rewrite::TyPtr(ref rw, mutbl) => {
                 match mutbl {
                     Mutability::Not => self.emit_str("*const "),
@@ -318,10 +380,14 @@ impl<'a, F: FnMut(&str)> Emitter<'a, F> {
                 }
                 self.emit_rewrite(rw, 0, emit_expr, emit_subexpr);
             }
-            Rewrite::TyRef(ref rw, mutbl) => {
-                match mutbl {
-                    Mutability::Not => self.emit_str("&"),
-                    Mutability::Mut => self.emit_str("&mut "),
+            Rewrite::TyRef(ref rw, mutbl, ref lifetime) => {
+                self.emit_str("&");
+                if let Some(lifetime) = lifetime {
+                    self.emit_str(lifetime);
+                    self.emit_str(" ");
+                }
+                if mutbl == Mutability::Mut {
+                    self.emit_str("mut ");
                 }
                 self.emit_rewrite(rw, 0, emit_expr, emit_subexpr);
             }
@@ -339,6 +405,22 @@ impl<'a, F: FnMut(&str)> Emitter<'a, F> {
                 self.emit_str(">");
             }
 
+            // The original type or name is emitted without parentheses, since the lifetimes are
+            // attached to it syntactically.
+            Rewrite::WithLifetimes(ref lifetimes) => {
+                emit_expr(self);
+                self.emit_str("<");
+                self.emit_str(&lifetimes.join(", "));
+                self.emit_str(">");
+            }
+            Rewrite::ExtraLifetimes(ref lifetimes) => {
+                emit_expr(self);
+                for lifetime in lifetimes {
+                    self.emit_str(", ");
+                    self.emit_str(lifetime);
+                }
+            }
+
             Rewrite::StaticMut(mutbl, span) => {
                 match mutbl {
                     Mutability::Not => self.emit_str("static "),
@@ -362,7 +444,8 @@ impl<'a, F: FnMut(&str)> Emitter<'a, F> {
             .src
             .as_ref()
             .unwrap_or_else(|| panic!("source is not available for file {:?}", self.file.name));
-        self.emit_str(&src[lo.0 as usize..hi.0 as usize]);
+        let start = self.file.start_pos;
+        self.emit_str(&src[(lo - start).0 as usize..(hi - start).0 as usize]);
     }
 
     fn emit_span_with_rewrites(&mut self, span: Span, rts: &[RewriteTree]) {
@@ -388,21 +471,36 @@ impl<'a, F: FnMut(&str)> Emitter<'a, F> {
     }
 }
 
-/// Apply rewrites `rws` to the source files covered by their `Span`s.  Returns a map giving the
-/// rewritten source code for each file that contains at least one rewritten `Span`.
+/// A top-level rewrite that was applied to a file.
+pub struct AppliedRewrite {
+    pub span: Span,
+    pub rw: Rewrite,
+    /// The byte range of `span` in the original source of the file.
+    pub old: Range<usize>,
+    /// The byte range of the rewritten code in the new source of the file.
+    pub new: Range<usize>,
+}
+
+/// The result of applying rewrites to one source file.
+pub struct RewrittenFile {
+    pub name: FileName,
+    pub old_src: String,
+    pub new_src: String,
+    /// The outermost rewrites applied to the file, sorted by position.  Rewrites nested inside
+    /// these are included in their new code.
+    pub rewrites: Vec<AppliedRewrite>,
+}
+
+/// Apply rewrites `rws` to the source files covered by their `Span`s.  Returns the rewritten source
+/// code for each file that contains at least one rewritten `Span`, in order of their position in
+/// the `SourceMap`, along with the rewrites that could not be applied.
 pub fn apply_rewrites(
     source_map: &SourceMap,
     rws: Vec<(Span, Rewrite)>,
-) -> HashMap<FileName, String> {
+) -> (Vec<RewrittenFile>, Vec<(Span, Rewrite, RewriteError)>) {
     let (rts, errs) = RewriteTree::build(rws);
-    for (span, rw, err) in errs {
-        eprintln!(
-            "{:?}: warning: failed to apply rewrite {:?}: {:?}",
-            span, rw, err
-        );
-    }
 
-    let mut new_src = HashMap::new();
+    let mut files = Vec::new();
     let mut rts = &rts as &[RewriteTree<Span>];
     while !rts.is_empty() {
         let file = source_map.lookup_source_file(rts[0].span.lo());
@@ -414,21 +512,45 @@ pub fn apply_rewrites(
         let (file_rts, rest) = rts.split_at(idx);
         rts = rest;
 
+        let old_src = file
+            .src
+            .as_ref()
+            .unwrap_or_else(|| panic!("source is not available for file {:?}", file.name));
+        let offset = |pos: BytePos| (pos - file.start_pos).0 as usize;
+
         let mut buf = String::new();
-        let mut emit = |s: &str| {
-            buf.push_str(s);
-        };
-        let mut emitter = Emitter {
-            file: &file,
-            emit: &mut emit,
-        };
-        let file_span = Span::new(file.start_pos, file.end_pos, SyntaxContext::root(), None);
-        emitter.emit_span_with_rewrites(file_span, file_rts);
-
-        new_src.insert(file.name.clone(), buf);
+        let mut rewrites = Vec::with_capacity(file_rts.len());
+        let mut pos = file.start_pos;
+        for rt in file_rts {
+            buf.push_str(&old_src[offset(pos)..offset(rt.span.lo())]);
+            let new_lo = buf.len();
+            let mut emit = |s: &str| {
+                buf.push_str(s);
+            };
+            let mut emitter = Emitter {
+                file: &file,
+                emit: &mut emit,
+            };
+            emitter.emit_span_with_rewrites(rt.span, std::slice::from_ref(rt));
+            rewrites.push(AppliedRewrite {
+                span: rt.span,
+                rw: rt.rw.clone(),
+                old: offset(rt.span.lo())..offset(rt.span.hi()),
+                new: new_lo..buf.len(),
+            });
+            pos = rt.span.hi();
+        }
+        buf.push_str(&old_src[offset(pos)..]);
+
+        files.push(RewrittenFile {
+            name: file.name.clone(),
+            old_src: old_src.to_string(),
+            new_src: buf,
+            rewrites,
+        });
     }
 
-    new_src
+    (files, errs)
 }
 
 #[cfg(test)]
@@ -1,10 +1,12 @@
 use crate::rewrite::expr::mir_op::{self, MirRewrite};
 use crate::rewrite::span_index::SpanIndex;
 use crate::rewrite::{build_span_index, Rewrite, SoleLocationError};
+use crate::util::{ty_callee, Callee};
 use assert_matches::assert_matches;
-use hir::{ExprKind, UnOp};
+use hir::{BinOpKind, ExprKind, HirId, UnOp};
+use rustc_ast::LitKind;
 use rustc_hir as hir;
-use rustc_hir::def::{Namespace, Res};
+use rustc_hir::def::{DefKind, Namespace, Res};
 use rustc_hir::intravisit;
 use rustc_middle::hir::nested_filter;
 use rustc_middle::mir::{self, Body, Location};
@@ -20,6 +22,15 @@ struct HirRewriteVisitor<'a, 'tcx> {
     mir: &'a Body<'tcx>,
     span_index: SpanIndex<Location>,
     rewrites: &'a HashMap<Location, Vec<MirRewrite>>,
+    /// Nullable pointer variables, keyed by the span of their declaration; see
+    /// `mir_op::nullable_ptr_locals`.
+    nullable_locals: &'a HashMap<Span, bool>,
+    /// Uses of nullable pointer variables that are dereferenced, and so must be unwrapped.  The
+    /// value is `true` if the `Option` must be accessed through `as_deref_mut`.
+    unwrap_paths: HashMap<HirId, bool>,
+    /// Nullable pointer variables that are shadowed by the `if let Some(p) = p` we're currently
+    /// inside, or by a preceding `let Some(p) = p else { ... }`, and so hold a non-null pointer.
+    if_let_locals: Vec<HirId>,
     locations_visited: HashSet<Location>,
     hir_rewrites: Vec<(Span, Rewrite)>,
     /// When `true`, any `Expr` where rustc added an implicit adjustment will be rewritten to make
@@ -146,6 +157,14 @@ impl<'a, 'tcx> HirRewriteVisitor<'a, 'tcx> {
         };
 
         match ex.kind {
+            hir::ExprKind::Call(func, _)
+                if matches!(func.kind, hir::ExprKind::Path(hir::QPath::Resolved(_, p))
+                    if matches!(p.res, Res::Def(DefKind::Ctor(..), _))) =>
+            {
+                // A call to a variant or tuple struct constructor, like `Some(x)`, builds an
+                // aggregate instead.
+                push_assign_loc(ex.span)
+            }
             hir::ExprKind::Call(..) | hir::ExprKind::MethodCall(..) => {
                 // We expect to find exactly one `TerminatorKind::Call` whose span exactly matches
                 // this `hir::Expr`.
@@ -161,11 +180,37 @@ impl<'a, 'tcx> HirRewriteVisitor<'a, 'tcx> {
             hir::ExprKind::Path(hir::QPath::Resolved(_, p)) if matches!(p.res, Res::Local(..)) => {
                 // Currently we only handle cases where the local is copied into a temporary.  If
                 // the local is used as-is in some other expression, this case will return `None`.
-                push_assign_loc(ex.span)
+                // A copy that's implicitly coerced, as from `*mut T` to `*const T`, is followed
+                // by a cast with the same span, which is only of interest to the enclosing
+                // expression.
+                let copy_loc = self
+                    .find_optional_location_matching(
+                        ex.span,
+                        |stmt| {
+                            matches!(stmt.kind, mir::StatementKind::Assign(ref x)
+                                if !matches!(x.1, mir::Rvalue::Cast(..)))
+                        },
+                        |_term| false,
+                    )
+                    .unwrap_or_else(|err| panic_location_error(err, "Assign statement"));
+                locations.extend(copy_loc);
+            }
+            hir::ExprKind::Lit(..) => {
+                // Only the constant itself is of interest here.  A string literal passed as a
+                // `&str` argument is reborrowed in a statement with the same span.
+                let const_loc = self
+                    .find_optional_location_matching(
+                        ex.span,
+                        |stmt| {
+                            matches!(stmt.kind, mir::StatementKind::Assign(ref x)
+                                if matches!(x.1, mir::Rvalue::Use(mir::Operand::Constant(..))))
+                        },
+                        |_term| false,
+                    )
+                    .unwrap_or_else(|err| panic_location_error(err, "Assign statement"));
+                locations.extend(const_loc);
             }
-            hir::ExprKind::Unary(UnOp::Deref, ..)
-            | hir::ExprKind::Lit(..)
-            | hir::ExprKind::Field(..) => {
+            hir::ExprKind::Unary(UnOp::Deref, ..) | hir::ExprKind::Field(..) => {
                 // For `hir::ExprKind::Field` we currently we only handle the case where the value
                 // retrieved from the field is stored into a temporary.  If it's stored into a
                 // local or some other place (e.g. `let y = x.f;`, or `y = x.f;` alone), then
@@ -186,6 +231,36 @@ impl<'a, 'tcx> HirRewriteVisitor<'a, 'tcx> {
                     .iter(),
                 );
             }
+            hir::ExprKind::Cast(..) => {
+                // Only the cast itself is of interest here; other statements (e.g. a reborrow of
+                // a coerced operand) can share the span of the cast expression.
+                let cast_loc = self
+                    .find_optional_location_matching(
+                        ex.span,
+                        |stmt| {
+                            matches!(stmt.kind, mir::StatementKind::Assign(ref x)
+                                if matches!(x.1, mir::Rvalue::Cast(..)))
+                        },
+                        |_term| false,
+                    )
+                    .unwrap_or_else(|err| panic_location_error(err, "Cast statement"));
+                locations.extend(cast_loc);
+            }
+            hir::ExprKind::Binary(op, ..) if matches!(op.node, BinOpKind::Eq | BinOpKind::Ne) => {
+                // Only the comparison itself is of interest here, for rewriting comparisons
+                // against null.
+                let cmp_loc = self
+                    .find_optional_location_matching(
+                        ex.span,
+                        |stmt| {
+                            matches!(stmt.kind, mir::StatementKind::Assign(ref x)
+                                if matches!(x.1, mir::Rvalue::BinaryOp(..)))
+                        },
+                        |_term| false,
+                    )
+                    .unwrap_or_else(|err| panic_location_error(err, "BinaryOp statement"));
+                locations.extend(cmp_loc);
+            }
             hir::ExprKind::Assign(..) => {
                 let assign_loc = self
                     .find_sole_location_matching(
@@ -245,6 +320,280 @@ impl<'a, 'tcx> HirRewriteVisitor<'a, 'tcx> {
         };
         Rewrite::Sub(idx, sub_ex.span)
     }
+
+    /// Like `get_subexpr`, but if the subexpression is a cast, such as the `p as *mut c_void` in
+    /// `free(p as *mut c_void)`, extract the operand of the cast instead.
+    fn get_subexpr_uncast(&self, ex: &'tcx hir::Expr<'tcx>, idx: usize) -> Rewrite {
+        match self.get_subexpr(ex, idx) {
+            Rewrite::Sub(_, span) => {
+                let sub_ex = match ex.kind {
+                    ExprKind::Call(_, args) => &args[idx],
+                    _ => panic!("expected a call, but got {:?}", ex),
+                };
+                match sub_ex.kind {
+                    ExprKind::Cast(e, _) => Rewrite::Sub(idx, e.span),
+                    _ => Rewrite::Sub(idx, span),
+                }
+            }
+            rw => rw,
+        }
+    }
+
+    /// Get the string argument `idx` of a call to a function like `strlen`, without any cast to
+    /// `*const c_char`.  If `unwrap` is set, the argument is nullable, and is unwrapped.
+    fn get_str_arg(&self, ex: &'tcx hir::Expr<'tcx>, idx: usize, unwrap: bool) -> Rewrite {
+        let arg = self.get_subexpr_uncast(ex, idx);
+        if unwrap {
+            Rewrite::MethodCall("unwrap".to_owned(), Box::new(arg), vec![])
+        } else {
+            arg
+        }
+    }
+
+    /// If `ex` is a use of a nullable pointer variable, return the variable's `HirId` and whether
+    /// its `Option` must be accessed through `as_deref_mut`.
+    fn nullable_local(&self, ex: &'tcx hir::Expr<'tcx>) -> Option<(HirId, bool)> {
+        let hir_id = path_local(ex)?;
+        let mutbl = *self.nullable_locals.get(&self.tcx.hir().span(hir_id))?;
+        Some((hir_id, mutbl))
+    }
+
+    /// Whether `ex` is a null pointer constant: `ptr::null()`, `ptr::null_mut()`, or `0 as *T`.
+    fn is_null_expr(&self, ex: &'tcx hir::Expr<'tcx>) -> bool {
+        match ex.kind {
+            ExprKind::Call(func, []) => {
+                let func_ty = self.typeck_results.expr_ty(func);
+                matches!(ty_callee(self.tcx, func_ty), Callee::Null)
+            }
+            ExprKind::Cast(e, _) => matches!(e.kind, ExprKind::Lit(ref lit)
+                if matches!(lit.node, LitKind::Int(0, _))),
+            _ => false,
+        }
+    }
+
+    /// Whether `ex` is a call to the `is_null` method of a raw pointer.
+    fn is_null_check(&self, ex: &'tcx hir::Expr<'tcx>) -> bool {
+        if !matches!(ex.kind, ExprKind::MethodCall(_, [_], _)) {
+            return false;
+        }
+        match self.typeck_results.type_dependent_def_id(ex.hir_id) {
+            Some(did) => matches!(ty_callee(self.tcx, self.tcx.type_of(did)), Callee::IsNull),
+            None => false,
+        }
+    }
+
+    /// If `ex` is `if !p.is_null() { ... }` or `if p != ptr::null() { ... }`, where `p` is a
+    /// nullable pointer variable that the `then` block only dereferences, return the `HirId` of
+    /// `p` and a rewrite of the condition into `let Some(p) = p`.  Inside the `then` block, `p` is
+    /// then shadowed by the non-null pointer, so its derefs don't need to be unwrapped.
+    fn if_let_some(&self, ex: &'tcx hir::Expr<'tcx>) -> Option<(HirId, Span, Rewrite)> {
+        let (cond, then) = match ex.kind {
+            ExprKind::If(cond, then, _) => (cond, then),
+            _ => return None,
+        };
+        let cond = match cond.kind {
+            ExprKind::DropTemps(e) => e,
+            _ => cond,
+        };
+        let ptr_ex = match cond.kind {
+            ExprKind::Unary(UnOp::Not, e) if self.is_null_check(e) => {
+                assert_matches!(e.kind, ExprKind::MethodCall(_, [recv], _) => recv)
+            }
+            ExprKind::Binary(op, a, b) if op.node == BinOpKind::Ne => self.null_cmp_ptr(a, b)?,
+            _ => return None,
+        };
+        let (hir_id, mutbl) = self.nullable_local(ptr_ex)?;
+
+        let mut uses = LocalUses::new(self.tcx, hir_id);
+        intravisit::Visitor::visit_expr(&mut uses, then);
+        if !uses.only_derefs() {
+            return None;
+        }
+
+        let (name, val) = self.unwrapped_local(hir_id, mutbl);
+        Some((hir_id, cond.span, Rewrite::LetSome(name, Box::new(val))))
+    }
+
+    /// If `stmt` is `if p.is_null() { ... }` or `if p == ptr::null() { ... }`, where `p` is a
+    /// nullable pointer variable, the block always exits early, and `rest` and `tail` (the remainder
+    /// of the enclosing block) only dereference `p`, return the `HirId` of `p` and rewrites of
+    /// `stmt` into `let Some(p) = p else { ... };`.  In the remainder of the block, `p` is then
+    /// shadowed by the non-null pointer, so its derefs don't need to be unwrapped.
+    fn let_some_else(
+        &self,
+        stmt: &'tcx hir::Stmt<'tcx>,
+        rest: &'tcx [hir::Stmt<'tcx>],
+        tail: Option<&'tcx hir::Expr<'tcx>>,
+    ) -> Option<(HirId, Vec<(Span, Rewrite)>)> {
+        let (ex, has_semi) = match stmt.kind {
+            hir::StmtKind::Expr(e) => (e, false),
+            hir::StmtKind::Semi(e) => (e, true),
+            _ => return None,
+        };
+        let (cond, then) = match ex.kind {
+            ExprKind::If(cond, then, None) => (cond, then),
+            _ => return None,
+        };
+        let cond = match cond.kind {
+            ExprKind::DropTemps(e) => e,
+            _ => cond,
+        };
+        let ptr_ex = match cond.kind {
+            ExprKind::MethodCall(_, [recv], _) if self.is_null_check(cond) => recv,
+            ExprKind::Binary(op, a, b) if op.node == BinOpKind::Eq => self.null_cmp_ptr(a, b)?,
+            _ => return None,
+        };
+        let (hir_id, mutbl) = self.nullable_local(ptr_ex)?;
+        if self.if_let_locals.contains(&hir_id) || !exits_early(then) {
+            return None;
+        }
+
+        let mut uses = LocalUses::new(self.tcx, hir_id);
+        for stmt in rest {
+            intravisit::Visitor::visit_stmt(&mut uses, stmt);
+        }
+        if let Some(tail) = tail {
+            intravisit::Visitor::visit_expr(&mut uses, tail);
+        }
+        if !uses.only_derefs() {
+            return None;
+        }
+
+        let (name, val) = self.unwrapped_local(hir_id, mutbl);
+        let mut rws = vec![(
+            ex.span.with_hi(cond.span.hi()),
+            Rewrite::LetSomeElse(name, Box::new(val)),
+        )];
+        if !has_semi {
+            // `let ... else { ... }` is a statement, so it needs a semicolon.
+            rws.push((then.span, Rewrite::Append(";".to_owned())));
+        }
+        Some((hir_id, rws))
+    }
+
+    /// If one of `a` and `b` is a null pointer constant, return the other one.
+    fn null_cmp_ptr(
+        &self,
+        a: &'tcx hir::Expr<'tcx>,
+        b: &'tcx hir::Expr<'tcx>,
+    ) -> Option<&'tcx hir::Expr<'tcx>> {
+        if self.is_null_expr(b) {
+            Some(a)
+        } else if self.is_null_expr(a) {
+            Some(b)
+        } else {
+            None
+        }
+    }
+
+    /// The name of the nullable pointer variable `hir_id`, and the `Option` to match against
+    /// `Some(name)` to shadow it with the non-null pointer.
+    fn unwrapped_local(&self, hir_id: HirId, mutbl: bool) -> (String, Rewrite) {
+        let name = self.tcx.hir().name(hir_id).to_string();
+        let mut val = Rewrite::Text(name.clone());
+        if mutbl {
+            val = Rewrite::MethodCall("as_deref_mut".to_owned(), Box::new(val), vec![]);
+        }
+        (name, val)
+    }
+}
+
+/// If `ex` is a path referring to a local variable, return the variable's `HirId`.
+fn path_local(ex: &hir::Expr) -> Option<HirId> {
+    match ex.kind {
+        ExprKind::Path(hir::QPath::Resolved(None, path)) => match path.res {
+            Res::Local(hir_id) => Some(hir_id),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// Whether the block `ex` always exits the enclosing function or loop at its end.
+fn exits_early(ex: &hir::Expr) -> bool {
+    let block = match ex.kind {
+        ExprKind::Block(block, None) => block,
+        _ => return false,
+    };
+    let last = match (block.expr, block.stmts.last()) {
+        (Some(e), _) => e,
+        (None, Some(stmt)) => match stmt.kind {
+            hir::StmtKind::Expr(e) | hir::StmtKind::Semi(e) => e,
+            _ => return false,
+        },
+        (None, None) => return false,
+    };
+    matches!(
+        last.kind,
+        ExprKind::Ret(_) | ExprKind::Break(..) | ExprKind::Continue(_)
+    )
+}
+
+/// Counts the uses of the local `hir_id`, and how many of them are the operand of a deref.
+struct LocalUses<'tcx> {
+    tcx: TyCtxt<'tcx>,
+    hir_id: HirId,
+    uses: usize,
+    derefs: usize,
+}
+
+impl<'tcx> LocalUses<'tcx> {
+    fn new(tcx: TyCtxt<'tcx>, hir_id: HirId) -> LocalUses<'tcx> {
+        LocalUses {
+            tcx,
+            hir_id,
+            uses: 0,
+            derefs: 0,
+        }
+    }
+
+    /// Whether every use of the local is the operand of a deref.
+    fn only_derefs(&self) -> bool {
+        self.uses == self.derefs
+    }
+}
+
+impl<'tcx> intravisit::Visitor<'tcx> for LocalUses<'tcx> {
+    type NestedFilter = nested_filter::OnlyBodies;
+
+    fn nested_visit_map(&mut self) -> Self::Map {
+        self.tcx.hir()
+    }
+
+    fn visit_expr(&mut self, ex: &'tcx hir::Expr<'tcx>) {
+        match ex.kind {
+            ExprKind::Unary(UnOp::Deref, e) if path_local(e) == Some(self.hir_id) => {
+                self.derefs += 1;
+            }
+            _ if path_local(ex) == Some(self.hir_id) => {
+                self.uses += 1;
+            }
+            _ => {}
+        }
+        intravisit::walk_expr(self, ex);
+    }
+}
+
+/// `<T>::default()`
+fn default_value(elem_ty: &str) -> Rewrite {
+    Rewrite::Call(format!("<{}>::default", elem_ty), vec![])
+}
+
+/// `len as usize / std::mem::size_of::<T>()`, converting a byte size into an element count
+fn elem_count(size: Rewrite, elem_ty: &str) -> Rewrite {
+    Rewrite::Div(
+        Box::new(Rewrite::Cast(Box::new(size), "usize".to_owned())),
+        Box::new(Rewrite::Call(
+            format!("std::mem::size_of::<{}>", elem_ty),
+            vec![],
+        )),
+    )
+}
+
+/// `vec![<T>::default(); len].into_boxed_slice()`
+fn boxed_slice_repeat(elem_ty: &str, len: Rewrite) -> Rewrite {
+    let vec = Rewrite::VecRepeat(Box::new(default_value(elem_ty)), Box::new(len));
+    Rewrite::MethodCall("into_boxed_slice".to_owned(), Box::new(vec), vec![])
 }
 
 impl<'a, 'tcx> intravisit::Visitor<'tcx> for HirRewriteVisitor<'a, 'tcx> {
@@ -254,6 +603,35 @@ impl<'a, 'tcx> intravisit::Visitor<'tcx> for HirRewriteVisitor<'a, 'tcx> {
         self.tcx.hir()
     }
 
+    fn visit_block(&mut self, block: &'tcx hir::Block<'tcx>) {
+        // `if p.is_null() { return ...; }` -> `let Some(p) = p else { return ...; };`, after which
+        // `p` is non-null for the rest of the block.
+        let mut shadowed = 0;
+        for (i, stmt) in block.stmts.iter().enumerate() {
+            let let_some_else = self.let_some_else(stmt, &block.stmts[i + 1..], block.expr);
+            let hir_id = let_some_else.map(|(hir_id, rws)| {
+                for (span, rw) in rws {
+                    eprintln!("rewrite {:?} at {:?}", rw, span);
+                    self.hir_rewrites.push((span, rw));
+                }
+                hir_id
+            });
+            // Rewrites within the condition are discarded, since it's replaced by the
+            // `let Some(p) = p`.
+            self.visit_stmt(stmt);
+            if let Some(hir_id) = hir_id {
+                self.if_let_locals.push(hir_id);
+                shadowed += 1;
+            }
+        }
+        if let Some(ex) = block.expr {
+            self.visit_expr(ex);
+        }
+        for _ in 0..shadowed {
+            self.if_let_locals.pop();
+        }
+    }
+
     fn visit_expr(&mut self, ex: &'tcx hir::Expr<'tcx>) {
         let mut hir_rw = Rewrite::Identity;
 
@@ -285,9 +663,22 @@ impl<'a, 'tcx> intravisit::Visitor<'tcx> for HirRewriteVisitor<'a, 'tcx> {
 
         let rewrite_from_mir_rws = |rw: &mir_op::RewriteKind, hir_rw: Rewrite| -> Rewrite {
             match rw {
-                mir_op::RewriteKind::OffsetSlice { mutbl } => {
+                mir_op::RewriteKind::OffsetSlice { mutbl, unwrap } => {
                     // `p.offset(i)` -> `&p[i as usize ..]`
-                    let arr = self.get_subexpr(ex, 0);
+                    let mut arr = self.get_subexpr(ex, 0);
+                    if *unwrap {
+                        // `p.offset(i)` -> `&p.unwrap()[i as usize ..]`, or
+                        // `&mut p.as_deref_mut().unwrap()[i as usize ..]`.  An immutable `p` is
+                        // already an `Option<&[T]>`, which is `Copy`.
+                        if *mutbl {
+                            arr = Rewrite::MethodCall(
+                                "as_deref_mut".to_owned(),
+                                Box::new(arr),
+                                vec![],
+                            );
+                        }
+                        arr = Rewrite::MethodCall("unwrap".to_owned(), Box::new(arr), vec![]);
+                    }
                     let idx = Rewrite::Cast(Box::new(self.get_subexpr(ex, 1)), "usize".to_owned());
                     let elem = Rewrite::SliceTail(Box::new(arr), Box::new(idx));
                     Rewrite::Ref(Box::new(elem), mutbl_from_bool(*mutbl))
@@ -339,6 +730,165 @@ impl<'a, 'tcx> intravisit::Visitor<'tcx> for HirRewriteVisitor<'a, 'tcx> {
                     let rhs = self.get_subexpr(ex, 1);
                     Rewrite::MethodCall("set".to_string(), Box::new(lhs), vec![rhs])
                 }
+
+                mir_op::RewriteKind::BorrowBox { mutbl } => {
+                    // `p` -> `&*p` / `&mut *p`
+                    let place = Rewrite::Deref(Box::new(hir_rw));
+                    Rewrite::Ref(Box::new(place), mutbl_from_bool(*mutbl))
+                }
+
+                mir_op::RewriteKind::RemoveCast => {
+                    // `p as *mut T` -> `p`
+                    assert!(matches!(hir_rw, Rewrite::Identity));
+                    self.get_subexpr(ex, 0)
+                }
+
+                mir_op::RewriteKind::MallocSafe { elem_ty, single } => {
+                    assert!(matches!(hir_rw, Rewrite::Identity));
+                    if *single {
+                        // `malloc(size)` -> `Box::new(<T>::default())`
+                        Rewrite::Call("Box::new".to_owned(), vec![default_value(elem_ty)])
+                    } else {
+                        // `malloc(size)` ->
+                        // `vec![<T>::default(); size as usize / size_of::<T>()].into_boxed_slice()`
+                        let len = elem_count(self.get_subexpr(ex, 0), elem_ty);
+                        boxed_slice_repeat(elem_ty, len)
+                    }
+                }
+
+                mir_op::RewriteKind::CallocSafe { elem_ty, single } => {
+                    assert!(matches!(hir_rw, Rewrite::Identity));
+                    if *single {
+                        // `calloc(n, size)` -> `Box::new(<T>::default())`
+                        Rewrite::Call("Box::new".to_owned(), vec![default_value(elem_ty)])
+                    } else {
+                        // `calloc(n, size)` -> `vec![<T>::default(); n as usize].into_boxed_slice()`
+                        let len =
+                            Rewrite::Cast(Box::new(self.get_subexpr(ex, 0)), "usize".to_owned());
+                        boxed_slice_repeat(elem_ty, len)
+                    }
+                }
+
+                mir_op::RewriteKind::ReallocSafe { elem_ty, single } => {
+                    assert!(matches!(hir_rw, Rewrite::Identity));
+                    let ptr = self.get_subexpr_uncast(ex, 0);
+                    if *single {
+                        // `realloc(p, size)` -> `p`
+                        ptr
+                    } else {
+                        // `realloc(p, size)` -> `{ let (mut v, n) = (p.into_vec(), size as usize
+                        // / size_of::<T>()); v.resize(n, <T>::default()); v }.into_boxed_slice()`
+                        let vec = Rewrite::MethodCall("into_vec".to_owned(), Box::new(ptr), vec![]);
+                        let len = elem_count(self.get_subexpr(ex, 1), elem_ty);
+                        let resized = Rewrite::VecResize(
+                            Box::new(vec),
+                            Box::new(len),
+                            Box::new(default_value(elem_ty)),
+                        );
+                        Rewrite::MethodCall(
+                            "into_boxed_slice".to_owned(),
+                            Box::new(resized),
+                            vec![],
+                        )
+                    }
+                }
+
+                mir_op::RewriteKind::FreeSafe => {
+                    // `free(p as *mut c_void)` -> `drop(p)`
+                    assert!(matches!(hir_rw, Rewrite::Identity));
+                    Rewrite::Call("drop".to_owned(), vec![self.get_subexpr_uncast(ex, 0)])
+                }
+
+                mir_op::RewriteKind::NullToNone => {
+                    // `ptr::null_mut()` / `0 as *mut T` -> `None`
+                    Rewrite::Text("None".to_owned())
+                }
+
+                mir_op::RewriteKind::NullCheck {
+                    ptr_idx,
+                    negate,
+                    non_null,
+                } => {
+                    assert!(matches!(hir_rw, Rewrite::Identity));
+                    if *non_null {
+                        // `p.is_null()` -> `false`
+                        Rewrite::Text(if *negate { "true" } else { "false" }.to_owned())
+                    } else {
+                        // `p.is_null()` / `p == ptr::null_mut()` -> `p.is_none()`
+                        let method = if *negate { "is_some" } else { "is_none" };
+                        let ptr = self.get_subexpr(ex, *ptr_idx);
+                        Rewrite::MethodCall(method.to_owned(), Box::new(ptr), vec![])
+                    }
+                }
+
+                mir_op::RewriteKind::OptionSome => {
+                    // `p` -> `Some(p)`
+                    Rewrite::Call("Some".to_owned(), vec![hir_rw])
+                }
+
+                mir_op::RewriteKind::OptionUnwrap => {
+                    // `p` -> `p.unwrap()`
+                    Rewrite::MethodCall("unwrap".to_owned(), Box::new(hir_rw), vec![])
+                }
+
+                mir_op::RewriteKind::OptionAsDeref { mutbl } => {
+                    // `p` -> `p.as_deref()` / `p.as_deref_mut()`
+                    let method = if *mutbl { "as_deref_mut" } else { "as_deref" };
+                    Rewrite::MethodCall(method.to_owned(), Box::new(hir_rw), vec![])
+                }
+
+                mir_op::RewriteKind::StrLen {
+                    ret_ty,
+                    slice,
+                    unwrap,
+                } => {
+                    assert!(matches!(hir_rw, Rewrite::Identity));
+                    let s = self.get_str_arg(ex, 0, *unwrap);
+                    let len = if *slice {
+                        // `strlen(s)` -> `s.iter().position(|&c| c == 0).unwrap() as T`
+                        let iter = Rewrite::MethodCall("iter".to_owned(), Box::new(s), vec![]);
+                        let pos = Rewrite::MethodCall(
+                            "position".to_owned(),
+                            Box::new(iter),
+                            vec![Rewrite::Text("|&c| c == 0".to_owned())],
+                        );
+                        Rewrite::MethodCall("unwrap".to_owned(), Box::new(pos), vec![])
+                    } else {
+                        // `strlen(s)` -> `s.to_bytes().len() as T`
+                        let bytes = Rewrite::MethodCall("to_bytes".to_owned(), Box::new(s), vec![]);
+                        Rewrite::MethodCall("len".to_owned(), Box::new(bytes), vec![])
+                    };
+                    Rewrite::Cast(Box::new(len), ret_ty.clone())
+                }
+
+                mir_op::RewriteKind::StrCmp { ret_ty, unwrap } => {
+                    // `strcmp(a, b)` -> `a.cmp(b) as T`
+                    assert!(matches!(hir_rw, Rewrite::Identity));
+                    let a = self.get_str_arg(ex, 0, unwrap[0]);
+                    let b = self.get_str_arg(ex, 1, unwrap[1]);
+                    let cmp = Rewrite::MethodCall("cmp".to_owned(), Box::new(a), vec![b]);
+                    Rewrite::Cast(Box::new(cmp), ret_ty.clone())
+                }
+
+                mir_op::RewriteKind::StrChr { unwrap } => {
+                    assert!(matches!(hir_rw, Rewrite::Identity));
+                    let s = self.get_str_arg(ex, 0, *unwrap);
+                    Rewrite::CStrChr(Box::new(s), Box::new(self.get_subexpr(ex, 1)))
+                }
+
+                mir_op::RewriteKind::CStrFromPtr { slice } => {
+                    // `p` -> `CStr::from_ptr(p)` / `CStr::from_ptr(p.as_ptr())`
+                    let mut ptr = hir_rw;
+                    if *slice {
+                        ptr = Rewrite::MethodCall("as_ptr".to_owned(), Box::new(ptr), vec![]);
+                    }
+                    Rewrite::Call("std::ffi::CStr::from_ptr".to_owned(), vec![ptr])
+                }
+
+                mir_op::RewriteKind::CStrAsPtr => {
+                    // `s` -> `s.as_ptr()`
+                    Rewrite::MethodCall("as_ptr".to_owned(), Box::new(hir_rw), vec![])
+                }
             }
         };
 
@@ -363,10 +913,24 @@ impl<'a, 'tcx> intravisit::Visitor<'tcx> for HirRewriteVisitor<'a, 'tcx> {
         use mir_op::RewriteKind::*;
         if !all_rws_unflattened.is_empty() {
             hir_rw = match &all_rws_unflattened[..] {
-                [[], [rw @ RawToRef { .. }]]
+                [[], rws @ [RawToRef { .. }, ..]]
                     if is_addr_of_expansion() || callsite_span == ex.span =>
                 {
-                    rewrite_from_mir_rws(rw, hir_rw)
+                    rws.iter()
+                        .fold(hir_rw, |acc, rw| rewrite_from_mir_rws(rw, acc))
+                }
+                [rws]
+                    if matches!(ex.kind, ExprKind::Assign(..))
+                        && !rws.is_empty()
+                        && rws.iter().all(|rw| converts_assigned_value(rw)) =>
+                {
+                    // An assignment whose RHS doesn't need a temporary shares its MIR location
+                    // with the RHS, so rewrites of the assigned value apply to the RHS only:
+                    // `p = q` -> `p = Some(q)`
+                    let rhs = rws.iter().fold(self.get_subexpr(ex, 1), |acc, rw| {
+                        rewrite_from_mir_rws(rw, acc)
+                    });
+                    Rewrite::Assign(Box::new(self.get_subexpr(ex, 0)), Box::new(rhs))
                 }
                 [rws] => rws
                     .iter()
@@ -379,10 +943,48 @@ impl<'a, 'tcx> intravisit::Visitor<'tcx> for HirRewriteVisitor<'a, 'tcx> {
             };
         }
 
+        // Dereferencing a nullable pointer requires unwrapping it first, unless it's been
+        // shadowed by an enclosing `if let Some(p) = p`.
+        if let ExprKind::Unary(UnOp::Deref, e) = ex.kind {
+            if let Some((hir_id, mutbl)) = self.nullable_local(e) {
+                if !self.if_let_locals.contains(&hir_id) {
+                    self.unwrap_paths.insert(e.hir_id, mutbl);
+                }
+            }
+        }
+        if let Some(mutbl) = self.unwrap_paths.remove(&ex.hir_id) {
+            // `p` -> `p.unwrap()` / `p.as_deref_mut().unwrap()`
+            if mutbl {
+                hir_rw = Rewrite::MethodCall("as_deref_mut".to_owned(), Box::new(hir_rw), vec![]);
+            }
+            hir_rw = Rewrite::MethodCall("unwrap".to_owned(), Box::new(hir_rw), vec![]);
+        }
+
+        // `if !p.is_null() { ... }` -> `if let Some(p) = p { ... }`
+        let if_let_some = self.if_let_some(ex);
+        let if_let_local = if_let_some.map(|(hir_id, span, rw)| {
+            eprintln!("rewrite {:?} at {:?}", rw, span);
+            self.hir_rewrites.push((span, rw));
+            hir_id
+        });
+
         // Emit rewrites on subexpressions first.
         let applied_mir_rewrite = !matches!(hir_rw, Rewrite::Identity);
-        self.with_materialize_adjustments(applied_mir_rewrite, |this| {
-            intravisit::walk_expr(this, ex);
+        self.with_materialize_adjustments(applied_mir_rewrite, |this| match if_let_local {
+            Some(hir_id) => {
+                let (cond, then, else_) = assert_matches!(ex.kind,
+                    ExprKind::If(cond, then, else_) => (cond, then, else_));
+                // Rewrites within `cond` are discarded, since they're replaced by the
+                // `let Some(p) = p`.
+                intravisit::Visitor::visit_expr(this, cond);
+                this.if_let_locals.push(hir_id);
+                intravisit::Visitor::visit_expr(this, then);
+                this.if_let_locals.pop();
+                if let Some(else_) = else_ {
+                    intravisit::Visitor::visit_expr(this, else_);
+                }
+            }
+            None => intravisit::walk_expr(this, ex),
         });
 
         // Materialize adjustments if requested by an ancestor.
@@ -402,6 +1004,24 @@ impl<'a, 'tcx> intravisit::Visitor<'tcx> for HirRewriteVisitor<'a, 'tcx> {
     }
 }
 
+/// Whether `rw` only converts the value it's applied to, so that when it's found on an assignment,
+/// it can be applied to the assigned value instead of the assignment as a whole.
+fn converts_assigned_value(rw: &mir_op::RewriteKind) -> bool {
+    use mir_op::RewriteKind::*;
+    matches!(
+        rw,
+        MutToImm
+            | BorrowBox { .. }
+            | SliceFirst { .. }
+            | NullToNone
+            | OptionSome
+            | OptionUnwrap
+            | OptionAsDeref { .. }
+            | CStrFromPtr { .. }
+            | CStrAsPtr
+    )
+}
+
 fn mutbl_from_bool(m: bool) -> hir::Mutability {
     if m {
         hir::Mutability::Mut
@@ -426,6 +1046,9 @@ fn apply_identity_adjustment<'tcx>(
             let s = ty.print(printer).unwrap().into_buffer();
             Rewrite::Cast(Box::new(rw), s)
         }
+        // The coercion from `*mut T` to `*const T` is still implicit if the pointer stays raw, and
+        // doesn't apply to the safe reference it's rewritten to otherwise.
+        Adjust::Pointer(PointerCast::MutToConstPointer) => rw,
         Adjust::Pointer(cast) => todo!("Adjust::Pointer({:?})", cast),
     }
 }
@@ -456,6 +1079,7 @@ pub fn gen_hir_rewrites<'tcx>(
     mir: &Body<'tcx>,
     hir_body_id: hir::BodyId,
     rewrites: &HashMap<Location, Vec<MirRewrite>>,
+    nullable_locals: &HashMap<Span, bool>,
 ) -> Vec<(Span, Rewrite)> {
     // Build `span_index`, which maps `Span`s to MIR `Locations`.
     let span_index = build_span_index(mir);
@@ -470,6 +1094,9 @@ pub fn gen_hir_rewrites<'tcx>(
         mir,
         span_index,
         rewrites,
+        nullable_locals,
+        unwrap_paths: HashMap::new(),
+        if_let_locals: Vec::new(),
         locations_visited: HashSet::new(),
         hir_rewrites: Vec::new(),
         materialize_adjustments: false,
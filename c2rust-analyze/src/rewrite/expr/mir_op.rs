@@ -7,16 +7,22 @@
 //! all adjustments, as this would make even non-rewritten code extremely verbose, so we try to
 //! materialize adjustments only on code that's subject to some rewrite.
 
+use crate::c_void_casts::CVoidCastDirection;
 use crate::context::{AnalysisCtxt, Assignment, FlagSet, LTy, PermissionSet, PointerId};
 use crate::pointer_id::PointerTable;
 use crate::type_desc::{self, Ownership, Quantity};
-use crate::util::{ty_callee, Callee};
+use crate::util::{is_null_const, null_locals, ty_callee, Callee};
 use rustc_ast::Mutability;
+use rustc_hir::def::Namespace;
+use rustc_hir::FnRetTy;
 use rustc_middle::mir::{
-    BasicBlock, Body, Location, Operand, Place, Rvalue, Statement, StatementKind, Terminator,
-    TerminatorKind,
+    BasicBlock, BinOp, Body, CastKind, Local, Location, Operand, Place, Rvalue, Statement,
+    StatementKind, Terminator, TerminatorKind,
 };
-use std::collections::HashMap;
+use rustc_middle::ty::print::{FmtPrinter, Print};
+use rustc_middle::ty::{Ty, TyKind};
+use rustc_span::Span;
+use std::collections::{HashMap, HashSet};
 
 #[derive(Clone, PartialEq, Eq, Debug)]
 pub enum SubLoc {
@@ -36,8 +42,10 @@ pub enum SubLoc {
 
 #[derive(Clone, PartialEq, Eq, Debug)]
 pub enum RewriteKind {
-    /// Replace `ptr.offset(i)` with something like `&ptr[i..]`.
-    OffsetSlice { mutbl: bool },
+    /// Replace `ptr.offset(i)` with something like `&ptr[i..]`.  If `unwrap` is set, `ptr` is
+    /// nullable and is unwrapped first, as in `&ptr.unwrap()[i..]`, or
+    /// `&mut ptr.as_deref_mut().unwrap()[i..]` to avoid moving out of a mutable `ptr`.
+    OffsetSlice { mutbl: bool, unwrap: bool },
     /// Replace `slice` with `&slice[0]`.
     SliceFirst { mutbl: bool },
     /// Replace `ptr` with `&*ptr`, converting `&mut T` to `&T`.
@@ -53,6 +61,60 @@ pub enum RewriteKind {
     CellGet,
     /// Replace `*y = x` with `Cell::set(x)` where `y` is a pointer
     CellSet,
+    /// Replace `ptr` with `&*ptr` or `&mut *ptr`, borrowing from a `Box<T>` or `Box<[T]>`.
+    BorrowBox { mutbl: bool },
+    /// Remove a cast to or from `*mut c_void` around an allocator call, i.e. replace `p as *mut T`
+    /// with `p`.
+    RemoveCast,
+    /// Replace `malloc(size)` with `Box::new(T::default())`, or with
+    /// `vec![T::default(); size / size_of::<T>()].into_boxed_slice()` if `single` is false.
+    MallocSafe { elem_ty: String, single: bool },
+    /// Replace `calloc(n, size)` with `Box::new(T::default())`, or with
+    /// `vec![T::default(); n].into_boxed_slice()` if `single` is false.
+    CallocSafe { elem_ty: String, single: bool },
+    /// Replace `realloc(p, size)` with `p`, or with a `Vec::resize` of `p.into_vec()` to
+    /// `size / size_of::<T>()` elements if `single` is false.
+    ReallocSafe { elem_ty: String, single: bool },
+    /// Replace `free(p)` with `drop(p)`.
+    FreeSafe,
+    /// Replace a null pointer constant, such as `ptr::null_mut()` or `0 as *mut T`, with `None`.
+    NullToNone,
+    /// Replace a null check on subexpression `ptr_idx`, such as `p.is_null()` or
+    /// `p == ptr::null_mut()`, with `p.is_none()`, or with `p.is_some()` if `negate` is set (as for
+    /// `p != ptr::null_mut()`).  If `non_null` is set, `p` can never be null, and the check is
+    /// replaced with a constant `false` (or `true`).
+    NullCheck {
+        ptr_idx: usize,
+        negate: bool,
+        non_null: bool,
+    },
+    /// Replace `p` with `Some(p)`.
+    OptionSome,
+    /// Replace `p` with `p.unwrap()`.
+    OptionUnwrap,
+    /// Replace `p` with `p.as_deref()` or `p.as_deref_mut()`, converting `Option<&mut T>` or
+    /// `Option<Box<T>>` to `Option<&T>` or `Option<&mut T>` without moving out of `p`.
+    OptionAsDeref { mutbl: bool },
+    /// Replace `strlen(s)` with `s.to_bytes().len() as T`, or with
+    /// `s.iter().position(|&c| c == 0).unwrap() as T` if `slice` is set, where `T` is the
+    /// declared return type of `strlen`.  If `unwrap` is set, `s` is nullable and is unwrapped
+    /// first.
+    StrLen {
+        ret_ty: String,
+        slice: bool,
+        unwrap: bool,
+    },
+    /// Replace `strcmp(a, b)` with `a.cmp(b) as T`, where `T` is the declared return type of
+    /// `strcmp`.  Each nullable argument, as indicated by `unwrap`, is unwrapped first.
+    StrCmp { ret_ty: String, unwrap: [bool; 2] },
+    /// Replace `strchr(s, c)` with the `Option<&CStr>` suffix of `s` that starts at `c`; see
+    /// `Rewrite::CStrChr`.  If `unwrap` is set, `s` is nullable and is unwrapped first.
+    StrChr { unwrap: bool },
+    /// Replace `p` with `CStr::from_ptr(p)`, or with `CStr::from_ptr(p.as_ptr())` if `slice` is
+    /// set, converting a raw pointer or a slice to `&CStr`.
+    CStrFromPtr { slice: bool },
+    /// Replace `s` with `s.as_ptr()`, converting a `&CStr` to a raw pointer.
+    CStrAsPtr,
 }
 
 #[derive(Clone, PartialEq, Eq, Debug)]
@@ -67,6 +129,9 @@ struct ExprRewriteVisitor<'a, 'tcx> {
     flags: PointerTable<'a, FlagSet>,
     rewrites: &'a mut HashMap<Location, Vec<MirRewrite>>,
     mir: &'a Body<'tcx>,
+    /// Locals that are assigned a null pointer constant, such as the temporary holding the result
+    /// of `ptr::null_mut()` in `p == ptr::null_mut()`.
+    null_locals: HashSet<Local>,
     loc: Location,
     sub_loc: Vec<SubLoc>,
 }
@@ -86,6 +151,7 @@ impl<'a, 'tcx> ExprRewriteVisitor<'a, 'tcx> {
             flags,
             rewrites,
             mir,
+            null_locals: null_locals(acx.tcx(), mir),
             loc: Location {
                 block: BasicBlock::from_usize(0),
                 statement_index: 0,
@@ -137,6 +203,18 @@ impl<'a, 'tcx> ExprRewriteVisitor<'a, 'tcx> {
                 let (pl, ref rv) = **x;
                 let pl_lty = self.acx.type_of(pl);
 
+                if self
+                    .acx
+                    .c_void_casts
+                    .direction(CVoidCastDirection::From)
+                    .contains_cast(loc)
+                    && self.box_quantity(pl_lty.label).is_some()
+                {
+                    // This is the `*mut c_void` to `*mut T` cast following an allocator call,
+                    // which will be rewritten to produce a `Box` directly.
+                    self.enter_assign_rvalue(|v| v.emit(RewriteKind::RemoveCast));
+                }
+
                 if pl.is_indirect() && self.acx.local_tys[pl.local].ty.is_any_ptr() {
                     let local_addr = self.acx.local_tys[pl.local].label;
                     let perms = self.perms[local_addr];
@@ -222,6 +300,10 @@ impl<'a, 'tcx> ExprRewriteVisitor<'a, 'tcx> {
 
                 // Special cases for particular functions.
                 match ty_callee(tcx, func_ty) {
+                    callee @ (Callee::Malloc | Callee::Calloc | Callee::Realloc | Callee::Free) => {
+                        self.visit_alloc_call(callee, args, destination);
+                        return;
+                    }
                     Callee::PtrOffset { .. } => {
                         self.visit_ptr_offset(&args[0], pl_ty);
                         return;
@@ -230,6 +312,21 @@ impl<'a, 'tcx> ExprRewriteVisitor<'a, 'tcx> {
                         self.visit_slice_as_ptr(&args[0], pl_ty);
                         return;
                     }
+                    Callee::IsNull => {
+                        let ptr = self.acx.type_of(&args[0]).label;
+                        self.emit_null_check(ptr, 0, false);
+                        return;
+                    }
+                    Callee::Null => {
+                        if self.is_nullable(pl_ty.label) {
+                            self.emit(RewriteKind::NullToNone);
+                        }
+                        return;
+                    }
+                    callee @ (Callee::Strlen | Callee::Strcmp | Callee::Strchr) => {
+                        self.visit_str_call(callee, func_ty, args, destination);
+                        return;
+                    }
                     _ => {}
                 }
 
@@ -287,13 +384,38 @@ impl<'a, 'tcx> ExprRewriteVisitor<'a, 'tcx> {
                     }),
                     _ => (),
                 });
+                if matches!(ownership, Ownership::Cell | Ownership::Imm | Ownership::Mut)
+                    && self.is_nullable(expect_ty.label)
+                {
+                    self.emit(RewriteKind::OptionSome);
+                }
             }
             Rvalue::Len(_pl) => {
                 // TODO
             }
+            Rvalue::Cast(CastKind::PointerFromExposedAddress, ref op, _ty) => {
+                // Only null pointers can be created this way; see `dataflow::type_check`.
+                let is_null = op.constant().copied().map_or(false, is_null_const);
+                if is_null && self.is_nullable(expect_ty.label) {
+                    self.emit(RewriteKind::NullToNone);
+                }
+            }
             Rvalue::Cast(_kind, ref _op, _ty) => {
                 // TODO
             }
+            Rvalue::BinaryOp(bop @ (BinOp::Eq | BinOp::Ne), ref ops) => {
+                // Comparisons against null, like `p == ptr::null_mut()`.
+                let negate = bop == BinOp::Ne;
+                let (ref a, ref b) = **ops;
+                let ptr_idx = match (self.is_null_operand(a), self.is_null_operand(b)) {
+                    (false, true) => 0,
+                    (true, false) => 1,
+                    _ => return,
+                };
+                let ptr_op = if ptr_idx == 0 { a } else { b };
+                let ptr = self.acx.type_of(ptr_op).label;
+                self.emit_null_check(ptr, ptr_idx, negate);
+            }
             Rvalue::BinaryOp(_bop, ref _ops) => {
                 // TODO
             }
@@ -341,11 +463,12 @@ impl<'a, 'tcx> ExprRewriteVisitor<'a, 'tcx> {
         op: &Operand<'tcx>,
         expect_own: Ownership,
         expect_qty: Quantity,
+        expect_option: bool,
     ) {
         match *op {
             Operand::Copy(pl) | Operand::Move(pl) => {
                 if let Some(ptr) = self.acx.ptr_of(pl) {
-                    self.emit_cast(ptr, expect_own, expect_qty);
+                    self.emit_cast(ptr, expect_own, expect_qty, expect_option);
                 }
 
                 // TODO: walk over `pl` to handle all derefs (casts, `*x` -> `(*x).get()`)
@@ -366,21 +489,37 @@ impl<'a, 'tcx> ExprRewriteVisitor<'a, 'tcx> {
             Quantity::Single => Quantity::Slice,
             Quantity::Slice => Quantity::Slice,
             Quantity::OffsetPtr => Quantity::OffsetPtr,
+            Quantity::CStr => Quantity::Slice,
         };
 
+        // A nullable argument is unwrapped by `OffsetSlice` itself.
+        let arg_nullable = self.is_nullable(self.acx.type_of(op).label);
+
         self.enter_call_arg(0, |v| {
-            v.visit_operand_desc(op, arg_expect_own, arg_expect_qty)
+            v.visit_operand_desc(op, arg_expect_own, arg_expect_qty, arg_nullable)
         });
 
         // Emit `OffsetSlice` for the offset itself.
         let mutbl = matches!(result_own, Ownership::Mut);
 
-        self.emit(RewriteKind::OffsetSlice { mutbl });
+        self.emit(RewriteKind::OffsetSlice {
+            mutbl,
+            unwrap: arg_nullable,
+        });
 
         // If the result is `Single`, also insert an upcast.
         if result_qty == Quantity::Single {
             self.emit(RewriteKind::SliceFirst { mutbl });
         }
+
+        // If the result is a string, convert the tail of the slice back to `&CStr`.
+        if result_qty == Quantity::CStr {
+            self.emit(RewriteKind::CStrFromPtr { slice: true });
+        }
+
+        if self.is_nullable(result_ptr) {
+            self.emit(RewriteKind::OptionSome);
+        }
     }
 
     fn visit_slice_as_ptr(&mut self, op: &Operand<'tcx>, result_lty: LTy<'tcx>) {
@@ -396,9 +535,217 @@ impl<'a, 'tcx> ExprRewriteVisitor<'a, 'tcx> {
             // Input and output types will be the same after rewriting, so the `as_ptr` call is not
             // needed.
             self.emit(RewriteKind::RemoveAsPtr);
+            // The input is a reference, so it's never null.
+            if self.is_nullable(result_ptr) {
+                self.emit(RewriteKind::OptionSome);
+            }
+        }
+    }
+
+    /// Rewrite calls to `malloc`, `calloc`, `realloc`, and `free` whose typed pointer (the one on
+    /// the other side of the cast to or from `*mut c_void`) has been inferred to be a `Box`.
+    /// `linearity::check_linearity` has already made sure the pointee type of each such
+    /// allocation implements `Default`, plus `Clone` for slices.
+    fn visit_alloc_call(
+        &mut self,
+        callee: Callee<'tcx>,
+        args: &[Operand<'tcx>],
+        destination: Place<'tcx>,
+    ) {
+        let c_void_casts = &self.acx.c_void_casts;
+        let out_lty = self
+            .acx
+            .type_of(c_void_casts.get_adjusted_place_or_default_to(
+                self.loc,
+                CVoidCastDirection::From,
+                destination,
+            ));
+        let in_lty = args.get(0).and_then(|op| op.place()).map(|pl| {
+            self.acx
+                .type_of(c_void_casts.get_adjusted_place_or_default_to(
+                    self.loc,
+                    CVoidCastDirection::To,
+                    pl,
+                ))
+        });
+
+        match callee {
+            Callee::Malloc | Callee::Calloc => {
+                let qty = match self.box_quantity(out_lty.label) {
+                    Some(x) => x,
+                    None => return,
+                };
+                let elem_ty = self.print_pointee_ty(out_lty);
+                let single = qty == Quantity::Single;
+                self.emit(if matches!(callee, Callee::Malloc) {
+                    RewriteKind::MallocSafe { elem_ty, single }
+                } else {
+                    RewriteKind::CallocSafe { elem_ty, single }
+                });
+                if self.is_nullable(out_lty.label) {
+                    self.emit(RewriteKind::OptionSome);
+                }
+            }
+            Callee::Realloc => {
+                let in_lty = match in_lty {
+                    Some(x) => x,
+                    None => return,
+                };
+                let (in_qty, out_qty) = match (
+                    self.box_quantity(in_lty.label),
+                    self.box_quantity(out_lty.label),
+                ) {
+                    (Some(x), Some(y)) => (x, y),
+                    _ => return,
+                };
+                let single = out_qty == Quantity::Single;
+                if (in_qty == Quantity::Single) != single {
+                    eprintln!(
+                        "unsupported realloc: {:?} -> {:?} at {:?}",
+                        in_qty, out_qty, self.loc
+                    );
+                    return;
+                }
+                let elem_ty = self.print_pointee_ty(out_lty);
+                self.emit(RewriteKind::ReallocSafe { elem_ty, single });
+            }
+            Callee::Free => {
+                if let Some(in_lty) = in_lty {
+                    if self.box_quantity(in_lty.label).is_some() {
+                        self.emit(RewriteKind::FreeSafe);
+                    }
+                }
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    /// Rewrite calls to `strlen`, `strcmp`, and `strchr` whose string arguments have been inferred
+    /// to be `&CStr`s, or for `strlen`, a slice.  Passing null to these functions is undefined
+    /// behavior, so nullable arguments are unwrapped.
+    fn visit_str_call(
+        &mut self,
+        callee: Callee<'tcx>,
+        func_ty: Ty<'tcx>,
+        args: &[Operand<'tcx>],
+        destination: Place<'tcx>,
+    ) {
+        // The `Quantity` of a string argument, and whether it's nullable.
+        let arg_desc = |i: usize| -> Option<(Quantity, bool)> {
+            let ptr = self.acx.type_of(&args[i]).label;
+            if ptr == PointerId::NONE || self.flags[ptr].contains(FlagSet::FIXED) {
+                return None;
+            }
+            match type_desc::perms_to_desc(self.perms[ptr], self.flags[ptr]) {
+                (Ownership::Imm, qty) => Some((qty, self.is_nullable(ptr))),
+                _ => None,
+            }
+        };
+        // The return type as declared, like `libc::c_ulong`.  Printing the MIR type instead would
+        // give the type the alias resolves to on this target, like `u64`.
+        let ret_ty = || {
+            let tcx = self.acx.tcx();
+            let declared = || -> Option<String> {
+                let did = match *func_ty.kind() {
+                    TyKind::FnDef(did, _) => did,
+                    _ => return None,
+                };
+                match tcx.hir().get_if_local(did)?.fn_decl()?.output {
+                    FnRetTy::Return(ty) => tcx.sess.source_map().span_to_snippet(ty.span).ok(),
+                    FnRetTy::DefaultReturn(_) => None,
+                }
+            };
+            declared().unwrap_or_else(|| match callee {
+                Callee::Strlen => "libc::size_t".to_owned(),
+                _ => "libc::c_int".to_owned(),
+            })
+        };
+
+        let rw = match callee {
+            Callee::Strlen => match arg_desc(0) {
+                Some((Quantity::CStr, unwrap)) => RewriteKind::StrLen {
+                    ret_ty: ret_ty(),
+                    slice: false,
+                    unwrap,
+                },
+                Some((Quantity::Slice | Quantity::OffsetPtr, unwrap)) => RewriteKind::StrLen {
+                    ret_ty: ret_ty(),
+                    slice: true,
+                    unwrap,
+                },
+                _ => return,
+            },
+            Callee::Strcmp => match (arg_desc(0), arg_desc(1)) {
+                (Some((Quantity::CStr, unwrap0)), Some((Quantity::CStr, unwrap1))) => {
+                    RewriteKind::StrCmp {
+                        ret_ty: ret_ty(),
+                        unwrap: [unwrap0, unwrap1],
+                    }
+                }
+                _ => return,
+            },
+            Callee::Strchr => {
+                // The result is always nullable, since `strchr` returns null when the character
+                // isn't found.
+                let out_ptr = self.acx.type_of(destination).label;
+                let out_cstr = out_ptr != PointerId::NONE
+                    && !self.flags[out_ptr].contains(FlagSet::FIXED)
+                    && type_desc::perms_to_desc(self.perms[out_ptr], self.flags[out_ptr]).1
+                        == Quantity::CStr;
+                match arg_desc(0) {
+                    Some((Quantity::CStr, unwrap)) if out_cstr => RewriteKind::StrChr { unwrap },
+                    _ => return,
+                }
+            }
+            _ => unreachable!(),
+        };
+        self.emit(rw);
+    }
+
+    /// If `ptr` will be rewritten to a `Box`, return its `Quantity`.
+    fn box_quantity(&self, ptr: PointerId) -> Option<Quantity> {
+        if ptr == PointerId::NONE {
+            return None;
+        }
+        match type_desc::perms_to_desc(self.perms[ptr], self.flags[ptr]) {
+            (Ownership::Box, qty) => Some(qty),
+            _ => None,
         }
     }
 
+    /// Whether `ptr` will be rewritten to an `Option`.
+    fn is_nullable(&self, ptr: PointerId) -> bool {
+        ptr != PointerId::NONE && type_desc::perms_to_option(self.perms[ptr], self.flags[ptr])
+    }
+
+    /// Whether `op` is a local that holds a null pointer constant.
+    fn is_null_operand(&self, op: &Operand<'tcx>) -> bool {
+        op.place()
+            .and_then(|pl| pl.as_local())
+            .map_or(false, |local| self.null_locals.contains(&local))
+    }
+
+    /// Emit `NullCheck` for a null check on `ptr`, which is subexpression `ptr_idx` of the check.
+    /// `FIXED` pointers keep their raw pointer types, so checks on them are left alone.
+    fn emit_null_check(&mut self, ptr: PointerId, ptr_idx: usize, negate: bool) {
+        if ptr == PointerId::NONE || self.flags[ptr].contains(FlagSet::FIXED) {
+            return;
+        }
+        let non_null = self.perms[ptr].contains(PermissionSet::NON_NULL);
+        self.emit(RewriteKind::NullCheck {
+            ptr_idx,
+            negate,
+            non_null,
+        });
+    }
+
+    fn print_pointee_ty(&self, lty: LTy<'tcx>) -> String {
+        // TODO: this prints the original pointee type, without applying rewrites to any pointers
+        // nested inside it
+        let printer = FmtPrinter::new(self.acx.tcx(), Namespace::TypeNS);
+        lty.args[0].ty.print(printer).unwrap().into_buffer()
+    }
+
     fn emit(&mut self, rw: RewriteKind) {
         self.rewrites
             .entry(self.loc)
@@ -413,16 +760,72 @@ impl<'a, 'tcx> ExprRewriteVisitor<'a, 'tcx> {
         assert!(expect_ptr != PointerId::NONE);
 
         let (own2, qty2) = type_desc::perms_to_desc(self.perms[expect_ptr], self.flags[expect_ptr]);
+        let option2 = self.is_nullable(expect_ptr);
 
-        self.emit_cast(ptr, own2, qty2);
+        self.emit_cast(ptr, own2, qty2, option2);
     }
 
-    fn emit_cast(&mut self, ptr: PointerId, expect_own: Ownership, expect_qty: Quantity) {
+    fn emit_cast(
+        &mut self,
+        ptr: PointerId,
+        expect_own: Ownership,
+        expect_qty: Quantity,
+        expect_option: bool,
+    ) {
         assert!(ptr != PointerId::NONE);
 
         let (own1, qty1) = type_desc::perms_to_desc(self.perms[ptr], self.flags[ptr]);
         let (own2, qty2) = (expect_own, expect_qty);
+        let (option1, option2) = (self.is_nullable(ptr), expect_option);
 
+        if (own1, qty1, option1) == (own2, qty2, option2) {
+            return;
+        }
+
+        // `as_deref` and `as_deref_mut` borrow from an `Option<&mut T>` or `Option<Box<T>>`
+        // without moving out of it.
+        let as_deref = qty1 == qty2
+            && own1 != own2
+            && matches!(own1, Ownership::Mut | Ownership::Box)
+            && matches!(own2, Ownership::Imm | Ownership::Mut);
+        let as_deref_mutbl = own2 == Ownership::Mut;
+
+        if option1 {
+            if as_deref || (qty1 == qty2 && own1 == Ownership::Mut && own2 == Ownership::Mut) {
+                self.emit(RewriteKind::OptionAsDeref {
+                    mutbl: as_deref_mutbl,
+                });
+                if !option2 {
+                    self.emit(RewriteKind::OptionUnwrap);
+                }
+                return;
+            }
+            if option2 {
+                eprintln!(
+                    "unsupported cast kind: {:?} {:?} -> {:?} (both nullable)",
+                    self.perms[ptr],
+                    (own1, qty1),
+                    (own2, qty2)
+                );
+                return;
+            }
+            self.emit(RewriteKind::OptionUnwrap);
+        }
+
+        self.emit_desc_cast(ptr, (own1, qty1), (own2, qty2));
+
+        if !option1 && option2 {
+            self.emit(RewriteKind::OptionSome);
+        }
+    }
+
+    /// Emit a cast between the non-`Option` forms of two pointer types.
+    fn emit_desc_cast(
+        &mut self,
+        ptr: PointerId,
+        (own1, qty1): (Ownership, Quantity),
+        (own2, qty2): (Ownership, Quantity),
+    ) {
         if (own1, qty1) == (own2, qty2) {
             return;
         }
@@ -432,6 +835,42 @@ impl<'a, 'tcx> ExprRewriteVisitor<'a, 'tcx> {
             return;
         }
 
+        if own1 == Ownership::Box && matches!(own2, Ownership::Imm | Ownership::Mut) {
+            // Borrow from the `Box`, leaving ownership with the original pointer.
+            let mutbl = own2 == Ownership::Mut;
+            if qty1 == qty2 {
+                self.emit(RewriteKind::BorrowBox { mutbl });
+                return;
+            }
+            if qty1 != Quantity::Single && qty2 == Quantity::Single {
+                // `Box<[T]>` can be indexed directly.
+                self.emit(RewriteKind::SliceFirst { mutbl });
+                return;
+            }
+        }
+
+        if (own2, qty2) == (Ownership::Imm, Quantity::CStr) {
+            match (own1, qty1) {
+                (Ownership::Raw | Ownership::RawMut, Quantity::Single) => {
+                    self.emit(RewriteKind::CStrFromPtr { slice: false });
+                    return;
+                }
+                (
+                    Ownership::Imm | Ownership::Mut | Ownership::Box,
+                    Quantity::Slice | Quantity::OffsetPtr,
+                ) => {
+                    self.emit(RewriteKind::CStrFromPtr { slice: true });
+                    return;
+                }
+                _ => {}
+            }
+        }
+
+        if (own1, qty1) == (Ownership::Imm, Quantity::CStr) && own2 == Ownership::Raw {
+            self.emit(RewriteKind::CStrAsPtr);
+            return;
+        }
+
         eprintln!(
             "unsupported cast kind: {:?} {:?} -> {:?}",
             self.perms[ptr],
@@ -441,6 +880,32 @@ impl<'a, 'tcx> ExprRewriteVisitor<'a, 'tcx> {
     }
 }
 
+/// Find the user variables and arguments of `mir` whose pointer types will be rewritten to
+/// `Option`.  The result maps the span of each variable's declaration to `true` if the `Option`
+/// holds a `&mut T` or `Box<T>`, so that it must be accessed through `as_deref_mut`, and `false`
+/// if it holds a `&T`.
+pub fn nullable_ptr_locals<'tcx>(
+    acx: &AnalysisCtxt<'_, 'tcx>,
+    asn: &Assignment,
+    mir: &Body<'tcx>,
+) -> HashMap<Span, bool> {
+    let (perms, flags) = (asn.perms(), asn.flags());
+    let mut out = HashMap::new();
+    for (local, decl) in mir.local_decls.iter_enumerated() {
+        if !decl.is_user_variable() && mir.args_iter().all(|arg| arg != local) {
+            continue;
+        }
+        let ptr = acx.local_tys[local].label;
+        if ptr == PointerId::NONE || !type_desc::perms_to_option(perms[ptr], flags[ptr]) {
+            continue;
+        }
+        let (own, _qty) = type_desc::perms_to_desc(perms[ptr], flags[ptr]);
+        let mutbl = matches!(own, Ownership::Mut | Ownership::Box);
+        out.insert(decl.source_info.span, mutbl);
+    }
+    out
+}
+
 pub fn gen_mir_rewrites<'tcx>(
     acx: &AnalysisCtxt<'_, 'tcx>,
     asn: &Assignment,
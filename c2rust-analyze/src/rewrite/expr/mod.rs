@@ -14,6 +14,8 @@ pub fn gen_expr_rewrites<'tcx>(
     hir_body_id: BodyId,
 ) -> Vec<(Span, Rewrite)> {
     let mir_rewrites = mir_op::gen_mir_rewrites(acx, asn, mir);
-    let hir_rewrites = hir_op::gen_hir_rewrites(acx.tcx(), mir, hir_body_id, &mir_rewrites);
+    let nullable_locals = mir_op::nullable_ptr_locals(acx, asn, mir);
+    let hir_rewrites =
+        hir_op::gen_hir_rewrites(acx.tcx(), mir, hir_body_id, &mir_rewrites, &nullable_locals);
     hir_rewrites
 }
@@ -0,0 +1,306 @@
+//! Generation of C ABI wrappers for FFI functions (see `crate::ffi`).
+//!
+//! The original function is rewritten like any other, and loses its `#[no_mangle]` or
+//! `#[export_name]` attribute.  A wrapper with the original raw pointer signature is inserted after
+//! it, named `{name}_ffi`, exported under the original symbol:
+//!
+//! ```ignore
+//! #[export_name = "sum"]
+//! pub unsafe extern "C" fn sum_ffi(p: *const i32, n: libc::c_int) -> libc::c_int {
+//!     if n < 0 { std::process::abort(); }
+//!     let p = if !p.is_null() { Some(std::slice::from_raw_parts(p, n as usize)) } else if n == 0 { Some(Default::default()) } else { None };
+//!     sum(p, n)
+//! }
+//! ```
+//!
+//! The original function loses its `extern "C"` too, since its new argument types may not be
+//! FFI-safe.  A panic can't unwind out of the wrapper, so a null argument that the original
+//! function can't accept aborts instead, as does a negative length.  A null pointer with a length
+//! of zero becomes an empty slice.
+//!
+//! Slices are built using the length parameter that `ffi::ffi_fn_def` found to bound the offsets
+//! of the pointer.  A read-only slice with no such parameter that's `NUL_TERMINATED` extends up to
+//! and including its NUL instead.  Pointers the wrapper can't convert, because they would become
+//! other slices with no length parameter or `Box`es that would free memory allocated by C, are
+//! found by [`find_unconvertible_ptrs`] and pinned before the wrapper is generated.
+
+use crate::context::{AnalysisCtxt, Assignment, FlagSet, PermissionSet, PointerId};
+use crate::ffi::{FfiFn, FfiFnDef};
+use crate::rewrite::Rewrite;
+use crate::type_desc::{self, Ownership, Quantity};
+use rustc_hir::def_id::LocalDefId;
+use rustc_hir::{Mutability, Unsafety};
+use rustc_middle::ty::{self, DefIdTree, Ty};
+use rustc_span::{sym, Span};
+
+/// How the outermost pointer of an argument or return type was rewritten: its ownership and
+/// quantity, and whether it's wrapped in `Option`.
+type PtrDesc = (Ownership, Quantity, bool);
+
+/// Describe the outermost pointer `ptr` of an argument or return type, or return `None` if it's
+/// `FIXED` and keeps its raw pointer type.
+fn ptr_desc(asn: &Assignment, ptr: PointerId) -> Option<PtrDesc> {
+    let (perms, flags) = (asn.perms(), asn.flags());
+    if ptr.is_none() || flags[ptr].contains(FlagSet::FIXED) {
+        return None;
+    }
+    let (own, qty) = type_desc::perms_to_desc(perms[ptr], flags[ptr]);
+    Some((own, qty, type_desc::perms_to_option(perms[ptr], flags[ptr])))
+}
+
+/// Whether the outermost pointer `ptr` of an argument is a read-only string that the wrapper can
+/// convert to a slice ending at its NUL, when there's no parameter giving its length.
+fn is_nul_terminated_slice(asn: &Assignment, ptr: PointerId) -> bool {
+    matches!(
+        ptr_desc(asn, ptr),
+        Some((Ownership::Imm, Quantity::Slice | Quantity::OffsetPtr, _))
+    ) && asn.perms()[ptr].contains(PermissionSet::NUL_TERMINATED)
+}
+
+/// Find the outermost pointers in the signature of the FFI function `ldid` that its wrapper
+/// can't convert, along with the reason.  These must be pinned before generating the wrapper.
+pub fn find_unconvertible_ptrs(
+    acx: &AnalysisCtxt,
+    asn: &Assignment,
+    ldid: LocalDefId,
+    def: &FfiFnDef,
+) -> Vec<(PointerId, String)> {
+    let lsig = &acx.gacx.fn_sigs[&ldid.to_def_id()];
+    let mut found = Vec::new();
+    for (i, lty) in lsig.inputs.iter().enumerate() {
+        let p = def.param_names[i];
+        let reason = match ptr_desc(asn, lty.label) {
+            None => continue,
+            Some((Ownership::Box, _, _)) => format!("argument `{}` would free C memory", p),
+            Some((Ownership::Imm | Ownership::Cell | Ownership::Mut, qty, _)) => match qty {
+                Quantity::Slice | Quantity::OffsetPtr
+                    if def.len_params[i].is_none() && !is_nul_terminated_slice(asn, lty.label) =>
+                {
+                    format!("no parameter gives the length of argument `{}`", p)
+                }
+                _ => continue,
+            },
+            Some((own, _, _)) => format!("can't convert argument `{}` to {:?}", p, own),
+        };
+        found.push((lty.label, reason));
+    }
+    let reason = match ptr_desc(asn, lsig.output.label) {
+        None => None,
+        Some((Ownership::Box, _, _)) => {
+            Some("C callers would free the return value with `free`".to_owned())
+        }
+        Some((Ownership::Imm | Ownership::Cell | Ownership::Mut, _, _)) => None,
+        Some((own, _, _)) => Some(format!("can't convert return value from {:?}", own)),
+    };
+    if let Some(reason) = reason {
+        found.push((lsig.output.label, reason));
+    }
+    found
+}
+
+/// Generate the rewrites that replace the FFI function `ldid` with a safely typed function and a
+/// wrapper that keeps its original signature.  Returns no rewrites if the signature is unchanged.
+/// The pointers found by [`find_unconvertible_ptrs`] must already be pinned.
+pub fn gen_ffi_wrapper_rewrites<'tcx>(
+    acx: &AnalysisCtxt<'_, 'tcx>,
+    asn: &Assignment,
+    ldid: LocalDefId,
+    ffi_fn: &FfiFn,
+    def: &FfiFnDef<'tcx>,
+) -> Vec<(Span, Rewrite)> {
+    let tcx = acx.tcx();
+    let did = ldid.to_def_id();
+
+    let lsig = &acx.gacx.fn_sigs[&did];
+    let arg_descs = lsig
+        .inputs
+        .iter()
+        .map(|lty| ptr_desc(asn, lty.label))
+        .collect::<Vec<_>>();
+    let ret_desc = ptr_desc(asn, lsig.output.label);
+    if arg_descs.iter().all(Option::is_none) && ret_desc.is_none() {
+        return Vec::new();
+    }
+
+    let fn_sig = tcx.fn_sig(did).skip_binder();
+    let arg_tys = fn_sig.inputs();
+    let names = def
+        .param_names
+        .iter()
+        .map(|name| name.to_string())
+        .collect::<Vec<_>>();
+    let name = tcx.item_name(did);
+    let wrapper_name = format!("{}_ffi", name);
+
+    // Convert the arguments, then call the original function.
+    let mut stmts = Vec::new();
+    let mut call_args = Vec::new();
+    for (i, (&arg_ty, &arg_desc)) in arg_tys.iter().zip(&arg_descs).enumerate() {
+        let nul_terminated = is_nul_terminated_slice(asn, lsig.inputs[i].label);
+        let p = &names[i];
+        call_args.push(p.clone());
+        let arg_desc = match arg_desc {
+            Some(x) => x,
+            None => continue,
+        };
+        let mutbl = raw_ptr_mutbl(arg_ty);
+        let (own, qty, option) = arg_desc;
+
+        let p_mut = match mutbl {
+            Mutability::Not => format!("({} as *mut _)", p),
+            Mutability::Mut => p.clone(),
+        };
+        let len_idx = match qty {
+            Quantity::Slice | Quantity::OffsetPtr
+                if def.len_params[i].is_none() && nul_terminated =>
+            {
+                None
+            }
+            Quantity::Slice | Quantity::OffsetPtr => Some(
+                def.len_params[i]
+                    .unwrap_or_else(|| panic!("argument `{}` should have been pinned", p)),
+            ),
+            Quantity::Single | Quantity::CStr => None,
+        };
+        let conv = match qty {
+            Quantity::Single => match own {
+                Ownership::Imm => format!("&*{}", p),
+                Ownership::Cell => format!("&*{}.cast::<std::cell::Cell<_>>()", p),
+                Ownership::Mut => format!("&mut *{}", p_mut),
+                _ => unreachable!("argument `{}` should have been pinned", p),
+            },
+            Quantity::Slice | Quantity::OffsetPtr if len_idx.is_none() => format!(
+                "std::slice::from_raw_parts({p}, std::ffi::CStr::from_ptr({p}).to_bytes_with_nul().len())"
+            ),
+            Quantity::Slice | Quantity::OffsetPtr => {
+                let len = format!("{} as usize", names[len_idx.unwrap()]);
+                match own {
+                    Ownership::Imm => format!("std::slice::from_raw_parts({}, {})", p, len),
+                    Ownership::Cell => format!(
+                        "std::slice::from_raw_parts({}.cast::<std::cell::Cell<_>>(), {})",
+                        p, len
+                    ),
+                    Ownership::Mut => format!("std::slice::from_raw_parts_mut({}, {})", p_mut, len),
+                    _ => unreachable!("argument `{}` should have been pinned", p),
+                }
+            }
+            Quantity::CStr => format!("std::ffi::CStr::from_ptr({})", p),
+        };
+
+        if let Some(len_idx) = len_idx {
+            // `from_raw_parts` needs a non-null pointer and a length that fits in `usize`, but C
+            // callers often pass null with a length of zero for an empty array.
+            let n = &names[len_idx];
+            let check_len = format!("if {n} < 0 {{ std::process::abort(); }}");
+            if arg_tys[len_idx].is_signed() && !stmts.contains(&check_len) {
+                stmts.push(check_len);
+            }
+            let (conv, empty, null) = if option {
+                (format!("Some({conv})"), "Some(Default::default())", "None")
+            } else {
+                (conv, "Default::default()", "std::process::abort()")
+            };
+            let empty_or_null = format!("if {n} == 0 {{ {empty} }} else {{ {null} }}");
+            stmts.push(format!(
+                "let {p} = if !{p}.is_null() {{ {conv} }} else {empty_or_null};"
+            ));
+        } else if option {
+            stmts.push(format!(
+                "let {p} = if {p}.is_null() {{ None }} else {{ Some({conv}) }};"
+            ));
+        } else {
+            // Unwinding out of an `extern "C"` function is undefined behavior.
+            stmts.push(format!("if {p}.is_null() {{ std::process::abort(); }}"));
+            stmts.push(format!("let {p} = {conv};"));
+        }
+    }
+    let call = format!("{}({})", name, call_args.join(", "));
+
+    // Convert the result back to a raw pointer.
+    match ret_desc {
+        None => stmts.push(call),
+        Some((own, qty, option)) => {
+            let mutbl = raw_ptr_mutbl(fn_sig.output());
+            let conv = match (qty, own) {
+                (Quantity::Single, Ownership::Imm) => "r as *const _ as _",
+                (Quantity::Single, Ownership::Mut) => "r as *mut _ as _",
+                (Quantity::Single, Ownership::Cell) => "r.as_ptr() as _",
+                (_, Ownership::Imm | Ownership::Cell) => "r.as_ptr() as _",
+                (_, Ownership::Mut) => "r.as_mut_ptr() as _",
+                _ => unreachable!("return value should have been pinned"),
+            };
+            stmts.push(format!("let r = {};", call));
+            if option {
+                let null = match mutbl {
+                    Mutability::Not => "std::ptr::null()",
+                    Mutability::Mut => "std::ptr::null_mut()",
+                };
+                stmts.push(format!(
+                    "match r {{ Some(r) => {}, None => {} }}",
+                    conv, null
+                ));
+            } else {
+                stmts.push(conv.to_string());
+            }
+        }
+    }
+
+    // Build the wrapper from the original signature.
+    let mut wrapper = String::from("\n\n");
+    if let Some(symbol) = ffi_fn.export_symbol {
+        wrapper.push_str(&format!("#[export_name = {:?}]\n", symbol.as_str()));
+    }
+    match tcx.visibility(did) {
+        ty::Visibility::Public => wrapper.push_str("pub "),
+        ty::Visibility::Restricted(module) if module != tcx.parent(did) => {
+            wrapper.push_str("pub(crate) ")
+        }
+        _ => {}
+    }
+    wrapper.push_str(&format!(
+        "{}{}{} {{\n",
+        def.header, wrapper_name, def.params_and_ret
+    ));
+    let indent = match def.sig.header.unsafety {
+        Unsafety::Unsafe => "    ",
+        Unsafety::Normal => {
+            wrapper.push_str("    unsafe {\n");
+            "        "
+        }
+    };
+    for stmt in &stmts {
+        wrapper.push_str(indent);
+        wrapper.push_str(stmt);
+        wrapper.push('\n');
+    }
+    if def.sig.header.unsafety == Unsafety::Normal {
+        wrapper.push_str("    }\n");
+    }
+    wrapper.push('}');
+
+    let mut rewrites = vec![(def.item.span, Rewrite::Append(wrapper))];
+    // The wrapper now provides the exported symbol.
+    for attr in tcx.hir().attrs(def.item.hir_id()) {
+        if attr.has_name(sym::no_mangle) || attr.has_name(sym::export_name) {
+            rewrites.push((attr.span, Rewrite::Text(String::new())));
+        }
+    }
+    // ... and the C ABI, so the original function can take arguments that aren't FFI-safe.
+    if let Some(span) = def.abi_span {
+        rewrites.push((span, Rewrite::Text(String::new())));
+    }
+    // Foreign code that receives a pointer to this function expects the original signature.
+    for &span in &ffi_fn.addr_taken_spans {
+        rewrites.push((span, Rewrite::Text(wrapper_name.clone())));
+    }
+    rewrites
+}
+
+/// The mutability of `ty`, which `ffi::ffi_fn_def` checked is a raw pointer if it has a pointer
+/// to convert.
+fn raw_ptr_mutbl(ty: Ty) -> Mutability {
+    match *ty.kind() {
+        ty::RawPtr(tm) => tm.mutbl,
+        _ => panic!("expected a raw pointer, but got {:?}", ty),
+    }
+}
@@ -14,7 +14,8 @@
 //!
 //! 3. Apply the rewrites to the source code of the input program. This reads the source of each
 //!    file and emits a new string consisting of the file source with certain `Span`s rewritten as
-//!    specified by the HIR rewrites. The code for this is in `rewrite::apply`.
+//!    specified by the HIR rewrites. The code for this is in `rewrite::apply`, and
+//!    `rewrite::output` writes the result to disk, as a diff, or as JSON.
 //!
 //! This covers rewriting of expressions; rewriting of types is similar but mostly skips step 1,
 //! since an abstract description of the changes to be made can be obtained by inspecting the
@@ -32,15 +33,25 @@ use std::fmt;
 
 mod apply;
 mod expr;
+mod ffi;
+mod output;
 mod span_index;
 mod statics;
 mod ty;
 
 pub use self::expr::gen_expr_rewrites;
+pub use self::ffi::{find_unconvertible_ptrs, gen_ffi_wrapper_rewrites};
+pub use self::output::OutputMode;
 use self::span_index::SpanIndex;
 pub use self::statics::gen_static_rewrites;
 pub use self::ty::dump_rewritten_local_tys;
+pub use self::ty::gen_adt_ty_rewrites;
 pub use self::ty::gen_ty_rewrites;
+pub use self::ty::pin_unrewritable_field_tys;
+
+/// The body of the block emitted for `Rewrite::CStrChr`, which has `s` and `c` in scope.
+const CSTR_CHR_BODY: &str = "s.to_bytes_with_nul().iter().position(|&b| b == c as u8).map(|i| \
+    std::ffi::CStr::from_bytes_with_nul(&s.to_bytes_with_nul()[i..]).unwrap())";
 
 #[derive(Clone, PartialEq, Eq, Debug)]
 pub enum Rewrite<S = Span> {
@@ -62,25 +73,54 @@ pub enum Rewrite<S = Span> {
     SliceTail(Box<Rewrite>, Box<Rewrite>),
     /// `e as T`
     Cast(Box<Rewrite>, String),
+    /// `x / y`
+    Div(Box<Rewrite>, Box<Rewrite>),
+    /// `lhs = rhs`
+    Assign(Box<Rewrite>, Box<Rewrite>),
     /// The integer literal `0`.
     LitZero,
+    /// Fixed source text, such as `None` or `false`.
+    Text(String),
     // Function calls
     Call(String, Vec<Rewrite>),
     // Method calls
     MethodCall(String, Box<Rewrite>, Vec<Rewrite>),
+    /// `vec![elem; len]`
+    VecRepeat(Box<Rewrite>, Box<Rewrite>),
+    /// `{ let (mut v, n) = (vec, len); v.resize(n, fill); v }`.  `len` is evaluated before `v`
+    /// and `n` come into scope, so it can't be captured by them.
+    VecResize(Box<Rewrite>, Box<Rewrite>, Box<Rewrite>),
+    /// `let Some(name) = e`, for use as the condition of an `if`.
+    LetSome(String, Box<Rewrite>),
+    /// `let Some(name) = e else`, replacing the `if cond` of an early-exit guard so that its block
+    /// becomes the `else` block.
+    LetSomeElse(String, Box<Rewrite>),
+    /// `{ let (s, c) = (s, c); ... }`: the suffix of the `&CStr` `s` starting at the first
+    /// occurrence of the character `c`, or `None` if there is none, as with `strchr`.
+    CStrChr(Box<Rewrite>, Box<Rewrite>),
+    /// `$e` followed by fixed source text, such as a new item to insert after the one at `$e`.
+    Append(String),
 
     // Type builders
     /// Emit a complete pretty-printed type, discarding the original annotation.
     PrintTy(String),
     /// `*const T`, `*mut T`
     TyPtr(Box<Rewrite>, Mutability),
-    /// `&T`, `&mut T`
-    TyRef(Box<Rewrite>, Mutability),
+    /// `&T`, `&mut T`, or with an explicit lifetime, `&'a T`, `&'a mut T`
+    TyRef(Box<Rewrite>, Mutability, Option<String>),
     /// `[T]`
     TySlice(Box<Rewrite>),
     /// `Foo<T1, T2>`
     TyCtor(String, Vec<Rewrite>),
 
+    // Lifetime builders
+    /// `$e<'a, 'b>`: add lifetime arguments to a type, or lifetime parameters to the name of a type
+    /// definition, that has no generic arguments or parameters yet.
+    WithLifetimes(Vec<String>),
+    /// `$e, 'a, 'b`: add lifetimes after `$e`, which is the last existing lifetime argument or
+    /// parameter.
+    ExtraLifetimes(Vec<String>),
+
     // `static` builders
     /// `static` mutability (`static` <-> `static mut`)
     StaticMut(Mutability, S),
@@ -120,9 +160,11 @@ impl Rewrite {
         }
 
         // Expr precedence:
-        // - Index, SliceTail: 3
-        // - Ref, Deref: 2
-        // - Cast: 1
+        // - Index, SliceTail, method call receivers: 4
+        // - Ref, Deref: 3
+        // - Cast: 2
+        // - Div: 1
+        // - Assign: 0
         //
         // Currently, we don't have any type builders that require parenthesization.
 
@@ -132,12 +174,12 @@ impl Rewrite {
             Rewrite::Identity => write!(f, "$e"),
             Rewrite::Sub(i, _) => write!(f, "${}", i),
 
-            Rewrite::Ref(ref rw, mutbl) => parenthesize_if(prec > 2, f, |f| {
+            Rewrite::Ref(ref rw, mutbl) => parenthesize_if(prec > 3, f, |f| {
                 match mutbl {
                     Mutability::Not => write!(f, "&")?,
                     Mutability::Mut => write!(f, "&mut ")?,
                 }
-                rw.pretty(f, 2)
+                rw.pretty(f, 3)
             }),
             Rewrite::AddrOf(ref rw, mutbl) => {
                 match mutbl {
@@ -148,29 +190,40 @@ impl Rewrite {
                 rw.pretty(f, 0)?;
                 f.write_str(")")
             }
-            Rewrite::Deref(ref rw) => parenthesize_if(prec > 2, f, |f| {
+            Rewrite::Deref(ref rw) => parenthesize_if(prec > 3, f, |f| {
                 write!(f, "*")?;
-                rw.pretty(f, 2)
+                rw.pretty(f, 3)
             }),
-            Rewrite::Index(ref arr, ref idx) => parenthesize_if(prec > 3, f, |f| {
-                arr.pretty(f, 3)?;
+            Rewrite::Index(ref arr, ref idx) => parenthesize_if(prec > 4, f, |f| {
+                arr.pretty(f, 4)?;
                 write!(f, "[")?;
                 idx.pretty(f, 0)?;
                 write!(f, "]")
             }),
-            Rewrite::SliceTail(ref arr, ref idx) => parenthesize_if(prec > 3, f, |f| {
-                arr.pretty(f, 3)?;
+            Rewrite::SliceTail(ref arr, ref idx) => parenthesize_if(prec > 4, f, |f| {
+                arr.pretty(f, 4)?;
                 write!(f, "[")?;
                 // Rather than figure out the right precedence for `..`, just force
                 // parenthesization in this position.
                 idx.pretty(f, 999)?;
                 write!(f, " ..]")
             }),
-            Rewrite::Cast(ref rw, ref ty) => parenthesize_if(prec > 1, f, |f| {
-                rw.pretty(f, 1)?;
+            Rewrite::Cast(ref rw, ref ty) => parenthesize_if(prec > 2, f, |f| {
+                rw.pretty(f, 2)?;
                 write!(f, " as {}", ty)
             }),
+            Rewrite::Div(ref x, ref y) => parenthesize_if(prec > 0, f, |f| {
+                x.pretty(f, 1)?;
+                write!(f, " / ")?;
+                y.pretty(f, 2)
+            }),
+            Rewrite::Assign(ref lhs, ref rhs) => parenthesize_if(prec > 0, f, |f| {
+                lhs.pretty(f, 1)?;
+                write!(f, " = ")?;
+                rhs.pretty(f, 0)
+            }),
             Rewrite::LitZero => write!(f, "0"),
+            Rewrite::Text(ref s) => f.write_str(s),
 
             Rewrite::PrintTy(ref s) => {
                 write!(f, "{}", s)
@@ -187,7 +240,7 @@ impl Rewrite {
                 f.write_str(")")
             }
             Rewrite::MethodCall(ref method, ref receiver_rw, ref arg_rws) => {
-                receiver_rw.pretty(f, 0)?;
+                receiver_rw.pretty(f, 4)?;
                 f.write_str(".")?;
                 f.write_str(method)?;
                 f.write_str("(")?;
@@ -199,6 +252,39 @@ impl Rewrite {
                 }
                 f.write_str(")")
             }
+            Rewrite::VecRepeat(ref elem, ref len) => {
+                write!(f, "vec![")?;
+                elem.pretty(f, 0)?;
+                write!(f, "; ")?;
+                len.pretty(f, 0)?;
+                write!(f, "]")
+            }
+            Rewrite::VecResize(ref vec, ref len, ref fill) => parenthesize_if(prec > 0, f, |f| {
+                write!(f, "{{ let (mut v, n) = (")?;
+                vec.pretty(f, 0)?;
+                write!(f, ", ")?;
+                len.pretty(f, 0)?;
+                write!(f, "); v.resize(n, ")?;
+                fill.pretty(f, 0)?;
+                write!(f, "); v }}")
+            }),
+            Rewrite::LetSome(ref name, ref rw) => {
+                write!(f, "let Some({}) = ", name)?;
+                rw.pretty(f, 0)
+            }
+            Rewrite::LetSomeElse(ref name, ref rw) => {
+                write!(f, "let Some({}) = ", name)?;
+                rw.pretty(f, 0)?;
+                write!(f, " else")
+            }
+            Rewrite::CStrChr(ref s, ref c) => parenthesize_if(prec > 0, f, |f| {
+                write!(f, "{{ let (s, c) = (")?;
+                s.pretty(f, 0)?;
+                write!(f, ", ")?;
+                c.pretty(f, 0)?;
+                write!(f, "); {} }}", CSTR_CHR_BODY)
+            }),
+            Rewrite::Append(ref s) => write!(f, "$e{}", s),
             Rewrite::TyPtr(ref rw, mutbl) => {
                 match mutbl {
                     Mutability::Not => write!(f, "*const ")?,
@@ -206,10 +292,13 @@ impl Rewrite {
                 }
                 rw.pretty(f, 0)
             }
-            Rewrite::TyRef(ref rw, mutbl) => {
-                match mutbl {
-                    Mutability::Not => write!(f, "&")?,
-                    Mutability::Mut => write!(f, "&mut ")?,
+            Rewrite::TyRef(ref rw, mutbl, ref lifetime) => {
+                write!(f, "&")?;
+                if let Some(lifetime) = lifetime {
+                    write!(f, "{} ", lifetime)?;
+                }
+                if mutbl == Mutability::Mut {
+                    write!(f, "mut ")?;
                 }
                 rw.pretty(f, 0)
             }
@@ -226,6 +315,9 @@ impl Rewrite {
                 write!(f, ">")
             }
 
+            Rewrite::WithLifetimes(ref lifetimes) => write!(f, "$e<{}>", lifetimes.join(", ")),
+            Rewrite::ExtraLifetimes(ref lifetimes) => write!(f, "$e, {}", lifetimes.join(", ")),
+
             Rewrite::StaticMut(mutbl, _) => {
                 match mutbl {
                     Mutability::Not => write!(f, "static (-mut) ")?,
@@ -270,24 +362,12 @@ fn build_span_index(mir: &Body<'_>) -> SpanIndex<Location> {
     SpanIndex::new(span_index_items)
 }
 
-pub fn apply_rewrites(tcx: TyCtxt, rewrites: Vec<(Span, Rewrite)>) {
-    // TODO: emit new source code properly instead of just printing
-    let new_src = apply::apply_rewrites(tcx.sess.source_map(), rewrites);
-
-    for (filename, src) in new_src {
-        eprintln!("\n\n ===== BEGIN {:?} =====", filename);
-        for line in src.lines() {
-            // Omit filecheck directives from the debug output, as filecheck can get confused due
-            // to directives matching themselves (e.g. `// CHECK: foo` will match the `foo` in the
-            // line `// CHECK: foo`).
-            if let Some((pre, _post)) = line.split_once("// CHECK") {
-                eprintln!("{}// (FileCheck directive omitted)", pre);
-            } else {
-                eprintln!("{}", line);
-            }
-        }
-        eprintln!(" ===== END {:?} =====", filename);
-    }
+/// Apply `rewrites` and output the rewritten source code as selected by `mode`; see the
+/// `rewrite::output` module for the available modes.
+pub fn apply_rewrites(tcx: TyCtxt, rewrites: Vec<(Span, Rewrite)>, mode: OutputMode) {
+    let source_map = tcx.sess.source_map();
+    let (files, errs) = apply::apply_rewrites(source_map, rewrites);
+    output::write_output(source_map, mode, &files, &errs);
 }
 
 #[cfg(test)]
@@ -0,0 +1,376 @@
+//! Output of the rewritten source code.  The `--rewrite-mode` flag selects what to do with it:
+//!
+//! * `print` (the default): print each rewritten file to stderr.  This is meant for debugging and
+//!   for the FileCheck tests.
+//! * `inplace`: overwrite the original files.
+//! * `alongside`: write the rewritten version of each `foo.rs` to `foo.new.rs`.
+//! * `unified-diff`: print a unified diff of all rewritten files to stdout.
+//! * `json`: print the applied rewrites and the rewrites that failed to apply to stdout as JSON,
+//!   like the `json` output mode of `c2rust-refactor`.
+//!
+//! Except in `json` mode, rewrites that failed to apply are reported as warnings on stderr.
+
+use super::apply::{RewriteError, RewrittenFile};
+use super::Rewrite;
+use rustc_span::source_map::{FileName, SourceMap};
+use rustc_span::Span;
+use serde_json::{json, Value};
+use std::fmt::Write as _;
+use std::fs;
+use std::ops::Range;
+use std::path::Path;
+use std::str::FromStr;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum OutputMode {
+    Print,
+    InPlace,
+    Alongside,
+    UnifiedDiff,
+    Json,
+}
+
+impl FromStr for OutputMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<OutputMode, String> {
+        Ok(match s {
+            "print" => OutputMode::Print,
+            "inplace" => OutputMode::InPlace,
+            "alongside" => OutputMode::Alongside,
+            "unified-diff" => OutputMode::UnifiedDiff,
+            "json" => OutputMode::Json,
+            _ => {
+                return Err(format!(
+                    "expected one of print, inplace, alongside, unified-diff, json, but got {:?}",
+                    s
+                ))
+            }
+        })
+    }
+}
+
+pub fn write_output(
+    source_map: &SourceMap,
+    mode: OutputMode,
+    files: &[RewrittenFile],
+    errs: &[(Span, Rewrite, RewriteError)],
+) {
+    if mode == OutputMode::Json {
+        let rewrites = files
+            .iter()
+            .flat_map(|file| {
+                file.rewrites.iter().map(move |applied| {
+                    json!({
+                        "span": encode_span(source_map, applied.span),
+                        "rewrite": applied.rw.to_string(),
+                        "new_src": &file.new_src[applied.new.clone()],
+                    })
+                })
+            })
+            .collect::<Vec<_>>();
+        let failed = errs
+            .iter()
+            .map(|(span, rw, err)| {
+                json!({
+                    "span": encode_span(source_map, *span),
+                    "rewrite": rw.to_string(),
+                    "error": encode_error(source_map, err),
+                })
+            })
+            .collect::<Vec<_>>();
+        let j = json!({ "rewrites": rewrites, "failed": failed });
+        println!("{}", serde_json::to_string_pretty(&j).unwrap());
+        return;
+    }
+
+    for (span, rw, err) in errs {
+        eprintln!(
+            "{:?}: warning: failed to apply rewrite {:?}: {:?}",
+            span, rw, err
+        );
+    }
+
+    for file in files {
+        match mode {
+            OutputMode::Print => print_file(file),
+            OutputMode::InPlace | OutputMode::Alongside => {
+                let path = match file.name {
+                    FileName::Real(ref real) => real.local_path(),
+                    _ => None,
+                };
+                let path = match path {
+                    Some(path) => path,
+                    None => {
+                        eprintln!(
+                            "warning: can't write rewritten {}, which is not a local file",
+                            file.name.prefer_local()
+                        );
+                        continue;
+                    }
+                };
+                let dest = if mode == OutputMode::Alongside {
+                    path.with_extension("new.rs")
+                } else {
+                    path.to_owned()
+                };
+                fs::write(&dest, &file.new_src)
+                    .unwrap_or_else(|e| panic!("failed to write {:?}: {}", dest, e));
+            }
+            OutputMode::UnifiedDiff => {
+                let path = file.name.prefer_local().to_string();
+                let changes = file
+                    .rewrites
+                    .iter()
+                    .map(|applied| (applied.old.clone(), applied.new.clone()))
+                    .collect::<Vec<_>>();
+                print!(
+                    "{}",
+                    unified_diff(Path::new(&path), &file.old_src, &file.new_src, &changes)
+                );
+            }
+            OutputMode::Json => unreachable!(),
+        }
+    }
+}
+
+fn print_file(file: &RewrittenFile) {
+    eprintln!("\n\n ===== BEGIN {:?} =====", file.name);
+    for line in file.new_src.lines() {
+        // Omit filecheck directives from the debug output, as filecheck can get confused due
+        // to directives matching themselves (e.g. `// CHECK: foo` will match the `foo` in the
+        // line `// CHECK: foo`).
+        if let Some((pre, _post)) = line.split_once("// CHECK") {
+            eprintln!("{}// (FileCheck directive omitted)", pre);
+        } else {
+            eprintln!("{}", line);
+        }
+    }
+    eprintln!(" ===== END {:?} =====", file.name);
+}
+
+fn encode_span(source_map: &SourceMap, span: Span) -> Value {
+    let lo = source_map.lookup_byte_offset(span.lo());
+    let hi = source_map.lookup_byte_offset(span.hi());
+    let src = lo
+        .sf
+        .src
+        .as_ref()
+        .map(|src| &src[lo.pos.0 as usize..hi.pos.0 as usize]);
+    json!({
+        "file": lo.sf.name.prefer_local().to_string(),
+        "lo": lo.pos.0,
+        "hi": hi.pos.0,
+        "src": src,
+    })
+}
+
+fn encode_error(source_map: &SourceMap, err: &RewriteError) -> Value {
+    match *err {
+        RewriteError::PartialOverlap(other) => json!({
+            "kind": "partial_overlap",
+            "other_span": encode_span(source_map, other),
+        }),
+        RewriteError::Conflict => json!({ "kind": "conflict" }),
+        RewriteError::_Discarded(other, ref other_rw) => json!({
+            "kind": "discarded",
+            "other_span": encode_span(source_map, other),
+            "other_rewrite": other_rw.to_string(),
+        }),
+    }
+}
+
+/// Number of unchanged lines shown before and after each change in a unified diff.
+const CONTEXT: usize = 3;
+
+/// Byte offsets of the start of each line of `s`.
+fn line_starts(s: &str) -> Vec<usize> {
+    let mut starts = vec![0];
+    starts.extend(
+        s.match_indices('\n')
+            .map(|(i, _)| i + 1)
+            .filter(|&i| i < s.len()),
+    );
+    starts
+}
+
+/// The lines of `s` containing the bytes in `range`, or the line containing `range.start` if
+/// `range` is empty.
+fn line_range(starts: &[usize], range: &Range<usize>) -> Range<usize> {
+    let line_of = |pos: usize| starts.partition_point(|&start| start <= pos) - 1;
+    line_of(range.start)..line_of(range.end.max(range.start + 1) - 1) + 1
+}
+
+/// Produce a unified diff between `old` and `new`, which differ exactly in `changes`: pairs of
+/// byte ranges of `old` and the corresponding byte ranges of `new`, sorted by position.
+fn unified_diff(
+    path: &Path,
+    old: &str,
+    new: &str,
+    changes: &[(Range<usize>, Range<usize>)],
+) -> String {
+    let old_starts = line_starts(old);
+    let new_starts = line_starts(new);
+    let line = |s: &'_ str, starts: &[usize], i: usize| -> String {
+        let end = starts.get(i + 1).copied().unwrap_or(s.len());
+        let text = &s[starts[i]..end];
+        if text.ends_with('\n') {
+            text.to_owned()
+        } else {
+            format!("{}\n\\ No newline at end of file\n", text)
+        }
+    };
+
+    // The old and new lines of each change, merging changes on the same line.  The text between
+    // changes is the same in `old` and `new`, so it consists of the same number of lines.
+    let mut line_changes: Vec<(Range<usize>, Range<usize>)> = Vec::new();
+    for (old_range, new_range) in changes {
+        let old_lines = line_range(&old_starts, old_range);
+        let new_lines = line_range(&new_starts, new_range);
+        match line_changes.last_mut() {
+            Some((prev_old, prev_new)) if old_lines.start < prev_old.end => {
+                prev_old.end = prev_old.end.max(old_lines.end);
+                prev_new.end = prev_new.end.max(new_lines.end);
+            }
+            _ => line_changes.push((old_lines, new_lines)),
+        }
+    }
+
+    let mut out = String::new();
+    if line_changes.is_empty() {
+        return out;
+    }
+    writeln!(out, "--- {}", path.display()).unwrap();
+    writeln!(out, "+++ {}", path.display()).unwrap();
+
+    let mut i = 0;
+    while i < line_changes.len() {
+        // Changes separated by at most twice the context go in the same hunk.
+        let mut j = i + 1;
+        while j < line_changes.len()
+            && line_changes[j].0.start - line_changes[j - 1].0.end <= 2 * CONTEXT
+        {
+            j += 1;
+        }
+        let hunk = &line_changes[i..j];
+        i = j;
+
+        let (first_old, first_new) = &hunk[0];
+        let (last_old, last_new) = &hunk[hunk.len() - 1];
+        let before = first_old.start.min(CONTEXT);
+        let after = (old_starts.len() - last_old.end).min(CONTEXT);
+        let old_lines = first_old.start - before..last_old.end + after;
+        let new_lines = first_new.start - before..last_new.end + after;
+        writeln!(
+            out,
+            "@@ -{},{} +{},{} @@",
+            old_lines.start + 1,
+            old_lines.len(),
+            new_lines.start + 1,
+            new_lines.len(),
+        )
+        .unwrap();
+
+        let mut pos = old_lines.start;
+        for (old_range, new_range) in hunk {
+            for l in pos..old_range.start {
+                write!(out, " {}", line(old, &old_starts, l)).unwrap();
+            }
+            for l in old_range.clone() {
+                write!(out, "-{}", line(old, &old_starts, l)).unwrap();
+            }
+            for l in new_range.clone() {
+                write!(out, "+{}", line(new, &new_starts, l)).unwrap();
+            }
+            pos = old_range.end;
+        }
+        for l in pos..old_lines.end {
+            write!(out, " {}", line(old, &old_starts, l)).unwrap();
+        }
+    }
+
+    out
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// Test `unified_diff` with changes that are merged into one hunk and changes that aren't.
+    #[test]
+    fn unified_diff_hunks() {
+        let old = (1..=20).map(|i| format!("line {i}\n")).collect::<String>();
+        let new = old
+            .replace("line 2\n", "line two\n")
+            .replace("line 4\n", "line four\n")
+            .replace("line 15\n", "line fifteen\nand a half\n");
+        let word = |s: &str, w: &str| {
+            let start = s.find(&format!("line {w}\n")).unwrap() + "line ".len();
+            start..start + w.len()
+        };
+        let changes = [
+            (word(&old, "2"), word(&new, "two")),
+            (word(&old, "4"), word(&new, "four")),
+            (word(&old, "15"), word(&new, "fifteen\nand a half")),
+        ];
+
+        let diff = unified_diff(Path::new("foo.rs"), &old, &new, &changes);
+        assert_eq!(
+            diff,
+            "\
+--- foo.rs
++++ foo.rs
+@@ -1,7 +1,7 @@
+ line 1
+-line 2
++line two
+ line 3
+-line 4
++line four
+ line 5
+ line 6
+ line 7
+@@ -12,7 +12,8 @@
+ line 12
+ line 13
+ line 14
+-line 15
++line fifteen
++and a half
+ line 16
+ line 17
+ line 18
+"
+        );
+    }
+
+    /// Test `unified_diff` with two changes on the same line at the end of a file without a
+    /// trailing newline.
+    #[test]
+    fn unified_diff_same_line() {
+        let old = "a\nx + y";
+        let new = "a\n(x) + (y)";
+        let changes = [(2..3, 2..5), (6..7, 8..11)];
+        let diff = unified_diff(Path::new("foo.rs"), old, new, &changes);
+        assert_eq!(
+            diff,
+            "\
+--- foo.rs
++++ foo.rs
+@@ -1,2 +1,2 @@
+ a
+-x + y
+\\ No newline at end of file
++(x) + (y)
+\\ No newline at end of file
+"
+        );
+    }
+
+    #[test]
+    fn parse_output_mode() {
+        assert_eq!("unified-diff".parse(), Ok(OutputMode::UnifiedDiff));
+        assert_eq!("json".parse(), Ok(OutputMode::Json));
+        assert!("diff".parse::<OutputMode>().is_err());
+    }
+}
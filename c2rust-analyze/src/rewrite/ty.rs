@@ -4,16 +4,19 @@
 //! with the materialization of adjustments in expr rewriting, we try to apply this transformation
 //! selectively, since we don't want to unfold all type aliases in the program.
 
-use std::collections::HashMap;
+use std::collections::{BTreeSet, HashMap};
+use std::ops::Index;
 
-use crate::context::{AnalysisCtxt, Assignment, LTy};
+use crate::borrowck::OriginArg;
+use crate::context::{AnalysisCtxt, Assignment, FlagSet, LTy, PermissionSet, PointerId};
 use crate::labeled_ty::{LabeledTy, LabeledTyCtxt};
 use crate::rewrite::Rewrite;
 use crate::type_desc::{self, Ownership, Quantity};
+use crate::{AdtMetadataTable, GlobalAnalysisCtxt, GlobalAssignment};
 use rustc_ast::ast;
 use rustc_hir as hir;
 use rustc_hir::def::{DefKind, Namespace, Res};
-use rustc_hir::def_id::LocalDefId;
+use rustc_hir::def_id::{DefId, LocalDefId};
 use rustc_hir::intravisit;
 use rustc_hir::Mutability;
 use rustc_middle::hir::nested_filter;
@@ -21,37 +24,51 @@ use rustc_middle::mir::{self, Body, LocalDecl};
 use rustc_middle::ty::print::{FmtPrinter, Print};
 use rustc_middle::ty::subst::GenericArg;
 use rustc_middle::ty::{self, ReErased, TyCtxt};
-use rustc_span::Span;
+use rustc_span::{sym, Span};
 
 /// A label for use with `LabeledTy` to indicate what rewrites to apply at each position in a type.
 #[derive(Clone, Copy, PartialEq, Eq, Debug, Hash)]
-struct RewriteLabel {
+struct RewriteLabel<'tcx> {
     /// Rewrite a raw pointer, whose ownership and quantity have been inferred as indicated.
     ty_desc: Option<(Ownership, Quantity)>,
+    /// Wrap the rewritten pointer in `Option`, since it may be null.  Only set along with
+    /// `ty_desc`.
+    option: bool,
     /// If set, a child or other descendant of this type requires rewriting.
     descendant_has_rewrite: bool,
+    /// The lifetime to write on the reference produced by `ty_desc`, if any.  This is only set in
+    /// struct field types, where lifetimes can't be elided.
+    lifetime: Option<OriginArg<'tcx>>,
 }
 
-type RwLTy<'tcx> = LabeledTy<'tcx, RewriteLabel>;
+type RwLTy<'tcx> = LabeledTy<'tcx, RewriteLabel<'tcx>>;
 
 /// Given an `LTy`, which is labeled with `PointerId`s, determine which rewrites to apply based on
 /// the permissions and flags inferred for each `PointerId`.
-fn relabel_rewrites<'tcx>(
-    asn: &Assignment,
-    lcx: LabeledTyCtxt<'tcx, RewriteLabel>,
+fn relabel_rewrites<'tcx, P, F>(
+    perms: &P,
+    flags: &F,
+    lcx: LabeledTyCtxt<'tcx, RewriteLabel<'tcx>>,
     lty: LTy<'tcx>,
-) -> RwLTy<'tcx> {
+) -> RwLTy<'tcx>
+where
+    P: Index<PointerId, Output = PermissionSet>,
+    F: Index<PointerId, Output = FlagSet>,
+{
     lcx.relabel_with_args(lty, &mut |lty, args| {
-        let ty_desc = if lty.label.is_none() {
+        let ty_desc = if lty.label.is_none() || flags[lty.label].contains(FlagSet::FIXED) {
+            // `FIXED` pointers keep their original type.
             None
         } else {
-            let perms = asn.perms()[lty.label];
-            let flags = asn.flags()[lty.label];
+            let perms = perms[lty.label];
+            let flags = flags[lty.label];
             // TODO: if the `Ownership` and `Quantity` exactly match `lty.ty`, then `ty_desc` can
             // be `None` (no rewriting required).  This might let us avoid inlining a type alias
             // for some pointers where no actual improvement was possible.
             Some(type_desc::perms_to_desc(perms, flags))
         };
+        let option =
+            ty_desc.is_some() && type_desc::perms_to_option(perms[lty.label], flags[lty.label]);
         // `args` were already rewritten, so we can compute `descendant_has_rewrite` just by
         // visiting the direct children.
         let descendant_has_rewrite = args.iter().any(|child| {
@@ -60,11 +77,45 @@ fn relabel_rewrites<'tcx>(
         });
         RewriteLabel {
             ty_desc,
+            option,
             descendant_has_rewrite,
+            lifetime: None,
         }
     })
 }
 
+/// Whether a pointer rewritten to `own` becomes a reference, which needs a lifetime.
+fn is_ref(own: Ownership) -> bool {
+    matches!(own, Ownership::Imm | Ownership::Cell | Ownership::Mut)
+}
+
+/// Set the `lifetime` of each pointer in `rw_lty` that will be rewritten into a reference, using
+/// the origins that `construct_adt_metadata` computed for the same field type.
+fn add_field_lifetimes<'tcx>(
+    lcx: LabeledTyCtxt<'tcx, RewriteLabel<'tcx>>,
+    rw_lty: RwLTy<'tcx>,
+    origin_lty: LabeledTy<'tcx, &'tcx [OriginArg<'tcx>]>,
+) -> RwLTy<'tcx> {
+    // References in a function pointer type are bound by the type itself, so they're left elided.
+    if matches!(rw_lty.ty.kind(), ty::TyKind::FnPtr(..)) {
+        return rw_lty;
+    }
+    assert_eq!(rw_lty.args.len(), origin_lty.args.len());
+    let args = rw_lty
+        .args
+        .iter()
+        .zip(origin_lty.args.iter())
+        .map(|(&arg, &origin_arg)| add_field_lifetimes(lcx, arg, origin_arg))
+        .collect::<Vec<_>>();
+    let mut label = rw_lty.label;
+    if let Some((own, _)) = label.ty_desc {
+        if is_ref(own) {
+            label.lifetime = origin_lty.label.first().copied();
+        }
+    }
+    lcx.mk(rw_lty.ty, lcx.mk_slice(&args), label)
+}
+
 /// Extract arguments from `hir_ty` if it corresponds to the tcx type `ty`.  If the two types
 /// differ in shape (for example, if `hir_ty` is `_` or a type alias), then the result is `None`.
 /// If they have the same shape, the result is a list of `hir::Ty`s of arguments, where "argument"
@@ -126,6 +177,43 @@ fn deconstruct_hir_ty<'a, 'tcx>(
                 None
             }
         }
+        (&ty::TyKind::FnPtr(sig), &hir::TyKind::BareFn(bare_fn)) => {
+            let decl = bare_fn.decl;
+            if decl.inputs.len() != sig.skip_binder().inputs().len() {
+                return None;
+            }
+            let mut hir_tys = decl.inputs.iter().collect::<Vec<_>>();
+            // With no return type written, the output is `()`, which has nothing to rewrite.
+            if let hir::FnRetTy::Return(hir_ty) = decl.output {
+                hir_tys.push(hir_ty);
+            }
+            Some(hir_tys)
+        }
+        // An ADT named by a path with all of its type arguments written out, like the `Option<T>`
+        // that translated C code uses for nullable function pointers.
+        (
+            &ty::TyKind::Adt(adt_def, substs),
+            &hir::TyKind::Path(hir::QPath::Resolved(None, path)),
+        ) if path.res.opt_def_id() == Some(adt_def.did()) => {
+            let hir_tys =
+                path.segments
+                    .last()
+                    .and_then(|seg| seg.args)
+                    .map_or_else(Vec::new, |args| {
+                        args.args
+                            .iter()
+                            .filter_map(|arg| match arg {
+                                hir::GenericArg::Type(hir_ty) => Some(hir_ty),
+                                _ => None,
+                            })
+                            .collect()
+                    });
+            if hir_tys.len() == substs.types().count() {
+                Some(hir_tys)
+            } else {
+                None
+            }
+        }
 
         _ => None,
     }
@@ -213,9 +301,50 @@ fn mk_cell<'tcx>(tcx: TyCtxt<'tcx>, ty: ty::Ty<'tcx>) -> ty::Ty<'tcx> {
     tcx.mk_adt(cell_adt, substs)
 }
 
+fn mk_cstr(tcx: TyCtxt) -> ty::Ty {
+    let std_crate = tcx
+        .crates(())
+        .iter()
+        .cloned()
+        .find(|&krate| tcx.crate_name(krate).as_str() == "std")
+        .expect("failed to find crate `std`");
+
+    let ffi_mod_child = tcx
+        .module_children(std_crate.as_def_id())
+        .iter()
+        .find(|child| child.ident.as_str() == "ffi")
+        .expect("failed to find module `std::ffi`");
+    let ffi_mod_did = match ffi_mod_child.res {
+        Res::Def(DefKind::Mod, did) => did,
+        ref r => panic!("unexpected resolution {:?} for `std::ffi`", r),
+    };
+
+    let cstr_struct_child = tcx
+        .module_children(ffi_mod_did)
+        .iter()
+        .find(|child| child.ident.as_str() == "CStr")
+        .expect("failed to find struct `std::ffi::CStr`");
+    let cstr_struct_did = match cstr_struct_child.res {
+        Res::Def(DefKind::Struct, did) => did,
+        ref r => panic!("unexpected resolution {:?} for `std::ffi::CStr`", r),
+    };
+
+    let cstr_adt = tcx.adt_def(cstr_struct_did);
+    tcx.mk_adt(cstr_adt, tcx.intern_substs(&[]))
+}
+
+fn mk_option<'tcx>(tcx: TyCtxt<'tcx>, ty: ty::Ty<'tcx>) -> ty::Ty<'tcx> {
+    let option_did = tcx
+        .get_diagnostic_item(sym::Option)
+        .expect("failed to find enum `core::option::Option`");
+    let option_adt = tcx.adt_def(option_did);
+    let substs = tcx.mk_substs([GenericArg::from(ty)].into_iter());
+    tcx.mk_adt(option_adt, substs)
+}
+
 /// Produce a `Ty` reflecting the rewrites indicated by the labels in `rw_lty`.
 fn mk_rewritten_ty<'tcx>(
-    lcx: LabeledTyCtxt<'tcx, RewriteLabel>,
+    lcx: LabeledTyCtxt<'tcx, RewriteLabel<'tcx>>,
     rw_lty: RwLTy<'tcx>,
 ) -> ty::Ty<'tcx> {
     let tcx = *lcx;
@@ -238,6 +367,7 @@ fn mk_rewritten_ty<'tcx>(
             Quantity::Slice => tcx.mk_slice(ty),
             // TODO: This should generate `OffsetPtr<T>` rather than `&[T]`, but `OffsetPtr` is NYI
             Quantity::OffsetPtr => tcx.mk_slice(ty),
+            Quantity::CStr => mk_cstr(tcx),
         };
 
         ty = match own {
@@ -246,10 +376,15 @@ fn mk_rewritten_ty<'tcx>(
             Ownership::Imm => tcx.mk_imm_ref(tcx.mk_region(ReErased), ty),
             Ownership::Cell => tcx.mk_imm_ref(tcx.mk_region(ReErased), ty),
             Ownership::Mut => tcx.mk_mut_ref(tcx.mk_region(ReErased), ty),
-            Ownership::Rc => todo!(),
+            // `perms_to_desc` never infers `Rc`, so keep a raw pointer in case it ever does.
+            Ownership::Rc => tcx.mk_mut_ptr(ty),
             Ownership::Box => tcx.mk_box(ty),
         };
 
+        if label.option {
+            ty = mk_option(tcx, ty);
+        }
+
         ty
     })
 }
@@ -257,68 +392,106 @@ fn mk_rewritten_ty<'tcx>(
 struct HirTyVisitor<'a, 'tcx> {
     asn: &'a Assignment<'a>,
     acx: &'a AnalysisCtxt<'a, 'tcx>,
-    rw_lcx: LabeledTyCtxt<'tcx, RewriteLabel>,
+    rw_lcx: LabeledTyCtxt<'tcx, RewriteLabel<'tcx>>,
     mir: &'a Body<'tcx>,
     hir_rewrites: Vec<(Span, Rewrite)>,
     hir_span_to_mir_local: HashMap<Span, rustc_middle::mir::Local>,
 }
 
-impl<'a, 'tcx> HirTyVisitor<'a, 'tcx> {
-    fn handle_ty(&mut self, rw_lty: RwLTy<'tcx>, hir_ty: &hir::Ty<'tcx>) {
-        if rw_lty.label.ty_desc.is_none() && !rw_lty.label.descendant_has_rewrite {
-            // No rewrites here or in any descendant of this HIR node.
+/// Emit rewrites into `hir_rewrites` to change `hir_ty` as indicated by the labels of `rw_lty`.
+fn rewrite_hir_ty<'tcx>(
+    rw_lcx: LabeledTyCtxt<'tcx, RewriteLabel<'tcx>>,
+    rw_lty: RwLTy<'tcx>,
+    hir_ty: &hir::Ty<'tcx>,
+    hir_rewrites: &mut Vec<(Span, Rewrite)>,
+) {
+    if rw_lty.label.ty_desc.is_none() && !rw_lty.label.descendant_has_rewrite {
+        // No rewrites here or in any descendant of this HIR node.
+        return;
+    }
+
+    let hir_args = match deconstruct_hir_ty(rw_lty.ty, hir_ty) {
+        Some(x) => x,
+        None => {
+            // `hir_ty` doesn't have the expected structure (for example, we expected a type
+            // like `*mut T`, but it's actually an alias `MyPtr`), so we can't rewrite inside
+            // it.  Instead, we discard it completely and pretty-print `rw_lty` (with rewrites
+            // applied).  The printed type has no lifetimes, which is why struct fields of this
+            // kind are pinned by `pin_unrewritable_field_tys` instead.
+            let ty = mk_rewritten_ty(rw_lcx, rw_lty);
+            let printer = FmtPrinter::new(*rw_lcx, Namespace::TypeNS);
+            let s = ty.print(printer).unwrap().into_buffer();
+            hir_rewrites.push((hir_ty.span, Rewrite::PrintTy(s)));
             return;
         }
+    };
 
-        let hir_args = match deconstruct_hir_ty(rw_lty.ty, hir_ty) {
-            Some(x) => x,
-            None => {
-                // `hir_ty` doesn't have the expected structure (for example, we expected a type
-                // like `*mut T`, but it's actually an alias `MyPtr`), so we can't rewrite inside
-                // it.  Instead, we discard it completely and pretty-print `rw_lty` (with rewrites
-                // applied).
-                let ty = mk_rewritten_ty(self.rw_lcx, rw_lty);
-                let printer = FmtPrinter::new(*self.rw_lcx, Namespace::TypeNS);
-                let s = ty.print(printer).unwrap().into_buffer();
-                self.hir_rewrites.push((hir_ty.span, Rewrite::PrintTy(s)));
-                return;
-            }
-        };
+    if let Some((own, qty)) = rw_lty.label.ty_desc {
+        assert_eq!(hir_args.len(), 1);
+        let mut rw = Rewrite::Sub(0, hir_args[0].span);
 
-        if let Some((own, qty)) = rw_lty.label.ty_desc {
-            assert_eq!(hir_args.len(), 1);
-            let mut rw = Rewrite::Sub(0, hir_args[0].span);
+        if own == Ownership::Cell {
+            rw = Rewrite::TyCtor("core::cell::Cell".into(), vec![rw]);
+        }
 
-            if own == Ownership::Cell {
-                rw = Rewrite::TyCtor("core::cell::Cell".into(), vec![rw]);
-            }
+        rw = match qty {
+            Quantity::Single => rw,
+            Quantity::Slice => Rewrite::TySlice(Box::new(rw)),
+            // TODO: This should generate `OffsetPtr<T>` rather than `&[T]`, but `OffsetPtr` is
+            // NYI
+            Quantity::OffsetPtr => Rewrite::TySlice(Box::new(rw)),
+            Quantity::CStr => Rewrite::PrintTy("std::ffi::CStr".into()),
+        };
 
-            rw = match qty {
-                Quantity::Single => rw,
-                Quantity::Slice => Rewrite::TySlice(Box::new(rw)),
-                // TODO: This should generate `OffsetPtr<T>` rather than `&[T]`, but `OffsetPtr` is
-                // NYI
-                Quantity::OffsetPtr => Rewrite::TySlice(Box::new(rw)),
-            };
+        let lifetime = rw_lty.label.lifetime.map(|origin| format!("{:?}", origin));
+        rw = match own {
+            Ownership::Raw => Rewrite::TyPtr(Box::new(rw), Mutability::Not),
+            Ownership::RawMut => Rewrite::TyPtr(Box::new(rw), Mutability::Mut),
+            Ownership::Imm => Rewrite::TyRef(Box::new(rw), Mutability::Not, lifetime),
+            Ownership::Cell => Rewrite::TyRef(Box::new(rw), Mutability::Not, lifetime),
+            Ownership::Mut => Rewrite::TyRef(Box::new(rw), Mutability::Mut, lifetime),
+            // As in `mk_rewritten_ty`
+            Ownership::Rc => Rewrite::TyPtr(Box::new(rw), Mutability::Mut),
+            Ownership::Box => Rewrite::TyCtor("Box".into(), vec![rw]),
+        };
 
-            rw = match own {
-                Ownership::Raw => Rewrite::TyPtr(Box::new(rw), Mutability::Not),
-                Ownership::RawMut => Rewrite::TyPtr(Box::new(rw), Mutability::Mut),
-                Ownership::Imm => Rewrite::TyRef(Box::new(rw), Mutability::Not),
-                Ownership::Cell => Rewrite::TyRef(Box::new(rw), Mutability::Not),
-                Ownership::Mut => Rewrite::TyRef(Box::new(rw), Mutability::Mut),
-                Ownership::Rc => todo!(),
-                Ownership::Box => todo!(),
-            };
+        if rw_lty.label.option {
+            rw = Rewrite::TyCtor("Option".into(), vec![rw]);
+        }
 
-            self.hir_rewrites.push((hir_ty.span, rw));
+        hir_rewrites.push((hir_ty.span, rw));
+    }
+
+    if rw_lty.label.descendant_has_rewrite {
+        for (&arg_rw_lty, arg_hir_ty) in rw_lty.args.iter().zip(hir_args.into_iter()) {
+            rewrite_hir_ty(rw_lcx, arg_rw_lty, arg_hir_ty, hir_rewrites);
         }
+    }
+}
 
-        if rw_lty.label.descendant_has_rewrite {
-            for (&arg_rw_lty, arg_hir_ty) in rw_lty.args.iter().zip(hir_args.into_iter()) {
-                self.handle_ty(arg_rw_lty, arg_hir_ty);
-            }
+impl<'a, 'tcx> HirTyVisitor<'a, 'tcx> {
+    fn handle_ty(&mut self, rw_lty: RwLTy<'tcx>, hir_ty: &hir::Ty<'tcx>) {
+        rewrite_hir_ty(self.rw_lcx, rw_lty, hir_ty, &mut self.hir_rewrites);
+    }
+
+    /// Make the binding `pat` of `mir_local` mutable if it becomes a `Box` that is written
+    /// through.  A raw pointer can be written through, or reborrowed as `&mut *p`, from an
+    /// immutable binding, but a `Box` can't.
+    fn handle_binding(&mut self, pat: &hir::Pat<'tcx>, mir_local: mir::Local) {
+        let ident = match pat.kind {
+            hir::PatKind::Binding(hir::BindingAnnotation::Unannotated, _, ident, None) => ident,
+            _ => return,
+        };
+        let lty = self.acx.local_tys[mir_local];
+        let rw_lty = relabel_rewrites(&self.asn.perms(), &self.asn.flags(), self.rw_lcx, lty);
+        if !matches!(rw_lty.label.ty_desc, Some((Ownership::Box, _))) {
+            return;
+        }
+        if !self.asn.perms()[lty.label].contains(PermissionSet::WRITE) {
+            return;
         }
+        self.hir_rewrites
+            .push((pat.span, Rewrite::Text(format!("mut {}", ident))));
     }
 }
 
@@ -330,20 +503,21 @@ impl<'a, 'tcx> intravisit::Visitor<'tcx> for HirTyVisitor<'a, 'tcx> {
     }
 
     fn visit_stmt(&mut self, s: &'tcx hir::Stmt<'tcx>) {
-        match s.kind {
-            // A local with a user type annotation
-            hir::StmtKind::Local(hir_local) if hir_local.ty.is_some() => {
-                if let Some(mir_local) = self.hir_span_to_mir_local.get(&hir_local.pat.span) {
-                    let mir_local_decl = &self.mir.local_decls[*mir_local];
-                    assert_eq!(mir_local_decl.source_info.span, hir_local.pat.span);
-                    let lty = self.acx.local_tys[*mir_local];
-                    let rw_lty = relabel_rewrites(self.asn, self.rw_lcx, lty);
-                    let hir_ty = hir_local.ty.unwrap();
+        if let hir::StmtKind::Local(hir_local) = s.kind {
+            if let Some(&mir_local) = self.hir_span_to_mir_local.get(&hir_local.pat.span) {
+                let mir_local_decl = &self.mir.local_decls[mir_local];
+                assert_eq!(mir_local_decl.source_info.span, hir_local.pat.span);
+                // A local with a user type annotation
+                if let Some(hir_ty) = hir_local.ty {
+                    let lty = self.acx.local_tys[mir_local];
+                    let rw_lty =
+                        relabel_rewrites(&self.asn.perms(), &self.asn.flags(), self.rw_lcx, lty);
                     self.handle_ty(rw_lty, hir_ty);
                 }
+                self.handle_binding(hir_local.pat, mir_local);
             }
-            _ => (),
         }
+        intravisit::walk_stmt(self, s);
     }
 }
 
@@ -380,28 +554,366 @@ pub fn gen_ty_rewrites<'tcx>(
 
     assert_eq!(lty_sig.inputs.len(), hir_sig.decl.inputs.len());
     for (&lty, hir_ty) in lty_sig.inputs.iter().zip(hir_sig.decl.inputs.iter()) {
-        let rw_lty = relabel_rewrites(asn, rw_lcx, lty);
+        let rw_lty = relabel_rewrites(&asn.perms(), &asn.flags(), rw_lcx, lty);
         v.handle_ty(rw_lty, hir_ty);
     }
 
     if let hir::FnRetTy::Return(hir_ty) = hir_sig.decl.output {
-        let rw_lty = relabel_rewrites(asn, rw_lcx, lty_sig.output);
+        let rw_lty = relabel_rewrites(&asn.perms(), &asn.flags(), rw_lcx, lty_sig.output);
         v.handle_ty(rw_lty, hir_ty);
     }
 
     let hir_body_id = acx.tcx().hir().body_owned_by(ldid);
     let body = acx.tcx().hir().body(hir_body_id);
+    for param in body.params {
+        if let Some(&mir_local) = v.hir_span_to_mir_local.get(&param.pat.span) {
+            v.handle_binding(param.pat, mir_local);
+        }
+    }
     intravisit::Visitor::visit_body(&mut v, body);
 
     // TODO: wrap locals in `Cell` if `addr_of_local` indicates that it's needed
 
     // TODO: update cast RHS types
 
-    // TODO: update struct field types
+    v.hir_rewrites
+}
+
+/// Compute the lifetime parameters to add to each local ADT and type alias.  Each raw pointer field
+/// has a hypothetical lifetime `'hN` in the `AdtMetadataTable`; an ADT needs that lifetime as a
+/// parameter if the pointer is rewritten into a reference.  An ADT or type alias also needs all of
+/// the added parameters of every ADT mentioned in its fields or definition.  The result maps each
+/// `DefId` to its new parameters, sorted by `N`; ADTs and aliases with no new parameters are
+/// omitted.
+fn hypothetical_lifetime_params<'tcx>(
+    gacx: &GlobalAnalysisCtxt<'tcx>,
+    gasn: &GlobalAssignment,
+    adt_metadata: &AdtMetadataTable<'tcx>,
+) -> HashMap<DefId, BTreeSet<i64>> {
+    fn collect<'tcx>(
+        gasn: &GlobalAssignment,
+        params: &HashMap<DefId, BTreeSet<i64>>,
+        lty: LTy<'tcx>,
+        origin_lty: LabeledTy<'tcx, &'tcx [OriginArg<'tcx>]>,
+        out: &mut BTreeSet<i64>,
+    ) {
+        // As in `add_field_lifetimes`, function pointer types need no lifetime parameters.
+        if matches!(lty.ty.kind(), ty::TyKind::FnPtr(..)) {
+            return;
+        }
+        if !lty.label.is_none() {
+            let (own, _) = type_desc::perms_to_desc(gasn.perms[lty.label], gasn.flags[lty.label]);
+            if let Some(&OriginArg::Hypothetical(h)) = origin_lty.label.first() {
+                if is_ref(own) {
+                    out.insert(h);
+                }
+            }
+        }
+        if let ty::TyKind::Adt(adt_def, _) = lty.ty.kind() {
+            if let Some(adt_params) = params.get(&adt_def.did()) {
+                out.extend(adt_params.iter().copied());
+            }
+        }
+        for (&arg, &origin_arg) in lty.args.iter().zip(origin_lty.args.iter()) {
+            collect(gasn, params, arg, origin_arg, out);
+        }
+    }
+
+    let tcx = gacx.tcx;
+    let mut params = HashMap::new();
+    loop {
+        // Iterate until a fixed point is reached, since structs can be recursive or mutually
+        // recursive.
+        let mut changed = false;
+        for &did in &adt_metadata.struct_dids {
+            let metadata = match adt_metadata.table.get(&did) {
+                Some(x) => x,
+                None => continue,
+            };
+            let mut adt_params = BTreeSet::new();
+            for field in tcx.adt_def(did).all_fields() {
+                let lty = match gacx.field_tys.get(&field.did) {
+                    Some(&x) => x,
+                    None => continue,
+                };
+                let origin_lty = match metadata.field_info.get(&field.did) {
+                    Some(x) => x.origin_args,
+                    None => continue,
+                };
+                collect(gasn, &params, lty, origin_lty, &mut adt_params);
+            }
+            if !adt_params.is_empty() && params.get(&did) != Some(&adt_params) {
+                params.insert(did, adt_params);
+                changed = true;
+            }
+        }
+        if !changed {
+            break;
+        }
+    }
+
+    // Type aliases get the parameters of all the ADTs in their definitions.  `type_of` expands
+    // nested aliases, so there's no need to iterate here.
+    for ldid in tcx.hir_crate_items(()).definitions() {
+        let did = ldid.to_def_id();
+        if tcx.def_kind(did) != DefKind::TyAlias {
+            continue;
+        }
+        let mut alias_params = BTreeSet::new();
+        for arg in tcx.type_of(did).walk() {
+            if let ty::subst::GenericArgKind::Type(ty) = arg.unpack() {
+                if let ty::TyKind::Adt(adt_def, _) = ty.kind() {
+                    if let Some(adt_params) = params.get(&adt_def.did()) {
+                        alias_params.extend(adt_params.iter().copied());
+                    }
+                }
+            }
+        }
+        if !alias_params.is_empty() {
+            params.insert(did, alias_params);
+        }
+    }
+
+    params
+}
+
+/// Which lifetimes to use for mentions of ADTs and type aliases that gained lifetime parameters.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum MentionLifetimes {
+    /// `'_`, in function signatures and bodies.
+    Elided,
+    /// `'static`, in the types of statics and consts.
+    Static,
+    /// The parameter names themselves, in type definitions, which declare all the parameters of
+    /// the types they mention.
+    Named,
+}
+
+struct AdtTyVisitor<'a, 'tcx> {
+    gacx: &'a GlobalAnalysisCtxt<'tcx>,
+    gasn: &'a GlobalAssignment,
+    adt_metadata: &'a AdtMetadataTable<'tcx>,
+    rw_lcx: LabeledTyCtxt<'tcx, RewriteLabel<'tcx>>,
+    /// The new lifetime parameters of each ADT and type alias.
+    lifetime_params: HashMap<DefId, Vec<String>>,
+    /// The ADT whose definition is currently being visited.
+    cur_adt: Option<DefId>,
+    mention_lifetimes: MentionLifetimes,
+    hir_rewrites: Vec<(Span, Rewrite)>,
+}
 
+impl<'a, 'tcx> AdtTyVisitor<'a, 'tcx> {
+    /// Add the new lifetime parameters of `did` to the generics of its definition.
+    fn handle_generics(&mut self, did: DefId, ident_span: Span, generics: &hir::Generics<'tcx>) {
+        let params = match self.lifetime_params.get(&did) {
+            Some(x) => x.clone(),
+            None => return,
+        };
+        if generics.params.is_empty() {
+            self.hir_rewrites
+                .push((ident_span, Rewrite::WithLifetimes(params)));
+            return;
+        }
+        let last_lifetime = generics
+            .params
+            .iter()
+            .filter(|param| matches!(param.kind, hir::GenericParamKind::Lifetime { .. }))
+            .last();
+        match last_lifetime {
+            Some(param) => self
+                .hir_rewrites
+                .push((param.span, Rewrite::ExtraLifetimes(params))),
+            None => eprintln!(
+                "warning: can't add lifetime parameters {:?} to {:?}, which has only type \
+                 parameters",
+                params, did
+            ),
+        }
+    }
+}
+
+impl<'a, 'tcx> intravisit::Visitor<'tcx> for AdtTyVisitor<'a, 'tcx> {
+    type NestedFilter = nested_filter::All;
+
+    fn nested_visit_map(&mut self) -> Self::Map {
+        self.gacx.tcx.hir()
+    }
+
+    fn visit_item(&mut self, item: &'tcx hir::Item<'tcx>) {
+        let did = item.def_id.to_def_id();
+        let old_cur_adt = self.cur_adt.take();
+        let old_mention_lifetimes = self.mention_lifetimes;
+        match item.kind {
+            hir::ItemKind::Struct(_, generics)
+            | hir::ItemKind::Union(_, generics)
+            | hir::ItemKind::Enum(_, generics) => {
+                self.handle_generics(did, item.ident.span, generics);
+                self.cur_adt = Some(did);
+                self.mention_lifetimes = MentionLifetimes::Named;
+            }
+            hir::ItemKind::TyAlias(_, generics) => {
+                self.handle_generics(did, item.ident.span, generics);
+                self.mention_lifetimes = MentionLifetimes::Named;
+            }
+            hir::ItemKind::Static(..) | hir::ItemKind::Const(..) => {
+                self.mention_lifetimes = MentionLifetimes::Static;
+            }
+            _ => {
+                self.mention_lifetimes = MentionLifetimes::Elided;
+            }
+        }
+        intravisit::walk_item(self, item);
+        self.cur_adt = old_cur_adt;
+        self.mention_lifetimes = old_mention_lifetimes;
+    }
+
+    fn visit_foreign_item(&mut self, item: &'tcx hir::ForeignItem<'tcx>) {
+        let old_mention_lifetimes = self.mention_lifetimes;
+        self.mention_lifetimes = match item.kind {
+            hir::ForeignItemKind::Static(..) => MentionLifetimes::Static,
+            _ => MentionLifetimes::Elided,
+        };
+        intravisit::walk_foreign_item(self, item);
+        self.mention_lifetimes = old_mention_lifetimes;
+    }
+
+    fn visit_field_def(&mut self, field: &'tcx hir::FieldDef<'tcx>) {
+        let tcx = self.gacx.tcx;
+        let did = tcx.hir().local_def_id(field.hir_id).to_def_id();
+        let lty = self.gacx.field_tys.get(&did).copied();
+        let origin_lty = self
+            .cur_adt
+            .and_then(|adt_did| self.adt_metadata.table.get(&adt_did))
+            .and_then(|metadata| metadata.field_info.get(&did))
+            .map(|field_metadata| field_metadata.origin_args);
+        if let (Some(lty), Some(origin_lty)) = (lty, origin_lty) {
+            let rw_lty = relabel_rewrites(&self.gasn.perms, &self.gasn.flags, self.rw_lcx, lty);
+            let rw_lty = add_field_lifetimes(self.rw_lcx, rw_lty, origin_lty);
+            rewrite_hir_ty(self.rw_lcx, rw_lty, field.ty, &mut self.hir_rewrites);
+        }
+        intravisit::walk_field_def(self, field);
+    }
+
+    fn visit_ty(&mut self, hir_ty: &'tcx hir::Ty<'tcx>) {
+        if let hir::TyKind::Path(hir::QPath::Resolved(None, path)) = hir_ty.kind {
+            let did = match path.res {
+                Res::Def(
+                    DefKind::Struct | DefKind::Union | DefKind::Enum | DefKind::TyAlias,
+                    did,
+                ) => Some(did),
+                _ => None,
+            };
+            let params = did.and_then(|did| self.lifetime_params.get(&did));
+            if let Some(params) = params.filter(|_| !hir_ty.span.from_expansion()) {
+                let lifetimes = match self.mention_lifetimes {
+                    MentionLifetimes::Elided => vec!["'_".to_owned(); params.len()],
+                    MentionLifetimes::Static => vec!["'static".to_owned(); params.len()],
+                    MentionLifetimes::Named => params.clone(),
+                };
+                let args = path.segments.last().and_then(|seg| seg.args);
+                let last_lifetime = args.and_then(|args| {
+                    args.args
+                        .iter()
+                        .filter_map(|arg| match arg {
+                            hir::GenericArg::Lifetime(lt) => Some(lt.span),
+                            _ => None,
+                        })
+                        .last()
+                });
+                match (args, last_lifetime) {
+                    (None, _) => self
+                        .hir_rewrites
+                        .push((hir_ty.span, Rewrite::WithLifetimes(lifetimes))),
+                    (Some(args), None) if args.args.is_empty() && args.bindings.is_empty() => self
+                        .hir_rewrites
+                        .push((hir_ty.span, Rewrite::WithLifetimes(lifetimes))),
+                    // An empty span means the existing lifetimes were elided entirely, which is
+                    // still allowed with the new parameters.
+                    (Some(_), Some(span)) if span.is_empty() => {}
+                    (Some(_), Some(span)) => self
+                        .hir_rewrites
+                        .push((span, Rewrite::ExtraLifetimes(lifetimes))),
+                    (Some(_), None) => eprintln!(
+                        "warning: can't add lifetime arguments {:?} to {:?}, which has only type \
+                         arguments",
+                        lifetimes, hir_ty.span
+                    ),
+                }
+            }
+        }
+        intravisit::walk_ty(self, hir_ty);
+    }
+}
+
+/// Generate rewrites for the types of struct, union, and enum fields, using the permissions and
+/// flags of the global assignment.  Pointer fields that become references are given lifetime
+/// parameters of the ADT, which are also added to its definition and to every mention of it
+/// elsewhere in the crate.
+pub fn gen_adt_ty_rewrites<'tcx>(
+    gacx: &GlobalAnalysisCtxt<'tcx>,
+    gasn: &GlobalAssignment,
+    adt_metadata: &AdtMetadataTable<'tcx>,
+) -> Vec<(Span, Rewrite)> {
+    let lifetime_params = hypothetical_lifetime_params(gacx, gasn, adt_metadata)
+        .into_iter()
+        .map(|(did, params)| {
+            let params = params
+                .into_iter()
+                .map(|h| format!("{:?}", OriginArg::Hypothetical(h)))
+                .collect();
+            (did, params)
+        })
+        .collect();
+
+    let mut v = AdtTyVisitor {
+        gacx,
+        gasn,
+        adt_metadata,
+        rw_lcx: LabeledTyCtxt::new(gacx.tcx),
+        lifetime_params,
+        cur_adt: None,
+        mention_lifetimes: MentionLifetimes::Elided,
+        hir_rewrites: Vec::new(),
+    };
+    gacx.tcx.hir().walk_toplevel_module(&mut v);
     v.hir_rewrites
 }
 
+/// Mark as `FIXED` the pointers in struct fields whose declared type doesn't have the same shape as
+/// the field's type, such as a field declared with a type alias like `MyPtr`.  We can't rewrite
+/// inside such a type, and pretty-printing its replacement would drop the lifetimes that struct
+/// fields need, so these pointers keep their original types.  This must run before the fixpoint so
+/// that the users of those fields are analyzed accordingly.
+pub fn pin_unrewritable_field_tys(gacx: &GlobalAnalysisCtxt, gasn: &mut GlobalAssignment) {
+    let tcx = gacx.tcx;
+    for (&did, &lty) in &gacx.field_tys {
+        let ldid = match did.as_local() {
+            Some(x) => x,
+            None => continue,
+        };
+        let hir_id = tcx.hir().local_def_id_to_hir_id(ldid);
+        if let Some(hir::Node::Field(field)) = tcx.hir().find(hir_id) {
+            pin_unrewritable_tys(lty, field.ty, gasn);
+        }
+    }
+}
+
+fn pin_unrewritable_tys<'tcx>(lty: LTy<'tcx>, hir_ty: &hir::Ty<'tcx>, gasn: &mut GlobalAssignment) {
+    match deconstruct_hir_ty(lty.ty, hir_ty) {
+        Some(hir_args) => {
+            for (&arg_lty, arg_hir_ty) in lty.args.iter().zip(hir_args.into_iter()) {
+                pin_unrewritable_tys(arg_lty, arg_hir_ty, gasn);
+            }
+        }
+        None => lty.for_each_label(&mut |ptr| {
+            if !ptr.is_none() {
+                gasn.perms[ptr] = PermissionSet::empty();
+                gasn.flags[ptr].insert(FlagSet::FIXED);
+            }
+        }),
+    }
+}
+
 /// Print the rewritten types for all locals in `mir`.  This is used for tests and debugging, as it
 /// reveals the inference results even for temporaries and other locals with no type annotation in
 /// the HIR.
@@ -414,7 +926,7 @@ pub fn dump_rewritten_local_tys<'tcx>(
     let rw_lcx = LabeledTyCtxt::new(acx.tcx());
     for (local, decl) in mir.local_decls.iter_enumerated() {
         // TODO: apply `Cell` if `addr_of_local` indicates it's needed
-        let rw_lty = relabel_rewrites(asn, rw_lcx, acx.local_tys[local]);
+        let rw_lty = relabel_rewrites(&asn.perms(), &asn.flags(), rw_lcx, acx.local_tys[local]);
         let ty = mk_rewritten_ty(rw_lcx, rw_lty);
         eprintln!(
             "{:?} ({}): {:?}",
@@ -28,10 +28,21 @@ pub enum Quantity {
     Slice,
     /// E.g. `OffsetPtr<T>`
     OffsetPtr,
+    /// `&CStr`, for a NUL-terminated string of `c_char`s.  The pointee type is replaced entirely.
+    CStr,
 }
 
 pub fn perms_to_desc(perms: PermissionSet, flags: FlagSet) -> (Ownership, Quantity) {
-    let own = if perms.contains(PermissionSet::UNIQUE | PermissionSet::WRITE) {
+    if flags.contains(FlagSet::FIXED) {
+        // Pinned pointers, such as those in functions that couldn't be analyzed, keep their
+        // original types, which type rewriting leaves untouched.  Here we only need to report that they aren't references.
+        return (Ownership::Raw, Quantity::Single);
+    }
+
+    let own = if perms.contains(PermissionSet::LINEAR | PermissionSet::FREE) {
+        // A pointer that is consumed by `free` (or `realloc`) owns its allocation.
+        Ownership::Box
+    } else if perms.contains(PermissionSet::UNIQUE | PermissionSet::WRITE) {
         Ownership::Mut
     } else if flags.contains(FlagSet::CELL) {
         Ownership::Cell
@@ -46,9 +57,21 @@ pub fn perms_to_desc(perms: PermissionSet, flags: FlagSet) -> (Ownership, Quanti
         Quantity::OffsetPtr
     } else if perms.contains(PermissionSet::OFFSET_ADD) {
         Quantity::Slice
+    } else if own == Ownership::Imm
+        && perms.contains(PermissionSet::NUL_TERMINATED)
+        && !perms.contains(PermissionSet::READ)
+    {
+        // The pointer is only passed to string functions like `strlen`, never dereferenced.
+        Quantity::CStr
     } else {
         Quantity::Single
     };
 
     (own, qty)
 }
+
+/// Whether a pointer with these permissions and flags may be null, and so should be wrapped in
+/// `Option`.  `FIXED` pointers keep their original raw pointer types, which can already be null.
+pub fn perms_to_option(perms: PermissionSet, flags: FlagSet) -> bool {
+    !flags.contains(FlagSet::FIXED) && !perms.contains(PermissionSet::NON_NULL)
+}
@@ -4,10 +4,12 @@ use rustc_const_eval::interpret::Scalar;
 use rustc_hir::def::DefKind;
 use rustc_hir::def_id::DefId;
 use rustc_middle::mir::{
-    BasicBlock, BasicBlockData, Constant, Field, Local, Location, Mutability, Operand, Place,
-    PlaceElem, PlaceRef, ProjectionElem, Rvalue, Statement, StatementKind,
+    BasicBlock, BasicBlockData, Body, CastKind, Constant, Field, Local, Location, Mutability,
+    Operand, Place, PlaceElem, PlaceRef, ProjectionElem, Rvalue, Statement, StatementKind,
+    TerminatorKind,
 };
 use rustc_middle::ty::{self, AdtDef, DefIdTree, SubstsRef, Ty, TyCtxt, TyKind, UintTy};
+use std::collections::HashSet;
 use std::fmt::Debug;
 
 #[derive(Debug)]
@@ -155,6 +157,18 @@ pub enum Callee<'tcx> {
 
     /// core::ptr::is_null
     IsNull,
+
+    /// `core::ptr::null` or `core::ptr::null_mut`
+    Null,
+
+    /// libc::strlen
+    Strlen,
+
+    /// libc::strcmp
+    Strcmp,
+
+    /// libc::strchr
+    Strchr,
 }
 
 pub fn ty_callee<'tcx>(tcx: TyCtxt<'tcx>, ty: Ty<'tcx>) -> Callee<'tcx> {
@@ -267,6 +281,27 @@ fn builtin_callee(tcx: TyCtxt, did: DefId) -> Option<Callee> {
             None
         }
 
+        "strlen" => {
+            if matches!(tcx.def_kind(tcx.parent(did)), DefKind::ForeignMod) {
+                return Some(Callee::Strlen);
+            }
+            None
+        }
+
+        "strcmp" => {
+            if matches!(tcx.def_kind(tcx.parent(did)), DefKind::ForeignMod) {
+                return Some(Callee::Strcmp);
+            }
+            None
+        }
+
+        "strchr" => {
+            if matches!(tcx.def_kind(tcx.parent(did)), DefKind::ForeignMod) {
+                return Some(Callee::Strchr);
+            }
+            None
+        }
+
         "is_null" => {
             // The `offset` inherent method of `*const T` and `*mut T`.
             let parent_did = tcx.parent(did);
@@ -284,6 +319,18 @@ fn builtin_callee(tcx: TyCtxt, did: DefId) -> Option<Callee> {
             Some(Callee::IsNull)
         }
 
+        "null" | "null_mut" => {
+            // The free functions `core::ptr::null` and `core::ptr::null_mut`.
+            let parent_did = tcx.parent(did);
+            if tcx.def_kind(parent_did) != DefKind::Mod {
+                return None;
+            }
+            if tcx.crate_name(did.krate).as_str() != "core" {
+                return None;
+            }
+            Some(Callee::Null)
+        }
+
         _ => {
             eprintln!("name: {name:?}");
             None
@@ -349,5 +396,34 @@ pub fn is_null_const(constant: Constant) -> bool {
     }
 }
 
+/// Find the locals of `mir` that are assigned a null pointer constant, either by a call to
+/// `ptr::null`/`ptr::null_mut` or by a cast like `0 as *mut T`.
+pub fn null_locals<'tcx>(tcx: TyCtxt<'tcx>, mir: &Body<'tcx>) -> HashSet<Local> {
+    let mut locals = HashSet::new();
+    for bb in mir.basic_blocks().iter() {
+        for stmt in &bb.statements {
+            if let StatementKind::Assign(ref x) = stmt.kind {
+                let (pl, ref rv) = **x;
+                if let Rvalue::Cast(CastKind::PointerFromExposedAddress, ref op, _) = *rv {
+                    if op.constant().copied().map_or(false, is_null_const) {
+                        locals.extend(pl.as_local());
+                    }
+                }
+            }
+        }
+        if let TerminatorKind::Call {
+            ref func,
+            destination,
+            ..
+        } = bb.terminator().kind
+        {
+            if matches!(ty_callee(tcx, func.ty(mir, tcx)), Callee::Null) {
+                locals.extend(destination.as_local());
+            }
+        }
+    }
+    locals
+}
+
 pub trait PhantomLifetime<'a> {}
 impl<'a, T: ?Sized> PhantomLifetime<'a> for T {}
@@ -49,6 +49,10 @@ struct AnalyzeArgs {
     /// Environment variables for `c2rust-analyze`.
     #[clap(long, value_parser)]
     env: Vec<EnvVar>,
+
+    /// Extra arguments for `c2rust-analyze`, such as `--arg=--explain=f:p`.
+    #[clap(long, value_parser)]
+    arg: Vec<String>,
 }
 
 impl AnalyzeArgs {
@@ -117,6 +121,7 @@ impl Analyze {
             .arg("rlib")
             .stdout(output_stdout)
             .stderr(output_stderr);
+        cmd.args(&args.arg);
         cmd.envs(args.env.iter().map(|EnvVar { var, value }| (var, value)));
         modify_cmd(&mut cmd);
         let status = cmd.status().unwrap();
@@ -41,21 +41,30 @@ define_tests! {
     alias2,
     alias3,
     alloc,
+    analysis_failure,
     as_ptr,
+    box_alloc,
+    c_str,
     call1,
     cast,
     cell,
     clone1,
+    explain,
     extern_fn1,
+    ffi_wrapper,
     fields,
     field_temp,
+    fn_ptr,
     insertion_sort,
     insertion_sort_driver,
     insertion_sort_rewrites,
+    non_null,
     offset1,
     offset2,
+    pdg_evidence,
     ptrptr1,
     statics,
+    struct_lifetimes,
     trivial,
     type_annotation_rewrite,
 }
@@ -0,0 +1,30 @@
+pub fn generic<T>(x: T) -> T {
+    x
+}
+
+// Calls to generic functions aren't supported yet.  Analysis of this function fails, but the rest
+// of the crate is still analyzed and rewritten.
+// CHECK: analysis of "calls_generic" failed: not yet implemented: call to generic function
+pub unsafe fn calls_generic(p: *mut i32) -> *mut i32 {
+    ok(p);
+    generic(p)
+}
+
+// `calls_generic` keeps passing a raw pointer, so the signature of `ok` stays as it is.
+// CHECK-LABEL: final labeling for "ok"
+// CHECK-DAG: ([[@LINE+1]]: p): {{.*}}type flags = FIXED#
+pub unsafe fn ok(p: *mut i32) {
+    *p = 1;
+}
+
+pub unsafe fn not_called(p: *mut i32) {
+    *p = 1;
+}
+
+// CHECK: ===== BEGIN
+// CHECK-DAG: pub unsafe fn calls_generic(p: *mut i32) -> *mut i32 {
+// CHECK-DAG: pub unsafe fn ok(p: *mut i32) {
+// CHECK-DAG: pub unsafe fn not_called(p: Option<&mut (i32)>) {
+
+// CHECK: {{^}}1 functions failed analysis:
+// CHECK-NEXT: "calls_generic": not yet implemented: call to generic function
@@ -0,0 +1,113 @@
+#![feature(rustc_private)]
+#![allow(unused_mut)]
+
+extern crate libc;
+
+extern "C" {
+    fn malloc(_: libc::c_ulong) -> *mut libc::c_void;
+    fn realloc(_: *mut libc::c_void, _: libc::c_ulong) -> *mut libc::c_void;
+    fn free(_: *mut libc::c_void);
+    fn calloc(_: libc::c_ulong, _: libc::c_ulong) -> *mut libc::c_void;
+}
+
+// CHECK-LABEL: final labeling for "box_single"
+pub unsafe fn box_single() {
+    // CHECK-DAG: ([[@LINE+1]]: i): addr_of = UNIQUE | NON_NULL, type = READ | WRITE | UNIQUE | LINEAR | FREE | NON_NULL#
+    let i = malloc(::std::mem::size_of::<i32>() as libc::c_ulong) as *mut i32;
+    // CHECK-DAG: ([[@LINE+1]]: mut b): addr_of = UNIQUE | NON_NULL, type = READ | WRITE | UNIQUE | NON_NULL#
+    let mut b = i;
+    *b = 2;
+    free(i as *mut libc::c_void);
+}
+
+// CHECK-LABEL: generated {{[0-9]+}} expr rewrites + {{[0-9]+}} ty rewrites for "box_single"
+// CHECK-DAG: 16: malloc({{.*}}): Box::new(<i32>::default())
+// CHECK-DAG: 16: malloc({{.*}} i32: $0
+// CHECK-DAG: 18: i: &mut *$e
+// CHECK-DAG: 20: free({{.*}}): drop($0)
+// CHECK-DAG: 16: i: mut i
+
+// CHECK-LABEL: final labeling for "box_slice"
+pub unsafe fn box_slice(n: libc::c_ulong) {
+    let p = calloc(n, ::std::mem::size_of::<i32>() as libc::c_ulong) as *mut i32;
+    *p.offset(1) = 1;
+    let m = 2 * n;
+    let q = realloc(p as *mut libc::c_void, m) as *mut i32;
+    *q.offset(2) = 2;
+    free(q as *mut libc::c_void);
+}
+
+// CHECK-LABEL: generated {{[0-9]+}} expr rewrites + {{[0-9]+}} ty rewrites for "box_slice"
+// CHECK-DAG: 32: calloc({{.*}}): vec![<i32>::default(); $0 as usize].into_boxed_slice()
+// CHECK-DAG: 33: p: &mut *$e
+// CHECK-DAG: 35: realloc({{.*}}): ({ let (mut v, n) = ($0.into_vec(), $1 as usize / std::mem::size_of::<i32>()); v.resize(n, <i32>::default()); v }).into_boxed_slice()
+// CHECK-DAG: 37: free({{.*}}): drop($0)
+// CHECK-DAG: 32: p: mut p
+// CHECK-DAG: 35: q: mut q
+
+#[derive(Clone, Copy, Default)]
+pub struct Point {
+    pub x: i32,
+    pub y: i32,
+}
+
+// CHECK-LABEL: final labeling for "box_struct"
+pub unsafe fn box_struct() -> i32 {
+    // CHECK-DAG: ([[@LINE+1]]: p): addr_of = UNIQUE | NON_NULL, type = READ | WRITE | UNIQUE | LINEAR | FREE | NON_NULL#
+    let p = malloc(::std::mem::size_of::<Point>() as libc::c_ulong) as *mut Point;
+    (*p).x = 1;
+    let x = (*p).x;
+    free(p as *mut libc::c_void);
+    x
+}
+
+// CHECK-LABEL: generated {{[0-9]+}} expr rewrites + {{[0-9]+}} ty rewrites for "box_struct"
+// CHECK-DAG: 57: malloc({{.*}}): Box::new(<Point>::default())
+// CHECK-DAG: 57: p: mut p
+
+#[derive(Clone, Copy)]
+pub struct Pair {
+    pub a: i32,
+    pub b: i32,
+}
+
+// `Pair` doesn't implement `Default`, so there's no safe way to allocate it, and the pointer stays
+// raw.
+// CHECK-LABEL: final labeling for "no_default"
+pub unsafe fn no_default() {
+    // CHECK-DAG: ([[@LINE+1]]: p): {{.*}}type flags = FIXED#
+    let p = malloc(::std::mem::size_of::<Pair>() as libc::c_ulong) as *mut Pair;
+    (*p).a = 1;
+    free(p as *mut libc::c_void);
+}
+
+pub struct Holder {
+    pub buf: *mut i32,
+}
+
+// `free((*h).buf)` would move the `Box` out from behind `h`, so the field stays raw.
+pub unsafe fn free_field(h: *mut Holder) {
+    free((*h).buf as *mut libc::c_void);
+}
+
+// `p` is still used after being moved into `q`, so both stay raw.
+// CHECK-LABEL: final labeling for "use_after_move"
+pub unsafe fn use_after_move() {
+    // CHECK-DAG: ([[@LINE+1]]: p): {{.*}}type flags = FIXED#
+    let p = malloc(::std::mem::size_of::<i32>() as libc::c_ulong) as *mut i32;
+    // CHECK-DAG: ([[@LINE+1]]: q): {{.*}}type flags = FIXED#
+    let q = p;
+    *p = 1;
+    free(q as *mut libc::c_void);
+}
+
+// CHECK-LABEL: ===== BEGIN
+// CHECK-DAG: let mut i = (Box::new(<i32>::default()));
+// CHECK-DAG: let mut b = &mut *(i);
+// CHECK-DAG: drop((i));
+// CHECK-DAG: drop((q));
+// CHECK-DAG: let mut p = (Box::new(<Point>::default()));
+// CHECK-DAG: let p = malloc(::std::mem::size_of::<Pair>() as libc::c_ulong) as *mut Pair;
+// CHECK-DAG: pub buf: *mut i32,
+// CHECK-DAG: free((*{{.*}}h{{.*}}).buf as *mut libc::c_void);
+// CHECK-DAG: free(q as *mut libc::c_void);
@@ -0,0 +1,87 @@
+#![feature(rustc_private)]
+
+extern crate libc;
+
+extern "C" {
+    fn strlen(s: *const libc::c_char) -> libc::c_ulong;
+    fn strcmp(a: *const libc::c_char, b: *const libc::c_char) -> libc::c_int;
+    fn strchr(s: *const libc::c_char, c: libc::c_int) -> *mut libc::c_char;
+}
+
+// CHECK-LABEL: final labeling for "len"
+pub unsafe fn len(s: *const libc::c_char) -> libc::c_ulong {
+    // CHECK-DAG: ([[@LINE-1]]: s): {{.*}}type = UNIQUE | NUL_TERMINATED#
+    strlen(s)
+}
+
+// CHECK-LABEL: final labeling for "same"
+pub unsafe fn same(a: *const libc::c_char, b: *const libc::c_char) -> bool {
+    // CHECK-DAG: ([[@LINE-1]]: a): {{.*}}type = UNIQUE | NUL_TERMINATED#
+    // CHECK-DAG: ([[@LINE-2]]: b): {{.*}}type = UNIQUE | NUL_TERMINATED#
+    strcmp(a, b) == 0
+}
+
+// CHECK-LABEL: final labeling for "suffix_len"
+pub unsafe fn suffix_len(s: *const libc::c_char, c: libc::c_int) -> libc::c_ulong {
+    // CHECK-DAG: ([[@LINE+1]]: p): {{.*}}type = UNIQUE | NUL_TERMINATED#
+    let p = strchr(s, c);
+    if p.is_null() {
+        return 0;
+    }
+    strlen(p)
+}
+
+// CHECK-LABEL: final labeling for "first"
+pub unsafe fn first(s: *const libc::c_char) -> libc::c_char {
+    // CHECK-DAG: ([[@LINE-1]]: s): {{.*}}type = READ | UNIQUE | NUL_TERMINATED#
+    if strlen(s) == 0 {
+        return 0;
+    }
+    *s
+}
+
+// A string that's read in a loop until its NUL is also `NUL_TERMINATED`.
+// CHECK-LABEL: final labeling for "walk_len"
+pub unsafe fn walk_len(s: *const libc::c_char) -> usize {
+    // CHECK-DAG: ([[@LINE-1]]: s): {{.*}}type = READ | UNIQUE | OFFSET_ADD | OFFSET_SUB | NUL_TERMINATED#
+    let mut p = s;
+    let mut n = 0;
+    while *p != 0 {
+        n += 1;
+        p = p.offset(1);
+    }
+    n
+}
+
+// An exported string needs no length parameter, since the wrapper can find its NUL.
+// CHECK-LABEL: final labeling for "count"
+#[no_mangle]
+pub unsafe extern "C" fn count(s: *const libc::c_char, c: libc::c_char) -> libc::c_int {
+    // CHECK-DAG: ([[@LINE-1]]: s): {{.*}}type = READ | UNIQUE | OFFSET_ADD | OFFSET_SUB | NUL_TERMINATED#
+    let mut n = 0;
+    let mut i = 0;
+    while *s.offset(i) != 0 {
+        if *s.offset(i) == c {
+            n += 1;
+        }
+        i += 1;
+    }
+    n
+}
+
+// CHECK-DAG: pub unsafe fn len(s: Option<&std::ffi::CStr>) -> libc::c_ulong {
+// CHECK-DAG: (s).unwrap().to_bytes().len() as libc::c_ulong
+
+// CHECK-DAG: pub unsafe fn same(a: Option<&std::ffi::CStr>, b: Option<&std::ffi::CStr>) -> bool {
+// CHECK-DAG: (a).unwrap().cmp((b).unwrap()) as libc::c_int == 0
+
+// CHECK-DAG: pub unsafe fn suffix_len(s: Option<&std::ffi::CStr>, c: libc::c_int) -> libc::c_ulong {
+// CHECK-DAG: let p = { let (s, c) = ((s).unwrap(), (c)); s.to_bytes_with_nul().iter().position(|&b| b == c as u8).map(|i| std::ffi::CStr::from_bytes_with_nul(&s.to_bytes_with_nul()[i..]).unwrap()) };
+// CHECK-DAG: if (p).is_none() {
+// CHECK-DAG: (p).unwrap().to_bytes().len() as libc::c_ulong
+
+// A string that's also dereferenced directly stays a reference to a single `c_char`.
+// CHECK-DAG: pub unsafe fn first(s: Option<&(libc::c_char)>) -> libc::c_char {
+
+// CHECK-DAG: pub unsafe fn count(s: Option<&[(libc::c_char)]>, c: libc::c_char) -> libc::c_int {
+// CHECK-DAG: let s = if s.is_null() { None } else { Some(std::slice::from_raw_parts(s, std::ffi::CStr::from_ptr(s).to_bytes_with_nul().len())) };
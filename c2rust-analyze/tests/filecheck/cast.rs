@@ -10,9 +10,16 @@ struct S {
 
 // CHECK-LABEL: final labeling for "null_ptr"
 pub unsafe fn null_ptr() {
-    // CHECK-DAG: ([[@LINE+3]]: s): addr_of = UNIQUE, type = READ | WRITE | UNIQUE#
+    // CHECK-DAG: ([[@LINE+3]]: s): addr_of = UNIQUE | NON_NULL, type = READ | WRITE | UNIQUE#
     // CHECK-LABEL: type assignment for "null_ptr":
-    // CHECK-DAG: ([[@LINE+1]]: s): &mut S
+    // CHECK-DAG: ([[@LINE+1]]: s): {{.*}}Option<&mut S>
     let s = 0 as *mut S;
     (*s).i = 0 as *const i32;
 }
+
+// CHECK-LABEL: generated {{[0-9]+}} expr rewrites + {{[0-9]+}} ty rewrites for "null_ptr"
+// CHECK-DAG: 16: 0 as *mut S: None
+// CHECK-DAG: 17: (*s).i = {{.*}}: $0 = None
+// CHECK-DAG: 17: s: $e.as_deref_mut().unwrap()
+
+// CHECK-DAG: i: Option<&'h0 (i32)>,
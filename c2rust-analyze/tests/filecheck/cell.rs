@@ -24,9 +24,9 @@ struct S {
 
 unsafe extern "C" fn cell_field(mut s: *mut S) {
     (*s).i = 1;
-    // CHECK-DAG: let r1: &core::cell::Cell<(R)> = &((*s).r);
+    // CHECK-DAG: let r1: &core::cell::Cell<(R)> = &((*{{.*}}).r);
     let r1: *mut R = &mut (*s).r;
-    // CHECK-DAG: let r2: &core::cell::Cell<(R)> = &((*s).r);
+    // CHECK-DAG: let r2: &core::cell::Cell<(R)> = &((*{{.*}}).r);
     let r2: *mut R = &mut (*s).r;
     // CHECK-DAG: ((*r1)).set((0));
     (*r1).i = 0;
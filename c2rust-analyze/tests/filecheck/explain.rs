@@ -0,0 +1,35 @@
+//! --arg=--explain=explain_write:p
+//! --arg=--explain=explain_alias:p
+//! --arg=--explain=explain_alias:28
+
+use std::ptr;
+
+// CHECK-LABEL: explain explain_write:p:
+pub unsafe fn explain_write(x: *mut i32) {
+    // CHECK-NEXT: _2 ([[@LINE+4]]: p): l{{[0-9]+}}, perms = READ | WRITE | UNIQUE, flags = (empty)
+    // CHECK-NEXT: WRITE:
+    // CHECK-NEXT: l{{[0-9]+}} flows into l{{[0-9]+}} (at [[@LINE+3]]: {{.*}})
+    // CHECK-NEXT: l{{[0-9]+}} needs READ | WRITE (at [[@LINE+3]]: *q = 1)
+    let p = x;
+    let q = p;
+    *q = 1;
+}
+
+// CHECK-LABEL: explain explain_alias:p:
+pub unsafe fn explain_alias() {
+    let mut x = 0;
+    // CHECK-NEXT: _2 ([[@LINE+5]]: p): l{{[0-9]+}}, perms = READ | WRITE | NON_NULL, flags = {{.*}}
+    // CHECK-NEXT: WRITE:
+    // CHECK-NEXT: l{{[0-9]+}} needs READ | WRITE (at [[@LINE+8]]: *p = 1)
+    // CHECK-NEXT: not UNIQUE:
+    // CHECK-NEXT: l{{[0-9]+}} is assigned from l{{[0-9]+}} (at [[@LINE+1]]: ptr::addr_of_mut!(x))
+    let p = ptr::addr_of_mut!(x);
+    // CHECK-NEXT: l{{[0-9]+}} was borrowed (at [[@LINE-1]]: ptr::addr_of_mut!(x)), and the loan conflicts with another access (at [[@LINE+1]]: {{.*}})
+    let q = ptr::addr_of_mut!(x);
+    // CHECK-LABEL: explain explain_alias:28:
+    // CHECK-NEXT: _3 ([[@LINE-2]]: q): l{{[0-9]+}}, perms = NON_NULL, flags = {{.*}}
+    *p = 1;
+    // CHECK-NEXT: not UNIQUE:
+    // CHECK-NEXT: l{{[0-9]+}} is assigned from l{{[0-9]+}} (at 28: ptr::addr_of_mut!(x))
+    // CHECK-NEXT: l{{[0-9]+}} was borrowed (at 26: ptr::addr_of_mut!(x)), and the loan conflicts with another access (at 28: {{.*}})
+}
@@ -0,0 +1,72 @@
+#![feature(rustc_private)]
+
+extern crate libc;
+
+extern "C" {
+    fn register(cb: Option<unsafe extern "C" fn(*mut i32)>);
+}
+
+// The signature of `last` is kept before the analysis runs, and the argument of `consume` once
+// it's inferred to be a `Box`.
+// CHECK: keeping the C ABI signature of "last": argument `p` is offset, but no parameter bounds the offsets
+// CHECK: keeping a raw pointer in the signature of "consume": argument `p` would free C memory
+
+// CHECK-LABEL: final labeling for "sum"
+#[no_mangle]
+pub unsafe extern "C" fn sum(p: *const i32, n: libc::c_int) -> i32 {
+    // CHECK-DAG: ([[@LINE-1]]: p): {{.*}}type = READ | UNIQUE | OFFSET_ADD | OFFSET_SUB#
+    let mut s = 0;
+    let mut i = 0;
+    while i < n {
+        s += *p.offset(i as isize);
+        i += 1;
+    }
+    s
+}
+
+// A negative length aborts, and a null pointer with a length of zero becomes an empty slice.
+// CHECK-LABEL: generated 3 FFI wrapper rewrites for "sum"
+// CHECK: #[export_name = "sum"]
+// CHECK-NEXT: {{^}}pub unsafe extern "C" fn sum_ffi(p: *const i32, n: libc::c_int) -> i32 {
+// CHECK-NEXT: if n < 0 { std::process::abort(); }
+// CHECK-NEXT: let p = if !p.is_null() { Some(std::slice::from_raw_parts(p, n as usize)) } else if n == 0 { Some(Default::default()) } else { None };
+// CHECK-NEXT: sum(p, n)
+// CHECK-NEXT: }
+// CHECK-NEXT: 15: #[no_mangle]:
+// CHECK-NEXT: 16: extern "C":
+
+// CHECK-LABEL: final labeling for "incr"
+unsafe extern "C" fn incr(p: *mut i32) {
+    // CHECK-DAG: ([[@LINE-1]]: p): {{.*}}type = READ | WRITE | UNIQUE#
+    *p += 1;
+}
+
+// CHECK-LABEL: generated 3 FFI wrapper rewrites for "incr"
+// CHECK: unsafe extern "C" fn incr_ffi(p: *mut i32) {
+// CHECK-NEXT: let p = if p.is_null() { None } else { Some(&mut *p) };
+// CHECK-NEXT: incr(p)
+// CHECK-NEXT: }
+// CHECK-NEXT: 39: extern "C":
+// CHECK-NEXT: [[@LINE+3]]: incr: incr_ffi
+
+pub unsafe fn setup() {
+    register(Some(incr));
+}
+
+// The offset isn't bounded by `n`, so a slice built from `n` could be too short.
+#[no_mangle]
+pub unsafe extern "C" fn last(p: *const i32, n: libc::c_int) -> i32 {
+    *p.offset(n as isize - 1)
+}
+
+// `p` was allocated by C, so it must not become a `Box`.
+#[no_mangle]
+pub unsafe extern "C" fn consume(p: *mut i32) {
+    libc::free(p as *mut libc::c_void);
+}
+
+// CHECK-NOT: FFI wrapper rewrites for "consume"
+// CHECK-LABEL: ===== BEGIN
+// CHECK: pub unsafe fn sum(p: Option<&[{{.*}}]>, n: libc::c_int) -> i32 {
+// CHECK: unsafe fn incr(p: Option<&mut (i32)>) {
+// CHECK: register(Some(incr_ffi));
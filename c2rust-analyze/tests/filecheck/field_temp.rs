@@ -5,11 +5,12 @@ pub struct MyList {
     len: usize,
 }
 
+// Rewrites are generated before the final labeling is printed.
+// CHECK: rewrite Ref(Deref(Identity), Not) at {{.*}}:[[@LINE+5]]:6: [[@LINE+5]]:15
 // CHECK-LABEL: final labeling for "list_get"
 pub unsafe fn list_get(l: *const MyList, i: usize) -> i32 {
     // The temporary `(*l).data` requires a MIR `MutToImm` rewrite.
-    // CHECK: ([[@LINE+2]]: (*l).data): &[i32]
-    // CHECK: rewrite Ref(Deref(Identity), Not) at {{.*}}:[[@LINE+1]]:6: [[@LINE+1]]:15
+    // CHECK: ([[@LINE+1]]: (*l).data): &[i32]
     *(*l).data.offset(i as isize)
 }
 
@@ -0,0 +1,86 @@
+extern "C" {
+    fn ext_get(p: *mut i32) -> i32;
+}
+
+pub struct Ops {
+    pub get: Option<unsafe extern "C" fn(*mut i32) -> i32>,
+}
+
+// `get_and_clear` is only called through `Ops::get`, so its parameter and the function pointer
+// type become `&mut` together.
+unsafe extern "C" fn get_and_clear(p: *mut i32) -> i32 {
+    let x = *p;
+    *p = 0;
+    x
+}
+
+unsafe extern "C" fn get(p: *mut i32) -> i32 {
+    *p
+}
+
+unsafe extern "C" fn get_other(p: *mut i32) -> i32 {
+    *p + 1
+}
+
+unsafe fn set(p: *mut i32) {
+    *p = 1;
+}
+
+pub unsafe fn make_ops() -> Ops {
+    Ops {
+        get: Some(get_and_clear),
+    }
+}
+
+// CHECK-LABEL: indirect call targets:
+// CHECK-DAG: "call_get" bb{{[0-9]+}}[{{[0-9]+}}]: ["get_and_clear"]
+// CHECK-DAG: "call_local" bb{{[0-9]+}}[{{[0-9]+}}]: ["get"]
+// CHECK-DAG: "call_set" bb{{[0-9]+}}[{{[0-9]+}}]: ["set"]
+// CHECK-DAG: "call_unknown" bb{{[0-9]+}}[{{[0-9]+}}]: unknown
+
+// CHECK-LABEL: final labeling for "call_get"
+pub unsafe fn call_get(ops: *const Ops, p: *mut i32) -> i32 {
+    // CHECK-DAG: ([[@LINE-1]]: p): addr_of = {{.*}}, type = READ | WRITE | UNIQUE#
+    (*ops).get.expect("non-null function pointer")(p)
+}
+
+// CHECK-LABEL: final labeling for "call_local"
+pub unsafe fn call_local(p: *mut i32) -> i32 {
+    // CHECK-DAG: ([[@LINE-1]]: p): addr_of = {{.*}}, type = READ | UNIQUE#
+    let f: unsafe extern "C" fn(*mut i32) -> i32 = get;
+    f(p)
+}
+
+// CHECK-LABEL: final labeling for "call_set"
+pub unsafe fn call_set(p: *mut i32) {
+    // CHECK-DAG: ([[@LINE-1]]: p): addr_of = {{.*}}, type = READ | WRITE | UNIQUE#
+    let f: unsafe fn(*mut i32) = set;
+    f(p)
+}
+
+// `f` may hold a foreign function, so the call isn't resolved, and the pointers passed through it
+// stay raw.
+// CHECK-LABEL: final labeling for "call_unknown"
+pub unsafe fn call_unknown(p: *mut i32, ext: bool) -> i32 {
+    // CHECK-DAG: ([[@LINE-1]]: p): {{.*}}type flags = FIXED#
+    // CHECK-DAG: ([[@LINE+1]]: f): {{.*}}type flags = (empty)#{{.*}}[(empty)#{{.*}}[FIXED#*mut i32
+    let f: Option<unsafe extern "C" fn(*mut i32) -> i32> =
+        if ext { Some(ext_get) } else { Some(get_other) };
+    f.unwrap()(p)
+}
+
+// CHECK-LABEL: ===== BEGIN
+// CHECK: pub get: Option<unsafe extern "C" fn(Option<&mut (i32)>) -> i32>,
+// CHECK: unsafe extern "C" fn get_and_clear(p: Option<&mut (i32)>) -> i32 {
+// CHECK: unsafe extern "C" fn get_other_ffi(p: *mut i32) -> i32 {
+// CHECK: get: Some(get_and_clear),
+// CHECK: pub unsafe fn call_get(ops: Option<&(Ops)>, p: Option<&mut (i32)>) -> i32 {
+// CHECK: pub unsafe fn call_local(p: Option<&(i32)>) -> i32 {
+// CHECK: let f: unsafe extern "C" fn(Option<&(i32)>) -> i32 = get;
+// CHECK-NEXT: f(p)
+// CHECK: pub unsafe fn call_set(p: Option<&mut (i32)>) {
+// CHECK: let f: unsafe fn(Option<&mut (i32)>) = set;
+// CHECK: pub unsafe fn call_unknown(p: *mut i32, ext: bool) -> i32 {
+// CHECK: let f: Option<unsafe extern "C" fn(*mut i32) -> i32> =
+// CHECK-NEXT: if ext { Some(ext_get) } else { Some(get_other_ffi) };
+// CHECK-NEXT: f.unwrap()(p)
@@ -0,0 +1,82 @@
+use std::ptr;
+
+// CHECK-LABEL: final labeling for "null_none"
+pub unsafe fn null_none(x: *mut i32, cond: bool) -> i32 {
+    // CHECK-DAG: ([[@LINE-1]]: x): {{.*}}type = READ | WRITE | UNIQUE#
+    // CHECK-DAG: ([[@LINE+1]]: mut p): {{.*}}type = READ | WRITE | UNIQUE#
+    let mut p = ptr::null_mut();
+    if cond {
+        p = x;
+    }
+    if p.is_null() {
+        return 0;
+    }
+    *p = 1;
+    *p
+}
+
+// CHECK-LABEL: generated {{[0-9]+}} expr rewrites + {{[0-9]+}} ty rewrites for "null_none"
+// CHECK-DAG: 7: ptr::null_mut(): None
+// CHECK-DAG: 11: if p.is_null(): let Some(p) = p.as_deref_mut() else
+// CHECK-DAG: 11: { return 0; }: $e;
+
+// CHECK-LABEL: final labeling for "if_let"
+pub unsafe fn if_let(x: *mut i32, cond: bool) {
+    let mut p = ptr::null_mut();
+    if cond {
+        p = x;
+    }
+    if !p.is_null() {
+        *p = 1;
+    }
+}
+
+// CHECK-LABEL: generated {{[0-9]+}} expr rewrites + {{[0-9]+}} ty rewrites for "if_let"
+// CHECK-DAG: 29: !p.is_null(): let Some(p) = p.as_deref_mut()
+
+// CHECK-LABEL: final labeling for "compare"
+pub unsafe fn compare(x: *mut i32, cond: bool) -> bool {
+    let mut p = ptr::null_mut();
+    if cond {
+        p = x;
+    }
+    p != ptr::null_mut()
+}
+
+// CHECK-LABEL: generated {{[0-9]+}} expr rewrites + {{[0-9]+}} ty rewrites for "compare"
+// CHECK-DAG: 43: p != ptr::null_mut(): $0.is_some()
+
+// CHECK-LABEL: final labeling for "null_check"
+pub unsafe fn null_check(x: *mut i32) -> bool {
+    // CHECK-DAG: ([[@LINE-1]]: x): {{.*}}type = UNIQUE#
+    x.is_null()
+}
+
+// CHECK-LABEL: generated {{[0-9]+}} expr rewrites + {{[0-9]+}} ty rewrites for "null_check"
+// CHECK-DAG: 52: x.is_null(): $0.is_none()
+
+// Callers may pass null, so the guard on the argument is kept.
+// CHECK-LABEL: final labeling for "param_guard"
+// CHECK-DAG: ([[@LINE+1]]: x): {{.*}}type = READ | UNIQUE#
+pub unsafe fn param_guard(x: *const i32) -> i32 {
+    if x.is_null() {
+        return 0;
+    }
+    *x
+}
+
+// CHECK-LABEL: generated {{[0-9]+}} expr rewrites + {{[0-9]+}} ty rewrites for "param_guard"
+// CHECK-DAG: 62: if x.is_null(): let Some(x) = x else
+
+// `p` is non-null after the guard, so it isn't unwrapped.
+// CHECK-LABEL: ===== BEGIN
+// CHECK: let Some(p) = p.as_deref_mut() else {
+// CHECK-NEXT: return 0;
+// CHECK-NEXT: };
+// CHECK-NEXT: *p = 1;
+// CHECK-NEXT: *p
+// CHECK: pub unsafe fn param_guard(x: Option<&(i32)>) -> i32 {
+// CHECK-NEXT: let Some(x) = x else {
+// CHECK-NEXT: return 0;
+// CHECK-NEXT: };
+// CHECK-NEXT: *x
@@ -0,0 +1,35 @@
+//! --arg=--pdg=tests/filecheck/pdg_evidence.json
+
+// Evidence for every function is reported before any of the final labelings.
+
+// CHECK-LABEL: dynamic permission evidence for "first"
+// CHECK: ([[@LINE+9]]: p): remove = WRITE | OFFSET_ADD | OFFSET_SUB, add = NON_NULL
+// CHECK-LABEL: dynamic permission evidence for "second"
+// CHECK: ([[@LINE+16]]: p): remove = WRITE | OFFSET_SUB, add = NON_NULL
+// CHECK-NEXT: rejected: OFFSET_SUB is needed (at [[@LINE+17]]: p.offset(1))
+
+// The facts say `p` was never written through, offset, or null.
+
+// CHECK-LABEL: final labeling for "first"
+// CHECK-DAG: ([[@LINE+1]]: p): {{.*}}type = READ | UNIQUE | NON_NULL#
+pub unsafe fn first(p: *const i32) -> i32 {
+    *p
+}
+
+// The facts say `p` was only ever offset forward, but `offset` statically needs both directions,
+// so the evidence is rejected.
+
+// CHECK-LABEL: final labeling for "second"
+// CHECK-DAG: ([[@LINE+1]]: p): {{.*}}type = READ | UNIQUE | OFFSET_ADD | OFFSET_SUB#
+pub unsafe fn second(p: *const i32) -> i32 {
+    // CHECK-DAG: ([[@LINE+1]]: p): {{.*}}type = READ | UNIQUE | OFFSET_ADD | OFFSET_SUB#
+    *p.offset(1)
+}
+
+// `q` has no facts of its own, and it keeps `OFFSET_SUB` from `second`'s argument.
+
+// CHECK-LABEL: final labeling for "call_second"
+// CHECK-DAG: ([[@LINE+1]]: q): {{.*}}type = READ | UNIQUE | OFFSET_ADD | OFFSET_SUB#
+pub unsafe fn call_second(q: *const i32) -> i32 {
+    second(q)
+}
@@ -1,15 +1,15 @@
 #![allow(dead_code)]
 
 // CHECK: final labeling for static items:
-// CHECK-DAG: "UNUSED": perms = UNIQUE, flags = (empty)
+// CHECK-DAG: "UNUSED": perms = UNIQUE | NON_NULL, flags = (empty)
 static UNUSED: usize = 2;
-// CHECK-DAG: "UNUSED_MUT": perms = UNIQUE, flags = (empty)
+// CHECK-DAG: "UNUSED_MUT": perms = UNIQUE | NON_NULL, flags = (empty)
 static mut UNUSED_MUT: usize = 6;
-// CHECK-DAG: "READ": perms = READ | UNIQUE, flags = (empty)
+// CHECK-DAG: "READ": perms = READ | UNIQUE | NON_NULL, flags = (empty)
 static READ: usize = 9;
-// CHECK-DAG: "READ_MUT": perms = READ | UNIQUE, flags = (empty)
+// CHECK-DAG: "READ_MUT": perms = READ | UNIQUE | NON_NULL, flags = (empty)
 static mut READ_MUT: usize = 21;
-// CHECK-DAG: "WRITTEN_MUT": perms = READ | WRITE | UNIQUE, flags = (empty)
+// CHECK-DAG: "WRITTEN_MUT": perms = READ | WRITE | UNIQUE | NON_NULL, flags = (empty)
 static mut WRITTEN_MUT: usize = 3;
 
 // CHECK: generated 2 static rewrites:
@@ -0,0 +1,38 @@
+// CHECK-LABEL: generated {{[0-9]+}} ADT type rewrites:
+
+// CHECK-LABEL: ===== BEGIN
+// CHECK-DAG: pub struct Node<'h0> {
+pub struct Node {
+    // CHECK-DAG: pub next: &'h0 (Node<'h0>),
+    pub next: *mut Node,
+    pub val: i32,
+}
+
+// `List` gets its own lifetime for `head`, plus the one it uses for `Node`.
+// CHECK-DAG: pub struct List<'h0, 'h1> {
+pub struct List {
+    // CHECK-DAG: pub head: &'h1 (Node<'h0>),
+    pub head: *mut Node,
+}
+
+// CHECK-DAG: pub unsafe fn list_head(l: Option<&(List<'_, '_>)>) -> i32 {
+pub unsafe fn list_head(l: *mut List) -> i32 {
+    (*(*l).head).val
+}
+
+pub type IntPtr = *mut i32;
+
+// A field declared through an alias can't be rewritten in place, so it keeps its raw type and
+// doesn't contribute a lifetime.
+// CHECK-DAG: pub struct Holder<'h3> {
+pub struct Holder {
+    // CHECK-DAG: pub aliased: IntPtr,
+    pub aliased: IntPtr,
+    // CHECK-DAG: pub direct: &'h3 (i32),
+    pub direct: *mut i32,
+}
+
+// CHECK-DAG: pub unsafe fn holder_sum(h: Option<&(Holder<'_>)>) -> i32 {
+pub unsafe fn holder_sum(h: *mut Holder) -> i32 {
+    *(*h).aliased + *(*h).direct
+}
@@ -15,23 +15,23 @@ pub unsafe fn let_decl_mut() {
     *x = *x;
 }
 
-// CHECK-DAG: pub unsafe extern "C" fn offset_mut(p: &mut [(i32)]) {
+// CHECK-DAG: pub unsafe extern "C" fn offset_mut(p: Option<&mut [(i32)]>) {
 pub unsafe extern "C" fn offset_mut(p: *mut i32) {
-    // CHECK-DAG: let x: &mut (i32) = &mut (&mut (p)[((1 as isize) as usize) ..])[0];
+    // CHECK-DAG: let x: &mut (i32) = &mut (&mut (p).as_deref_mut().unwrap()[((1 as isize) as usize) ..])[0];
     let x: *mut i32 = p.offset(1 as isize);
     *x = 1;
 }
 
-// CHECK-DAG: pub unsafe fn aggregate1_array(p: &core::cell::Cell<(i32)>)
+// CHECK-DAG: pub unsafe fn aggregate1_array(p: Option<&core::cell::Cell<(i32)>>)
 pub unsafe fn aggregate1_array(p: *mut i32) {
-    // CHECK-DAG: let arr: [&core::cell::Cell<(i32)>; 3] = [p, p, p];
+    // CHECK-DAG: let arr: [Option<&core::cell::Cell<(i32)>>; 3] = [p, p, p];
     let arr: [*mut i32; 3] = [p, p, p];
     *arr[0] = 1;
 }
 
-// CHECK-DAG: pub unsafe fn aggregate1_array1(p: &mut (i32))
+// CHECK-DAG: pub unsafe fn aggregate1_array1(p: Option<&mut (i32)>)
 pub unsafe fn aggregate1_array1(p: *mut i32) {
-    // CHECK-DAG: let arr: [&mut (i32); 1] = [p];
+    // CHECK-DAG: let arr: [Option<&mut (i32)>; 1] = [p];
     let arr: [*mut i32; 1] = [p];
     *arr[0] = 1;
 }
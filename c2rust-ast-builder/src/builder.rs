@@ -133,7 +133,7 @@ pub enum Extern {
 
 pub enum SelfKind {
     Value(Mutability),
-    Region(Lifetime, Mutability),
+    Region(Option<Lifetime>, Mutability),
 }
 
 fn use_tree_with_prefix(prefix: Path, leaf: UseTree) -> UseTree {
@@ -929,6 +929,16 @@ impl Builder {
         })))
     }
 
+    /// A half-open range, `from..to`, with either end optional.
+    pub fn range_expr(self, from: Option<Box<Expr>>, to: Option<Box<Expr>>) -> Box<Expr> {
+        Box::new(Expr::Range(ExprRange {
+            attrs: self.attrs,
+            from,
+            limits: RangeLimits::HalfOpen(Token![..](self.span)),
+            to,
+        }))
+    }
+
     pub fn abs_path_expr<Pa>(self, path: Pa) -> Box<Expr>
     where
         Pa: Make<Path>,
@@ -1823,6 +1833,20 @@ impl Builder {
 
     // Impl Items
 
+    pub fn method_impl_item<S>(self, sig: S, block: Block) -> ImplItem
+    where
+        S: Make<Signature>,
+    {
+        let sig = sig.make(&self);
+        ImplItem::Method(ImplItemMethod {
+            attrs: self.attrs,
+            vis: self.vis,
+            defaultness: None,
+            sig,
+            block,
+        })
+    }
+
     pub fn mac_impl_item(self, mac: Macro) -> ImplItem {
         ImplItem::Macro(ImplItemMacro {
             attrs: self.attrs,
@@ -1991,9 +2015,7 @@ impl Builder {
     pub fn self_arg(self, kind: SelfKind) -> FnArg {
         let (reference, mutability) = match kind {
             SelfKind::Value(mutability) => (None, mutability),
-            SelfKind::Region(lt, mutability) => {
-                (Some((Token![&](self.span), Some(lt))), mutability)
-            }
+            SelfKind::Region(lt, mutability) => (Some((Token![&](self.span), lt)), mutability),
         };
         let attrs = Vec::new();
         FnArg::Receiver(Receiver {
@@ -562,6 +562,10 @@ class TranslateASTVisitor final
     SmallVector<MacroInfo*, 1> curMacroExpansionStack;
     StringRef curMacroExpansionSource;
 
+    // Label operands of `asm goto` statements, which are exported as part of
+    // the statement rather than as address-of-label expressions
+    std::unordered_set<const AddrLabelExpr *> asmGotoLabels;
+
     // Returns true when a new entry is added to exportedTags
     bool markForExport(void *ptr, ASTEntryTag tag) {
         return exportedTags.emplace(ptr, tag).second;
@@ -980,6 +984,18 @@ class TranslateASTVisitor final
         return true;
     }
 
+    // Encoding for file-scope (top-level) assembly:
+    //   Assembly string, verbatim
+    bool VisitFileScopeAsmDecl(FileScopeAsmDecl *D) {
+        std::vector<void *> childIds;
+        encode_entry(D, TagFileScopeAsmDecl, childIds, QualType(),
+                     [D](CborEncoder *local) {
+                         cbor_encode_string(local,
+                                            D->getAsmString()->getString().str());
+                     });
+        return true;
+    }
+
     bool VisitLabelStmt(LabelStmt *LS) {
 
         std::vector<void *> childIds = {LS->getSubStmt()};
@@ -1097,13 +1113,24 @@ class TranslateASTVisitor final
     //   List of clobbers
     //
     // The number of input and output expressions in the child id list will
-    // match the length of the corresponding constraint arrays.
+    // match the length of the corresponding constraint arrays. For `asm goto`,
+    // the target label statements follow the output expressions.
     bool VisitGCCAsmStmt(GCCAsmStmt *E) {
 
         std::vector<void *> childIds;
         copy(E->begin_inputs(), E->end_inputs(), std::back_inserter(childIds));
         copy(E->begin_outputs(), E->end_outputs(),
              std::back_inserter(childIds));
+        // `asm goto` label operands follow the outputs; we export the
+        // `LabelStmt`s they refer to
+#if CLANG_VERSION_MAJOR >= 9
+        if (E->isAsmGoto()) {
+            for (auto *L : E->labels()) {
+                asmGotoLabels.insert(L);
+                childIds.push_back(L->getLabel()->getStmt());
+            }
+        }
+#endif // CLANG_VERSION_MAJOR >= 9
 
         encode_entry(E, TagAsmStmt, childIds, [E, this](CborEncoder *local) {
             cbor_encode_boolean(local, E->isVolatile());
@@ -1759,7 +1786,45 @@ class TranslateASTVisitor final
         return true;
     }
 
+    /*
+     Block literal (`^(params) { body }`, Clang blocks extension)
+     Children:
+     - parameter declarations
+     - body
+     Extras:
+     - array of captures, each an array of the captured variable and
+       whether it is captured by reference (`__block`)
+     */
+    bool VisitBlockExpr(BlockExpr *E) {
+        auto BD = E->getBlockDecl();
+
+        std::vector<void *> childIds;
+        for (auto x : BD->parameters()) {
+            auto cd = x->getCanonicalDecl();
+            childIds.push_back(cd);
+            TraverseDecl(cd);
+        }
+        childIds.push_back(BD->getBody());
+
+        encode_entry(E, TagBlockExpr, childIds, [BD](CborEncoder *extras) {
+            CborEncoder captures;
+            cbor_encoder_create_array(extras, &captures, BD->getNumCaptures());
+            for (auto &C : BD->captures()) {
+                CborEncoder capture;
+                cbor_encoder_create_array(&captures, &capture, 2);
+                cbor_encode_uint(&capture,
+                                 uintptr_t(C.getVariable()->getCanonicalDecl()));
+                cbor_encode_boolean(&capture, C.isByRef());
+                cbor_encoder_close_container(&captures, &capture);
+            }
+            cbor_encoder_close_container(extras, &captures);
+        });
+        return true;
+    }
+
     bool VisitAddrLabelExpr(AddrLabelExpr *E) {
+        if (asmGotoLabels.count(E))
+            return true;
         printWarning("Cannot translate GNU address of label expression", E);
         return true;
     }
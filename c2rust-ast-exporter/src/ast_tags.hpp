@@ -28,6 +28,8 @@ enum ASTEntryTag {
     TagMacroObjectDef,
     TagMacroFunctionDef,
 
+    TagFileScopeAsmDecl,
+
     TagCompoundStmt = 100,
     TagReturnStmt,
     TagIfStmt,
@@ -85,6 +87,9 @@ enum ASTEntryTag {
 
     TagAtomicExpr,
 
+    // Clang blocks extension
+    TagBlockExpr,
+
     TagIntegerLiteral = 300,
     TagStringLiteral,
     TagCharacterLiteral,
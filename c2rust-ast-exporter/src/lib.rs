@@ -4,6 +4,7 @@ use std::ffi::{CStr, CString};
 use std::io::{Error, ErrorKind};
 use std::path::Path;
 use std::slice;
+use std::sync::{Mutex, PoisonError};
 
 pub mod clang_ast;
 
@@ -43,6 +44,10 @@ pub fn get_untyped_ast(
     }
 }
 
+/// The exporter parses its arguments through LLVM's global `cl::opt` registry
+/// and keeps other process-wide state, so at most one export may run at a time.
+static EXPORTER_LOCK: Mutex<()> = Mutex::new(());
+
 fn get_ast_cbors(
     file_path: &Path,
     cc_db: &Path,
@@ -63,6 +68,7 @@ fn get_ast_cbors(
     let args_ptrs: Vec<*const libc::c_char> = args_owned.iter().map(|x| x.as_ptr()).collect();
 
     let hashmap;
+    let _guard = EXPORTER_LOCK.lock().unwrap_or_else(PoisonError::into_inner);
     unsafe {
         let ptr = ast_exporter(
             args_ptrs.len() as libc::c_int,
@@ -6,13 +6,10 @@ use syn::parse::Error;
 use syn::punctuated::Punctuated;
 use syn::spanned::Spanned;
 use syn::{
-    parse_macro_input, Attribute, Field, Fields, Ident, ItemStruct, Lit, Meta, NestedMeta, Path,
-    PathArguments, PathSegment, Token,
+    parse_macro_input, Attribute, Expr, ExprLit, Field, Fields, Ident, ItemStruct, Lit, Meta,
+    NestedMeta, Path, PathArguments, PathSegment, Token, Type, TypeArray,
 };
 
-#[cfg(target_endian = "big")]
-compile_error!("Big endian architectures are not currently supported");
-
 /// This struct keeps track of a single bitfield attr's params
 /// as well as the bitfield's field name.
 #[derive(Debug)]
@@ -183,79 +180,208 @@ fn bitfield_struct_impl(struct_item: ItemStruct) -> Result<TokenStream, Error> {
     let bitfields: Result<Vec<BFFieldAttr>, Error> =
         fields.iter().flat_map(filter_and_parse_fields).collect();
     let bitfields = bitfields?;
-    let field_types: Vec<_> = bitfields.iter().map(parse_bitfield_ty_path).collect();
-    let field_types_return = &field_types;
-    let field_types_typedef = &field_types;
-    let field_types_setter_arg = &field_types;
-    let method_names: Vec<_> = bitfields
+    let accessors: Vec<_> = bitfields
         .iter()
-        .map(|field| Ident::new(&field.name, Span::call_site().into()))
-        .collect();
-    let field_names: Vec<_> = bitfields.iter().map(|field| &field.field_name).collect();
-    let field_names_setters = &field_names;
-    let field_names_getters = &field_names;
-    let method_name_setters: Vec<_> = method_names
+        .map(bitfield_accessors)
+        .collect::<Result<_, Error>>()?;
+    let group_methods: Vec<_> = fields
         .iter()
-        .map(|field_ident| {
-            let span = Span::call_site().into();
-            let setter_name = &format!("set_{}", field_ident);
-
-            Ident::new(setter_name, span)
-        })
+        .filter(|field| !filter_and_parse_fields(field).is_empty())
+        .filter_map(bitfield_group_methods)
         .collect();
-    let field_bit_info: Result<Vec<_>, Error> = bitfields
-        .iter()
-        .map(|field| {
-            let bit_string = &field.bits.0;
-            let nums: Vec<_> = bit_string.split("..=").collect();
-            let err_str = "bits param must be in the format \"1..=4\"";
-
-            if nums.len() != 2 {
-                return Err(Error::new(field.bits.1, err_str));
-            }
-
-            let lhs = nums[0].parse::<usize>();
-            let rhs = nums[1].parse::<usize>();
-
-            let (lhs, rhs) = match (lhs, rhs) {
-                (Err(_), _) | (_, Err(_)) => return Err(Error::new(field.bits.1, err_str)),
-                (Ok(lhs), Ok(rhs)) => (lhs, rhs),
-            };
-
-            Ok(quote! { (#lhs, #rhs) })
-        })
-        .collect();
-    let field_bit_info = field_bit_info?;
-    let field_bit_info_setters = &field_bit_info;
-    let field_bit_info_getters = &field_bit_info;
 
     // TODO: Method visibility determined by struct field visibility?
     let q = quote! {
         #[automatically_derived]
         impl #struct_ident {
-            #(
+            #(#accessors)*
+            #(#group_methods)*
+        }
+    };
+
+    Ok(q.into())
+}
+
+fn parse_bit_range(field: &BFFieldAttr) -> Result<(usize, usize), Error> {
+    let bit_string = &field.bits.0;
+    let nums: Vec<_> = bit_string.split("..=").collect();
+    let err_str = "bits param must be in the format \"1..=4\"";
+
+    if nums.len() != 2 {
+        return Err(Error::new(field.bits.1, err_str));
+    }
+
+    let lhs = nums[0].parse::<usize>();
+    let rhs = nums[1].parse::<usize>();
+
+    match (lhs, rhs) {
+        (Ok(lhs), Ok(rhs)) if lhs <= rhs => Ok((lhs, rhs)),
+        _ => Err(Error::new(field.bits.1, err_str)),
+    }
+}
+
+/// The smallest unsigned integer type with at least `bytes` bytes, along with
+/// its size, if there is one.
+fn storage_unit(bytes: usize) -> Option<(Ident, usize)> {
+    let size = bytes.next_power_of_two();
+    if size > 16 {
+        return None;
+    }
+
+    Some((
+        Ident::new(&format!("u{}", size * 8), Span::call_site().into()),
+        size,
+    ))
+}
+
+/// Generate the getter and setter of a bitfield.
+///
+/// These load the bytes the bitfield occupies as a single integer, the
+/// storage unit, and then shift and mask it. Bit offsets count from the least
+/// significant bit of the first byte on little-endian targets, and from the
+/// most significant bit of the first byte on big-endian ones, just like the
+/// offsets clang reports.
+fn bitfield_accessors(field: &BFFieldAttr) -> Result<proc_macro2::TokenStream, Error> {
+    let (lhs_bit, rhs_bit) = parse_bit_range(field)?;
+    let field_name = &field.field_name;
+    let field_type = parse_bitfield_ty_path(field);
+    let method_name = Ident::new(&field.name, Span::call_site().into());
+    let method_name_setter = Ident::new(&format!("set_{}", field.name), Span::call_site().into());
+
+    let start_byte = lhs_bit / 8;
+    let bytes = rhs_bit / 8 - start_byte + 1;
+    let (unit, unit_bytes) = match storage_unit(bytes) {
+        Some(unit) => unit,
+        // Only 128-bit fields can straddle more than 16 bytes
+        None => {
+            return Ok(quote! {
                 /// This method allows you to write to a bitfield with a value
-                pub fn #method_name_setters(&mut self, int: #field_types_setter_arg) {
+                pub fn #method_name_setter(&mut self, int: #field_type) {
                     use c2rust_bitfields::FieldType;
 
-                    let field = &mut self.#field_names_setters;
-                    let (lhs_bit, rhs_bit) = #field_bit_info_setters;
-                    int.set_field(field, (lhs_bit, rhs_bit));
+                    let field = &mut self.#field_name;
+                    int.set_field(field, (#lhs_bit, #rhs_bit));
                 }
 
                 /// This method allows you to read from a bitfield to a value
-                pub fn #method_names(&self) -> #field_types_return {
+                pub fn #method_name(&self) -> #field_type {
                     use c2rust_bitfields::FieldType;
 
-                    type IntType = #field_types_typedef;
+                    type IntType = #field_type;
 
-                    let field = &self.#field_names_getters;
-                    let (lhs_bit, rhs_bit) = #field_bit_info_getters;
-                    <IntType as FieldType>::get_field(field, (lhs_bit, rhs_bit))
+                    let field = &self.#field_name;
+                    <IntType as FieldType>::get_field(field, (#lhs_bit, #rhs_bit))
                 }
-            )*
+            });
         }
     };
 
-    Ok(q.into())
+    let width = (rhs_bit - lhs_bit + 1) as u32;
+    let mask = proc_macro2::Literal::u128_unsuffixed(u128::MAX >> (128 - width));
+    let le_shift = (lhs_bit - start_byte * 8) as u32;
+    let be_shift = (unit_bytes * 8 - 1 - (rhs_bit - start_byte * 8)) as u32;
+    let byte_indices: Vec<_> = (start_byte..start_byte + bytes).collect();
+    let unit_indices: Vec<_> = (0..bytes).collect();
+    let load_bytes: Vec<_> = (0..unit_bytes)
+        .map(|i| match byte_indices.get(i) {
+            Some(byte) => quote! { self.#field_name[#byte] },
+            None => quote! { 0 },
+        })
+        .collect();
+    let load = quote! {
+        #unit::from_ne_bytes([#(#load_bytes),*])
+    };
+    let shift = quote! {
+        if cfg!(target_endian = "big") { #be_shift } else { #le_shift }
+    };
+
+    let convert = if field.ty == "bool" {
+        quote! { raw != 0 }
+    } else {
+        // Shifting back down sign extends signed types and is a no-op for
+        // unsigned ones
+        quote! {
+            const BITS: u32 = (::core::mem::size_of::<#field_type>() * 8) as u32;
+            ((raw as #field_type) << (BITS - #width)) >> (BITS - #width)
+        }
+    };
+
+    Ok(quote! {
+        /// This method allows you to write to a bitfield with a value
+        pub fn #method_name_setter(&mut self, int: #field_type) {
+            let shift: u32 = #shift;
+            let mask: #unit = #mask;
+            let unit = (#load & !(mask << shift)) | ((int as #unit & mask) << shift);
+            let bytes = unit.to_ne_bytes();
+            #(self.#field_name[#byte_indices] = bytes[#unit_indices];)*
+        }
+
+        /// This method allows you to read from a bitfield to a value
+        pub const fn #method_name(&self) -> #field_type {
+            let shift: u32 = #shift;
+            let raw = (#load >> shift) & #mask;
+            #convert
+        }
+    })
+}
+
+/// Generate `to_bits` and `from_bits` methods for a field holding a group of
+/// bitfields of at most 16 bytes.
+fn bitfield_group_methods(field: &Field) -> Option<proc_macro2::TokenStream> {
+    let bytes = match &field.ty {
+        Type::Array(TypeArray {
+            len: Expr::Lit(ExprLit {
+                lit: Lit::Int(len), ..
+            }),
+            ..
+        }) => len.base10_parse::<usize>().ok()?,
+        _ => return None,
+    };
+    let (unit, unit_bytes) = storage_unit(bytes)?;
+    let field_name = field.ident.as_ref()?;
+    let to_bits = Ident::new(&format!("{}_to_bits", field_name), Span::call_site().into());
+    let from_bits = Ident::new(
+        &format!("{}_from_bits", field_name),
+        Span::call_site().into(),
+    );
+
+    // The group is zero-extended to the size of the integer
+    let padding = unit_bytes - bytes;
+    let le_bytes = (0..unit_bytes).map(|i| {
+        if i < bytes {
+            quote! { self.#field_name[#i] }
+        } else {
+            quote! { 0 }
+        }
+    });
+    let be_bytes = (0..unit_bytes).map(|i| match i.checked_sub(padding) {
+        Some(i) => quote! { self.#field_name[#i] },
+        None => quote! { 0 },
+    });
+    let le_indices = 0..bytes;
+    let be_indices = padding..unit_bytes;
+
+    Some(quote! {
+        /// This method reads all the bitfields stored in a field at once, as
+        /// an integer in the target's byte order
+        pub const fn #to_bits(&self) -> #unit {
+            if cfg!(target_endian = "big") {
+                #unit::from_be_bytes([#(#be_bytes),*])
+            } else {
+                #unit::from_le_bytes([#(#le_bytes),*])
+            }
+        }
+
+        /// This method creates the contents of a field storing bitfields from
+        /// an integer, the inverse of the `to_bits` method
+        pub const fn #from_bits(bits: #unit) -> [u8; #bytes] {
+            if cfg!(target_endian = "big") {
+                let bytes = bits.to_be_bytes();
+                [#(bytes[#be_indices]),*]
+            } else {
+                let bytes = bits.to_le_bytes();
+                [#(bytes[#le_indices]),*]
+            }
+        }
+    })
 }
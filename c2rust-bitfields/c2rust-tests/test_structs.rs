@@ -620,3 +620,58 @@ fn test_bool_bits() {
     assert!(bool_bits.y());
     assert!(bool_bits.z());
 }
+
+const CONST_DATE: ThreeByteDate = ThreeByteDate {
+    day_month_year: ThreeByteDate::day_month_year_from_bits(18 | 7 << 5 | 2000 << 9),
+};
+const CONST_YEAR: libc::c_ushort = CONST_DATE.year();
+
+#[test]
+fn test_const_and_bulk_access() {
+    assert_eq!(CONST_YEAR, 2000);
+    assert_eq!(CONST_DATE.day(), 18);
+    assert_eq!(CONST_DATE.month(), 7);
+    assert_eq!(
+        CONST_DATE.day_month_year,
+        [0b11110010, 0b10100000, 0b00001111]
+    );
+    assert_eq!(CONST_DATE.day_month_year_to_bits(), 18 | 7 << 5 | 2000 << 9);
+
+    let mut bits = SignedBitfields { x_y_z: [0; 2] };
+
+    bits.x_y_z = SignedBitfields::x_y_z_from_bits(u16::max_value());
+    assert_eq!(bits.x(), -1);
+    assert_eq!(bits.y(), 31);
+    assert_eq!(bits.z(), -1);
+    assert_eq!(bits.x_y_z_to_bits(), u16::max_value());
+}
+
+// A field that straddles 17 bytes, which is accessed bit by bit
+#[repr(C)]
+#[derive(BitfieldStruct)]
+struct WideBitfield {
+    #[bitfield(name = "a", ty = "u8", bits = "0..=3")]
+    #[bitfield(name = "wide", ty = "i128", bits = "4..=131")]
+    a_wide: [u8; 17],
+}
+
+#[test]
+fn test_wide_bitfield() {
+    let mut wide = WideBitfield { a_wide: [0; 17] };
+
+    wide.set_a(0xf);
+    wide.set_wide(i128::min_value() + 5);
+
+    assert_eq!(wide.a(), 0xf);
+    assert_eq!(wide.wide(), i128::min_value() + 5);
+
+    wide.set_wide(-1);
+
+    assert_eq!(wide.a(), 0xf);
+    assert_eq!(wide.wide(), -1);
+
+    // Bits 132..=135 of the last byte lie outside both fields.
+    let mut expected = [0xff; 17];
+    expected[16] = 0x0f;
+    assert_eq!(wide.a_wide, expected);
+}
@@ -2,6 +2,27 @@
 
 pub use c2rust_bitfields_derive::BitfieldStruct;
 
+/// Find where bit `i` of a value stored in bits `bit_range` of a field lives,
+/// as the index of its byte and its position within that byte.
+///
+/// Bit offsets count from the least significant bit of the first byte on
+/// little-endian targets and from the most significant one on big-endian
+/// targets, so the value's bits run in opposite directions.
+fn bit_location(bit_range: (usize, usize), i: usize) -> (usize, u8) {
+    let (lhs_bit, rhs_bit) = bit_range;
+
+    if cfg!(target_endian = "big") {
+        let bit_index = rhs_bit - i;
+        (bit_index / 8, 7 - (bit_index % 8) as u8)
+    } else {
+        let bit_index = lhs_bit + i;
+        (bit_index / 8, (bit_index % 8) as u8)
+    }
+}
+
+/// Bit-by-bit access to bitfields. The `BitfieldStruct` derive only uses this
+/// for bitfields spanning more than 16 bytes, which it can't load as one
+/// integer.
 pub trait FieldType: Sized {
     const IS_SIGNED: bool;
 
@@ -13,28 +34,16 @@ pub trait FieldType: Sized {
     fn get_bit(&self, bit: usize) -> bool;
 
     fn set_field(&self, field: &mut [u8], bit_range: (usize, usize)) {
-        fn zero_bit(byte: &mut u8, n_bit: u64) {
-            let bit = 1 << n_bit;
-
-            *byte &= !bit as u8;
-        }
-
-        fn one_bit(byte: &mut u8, n_bit: u64) {
-            let bit = 1 << n_bit;
-
-            *byte |= bit as u8;
-        }
-
         let (lhs_bit, rhs_bit) = bit_range;
 
-        for (i, bit_index) in (lhs_bit..=rhs_bit).enumerate() {
-            let byte_index = bit_index / 8;
+        for i in 0..=rhs_bit - lhs_bit {
+            let (byte_index, n_bit) = bit_location(bit_range, i);
             let byte = &mut field[byte_index];
 
             if self.get_bit(i) {
-                one_bit(byte, (bit_index % 8) as u64);
+                *byte |= 1 << n_bit;
             } else {
-                zero_bit(byte, (bit_index % 8) as u64);
+                *byte &= !(1 << n_bit);
             }
         }
     }
@@ -54,24 +63,19 @@ macro_rules! impl_int {
 
                 fn get_field(field: &[u8], bit_range: (usize, usize)) -> Self {
                     let (lhs_bit, rhs_bit) = bit_range;
+                    let bit_width = rhs_bit - lhs_bit + 1;
                     let mut val = 0;
 
-                    for (i, bit_index) in (lhs_bit..=rhs_bit).enumerate() {
-                        let byte_index = bit_index / 8;
-                        let byte = field[byte_index];
-                        let bit = 1 << (bit_index % 8);
-                        let read_bit = byte & bit;
-
-                        if read_bit != 0 {
-                            let write_bit = 1 << i;
+                    for i in 0..bit_width {
+                        let (byte_index, n_bit) = bit_location(bit_range, i);
 
-                            val |= write_bit;
+                        if field[byte_index] & (1 << n_bit) != 0 {
+                            val |= 1 << i;
                         }
                     }
 
                     // If the int type is signed, sign extend unconditionally
                     if Self::IS_SIGNED {
-                        let bit_width = rhs_bit - lhs_bit + 1;
                         let unused_bits = Self::TOTAL_BIT_SIZE - bit_width;
 
                         val <<= unused_bits;
@@ -96,19 +100,11 @@ impl FieldType for bool {
 
     fn get_field(field: &[u8], bit_range: (usize, usize)) -> Self {
         let (lhs_bit, rhs_bit) = bit_range;
-        let mut val = false;
 
-        for bit_index in lhs_bit..=rhs_bit {
-            let byte_index = bit_index / 8;
-            let byte = field[byte_index];
-            let bit = 1 << (bit_index % 8);
-            let read_bit = byte & bit;
-
-            if read_bit != 0 {
-                val = true;
-            }
-        }
+        (0..=rhs_bit - lhs_bit).any(|i| {
+            let (byte_index, n_bit) = bit_location(bit_range, i);
 
-        val
+            field[byte_index] & (1 << n_bit) != 0
+        })
     }
 }
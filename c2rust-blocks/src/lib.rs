@@ -0,0 +1,170 @@
+#![no_std]
+//! Support for Clang blocks (`^{ ... }`, enabled with `-fblocks`) in C2Rust translations.
+//!
+//! Blocks are laid out according to the
+//! [Blocks ABI](https://clang.llvm.org/docs/Block-ABI-Apple.html), so translated blocks can be
+//! passed to C code and blocks created by C code can be called from Rust. A block literal is
+//! translated to a `#[repr(C)]` struct starting with a [`Block`] header and followed by copies of
+//! the variables it captures. A block pointer `R (^)(A, B)` becomes
+//! `*mut Block<unsafe extern "C" fn(*mut c_void, A, B) -> R>`: calling it calls
+//! [`invoke`](Block::invoke) with the block itself as the first argument.
+//!
+//! Block literals live on the stack, like in C. [`block_copy`] and [`block_release`] move them to
+//! the heap and release them again through the blocks runtime, which is `libBlocksRuntime` on
+//! Linux and part of `libSystem` on Apple platforms, so they interoperate with C code calling
+//! `Block_copy` and `Block_release`.
+
+use core::ffi::{c_int, c_ulong, c_void};
+use core::mem::size_of;
+use core::ptr;
+
+/// Set in [`Block::flags`] if the descriptor has a copy and a dispose helper.
+pub const BLOCK_HAS_COPY_DISPOSE: c_int = 1 << 25;
+/// Set in [`Block::flags`] by the runtime for blocks allocated statically.
+pub const BLOCK_IS_GLOBAL: c_int = 1 << 28;
+
+/// Passed to [`_Block_object_assign`] and [`_Block_object_dispose`] for captured blocks.
+pub const BLOCK_FIELD_IS_BLOCK: c_int = 7;
+
+#[cfg_attr(all(not(target_vendor = "apple"), not(test)), link(name = "BlocksRuntime"))]
+extern "C" {
+    /// The `isa` of blocks on the stack.
+    pub static _NSConcreteStackBlock: [*const c_void; 32];
+    /// The `isa` of blocks allocated statically.
+    pub static _NSConcreteGlobalBlock: [*const c_void; 32];
+
+    pub fn _Block_copy(block: *const c_void) -> *mut c_void;
+    pub fn _Block_release(block: *const c_void);
+    pub fn _Block_object_assign(dst: *mut c_void, object: *const c_void, flags: c_int);
+    pub fn _Block_object_dispose(object: *const c_void, flags: c_int);
+}
+
+/// The header every block starts with.
+///
+/// `F` is the type of [`invoke`](Self::invoke), which is an
+/// `unsafe extern "C" fn(*mut c_void, ...)` taking the block as its first argument.
+#[repr(C)]
+pub struct Block<F> {
+    pub isa: *const c_void,
+    pub flags: c_int,
+    pub reserved: c_int,
+    pub invoke: F,
+    pub descriptor: *const BlockDescriptor,
+}
+
+impl<F> Block<F> {
+    /// The header of a block literal on the stack.
+    pub fn new(invoke: F, descriptor: &'static BlockDescriptor) -> Self {
+        let flags = if descriptor.copy_helper.is_some() {
+            BLOCK_HAS_COPY_DISPOSE
+        } else {
+            0
+        };
+        Block {
+            // Safety: only the address of the runtime's static is taken
+            isa: unsafe { ptr::addr_of!(_NSConcreteStackBlock) }.cast(),
+            flags,
+            reserved: 0,
+            invoke,
+            descriptor,
+        }
+    }
+}
+
+/// Describes the block literal a [`Block`] header belongs to.
+///
+/// The helpers are only read if [`BLOCK_HAS_COPY_DISPOSE`] is set, which [`Block::new`] does if
+/// there is a copy helper.
+#[repr(C)]
+pub struct BlockDescriptor {
+    pub reserved: c_ulong,
+    /// The size of the block literal, including the header.
+    pub size: c_ulong,
+    /// Copies the captured variables from a block literal to its copy on the heap.
+    pub copy_helper: Option<unsafe extern "C" fn(dst: *mut c_void, src: *const c_void)>,
+    /// Releases the captured variables of a block on the heap.
+    pub dispose_helper: Option<unsafe extern "C" fn(block: *const c_void)>,
+}
+
+impl BlockDescriptor {
+    /// The descriptor of a block literal of type `T`, with helpers if it captures other blocks.
+    pub const fn new<T>(
+        copy_helper: Option<unsafe extern "C" fn(*mut c_void, *const c_void)>,
+        dispose_helper: Option<unsafe extern "C" fn(*const c_void)>,
+    ) -> Self {
+        BlockDescriptor {
+            reserved: 0,
+            size: size_of::<T>() as c_ulong,
+            copy_helper,
+            dispose_helper,
+        }
+    }
+}
+
+/// Copy a block to the heap, or retain it if it is already there, like `Block_copy`.
+///
+/// # Safety
+///
+/// `block` must point to a valid block.
+pub unsafe fn block_copy<F>(block: *mut Block<F>) -> *mut Block<F> {
+    _Block_copy(block as *const c_void).cast()
+}
+
+/// Release a block returned by [`block_copy`], like `Block_release`.
+///
+/// # Safety
+///
+/// `block` must point to a valid block that has been copied to the heap.
+pub unsafe fn block_release<F>(block: *mut Block<F>) {
+    _Block_release(block as *const c_void)
+}
+
+/// Copy the block captured by a block literal to its copy on the heap, for use in copy helpers.
+///
+/// # Safety
+///
+/// `dst` must point to the field of the copy on the heap, and `src` must be a valid block.
+pub unsafe fn assign_block<F>(dst: *mut *mut Block<F>, src: *mut Block<F>) {
+    _Block_object_assign(dst.cast(), src as *const c_void, BLOCK_FIELD_IS_BLOCK)
+}
+
+/// Release the block captured by a block on the heap, for use in dispose helpers.
+///
+/// # Safety
+///
+/// `block` must have been copied by [`assign_block`].
+pub unsafe fn dispose_block<F>(block: *mut Block<F>) {
+    _Block_object_dispose(block as *const c_void, BLOCK_FIELD_IS_BLOCK)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::mem::{align_of, size_of};
+
+    type Invoke = unsafe extern "C" fn(*mut c_void, c_int) -> c_int;
+
+    #[repr(C)]
+    struct Literal {
+        header: Block<Invoke>,
+        captured: c_int,
+    }
+
+    #[test]
+    fn test_layout() {
+        let word = size_of::<*const c_void>();
+        // isa, flags and reserved, invoke, descriptor
+        assert_eq!(size_of::<Block<Invoke>>(), 4 * word);
+        assert_eq!(align_of::<Block<Invoke>>(), word);
+        // reserved, size, copy and dispose helpers
+        assert_eq!(size_of::<BlockDescriptor>(), 4 * word);
+    }
+
+    #[test]
+    fn test_descriptor() {
+        static DESCRIPTOR: BlockDescriptor = BlockDescriptor::new::<Literal>(None, None);
+        assert_eq!(DESCRIPTOR.size as usize, size_of::<Literal>());
+        assert_eq!(DESCRIPTOR.size as usize, 5 * size_of::<*const c_void>());
+        assert!(DESCRIPTOR.copy_helper.is_none());
+    }
+}
@@ -248,6 +248,7 @@ fn emit_lib_rs(
         "modules": modules,
         "pragmas": pragmas,
         "crates": crates,
+        "no_std": tcfg.emit_no_std,
     });
 
     let output_path = build_dir.join(file_name);
@@ -286,12 +287,14 @@ fn emit_cargo_toml<'lcmd>(
             ModuleSubset::Binaries,
         );
         let dependencies = convert_dependencies_list(ccfg.crates.clone());
+        let is_library = ccfg.link_cmd.r#type.is_library();
         let crate_json = json!({
             "crate_name": ccfg.crate_name,
             "crate_rust_name": ccfg.crate_name.replace('-', "_"),
             "crate_types": ccfg.link_cmd.r#type.as_cargo_types(),
-            "is_library": ccfg.link_cmd.r#type.is_library(),
+            "is_library": is_library,
             "lib_rs_file": get_lib_rs_file_name(tcfg),
+            "abort_on_panic": tcfg.emit_no_std && (!is_library || !binaries.is_empty()),
             "binaries": binaries,
             "dependencies": dependencies,
         });
@@ -162,6 +162,7 @@ fn parse_cast_kind(kind: &str) -> CastKind {
         "BuiltinFnToFnPtr" => CastKind::BuiltinFnToFnPtr,
         "ConstCast" => CastKind::ConstCast,
         "VectorSplat" => CastKind::VectorSplat,
+        "AnyPointerToBlockPointerCast" => CastKind::AnyPointerToBlockPointerCast,
         k => panic!("Unsupported implicit cast: {}", k),
     }
 }
@@ -1087,6 +1088,8 @@ impl ConversionContext {
 
                     let (input_children, output_children) =
                         node.children.split_at(raw_inputs.len());
+                    let (output_children, label_children) =
+                        output_children.split_at(raw_outputs.len());
 
                     let inputs: Vec<AsmOperand> = raw_inputs
                         .into_iter()
@@ -1119,12 +1122,21 @@ impl ConversionContext {
                         .map(|c| from_value(c).expect("clobber string"))
                         .collect();
 
+                    let labels: Vec<CLabelId> = label_children
+                        .iter()
+                        .map(|l| {
+                            let label = l.expect("asm goto target label not found");
+                            CStmtId(self.visit_node_type(label, LABEL_STMT))
+                        })
+                        .collect();
+
                     let stmt = CStmtKind::Asm {
                         is_volatile,
                         asm,
                         inputs,
                         outputs,
                         clobbers,
+                        labels,
                     };
                     self.add_stmt(new_id, located(node, stmt));
                     self.processed_nodes.insert(new_id, OTHER_STMT);
@@ -1770,6 +1782,51 @@ impl ConversionContext {
                     self.expr_possibly_as_stmt(expected_ty, new_id, node, e)
                 }
 
+                ASTEntryTag::TagBlockExpr => {
+                    let (body_id, parameter_ids) = node
+                        .children
+                        .split_last()
+                        .expect("Expected to find a block body");
+
+                    let body = self.visit_stmt(body_id.expect("Expected to find a block body"));
+                    let parameters = parameter_ids
+                        .iter()
+                        .map(|id| {
+                            let param = id.expect("Param field decl not found");
+                            CDeclId(self.visit_node_type(param, VAR_DECL))
+                        })
+                        .collect();
+
+                    let captures = from_value::<Vec<Value>>(node.extras[0].clone())
+                        .expect("Expected to find block captures")
+                        .into_iter()
+                        .map(|capture| {
+                            let capture = from_value::<Vec<Value>>(capture)
+                                .expect("Expected block capture array");
+                            let var = from_value(capture[0].clone())
+                                .expect("Expected to find captured variable");
+                            let by_ref = from_value(capture[1].clone())
+                                .expect("Expected to find capture kind");
+                            CBlockCapture {
+                                var: self.visit_decl(var),
+                                by_ref,
+                            }
+                        })
+                        .collect();
+
+                    let typ = node.type_id.expect("Expected expression to have type");
+                    let typ = self.visit_qualified_type(typ);
+
+                    let e = CExprKind::BlockLiteral {
+                        typ,
+                        parameters,
+                        body,
+                        captures,
+                    };
+
+                    self.expr_possibly_as_stmt(expected_ty, new_id, node, e)
+                }
+
                 // Declarations
                 ASTEntryTag::TagFunctionDecl if expected_ty & OTHER_DECL != 0 => {
                     let name = from_value::<String>(node.extras[0].clone())
@@ -2140,6 +2197,12 @@ impl ConversionContext {
                     self.add_decl(new_id, located(node, static_assert));
                 }
 
+                ASTEntryTag::TagFileScopeAsmDecl if expected_ty & DECL != 0 => {
+                    let asm = from_value(node.extras[0].clone()).expect("assembly string");
+                    let file_scope_asm = CDeclKind::FileScopeAsm { asm };
+                    self.add_decl(new_id, located(node, file_scope_asm));
+                }
+
                 t => panic!("Could not translate node {:?} as type {}", t, expected_ty),
             }
         }
@@ -79,6 +79,7 @@ fn immediate_expr_children(kind: &CExprKind) -> Vec<SomeId> {
         | Predefined(_, e)
         | VAArg(_, e) => intos![e],
         Statements(_, s) => vec![s.into()],
+        BlockLiteral { body, .. } => intos![body],
     }
 }
 
@@ -140,6 +141,17 @@ fn immediate_expr_children_all_types(kind: &CExprKind) -> Vec<SomeId> {
             intos![qty.ctype, e]
         }
         Statements(_, s) => vec![s.into()],
+        BlockLiteral {
+            typ,
+            ref parameters,
+            body,
+            ..
+        } => {
+            let mut res = intos![typ.ctype];
+            res.extend(parameters.iter().map(|&x| -> SomeId { x.into() }));
+            res.push(body.into());
+            res
+        }
     }
 }
 
@@ -194,6 +206,7 @@ fn immediate_decl_children(kind: &CDeclKind) -> Vec<SomeId> {
                 intos![assert_expr]
             }
         }
+        FileScopeAsm { .. } => vec![],
     }
 }
 
@@ -238,6 +238,13 @@ impl TypedAstContext {
         self.include_map[file].first().map(|loc| loc.line)
     }
 
+    /// The file containing the `#include` that first brought `file` into the
+    /// translation unit, and the line of that `#include`.
+    pub fn get_includer(&self, file: FileId) -> Option<(FileId, u64)> {
+        let loc = self.files[file].include_loc.as_ref()?;
+        Some((self.file_map[loc.fileid as usize], loc.line))
+    }
+
     pub fn find_file_id(&self, path: &Path) -> Option<FileId> {
         self.files
             .iter()
@@ -313,6 +320,8 @@ impl TypedAstContext {
     /// `__buitin_va_list` thus naming the type clang uses to represent `va_list`s.
     pub fn is_builtin_va_list(&self, typ: CTypeId) -> bool {
         match self.index(typ).kind {
+            CTypeKind::Elaborated(ty) | CTypeKind::Paren(ty) => self.is_builtin_va_list(ty),
+            CTypeKind::Attributed(qty, _) => self.is_builtin_va_list(qty.ctype),
             CTypeKind::Typedef(decl) => match &self.index(decl).kind {
                 CDeclKind::Typedef {
                     name: nam, typ: ty, ..
@@ -330,6 +339,8 @@ impl TypedAstContext {
     }
 
     /// Predicate for types that are used to implement C's `va_list`.
+    /// This recognizes the struct-based `va_list`s: `struct __va_list_tag` (x86_64 SysV,
+    /// 32-bit PowerPC, SystemZ) and `struct __va_list` (AArch64 and ARM AAPCS).
     /// FIXME: can we get rid of this method and use `is_builtin_va_list` instead?
     pub fn is_va_list_struct(&self, typ: CTypeId) -> bool {
         // detect `va_list`s based on typedef (should work across implementations)
@@ -362,7 +373,15 @@ impl TypedAstContext {
     pub fn is_va_list(&self, typ: CTypeId) -> bool {
         use BuiltinVaListKind::*;
         match self.va_list_kind {
-            CharPtrBuiltinVaList | VoidPtrBuiltinVaList | X86_64ABIBuiltinVaList => {
+            // `va_list` is a plain pointer (i386, Darwin AArch64, PowerPC64, RISC-V, ...)
+            // or an `int[4]` (PNaCl). These can only be told apart from other pointers
+            // by the typedef they were declared with.
+            CharPtrBuiltinVaList | VoidPtrBuiltinVaList | PNaClABIBuiltinVaList => {
+                self.is_builtin_va_list(typ)
+            }
+
+            // `va_list` is a `struct __va_list_tag[1]`, which decays to a pointer when passed
+            X86_64ABIBuiltinVaList | PowerABIBuiltinVaList | SystemZBuiltinVaList => {
                 match self.resolve_type(typ).kind {
                     CTypeKind::Pointer(CQualTypeId { ctype, .. })
                     | CTypeKind::ConstantArray(ctype, _) => self.is_va_list_struct(ctype),
@@ -379,8 +398,6 @@ impl TypedAstContext {
                 // number IHI 0042J") Section 8.1.4 "Additional Types"
                 self.is_va_list_struct(typ)
             }
-
-            kind => unimplemented!("va_list type {:?} not yet implemented", kind),
         }
     }
 
@@ -495,7 +512,8 @@ impl TypedAstContext {
             Predefined(..) |
             Statements(..) | // TODO: more precision
             VAArg(..) |
-            Atomic{..} => false,
+            Atomic{..} |
+            BlockLiteral{..} => false,
 
             Literal(_, _) |
             DeclRef(_, _, _) |
@@ -581,6 +599,7 @@ impl TypedAstContext {
                 } => true,
                 Variable { ref attrs, .. } | Function { ref attrs, .. }
                     if attrs.contains(&Attribute::Used) => true,
+                FileScopeAsm { .. } => true,
                 _ => false,
             };
 
@@ -763,6 +782,18 @@ impl CommentContext {
     }
 
     pub fn get_comments_before(&self, loc: SrcLoc, ctx: &TypedAstContext) -> Vec<String> {
+        self.get_located_comments_before(loc, ctx)
+            .into_iter()
+            .map(|c| c.kind)
+            .collect()
+    }
+
+    /// Like [`Self::get_comments_before`], but keeps the location of each comment.
+    pub fn get_located_comments_before(
+        &self,
+        loc: SrcLoc,
+        ctx: &TypedAstContext,
+    ) -> Vec<Located<String>> {
         let file_id = ctx.file_map[loc.fileid as usize];
         let mut extracted_comments = vec![];
         let mut comments = match self.comments_by_file.get(&file_id) {
@@ -779,7 +810,7 @@ impl CommentContext {
                 break;
             }
 
-            extracted_comments.push(comments.pop().unwrap().kind);
+            extracted_comments.push(comments.pop().unwrap());
         }
         extracted_comments
     }
@@ -981,6 +1012,11 @@ pub enum CDeclKind {
         assert_expr: CExprId,
         message: Option<CExprId>,
     },
+
+    // File-scope (top-level) GNU assembly
+    FileScopeAsm {
+        asm: String,
+    },
 }
 
 impl CDeclKind {
@@ -1115,9 +1151,25 @@ pub enum CExprKind {
         weak: Option<CExprId>,
     },
 
+    // Clang block literal
+    BlockLiteral {
+        typ: CQualTypeId,
+        parameters: Vec<CParamId>,
+        body: CStmtId,
+        captures: Vec<CBlockCapture>,
+    },
+
     BadExpr,
 }
 
+/// A variable captured by a block literal
+#[derive(Debug, Clone, Copy)]
+pub struct CBlockCapture {
+    pub var: CDeclId,
+    /// Whether the variable is declared `__block` and shared with the block
+    pub by_ref: bool,
+}
+
 #[derive(Copy, Debug, Clone)]
 pub enum MemberKind {
     Arrow,
@@ -1164,7 +1216,9 @@ impl CExprKind {
             | CExprKind::ConvertVector(ty, _)
             | CExprKind::DesignatedInitExpr(ty, _, _)
             | CExprKind::ConstantExpr(ty, _, _) => Some(ty),
-            CExprKind::Choose(ty, _, _, _, _) | CExprKind::Atomic { typ: ty, .. } => Some(ty),
+            CExprKind::Choose(ty, _, _, _, _)
+            | CExprKind::Atomic { typ: ty, .. }
+            | CExprKind::BlockLiteral { typ: ty, .. } => Some(ty),
         }
     }
 
@@ -1214,6 +1268,7 @@ pub enum CastKind {
     BuiltinFnToFnPtr,
     ConstCast,
     VectorSplat,
+    AnyPointerToBlockPointerCast,
 }
 
 /// Represents a unary operator in C (6.5.3 Unary operators) and GNU C extensions
@@ -1502,6 +1557,8 @@ pub enum CStmtKind {
         inputs: Vec<AsmOperand>,
         outputs: Vec<AsmOperand>,
         clobbers: Vec<String>,
+        /// Target labels of an `asm goto`, in operand order
+        labels: Vec<CLabelId>,
         is_volatile: bool,
     },
 
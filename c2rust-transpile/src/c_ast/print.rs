@@ -282,6 +282,27 @@ impl<W: Write> Printer<W> {
                 }
 
                 self.writer.write_all(b")")?;
+            }
+
+            &BlockLiteral {
+                ref parameters,
+                body,
+                ..
+            } => {
+                self.writer.write_all(b"^(")?;
+                for (i, parameter) in parameters.iter().enumerate() {
+                    if i > 0 {
+                        self.writer.write_all(b", ")?;
+                    }
+                    match context.c_decls.get(parameter).map(|l| &l.kind) {
+                        Some(CDeclKind::Variable { ident, typ, .. }) => {
+                            self.print_qtype(*typ, Some(ident.as_str()), context)?;
+                        }
+                        _ => panic!("Block argument is not VarDecl"),
+                    }
+                }
+                self.writer.write_all(b") ")?;
+                self.print_stmt(body, false, false, context)?;
             } // _ => unimplemented!("Printer::print_expr"),
         };
         Ok(())
@@ -668,6 +689,10 @@ impl<W: Write> Printer<W> {
 
             StaticAssert { .. } => {
                 self.writer.write_fmt(format_args!("static_assert(...)"))?;
+            }
+
+            FileScopeAsm { asm } => {
+                self.writer.write_fmt(format_args!("__asm__({:?});", asm))?;
             } // _ => unimplemented!("Printer::print_decl"),
         };
 
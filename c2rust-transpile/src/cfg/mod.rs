@@ -563,8 +563,9 @@ impl Cfg<Label, StmtOrDecl> {
             .flat_map(|&stmt_id| DFExpr::new(&translator.ast_context, stmt_id.into()))
             .flat_map(SomeId::stmt)
             .flat_map(|x| match translator.ast_context[x].kind {
-                CStmtKind::Goto(target) => Some((target, x)),
-                _ => None,
+                CStmtKind::Goto(target) => vec![(target, x)],
+                CStmtKind::Asm { ref labels, .. } => labels.iter().map(|&l| (l, x)).collect(),
+                _ => vec![],
             })
         {
             c_label_to_goto
@@ -1946,17 +1947,57 @@ impl CfgBuilder {
                 ref inputs,
                 ref outputs,
                 ref clobbers,
+                ref labels,
             } => {
-                wip.extend(translator.convert_asm(
-                    ctx,
-                    Span::call_site(),
-                    is_volatile,
-                    asm,
-                    inputs,
-                    outputs,
-                    clobbers,
-                )?);
-                Ok(Some(wip))
+                let (stmts, goto_index) = translator
+                    .convert_asm(
+                        ctx,
+                        Span::call_site(),
+                        is_volatile,
+                        asm,
+                        inputs,
+                        outputs,
+                        clobbers,
+                        labels.len(),
+                    )?
+                    .discard_unsafe();
+                wip.extend(stmts);
+
+                match goto_index {
+                    None => Ok(Some(wip)),
+                    Some(goto_index) => {
+                        // `asm goto`: branch on the index of the label the assembly took,
+                        // where 0 means it fell through
+                        let next_label = self.fresh_label();
+                        let mut cases = vec![];
+                        for (idx, &label_id) in labels.iter().enumerate() {
+                            let label_name = translator
+                                .ast_context
+                                .label_names
+                                .get(&label_id)
+                                .cloned()
+                                .expect("missing label name for an asm goto label");
+                            let pat =
+                                mk().lit_pat(mk().lit_expr(mk().int_lit(idx as u128 + 1, "")));
+                            cases.push((pat, Label::FromC(label_id, Some(label_name))));
+                            self.last_per_stmt_mut()
+                                .c_labels_used
+                                .entry(label_id)
+                                .or_insert(IndexSet::new())
+                                .insert(stmt_id);
+                        }
+                        cases.push((mk().wild_pat(), next_label.clone()));
+                        self.add_wip_block(
+                            wip,
+                            Switch {
+                                expr: goto_index,
+                                cases,
+                            },
+                        );
+
+                        Ok(Some(self.new_wip_block(next_label)))
+                    }
+                }
             }
         };
         let out_wip: Option<WipBlock> = out_wip?; // This statement exists to help type inference...
@@ -22,11 +22,11 @@ pub struct CompileCmd {
     /// to rerun the exact compilation step for the translation unit in the environment
     /// the build system uses. Parameters use shell quoting and shell escaping of quotes,
     /// with ‘"’ and ‘\’ being the only special characters. Shell expansion is not supported.
-    #[serde(skip_deserializing)]
-    _command: Option<String>,
+    #[serde(default)]
+    command: Option<String>,
     /// The compile command executed as list of strings. Either arguments or command is required.
-    #[serde(default, skip_deserializing)]
-    _arguments: Vec<String>,
+    #[serde(default)]
+    arguments: Vec<String>,
     /// The name of the output created by this compilation step. This field is optional. It can
     /// be used to distinguish different processing modes of the same input file.
     output: Option<String>,
@@ -43,6 +43,52 @@ impl CompileCmd {
             }
         }
     }
+
+    /// The flags of this compilation that decide what headers see: include
+    /// paths, macro definitions and the language standard. Each flag is a
+    /// single argument, with relative paths resolved against the working
+    /// directory of the compilation.
+    pub fn preprocessor_flags(&self) -> Vec<String> {
+        const PATH_FLAGS: &[&str] = &["-I", "-isystem", "-iquote", "-idirafter", "-include"];
+        const NAME_FLAGS: &[&str] = &["-D", "-U"];
+
+        let arguments = if !self.arguments.is_empty() {
+            self.arguments.clone()
+        } else {
+            self.command
+                .as_deref()
+                .and_then(shlex::split)
+                .unwrap_or_default()
+        };
+
+        let mut flags = vec![];
+        // Skip the compiler itself
+        let mut arguments = arguments.iter().skip(1);
+        while let Some(arg) = arguments.next() {
+            if arg.starts_with("-std=") {
+                flags.push(arg.clone());
+                continue;
+            }
+            // Flags may be joined with their value or separate from it
+            let joined = |flag: &str| arg.starts_with(flag) && arg.len() > flag.len();
+            if let Some(flag) = PATH_FLAGS.iter().find(|flag| joined(flag)) {
+                let path = self.directory.join(&arg[flag.len()..]);
+                flags.push(format!("{}{}", flag, path.display()));
+            } else if let Some(flag) = PATH_FLAGS.iter().find(|flag| *flag == arg) {
+                if let Some(path) = arguments.next() {
+                    let path = self.directory.join(path);
+                    flags.push(format!("{}{}", flag, path.display()));
+                }
+            } else if NAME_FLAGS.iter().any(|flag| joined(flag)) {
+                flags.push(arg.clone());
+            } else if NAME_FLAGS.contains(&arg.as_str()) {
+                if let Some(name) = arguments.next() {
+                    flags.push(format!("{}{}", arg, name));
+                }
+            }
+        }
+        flags
+    }
 }
 
 #[derive(Deserialize, Debug, PartialEq, Eq)]
@@ -14,8 +14,24 @@ enum FieldKey {
     Padding(usize),
 }
 
+/// How C's `long double` is represented in Rust.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum LongDoubleRepr {
+    /// Plain `f64`. Not layout compatible with C wherever `long double` is
+    /// wider than `double`, and loses precision there.
+    F64,
+    /// `c2rust_x87::f80`, a software x87 80-bit float with the same layout as
+    /// `long double` on x86 and x86_64.
+    X87,
+    /// `f128::f128`, from the `f128` crate, which wraps the native `__float128`.
+    #[default]
+    F128,
+}
+
 pub struct TypeConverter {
     pub translate_valist: bool,
+    naming_policy: NamingPolicy,
+    pub long_double: LongDoubleRepr,
     renamer: Renamer<CDeclId>,
     fields: HashMap<CDeclId, Renamer<FieldKey>>,
     suffix_names: HashMap<(CDeclId, &'static str), String>,
@@ -142,6 +158,8 @@ impl TypeConverter {
     pub fn new() -> TypeConverter {
         TypeConverter {
             translate_valist: false,
+            naming_policy: NamingPolicy::Preserve,
+            long_double: LongDoubleRepr::F128,
             renamer: Renamer::new(&RESERVED_NAMES),
             fields: HashMap::new(),
             suffix_names: HashMap::new(),
@@ -153,6 +171,15 @@ impl TypeConverter {
         &self.features
     }
 
+    pub fn set_naming_policy(&mut self, naming_policy: NamingPolicy) {
+        self.naming_policy = naming_policy;
+        if naming_policy == NamingPolicy::Idiomatic {
+            self.renamer.use_camel_case_suffixes();
+        }
+    }
+
+    /// Name a struct, union, enum or typedef `name`, which the caller has
+    /// already adjusted to the naming policy (see [`NamingPolicy::type_name`]).
     pub fn declare_decl_name(&mut self, decl_id: CDeclId, name: &str) -> String {
         self.renamer
             .insert(decl_id, name)
@@ -187,15 +214,15 @@ impl TypeConverter {
         name: &str,
     ) -> String {
         let name = if name.is_empty() {
-            "c2rust_unnamed"
+            "c2rust_unnamed".into()
         } else {
-            name
+            self.naming_policy.value_name(name)
         };
 
         self.fields
             .entry(record_id)
             .or_insert_with(|| Renamer::new(&RESERVED_NAMES))
-            .insert(FieldKey::Field(field_id), name)
+            .insert(FieldKey::Field(field_id), &name)
             .expect("Field already declared")
     }
 
@@ -297,6 +324,39 @@ impl TypeConverter {
         }
     }
 
+    /// Block pointers are translated to pointers to the `c2rust_blocks::Block` header that every
+    /// block starts with, which is generic over the type of the function implementing the block.
+    pub fn convert_block_pointer(
+        &mut self,
+        ctxt: &TypedAstContext,
+        ctype: CTypeId,
+    ) -> TranslationResult<Box<Type>> {
+        let invoke_ty = self.convert_block_invoke(ctxt, ctype)?;
+        let header_ty = mk().path_ty(vec![
+            mk().path_segment("c2rust_blocks"),
+            mk().path_segment_with_args("Block", mk().angle_bracketed_args(vec![invoke_ty])),
+        ]);
+        Ok(mk().mutbl().ptr_ty(header_ty))
+    }
+
+    /// Convert the function type of a block to the type of the function implementing it, which
+    /// takes a pointer to the block as its first argument.
+    pub fn convert_block_invoke(
+        &mut self,
+        ctxt: &TypedAstContext,
+        ctype: CTypeId,
+    ) -> TranslationResult<Box<Type>> {
+        let mut fn_ty = self.convert(ctxt, ctype)?;
+        match *fn_ty {
+            Type::BareFn(TypeBareFn { ref mut inputs, .. }) => {
+                let block_ty = mk().mutbl().ptr_ty(mk().path_ty(vec!["libc", "c_void"]));
+                inputs.insert(0, mk().bare_arg(block_ty, None::<Box<Ident>>));
+                Ok(fn_ty)
+            }
+            _ => Err(format_err!("Block of non-function type {:?}", ctxt.index(ctype).kind).into()),
+        }
+    }
+
     /// Convert a `C` type to a `Rust` one. For the moment, these are expected to have compatible
     /// memory layouts.
     pub fn convert(
@@ -325,13 +385,18 @@ impl TypeConverter {
             CTypeKind::UChar => Ok(mk().path_ty(mk().path(vec!["libc", "c_uchar"]))),
             CTypeKind::Char => Ok(mk().path_ty(mk().path(vec!["libc", "c_char"]))),
             CTypeKind::Double => Ok(mk().path_ty(mk().path(vec!["libc", "c_double"]))),
-            CTypeKind::LongDouble => Ok(mk().path_ty(mk().path(vec!["f128", "f128"]))),
+            CTypeKind::LongDouble => Ok(mk().path_ty(mk().path(match self.long_double {
+                LongDoubleRepr::F64 => vec!["f64"],
+                LongDoubleRepr::X87 => vec!["c2rust_x87", "f80"],
+                LongDoubleRepr::F128 => vec!["f128", "f128"],
+            }))),
             CTypeKind::Float => Ok(mk().path_ty(mk().path(vec!["libc", "c_float"]))),
             CTypeKind::Int128 => Ok(mk().path_ty(mk().path(vec!["i128"]))),
             CTypeKind::UInt128 => Ok(mk().path_ty(mk().path(vec!["u128"]))),
             CTypeKind::BFloat16 => Ok(mk().path_ty(mk().path(vec!["bf16"]))),
 
             CTypeKind::Pointer(qtype) => self.convert_pointer(ctxt, qtype),
+            CTypeKind::BlockPointer(qtype) => self.convert_block_pointer(ctxt, qtype.ctype),
 
             CTypeKind::Elaborated(ref ctype) => self.convert(ctxt, *ctype),
             CTypeKind::Decayed(ref ctype) => self.convert(ctxt, *ctype),
@@ -2,9 +2,9 @@ use colored::Colorize;
 use failure::{err_msg, Backtrace, Context, Error, Fail};
 use fern::colors::ColoredLevelConfig;
 use log::{Level, SetLoggerError};
+use std::cell::RefCell;
 use std::collections::HashSet;
 use std::fmt::{self, Display};
-use std::io;
 use std::str::FromStr;
 use std::sync::Arc;
 use strum_macros::{Display, EnumString};
@@ -12,7 +12,7 @@ use strum_macros::{Display, EnumString};
 use crate::c_ast::{ClangAstParseErrorKind, DisplaySrcSpan};
 use c2rust_ast_exporter::get_clang_major_version;
 
-const DEFAULT_WARNINGS: &[Diagnostic] = &[Diagnostic::ClangAst];
+const DEFAULT_WARNINGS: &[Diagnostic] = &[Diagnostic::ClangAst, Diagnostic::StaticInitializers];
 
 #[derive(PartialEq, Eq, Hash, Debug, Display, EnumString, Clone)]
 #[strum(serialize_all = "kebab-case")]
@@ -20,6 +20,7 @@ pub enum Diagnostic {
     All,
     Comments,
     ClangAst,
+    StaticInitializers,
 }
 
 macro_rules! diag {
@@ -28,6 +29,62 @@ macro_rules! diag {
 
 pub(crate) use diag;
 
+/// A line of output buffered by [`capture`].
+pub enum CapturedLine {
+    Stdout(String),
+    Stderr(String),
+}
+
+thread_local! {
+    /// Output emitted on this thread while inside [`capture`].
+    static CAPTURED: RefCell<Option<Vec<CapturedLine>>> = RefCell::new(None);
+}
+
+/// Run `f`, buffering everything it logs or [`print`]s on the current thread
+/// instead of writing it out. The buffered lines can later be emitted with
+/// [`replay`], which lets parallel translation report its output in a
+/// deterministic order.
+pub fn capture<R>(f: impl FnOnce() -> R) -> (R, Vec<CapturedLine>) {
+    let outer = CAPTURED.with(|captured| captured.replace(Some(Vec::new())));
+    let result = f();
+    let lines = CAPTURED.with(|captured| captured.replace(outer));
+    (result, lines.unwrap_or_default())
+}
+
+/// Write lines previously buffered by [`capture`] to stdout and stderr.
+pub fn replay(lines: Vec<CapturedLine>) {
+    for line in lines {
+        match line {
+            CapturedLine::Stdout(line) => println!("{}", line),
+            CapturedLine::Stderr(line) => eprintln!("{}", line),
+        }
+    }
+}
+
+fn emit(line: CapturedLine) {
+    let line = CAPTURED.with(|captured| match captured.borrow_mut().as_mut() {
+        Some(lines) => {
+            lines.push(line);
+            None
+        }
+        None => Some(line),
+    });
+    if let Some(line) = line {
+        replay(vec![line]);
+    }
+}
+
+/// Print `line` to stdout, unless we're inside [`capture`]. Output of the
+/// translation of a single file should go through here rather than `println!`.
+pub fn print(line: String) {
+    emit(CapturedLine::Stdout(line))
+}
+
+/// Like [`print`], but for stderr.
+pub fn eprint(line: String) {
+    emit(CapturedLine::Stderr(line))
+}
+
 pub fn init(mut enabled_warnings: HashSet<Diagnostic>, log_level: log::LevelFilter) {
     enabled_warnings.extend(DEFAULT_WARNINGS.iter().cloned());
 
@@ -62,7 +119,9 @@ pub fn init(mut enabled_warnings: HashSet<Diagnostic>, log_level: log::LevelFilt
                 .map(|d| enabled_warnings.contains(&d))
                 .unwrap_or(true)
         })
-        .chain(io::stderr())
+        .chain(fern::Output::call(|record| {
+            eprint(record.args().to_string())
+        }))
         .into_log();
     // Ignore the [`SetLoggerError`] b/c we just want to make sure it's set at least once.
     let _: Result<(), SetLoggerError> = log_reroute::init();
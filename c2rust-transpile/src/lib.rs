@@ -7,21 +7,26 @@ pub mod c_ast;
 pub mod cfg;
 mod compile_cmds;
 pub mod convert_type;
+mod libc_mapping;
 pub mod renamer;
 pub mod rust_ast;
 pub mod translator;
 pub mod with_stmts;
 
 use std::collections::HashSet;
+use std::env;
 use std::fs::{self, File};
 use std::io;
 use std::io::prelude::*;
+use std::panic::{self, AssertUnwindSafe};
 use std::path::{Path, PathBuf};
 use std::process;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
 
-use failure::Error;
+use failure::{format_err, Error};
 use itertools::Itertools;
-use log::{info, warn};
+use log::{error, info, warn};
 use regex::Regex;
 use serde_derive::Serialize;
 
@@ -32,14 +37,29 @@ use c2rust_ast_exporter as ast_exporter;
 
 use crate::build_files::{emit_build_files, get_build_dir, CrateConfig};
 use crate::compile_cmds::get_compile_commands;
+pub use crate::convert_type::LongDoubleRepr;
 use crate::convert_type::RESERVED_NAMES;
+pub use crate::libc_mapping::LibcMapping;
+pub use crate::renamer::NamingPolicy;
+use crate::renamer::RenamedDecl;
 pub use crate::translator::ReplaceMode;
+use crate::translator::{CHeader, SharedItems, SHARED_MODULE};
 use std::prelude::v1::Vec;
 
 type PragmaVec = Vec<(&'static str, Vec<&'static str>)>;
 type PragmaSet = indexmap::IndexSet<(&'static str, &'static str)>;
 type CrateSet = indexmap::IndexSet<ExternCrate>;
-type TranspileResult = Result<(PathBuf, PragmaVec, CrateSet), ()>;
+type TranspileResult = Result<
+    (
+        PathBuf,
+        PragmaVec,
+        CrateSet,
+        Vec<RenamedDecl>,
+        CHeader,
+        SharedItems,
+    ),
+    (),
+>;
 
 /// Configuration settings for the translation process
 #[derive(Debug)]
@@ -61,6 +81,13 @@ pub struct TranspilerConfig {
     pub filter: Option<Regex>,
     pub debug_relooper_labels: bool,
     pub prefix_function_names: Option<String>,
+    /// How C identifiers are mapped to Rust identifiers
+    pub naming_policy: NamingPolicy,
+    /// How `long double` is represented
+    pub long_double: LongDoubleRepr,
+    /// Replace calls to the libc functions in this table with safe Rust
+    /// where their arguments allow it
+    pub libc_mapping: Option<LibcMapping>,
     pub translate_asm: bool,
     pub use_c_loop_info: bool,
     pub use_c_multiple_info: bool,
@@ -81,6 +108,8 @@ pub struct TranspilerConfig {
     pub disable_refactoring: bool,
     pub preserve_unused_functions: bool,
     pub log_level: log::LevelFilter,
+    /// Number of translation units to transpile concurrently; 0 uses all available cores
+    pub jobs: usize,
 
     // Options that control build files
     /// Emit `Cargo.toml` and `lib.rs`
@@ -88,6 +117,13 @@ pub struct TranspilerConfig {
     /// Names of translation units containing main functions that we should make
     /// into binaries
     pub binaries: Vec<String>,
+    /// Emit `NAME.h` declaring the functions and types the crate exports
+    pub emit_c_header: bool,
+    /// Original C headers to check the emitted C header against
+    pub check_c_headers: Vec<PathBuf>,
+    /// Emit `static inline` header functions that several translation units
+    /// translate the same way only once, in a module they share
+    pub share_header_items: bool,
 }
 
 impl TranspilerConfig {
@@ -135,6 +171,8 @@ impl TranspilerConfig {
 pub enum ExternCrate {
     C2RustBitfields,
     C2RustAsmCasts,
+    C2RustX87,
+    C2RustBlocks,
     F128,
     NumTraits,
     Memoffset,
@@ -165,6 +203,8 @@ impl From<ExternCrate> for ExternCrateDetails {
         match extern_crate {
             ExternCrate::C2RustBitfields => Self::new("c2rust-bitfields", "0.3", true),
             ExternCrate::C2RustAsmCasts => Self::new("c2rust-asm-casts", "0.2", true),
+            ExternCrate::C2RustX87 => Self::new("c2rust-x87", "0.17", false),
+            ExternCrate::C2RustBlocks => Self::new("c2rust-blocks", "0.17", false),
             ExternCrate::F128 => Self::new("f128", "0.2", false),
             ExternCrate::NumTraits => Self::new("num-traits", "0.2", true),
             ExternCrate::Memoffset => Self::new("memoffset", "0.5", true),
@@ -227,7 +267,15 @@ fn get_module_name(
 
 /// Main entry point to transpiler. Called from CLI tools with the result of
 /// clap::App::get_matches().
-pub fn transpile(tcfg: TranspilerConfig, cc_db: &Path, extra_clang_args: &[&str]) {
+///
+/// Fails if an emitted C header doesn't match the original headers it is
+/// checked against (see [`TranspilerConfig::check_c_headers`]), after
+/// translating everything else.
+pub fn transpile(
+    tcfg: TranspilerConfig,
+    cc_db: &Path,
+    extra_clang_args: &[&str],
+) -> Result<(), Error> {
     diagnostics::init(tcfg.enabled_warnings.clone(), tcfg.log_level);
 
     let lcmds = get_compile_commands(cc_db, &tcfg.filter).unwrap_or_else(|_| {
@@ -246,6 +294,17 @@ pub fn transpile(tcfg: TranspilerConfig, cc_db: &Path, extra_clang_args: &[&str]
     let mut workspace_members = vec![];
     let mut num_transpiled_files = 0;
     let mut transpiled_modules = Vec::new();
+    let mut c_header_mismatches = 0;
+    let c_header_result = |mismatches| match mismatches {
+        0 => Ok(()),
+        1 => Err(format_err!(
+            "an emitted C header does not match the original headers"
+        )),
+        n => Err(format_err!(
+            "{} emitted C headers do not match the original headers",
+            n
+        )),
+    };
     let build_dir = get_build_dir(&tcfg, cc_db);
     for lcmd in &lcmds {
         let cmds = &lcmd.cmd_inputs;
@@ -289,28 +348,30 @@ pub fn transpile(tcfg: TranspilerConfig, cc_db: &Path, extra_clang_args: &[&str]
             }
         }
 
-        let results = cmds
-            .iter()
-            .map(|cmd| {
-                transpile_single(
-                    &tcfg,
-                    cmd.abs_file(),
-                    &ancestor_path,
-                    &build_dir,
-                    cc_db,
-                    &clang_args,
-                )
-            })
-            .collect::<Vec<TranspileResult>>();
+        let input_paths = cmds.iter().map(|cmd| cmd.abs_file()).collect::<Vec<_>>();
+        let results = transpile_many(
+            &tcfg,
+            &input_paths,
+            &ancestor_path,
+            &build_dir,
+            cc_db,
+            &clang_args,
+        );
         let mut modules = vec![];
         let mut modules_skipped = false;
         let mut pragmas = PragmaSet::new();
         let mut crates = CrateSet::new();
+        let mut renamed = indexmap::IndexSet::new();
+        let mut c_header = CHeader::default();
+        let mut shared_items = vec![];
         for res in results {
             match res {
-                Ok((module, pragma_vec, crate_set)) => {
+                Ok((module, pragma_vec, crate_set, renamed_decls, header, shared)) => {
                     modules.push(module);
+                    shared_items.push(shared);
                     crates.extend(crate_set);
+                    renamed.extend(renamed_decls);
+                    c_header.merge(header);
 
                     num_transpiled_files += 1;
                     for (key, vals) in pragma_vec {
@@ -327,13 +388,54 @@ pub fn transpile(tcfg: TranspilerConfig, cc_db: &Path, extra_clang_args: &[&str]
         pragmas.sort();
         crates.sort();
 
+        // If we skipped a file, we don't know which definitions it would share
+        emit_shared_items(
+            &tcfg,
+            &build_dir,
+            &mut modules,
+            &shared_items,
+            !modules_skipped,
+        )
+        .unwrap_or_else(|e| panic!("Unable to write shared definitions: {}", e));
+
+        if tcfg.naming_policy != NamingPolicy::Preserve {
+            let renamed = renamed.into_iter().collect::<Vec<_>>();
+            write_name_mapping(&build_dir, &renamed)
+                .unwrap_or_else(|e| warn!("Writing name mapping failed: {}", e));
+        }
+
+        if tcfg.emit_c_header {
+            if modules_skipped {
+                warn!("Can't emit C header after incremental transpiler run; skipped.");
+            } else {
+                // The original headers only make sense with the flags their
+                // translation units were compiled with
+                let tu_flags = cmds
+                    .iter()
+                    .flat_map(|cmd| cmd.preprocessor_flags())
+                    .unique()
+                    .collect::<Vec<_>>();
+                if let Err(e) = emit_c_header(
+                    &tcfg,
+                    &build_dir,
+                    &lcmd_name,
+                    &c_header,
+                    &tu_flags,
+                    &clang_args,
+                ) {
+                    error!("{}", e);
+                    c_header_mismatches += 1;
+                }
+            }
+        }
+
         transpiled_modules.extend(modules.iter().cloned());
 
         if tcfg.emit_build_files {
             if modules_skipped {
                 // If we skipped a file, we may not have collected all required pragmas
                 warn!("Can't emit build files after incremental transpiler run; skipped.");
-                return;
+                return c_header_result(c_header_mismatches);
             }
 
             let ccfg = CrateConfig {
@@ -356,7 +458,7 @@ pub fn transpile(tcfg: TranspilerConfig, cc_db: &Path, extra_clang_args: &[&str]
 
     if num_transpiled_files == 0 {
         warn!("No C files found in compile_commands.json; nothing to do.");
-        return;
+        return Ok(());
     }
 
     if tcfg.emit_build_files {
@@ -367,6 +469,7 @@ pub fn transpile(tcfg: TranspilerConfig, cc_db: &Path, extra_clang_args: &[&str]
     }
 
     tcfg.check_if_all_binaries_used(&transpiled_modules);
+    c_header_result(c_header_mismatches)
 }
 
 /// Ensure that clang can locate the system headers on macOS 10.14+.
@@ -401,6 +504,103 @@ fn get_extra_args_macos() -> Vec<String> {
     args
 }
 
+/// Write the definitions the translation units in `modules` share to a new
+/// module in `build_dir`, which is added to `modules`, and append to each of
+/// `modules` its imports of those and its own copies of the rest; see
+/// [`SharedItems::share`]. Nothing is shared unless `share` is set.
+fn emit_shared_items(
+    tcfg: &TranspilerConfig,
+    build_dir: &Path,
+    modules: &mut Vec<PathBuf>,
+    units: &[SharedItems],
+    share: bool,
+) -> Result<(), Error> {
+    // `lib.rs` declares the modules by their paths relative to `build_dir`
+    let mut shared_path = build_dir.to_path_buf();
+    let mut module_path = vec!["crate".to_owned()];
+    if tcfg.output_dir.is_some() {
+        shared_path.push("src");
+        module_path.push("src".to_owned());
+    }
+    shared_path.push(format!("{}.rs", SHARED_MODULE));
+    module_path.push(SHARED_MODULE.to_owned());
+
+    let (shared, appendices) = SharedItems::share(units, share.then(|| &module_path[..]));
+    for (module, appendix) in modules.iter().zip(appendices) {
+        if !appendix.is_empty() {
+            let mut file = fs::OpenOptions::new().append(true).open(module)?;
+            write!(file, "\n{}", appendix)?;
+        }
+    }
+    if !shared.is_empty() {
+        fs::write(&shared_path, shared)?;
+        modules.push(shared_path);
+    }
+    Ok(())
+}
+
+/// Write the C-to-Rust name mapping produced by a non-preserving
+/// [`NamingPolicy`] to `c2rust-names.json` in `build_dir`.
+fn write_name_mapping(build_dir: &Path, renamed: &[RenamedDecl]) -> Result<(), Error> {
+    fs::create_dir_all(build_dir)?;
+    let file = File::create(build_dir.join("c2rust-names.json"))?;
+    serde_json::to_writer_pretty(io::BufWriter::new(file), renamed)?;
+    Ok(())
+}
+
+/// Write the C header of the crate `name` to `build_dir/NAME.h` and, if there
+/// are original headers to check it against, compile it together with them in
+/// check mode (see [`CHeader::render_check`]) using `$CC` or `clang` with the
+/// preprocessor flags of the translated files, `tu_flags`.
+fn emit_c_header(
+    tcfg: &TranspilerConfig,
+    build_dir: &Path,
+    name: &str,
+    header: &CHeader,
+    tu_flags: &[String],
+    clang_args: &[&str],
+) -> Result<(), Error> {
+    if header.is_empty() {
+        info!("{} exports nothing; not emitting a C header", name);
+        return Ok(());
+    }
+    fs::create_dir_all(build_dir)?;
+    let header_name = format!("{}.h", name);
+    let guard = name
+        .chars()
+        .map(|c| match c {
+            'a'..='z' | 'A'..='Z' | '0'..='9' => c.to_ascii_uppercase(),
+            _ => '_',
+        })
+        .collect::<String>()
+        + "_H";
+    fs::write(build_dir.join(&header_name), header.render(&guard))?;
+
+    if tcfg.check_c_headers.is_empty() {
+        return Ok(());
+    }
+    let check_path = build_dir.join(format!("{}_check.c", name));
+    fs::write(
+        &check_path,
+        header.render_check(&header_name, &tcfg.check_c_headers),
+    )?;
+    let cc = env::var_os("CC").unwrap_or_else(|| "clang".into());
+    let output = process::Command::new(&cc)
+        .args(&["-std=gnu11", "-fsyntax-only"])
+        .args(tu_flags)
+        .args(clang_args)
+        .arg(&check_path)
+        .output()?;
+    if !output.status.success() {
+        return Err(format_err!(
+            "{} does not match the original headers:\n{}",
+            build_dir.join(&header_name).display(),
+            String::from_utf8_lossy(&output.stderr)
+        ));
+    }
+    Ok(())
+}
+
 fn invoke_refactor(_build_dir: &Path) -> Result<(), Error> {
     Ok(())
 }
@@ -427,6 +627,111 @@ fn reorganize_definitions(
     Ok(())
 }
 
+/// Transpile each of `input_paths`, running up to [`TranspilerConfig::jobs`]
+/// translations at once.
+///
+/// Every translation unit gets its own AST context and `Translation`, so the
+/// only coordination needed is around the AST exporter (see
+/// `c2rust_ast_exporter`) and the output. Whatever a worker logs or prints
+/// through [`diagnostics::print`] is buffered and replayed, and results are
+/// returned, in the order of `input_paths`, so the output does not depend on
+/// scheduling. If a translation panics, the buffered output of every file is
+/// still replayed before the panic is resumed.
+fn transpile_many(
+    tcfg: &TranspilerConfig,
+    input_paths: &[PathBuf],
+    ancestor_path: &Path,
+    build_dir: &Path,
+    cc_db: &Path,
+    extra_clang_args: &[&str],
+) -> Vec<TranspileResult> {
+    // Header definitions can only be shared through the crate `lib.rs` declares
+    let share_items = tcfg.share_header_items
+        && tcfg.emit_build_files
+        && !tcfg.reorganize_definitions
+        && input_paths.len() > 1;
+    let transpile_one = |input_path: &PathBuf| {
+        transpile_single(
+            tcfg,
+            input_path.clone(),
+            ancestor_path,
+            build_dir,
+            cc_db,
+            extra_clang_args,
+            share_items,
+        )
+    };
+
+    let jobs = match tcfg.jobs {
+        0 => thread::available_parallelism().map_or(1, usize::from),
+        jobs => jobs,
+    };
+    let jobs = jobs.min(input_paths.len());
+    if jobs <= 1 {
+        return input_paths.iter().map(transpile_one).collect();
+    }
+
+    let next_input = AtomicUsize::new(0);
+    let mut slots = Vec::new();
+    slots.resize_with(input_paths.len(), || None);
+    thread::scope(|s| {
+        let workers = (0..jobs)
+            .map(|_| {
+                s.spawn(|| {
+                    let mut finished = vec![];
+                    loop {
+                        let i = next_input.fetch_add(1, Ordering::Relaxed);
+                        let input_path = match input_paths.get(i) {
+                            Some(input_path) => input_path,
+                            None => break finished,
+                        };
+                        // Catch panics inside `capture`, so the output leading up to one is kept
+                        let (result, log) = diagnostics::capture(|| {
+                            panic::catch_unwind(AssertUnwindSafe(|| transpile_one(input_path)))
+                        });
+                        let panicked = result.is_err();
+                        finished.push((i, (result, log)));
+                        if panicked {
+                            break finished;
+                        }
+                    }
+                })
+            })
+            .collect::<Vec<_>>();
+        for worker in workers {
+            let finished = worker
+                .join()
+                .unwrap_or_else(|panic| panic::resume_unwind(panic));
+            for (i, result) in finished {
+                slots[i] = Some(result);
+            }
+        }
+    });
+
+    // Replay the output of every file that was translated, even if one of the
+    // workers panicked, and only then re-raise the first panic.
+    let mut results = Vec::with_capacity(slots.len());
+    let mut first_panic = None;
+    for (result, log) in slots.into_iter().flatten() {
+        diagnostics::replay(log);
+        match result {
+            Ok(result) => results.push(result),
+            Err(panic) => {
+                first_panic.get_or_insert(panic);
+            }
+        }
+    }
+    if let Some(panic) = first_panic {
+        panic::resume_unwind(panic);
+    }
+    assert_eq!(
+        results.len(),
+        input_paths.len(),
+        "every input is claimed by a worker"
+    );
+    results
+}
+
 fn transpile_single(
     tcfg: &TranspilerConfig,
     input_path: PathBuf,
@@ -434,6 +739,7 @@ fn transpile_single(
     build_dir: &Path,
     cc_db: &Path,
     extra_clang_args: &[&str],
+    share_items: bool,
 ) -> TranspileResult {
     let output_path = get_output_path(tcfg, input_path.clone(), ancestor_path, build_dir);
     if output_path.exists() && !tcfg.overwrite_existing {
@@ -451,7 +757,10 @@ fn transpile_single(
     }
 
     if tcfg.verbose {
-        println!("Additional Clang arguments: {}", extra_clang_args.join(" "));
+        diagnostics::print(format!(
+            "Additional Clang arguments: {}",
+            extra_clang_args.join(" ")
+        ));
     }
 
     // Extract the untyped AST from the CBOR file
@@ -472,11 +781,11 @@ fn transpile_single(
         Ok(cxt) => cxt,
     };
 
-    println!("Transpiling {}", file);
+    diagnostics::print(format!("Transpiling {}", file));
 
     if tcfg.dump_untyped_context {
-        println!("CBOR Clang AST");
-        println!("{:#?}", untyped_context);
+        diagnostics::print("CBOR Clang AST".to_owned());
+        diagnostics::print(format!("{:#?}", untyped_context));
     }
 
     // Convert this into a typed AST
@@ -489,18 +798,22 @@ fn transpile_single(
     };
 
     if tcfg.dump_typed_context {
-        println!("Clang AST");
-        println!("{:#?}", typed_context);
+        diagnostics::print("Clang AST".to_owned());
+        diagnostics::print(format!("{:#?}", typed_context));
     }
 
     if tcfg.pretty_typed_context {
-        println!("Pretty-printed Clang AST");
-        println!("{:#?}", Printer::new(io::stdout()).print(&typed_context));
+        diagnostics::print("Pretty-printed Clang AST".to_owned());
+        let mut pretty = vec![];
+        Printer::new(&mut pretty)
+            .print(&typed_context)
+            .expect("writing to a Vec can't fail");
+        diagnostics::print(String::from_utf8_lossy(&pretty).into_owned());
     }
 
     // Perform the translation
-    let (translated_string, pragmas, crates) =
-        translator::translate(typed_context, tcfg, input_path);
+    let (translated_string, pragmas, crates, renamed, c_header, shared_items) =
+        translator::translate(typed_context, tcfg, input_path, share_items);
 
     let mut file = match File::create(&output_path) {
         Ok(file) => file,
@@ -520,7 +833,14 @@ fn transpile_single(
         ),
     };
 
-    Ok((output_path, pragmas, crates))
+    Ok((
+        output_path,
+        pragmas,
+        crates,
+        renamed,
+        c_header,
+        shared_items,
+    ))
 }
 
 fn get_output_path(
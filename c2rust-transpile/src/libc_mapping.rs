@@ -0,0 +1,180 @@
+//! The table of libc functions that the translator replaces with safe Rust
+//! when their arguments are known arrays or string literals.
+//!
+//! The built-in table lives in `libc_mapping.toml`; users can add entries or
+//! override existing ones with a TOML file in the same format.
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+use failure::{format_err, Error};
+use toml_edit::{Document, Item};
+
+const BUILTIN_MAPPING: &str = include_str!("libc_mapping.toml");
+
+/// A rewrite the translator knows how to perform, with the index of the
+/// argument playing each role.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LibcRewrite {
+    /// `memcpy(dst, src, len)` to `dst[..n].copy_from_slice(&src[..n])`.
+    /// `overlapping` copies are only rewritten between distinct variables.
+    Copy {
+        dst: usize,
+        src: usize,
+        len: usize,
+        overlapping: bool,
+    },
+    /// `memset(dst, value, len)` to `dst[..n].fill(value)`.
+    Fill {
+        dst: usize,
+        value: usize,
+        len: usize,
+    },
+    /// `strlen(s)` to `CStr::to_bytes().len()`.
+    Strlen { s: usize },
+    /// `strcmp(lhs, rhs)` to a comparison of `CStr`s.
+    Strcmp { lhs: usize, rhs: usize },
+}
+
+impl LibcRewrite {
+    /// The number of arguments a call needs for this rewrite to apply.
+    pub fn min_args(&self) -> usize {
+        let max = match *self {
+            LibcRewrite::Copy { dst, src, len, .. } => dst.max(src).max(len),
+            LibcRewrite::Fill { dst, value, len } => dst.max(value).max(len),
+            LibcRewrite::Strlen { s } => s,
+            LibcRewrite::Strcmp { lhs, rhs } => lhs.max(rhs),
+        };
+        max + 1
+    }
+
+    fn from_toml(name: &str, entry: &Item) -> Result<Self, Error> {
+        let arg = |role: &str| -> Result<usize, Error> {
+            entry
+                .get(role)
+                .and_then(Item::as_integer)
+                .and_then(|i| usize::try_from(i).ok())
+                .ok_or_else(|| format_err!("`{}` needs an argument index for `{}`", name, role))
+        };
+        let rewrite = entry
+            .get("rewrite")
+            .and_then(Item::as_str)
+            .ok_or_else(|| format_err!("`{}` needs a `rewrite`", name))?;
+        Ok(match rewrite {
+            "copy" => LibcRewrite::Copy {
+                dst: arg("dst")?,
+                src: arg("src")?,
+                len: arg("len")?,
+                overlapping: entry
+                    .get("overlapping")
+                    .and_then(Item::as_bool)
+                    .unwrap_or(false),
+            },
+            "fill" => LibcRewrite::Fill {
+                dst: arg("dst")?,
+                value: arg("value")?,
+                len: arg("len")?,
+            },
+            "strlen" => LibcRewrite::Strlen { s: arg("s")? },
+            "strcmp" => LibcRewrite::Strcmp {
+                lhs: arg("lhs")?,
+                rhs: arg("rhs")?,
+            },
+            _ => return Err(format_err!("`{}` has unknown rewrite `{}`", name, rewrite)),
+        })
+    }
+}
+
+/// Which libc functions to rewrite, and how.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LibcMapping {
+    rewrites: HashMap<String, LibcRewrite>,
+}
+
+impl Default for LibcMapping {
+    fn default() -> Self {
+        let mut mapping = LibcMapping {
+            rewrites: HashMap::new(),
+        };
+        mapping
+            .extend_from_toml(BUILTIN_MAPPING)
+            .expect("invalid built-in libc mapping");
+        mapping
+    }
+}
+
+impl LibcMapping {
+    /// The built-in table extended with the entries in the TOML file at `path`.
+    pub fn load(path: &Path) -> Result<Self, Error> {
+        let mut mapping = Self::default();
+        let toml = fs::read_to_string(path)?;
+        mapping
+            .extend_from_toml(&toml)
+            .map_err(|e| format_err!("{}: {}", path.display(), e))?;
+        Ok(mapping)
+    }
+
+    /// Add the entries from a TOML table, replacing any with the same name.
+    pub fn extend_from_toml(&mut self, toml: &str) -> Result<(), Error> {
+        let doc = toml.parse::<Document>().map_err(|e| format_err!("{}", e))?;
+        for (name, entry) in doc.iter() {
+            let rewrite = LibcRewrite::from_toml(name, entry)?;
+            self.rewrites.insert(name.to_owned(), rewrite);
+        }
+        Ok(())
+    }
+
+    pub fn get(&self, name: &str) -> Option<LibcRewrite> {
+        self.rewrites.get(name).copied()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_builtin_mapping() {
+        let mapping = LibcMapping::default();
+        assert_eq!(
+            mapping.get("bcopy"),
+            Some(LibcRewrite::Copy {
+                dst: 1,
+                src: 0,
+                len: 2,
+                overlapping: true,
+            })
+        );
+        assert_eq!(mapping.get("strlen"), Some(LibcRewrite::Strlen { s: 0 }));
+        assert_eq!(mapping.get("printf"), None);
+    }
+
+    #[test]
+    fn test_extend_mapping() {
+        let mut mapping = LibcMapping::default();
+        mapping
+            .extend_from_toml(
+                r#"
+                [xmemcpy]
+                rewrite = "copy"
+                dst = 1
+                src = 2
+                len = 3
+
+                [strlen]
+                rewrite = "strlen"
+                s = 1
+                "#,
+            )
+            .unwrap();
+        assert_eq!(mapping.get("xmemcpy").map(|r| r.min_args()), Some(4));
+        assert_eq!(mapping.get("strlen"), Some(LibcRewrite::Strlen { s: 1 }));
+        assert!(mapping
+            .extend_from_toml("[strcpy]\nrewrite = \"copy\"\ndst = 0\n")
+            .is_err());
+        assert!(mapping
+            .extend_from_toml("[strcpy]\nrewrite = \"strcpy\"\n")
+            .is_err());
+    }
+}
@@ -1,8 +1,171 @@
+use serde_derive::Serialize;
+use std::borrow::Cow;
 use std::collections::HashMap;
 use std::collections::HashSet;
 use std::hash::Hash;
 use std::iter::FromIterator;
 
+/// How C identifiers are turned into Rust identifiers.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum NamingPolicy {
+    /// Keep C names, only renaming to avoid keywords and duplicates.
+    #[default]
+    Preserve,
+    /// Follow Rust naming conventions: `snake_case` functions, locals and
+    /// fields, `SCREAMING_SNAKE_CASE` statics and `UpperCamelCase` types
+    /// without `struct_`/`_t` decorations, except for standard and system
+    /// header types. Exported and imported symbols keep their C names through
+    /// `#[export_name]`/`#[link_name]`.
+    Idiomatic,
+}
+
+impl NamingPolicy {
+    /// Name for a function, parameter or local variable.
+    pub fn value_name(self, name: &str) -> Cow<str> {
+        match self {
+            Self::Idiomatic if !is_reserved_c_name(name) => Cow::Owned(to_snake_case(name)),
+            _ => Cow::Borrowed(name),
+        }
+    }
+
+    /// Name for a variable with static or thread storage duration.
+    pub fn static_name(self, name: &str) -> Cow<str> {
+        match self {
+            Self::Idiomatic if !is_reserved_c_name(name) => {
+                Cow::Owned(to_snake_case(name).to_uppercase())
+            }
+            _ => Cow::Borrowed(name),
+        }
+    }
+
+    /// Name for a struct, union, enum or typedef. Standard C typedefs such as
+    /// `uint32_t` and `size_t` keep their names.
+    pub fn type_name(self, name: &str) -> Cow<str> {
+        match self {
+            Self::Idiomatic if !is_reserved_c_name(name) && !is_std_type_name(name) => {
+                Cow::Owned(to_upper_camel_case(strip_type_decorations(name)))
+            }
+            _ => Cow::Borrowed(name),
+        }
+    }
+}
+
+/// Identifiers reserved to the C implementation (`__foo`, `_Foo`) name
+/// compiler and libc internals, some of which (e.g. `__m128`) the translator
+/// refers to by name, so naming policies leave them alone.
+fn is_reserved_c_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars.next() == Some('_') && chars.next().map_or(false, |c| c == '_' || c.is_uppercase())
+}
+
+/// Typedefs from `<stdint.h>`, `<stddef.h>` and other standard headers, which
+/// Rust code knows by their C names (as does `libc`).
+fn is_std_type_name(name: &str) -> bool {
+    const STD_TYPES: &[&str] = &[
+        "size_t",
+        "ssize_t",
+        "ptrdiff_t",
+        "intptr_t",
+        "uintptr_t",
+        "intmax_t",
+        "uintmax_t",
+        "max_align_t",
+        "wchar_t",
+        "wint_t",
+        "char16_t",
+        "char32_t",
+        "off_t",
+        "time_t",
+        "clock_t",
+        "pid_t",
+        "sig_atomic_t",
+        "va_list",
+        "FILE",
+    ];
+    if STD_TYPES.contains(&name) {
+        return true;
+    }
+    // `int8_t`, `uint_least16_t`, `int_fast64_t`, ...
+    let width = name
+        .strip_suffix("_t")
+        .map(|name| name.strip_prefix('u').unwrap_or(name))
+        .and_then(|name| name.strip_prefix("int"))
+        .map(|name| {
+            ["_least", "_fast"]
+                .iter()
+                .fold(name, |name, kind| name.strip_prefix(kind).unwrap_or(name))
+        });
+    matches!(width, Some("8" | "16" | "32" | "64"))
+}
+
+fn strip_type_decorations(name: &str) -> &str {
+    let stripped = ["struct_", "union_", "enum_"]
+        .iter()
+        .fold(name, |name, prefix| {
+            name.strip_prefix(prefix).unwrap_or(name)
+        });
+    let stripped = ["_t", "_s"].iter().fold(stripped, |name, suffix| {
+        name.strip_suffix(suffix).unwrap_or(name)
+    });
+    if stripped.trim_matches('_').is_empty() {
+        name
+    } else {
+        stripped
+    }
+}
+
+/// Convert `XML_ParserCreate` or `parserCreate` to `xml_parser_create` or
+/// `parser_create`. Leading underscores are kept.
+pub fn to_snake_case(name: &str) -> String {
+    let chars: Vec<char> = name.chars().collect();
+    let mut snake = String::with_capacity(name.len() + 4);
+    for (i, &c) in chars.iter().enumerate() {
+        if !c.is_uppercase() {
+            snake.push(c);
+            continue;
+        }
+        // Start a new word at `aB`, `1B` and at the last capital of `ABc`
+        let starts_word = match i.checked_sub(1).map(|j| chars[j]) {
+            Some(prev) if prev.is_lowercase() || prev.is_ascii_digit() => true,
+            Some(prev) if prev.is_uppercase() => {
+                chars.get(i + 1).map_or(false, |next| next.is_lowercase())
+            }
+            _ => false,
+        };
+        if starts_word && !snake.ends_with('_') {
+            snake.push('_');
+        }
+        snake.extend(c.to_lowercase());
+    }
+    snake
+}
+
+/// Convert `foo_bar` to `FooBar`, keeping the capitalization within words.
+pub fn to_upper_camel_case(name: &str) -> String {
+    let mut camel = String::with_capacity(name.len());
+    for word in name.split('_').filter(|word| !word.is_empty()) {
+        let mut chars = word.chars();
+        if let Some(first) = chars.next() {
+            camel.extend(first.to_uppercase());
+            camel.extend(chars);
+        }
+    }
+    // A leading digit can only come from a leading `_`, which we must keep
+    if camel.is_empty() || camel.starts_with(|c: char| c.is_ascii_digit()) {
+        camel.insert(0, '_');
+    }
+    camel
+}
+
+/// A C declaration that was given a different name in Rust, as recorded in
+/// the name mapping file.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize)]
+pub struct RenamedDecl {
+    pub kind: &'static str,
+    pub c_name: String,
+    pub rust_name: String,
+}
+
 struct Scope<T> {
     name_map: HashMap<T, String>,
     used: HashSet<String>,
@@ -40,6 +203,7 @@ impl<T: Clone + Eq + Hash> Scope<T> {
 pub struct Renamer<T> {
     scopes: Vec<Scope<T>>,
     next_fresh: u64,
+    camel_case_suffixes: bool,
 }
 
 impl<T: Clone + Eq + Hash> Renamer<T> {
@@ -51,9 +215,17 @@ impl<T: Clone + Eq + Hash> Renamer<T> {
         Renamer {
             scopes: vec![Scope::new_with_reserved(set)],
             next_fresh: 0,
+            camel_case_suffixes: false,
         }
     }
 
+    /// Disambiguate colliding names without underscores as `Foo2`, `Foo3`, ...
+    /// instead of `Foo_0`, `Foo_1`, ..., keeping `UpperCamelCase` names in
+    /// that case.
+    pub fn use_camel_case_suffixes(&mut self) {
+        self.camel_case_suffixes = true;
+    }
+
     /// Introduces a new name binding scope
     pub fn add_scope(&mut self) {
         self.scopes.push(Scope::new())
@@ -90,7 +262,11 @@ impl<T: Clone + Eq + Hash> Renamer<T> {
 
         for i in 0.. {
             if self.is_target_used(&target) {
-                target = format!("{}_{}", basename, i);
+                target = if self.camel_case_suffixes && !basename.contains('_') {
+                    format!("{}{}", basename, i + 2)
+                } else {
+                    format!("{}_{}", basename, i)
+                };
             } else {
                 break;
             }
@@ -217,6 +393,39 @@ mod tests {
         assert_eq!(one5, one2);
     }
 
+    #[test]
+    fn naming_policy() {
+        let policy = NamingPolicy::Idiomatic;
+        assert_eq!(policy.value_name("XML_ParserCreate"), "xml_parser_create");
+        assert_eq!(policy.value_name("parseHTTPHeader2"), "parse_http_header2");
+        assert_eq!(policy.value_name("_private_fn"), "_private_fn");
+        assert_eq!(policy.value_name("__builtin_thing"), "__builtin_thing");
+        assert_eq!(policy.static_name("gCounter"), "G_COUNTER");
+        assert_eq!(policy.type_name("struct_foo_s"), "Foo");
+        assert_eq!(policy.type_name("uint32_t"), "uint32_t");
+        assert_eq!(policy.type_name("uint_least8_t"), "uint_least8_t");
+        assert_eq!(policy.type_name("size_t"), "size_t");
+        assert_eq!(policy.type_name("uint32"), "Uint32");
+        assert_eq!(policy.type_name("int128_t"), "Int128");
+        assert_eq!(policy.type_name("XML_Parser"), "XMLParser");
+        assert_eq!(policy.type_name("__m128i"), "__m128i");
+        assert_eq!(policy.type_name("_t"), "T");
+        assert_eq!(
+            NamingPolicy::Preserve.value_name("XML_Parser"),
+            "XML_Parser"
+        );
+    }
+
+    #[test]
+    fn camel_case_suffixes() {
+        let mut renamer = Renamer::new(&["Foo"]);
+        renamer.use_camel_case_suffixes();
+        assert_eq!(renamer.insert(1, "Foo").unwrap(), "Foo2");
+        assert_eq!(renamer.insert(2, "Foo").unwrap(), "Foo3");
+        assert_eq!(renamer.insert(3, "uint32_t").unwrap(), "uint32_t");
+        assert_eq!(renamer.insert(4, "uint32_t").unwrap(), "uint32_t_0");
+    }
+
     #[test]
     fn forgets() {
         let mut renamer = Renamer::new(&[]);
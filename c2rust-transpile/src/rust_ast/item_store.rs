@@ -5,7 +5,7 @@ use syn::{ForeignItem, Ident, Item};
 use std::borrow::Cow;
 use std::mem::swap;
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct MultiImport {
     attrs: Option<Builder>,
     leaves: IndexSet<String>,
@@ -47,6 +47,33 @@ impl PathedMultiImports {
         self.0.entry(path).or_insert(MultiImport::new())
     }
 
+    /// Whether the imports from `path` are of other crates rather than of this
+    /// crate's own modules
+    fn is_external(path: &[String]) -> bool {
+        !matches!(
+            path.first().map(String::as_str),
+            Some("crate" | "self" | "super")
+        )
+    }
+
+    /// The names these imports bring into scope from this crate's own modules
+    pub fn local_leaves(&self) -> impl Iterator<Item = &str> {
+        self.0
+            .iter()
+            .filter(|(path, _)| !Self::is_external(path))
+            .flat_map(|(_, imports)| imports.leaves.iter().map(String::as_str))
+    }
+
+    /// The `use` items of the imports from other crates
+    pub fn external_items(&self) -> Vec<Box<Item>> {
+        let external = self
+            .0
+            .iter()
+            .filter(|(path, _)| Self::is_external(path))
+            .map(|(path, imports)| (path.clone(), imports.clone()));
+        PathedMultiImports(external.collect()).into_items()
+    }
+
     pub fn into_items(self) -> Vec<Box<Item>> {
         fn build_items((mut path, imports): (Vec<String>, MultiImport)) -> Box<Item> {
             let mut leaves = imports.leaves;
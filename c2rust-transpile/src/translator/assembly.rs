@@ -1,7 +1,7 @@
 #![deny(missing_docs)]
 //! This module provides basic support for converting inline assembly statements.
 
-use crate::diagnostics::TranslationResult;
+use crate::diagnostics::{self, TranslationResult};
 
 use super::*;
 use log::warn;
@@ -629,7 +629,131 @@ fn rewrite_asm<F: Fn(&str) -> bool, M: Fn(usize) -> usize>(
     Ok(out)
 }
 
+/// Numeric local label at which the `asm goto` dispatch code generated by
+/// `asm_goto_dispatch` rejoins; target label `i` is `ASM_GOTO_LABEL_BASE + 1 + i`.
+const ASM_GOTO_LABEL_BASE: usize = 7700;
+
+/// Name of the `asm!` operand receiving the index of the `asm goto` label taken.
+const ASM_GOTO_OPERAND: &str = "c2rust_goto";
+
+/// Rewrite references to `asm goto` label operands (`${N:l}` in LLVM syntax,
+/// where `N` counts past the input and output operands) into forward
+/// references to the numeric local labels emitted by `asm_goto_dispatch`.
+fn rewrite_asm_goto_labels(
+    asm: &str,
+    num_operands: usize,
+    num_labels: usize,
+) -> TranslationResult<String> {
+    let mut out = String::with_capacity(asm.len());
+    let mut rest = asm;
+    while let Some(start) = rest.find("${") {
+        out.push_str(&rest[..start]);
+        let after = &rest[start + 2..];
+        let end = after
+            .find('}')
+            .ok_or_else(|| TranslationError::generic("unterminated asm operand reference"))?;
+        let reference = &after[..end];
+        let label_idx = reference
+            .strip_suffix(":l")
+            .and_then(|idx| idx.parse::<usize>().ok())
+            .and_then(|idx| idx.checked_sub(num_operands));
+        match label_idx {
+            Some(label_idx) if label_idx < num_labels => {
+                out.push_str(&format!("{}f", ASM_GOTO_LABEL_BASE + 1 + label_idx));
+            }
+            _ => {
+                out.push_str("${");
+                out.push_str(reference);
+                out.push('}');
+            }
+        }
+        rest = &after[end + 1..];
+    }
+    out.push_str(rest);
+    Ok(out)
+}
+
+/// Generate the assembly appended to an `asm goto` template: falling through
+/// stores 0 into the `ASM_GOTO_OPERAND` register, and jumping to label `i`
+/// stores `i + 1`. The translated code then branches on this value.
+fn asm_goto_dispatch(arch: Arch, att_syntax: bool, num_labels: usize) -> String {
+    let set_index = |idx: usize| match arch {
+        Arch::X86OrX86_64 if att_syntax => format!("movl ${}, {{{}:e}}", idx, ASM_GOTO_OPERAND),
+        Arch::X86OrX86_64 => format!("mov {{{}:e}}, {}", ASM_GOTO_OPERAND, idx),
+        Arch::Aarch64 => format!("mov {{{}:w}}, #{}", ASM_GOTO_OPERAND, idx),
+        Arch::Arm => format!("mov {{{}}}, #{}", ASM_GOTO_OPERAND, idx),
+        Arch::Riscv => format!("li {{{}}}, {}", ASM_GOTO_OPERAND, idx),
+    };
+    let jump_to_end = match arch {
+        Arch::X86OrX86_64 => format!("jmp {}f", ASM_GOTO_LABEL_BASE),
+        Arch::Aarch64 | Arch::Arm => format!("b {}f", ASM_GOTO_LABEL_BASE),
+        Arch::Riscv => format!("j {}f", ASM_GOTO_LABEL_BASE),
+    };
+
+    let mut dispatch = String::new();
+    for idx in 0..=num_labels {
+        if idx > 0 {
+            dispatch.push_str(&format!("\n{}:", ASM_GOTO_LABEL_BASE + idx));
+        }
+        dispatch.push('\n');
+        dispatch.push_str(&set_index(idx));
+        if idx < num_labels {
+            dispatch.push('\n');
+            dispatch.push_str(&jump_to_end);
+        }
+    }
+    dispatch.push_str(&format!("\n{}:", ASM_GOTO_LABEL_BASE));
+    dispatch
+}
+
 impl<'c> Translation<'c> {
+    /// Convert a file-scope assembly block into a `global_asm!` item.
+    pub fn convert_file_scope_asm(&self, span: Span, asm: &str) -> TranslationResult<Box<Item>> {
+        if !self.tcfg.translate_asm {
+            return Err(TranslationError::generic(
+                "Inline assembly translation not enabled.",
+            ));
+        }
+
+        let arch = match parse_arch(&self.ast_context.target) {
+            Some(arch) => arch,
+            None => {
+                return Err(TranslationError::generic(
+                    "Cannot translate inline assembly for unfamiliar architecture",
+                ))
+            }
+        };
+
+        // File-scope assembly has no operands, so braces are literal and must
+        // be escaped for the `global_asm!` template.
+        let template = asm.replace('{', "{{").replace('}', "}}");
+
+        let mut tokens: Vec<TokenTree> = vec![];
+        tokens.extend(mk().lit_expr(template).to_token_stream());
+        // C compilers assemble file-scope assembly as AT&T unless it switches
+        // to Intel syntax itself, whereas `global_asm!` defaults to Intel
+        let intel = remove_comments(asm).contains(".intel_syntax");
+        if arch == Arch::X86OrX86_64 && !intel {
+            tokens.push(TokenTree::Punct(Punct::new(',', Alone)));
+            let options = mk().call_expr(
+                mk().ident_expr("options"),
+                vec![mk().ident_expr("att_syntax")],
+            );
+            tokens.extend(options.to_token_stream());
+        }
+
+        self.with_cur_file_item_store(|item_store| {
+            item_store.add_use(vec!["core".into(), "arch".into()], "global_asm");
+        });
+
+        let mac = mk().mac(
+            mk().path(vec!["global_asm"]),
+            tokens.into_iter().collect::<TokenStream>(),
+            MacroDelimiter::Paren(Default::default()),
+        );
+        Ok(mk().span(span).mac_item(mac))
+    }
+
     /// Convert an inline-assembly statement into one or more Rust statements.
     /// If inline assembly translation is not enabled this will result in an
     /// error message instead of a conversion. Because the inline assembly syntax
@@ -637,6 +761,10 @@ impl<'c> Translation<'c> {
     /// directly) the resulting translated assembly statements will be unlikely to work
     /// without further manual translation. The translator will properly translate
     /// the arguments to the assembly statement, however.
+    ///
+    /// For `asm goto` (`num_labels > 0`), the returned value is a fresh local
+    /// holding 0 when the assembly fell through, or `i + 1` when it jumped to
+    /// its `i`th target label.
     pub fn convert_asm(
         &self,
         ctx: ExprContext,
@@ -646,7 +774,8 @@ impl<'c> Translation<'c> {
         inputs: &[AsmOperand],
         outputs: &[AsmOperand],
         clobbers: &[String],
-    ) -> TranslationResult<Vec<Stmt>> {
+        num_labels: usize,
+    ) -> TranslationResult<WithStmts<Option<Box<Expr>>>> {
         if !self.tcfg.translate_asm {
             return Err(TranslationError::generic(
                 "Inline assembly translation not enabled.",
@@ -695,14 +824,33 @@ impl<'c> Translation<'c> {
             {
                 mem_only
             } else {
-                println!("could not parse asm constraints: {}", operand.constraints);
+                diagnostics::print(format!(
+                    "could not parse asm constraints: {}",
+                    operand.constraints
+                ));
                 false
             }
         };
 
+        let goto_index = if num_labels > 0 {
+            Some(self.renamer.borrow_mut().fresh())
+        } else {
+            None
+        };
+
+        // Point `asm goto` label references at our dispatch code
+        let asm = match goto_index {
+            Some(_) => Cow::Owned(rewrite_asm_goto_labels(
+                asm,
+                outputs.len() + inputs.len(),
+                num_labels,
+            )?),
+            None => Cow::Borrowed(asm),
+        };
+
         // Rewrite arg references in assembly template
         let rewritten_asm = rewrite_asm(
-            asm,
+            &asm,
             |idx: usize| map_input_op_idx(idx, outputs.len(), &tied_operands),
             |ref_str: &str| {
                 if let Ok(idx) = ref_str.parse::<usize>() {
@@ -766,7 +914,7 @@ impl<'c> Translation<'c> {
                     });
                 }
                 // Constraint could not be parsed, drop it
-                Err(e) => eprintln!("{}", e),
+                Err(e) => diagnostics::eprint(e.to_string()),
             }
         }
         // Add unmatched inputs
@@ -777,7 +925,7 @@ impl<'c> Translation<'c> {
             let (dir_spec, mem_only, parsed) = match parse_constraints(&input.constraints, arch) {
                 Ok(x) => x,
                 Err(e) => {
-                    eprintln!("{}", e);
+                    diagnostics::eprint(e.to_string());
                     continue;
                 }
             };
@@ -799,14 +947,35 @@ impl<'c> Translation<'c> {
 
         // Add workaround for reserved registers (e.g. rbx on x86_64)
         let (prolog, epilog) = rewrite_reserved_reg_operands(att_syntax, arch, &mut args);
-        let rewritten_asm = prolog + &rewritten_asm + &epilog;
+        let mut rewritten_asm = prolog + &rewritten_asm + &epilog;
+        if goto_index.is_some() {
+            rewritten_asm.push_str(&asm_goto_dispatch(arch, att_syntax, num_labels));
+        }
 
         // Emit assembly template
         push_expr(&mut tokens, mk().lit_expr(rewritten_asm));
 
+        // The `asm goto` index is a named operand, so it must precede any
+        // operands using explicit registers
+        let mut goto_operand = goto_index.as_deref().map(|goto_index| {
+            let local = mk().local(mk().ident_pat(goto_index), Some(mk().ident_ty("u32")), None);
+            stmts.push(mk().local_stmt(Box::new(local)));
+
+            let mut operand = vec![TokenTree::Punct(Punct::new(',', Alone))];
+            operand.extend(mk().ident_expr(ASM_GOTO_OPERAND).to_token_stream());
+            operand.push(TokenTree::Punct(Punct::new('=', Alone)));
+            let out = mk().call_expr(mk().ident_expr("out"), vec![mk().ident_expr("reg")]);
+            operand.extend(out.to_token_stream());
+            operand.extend(mk().ident_expr(goto_index).to_token_stream());
+            operand
+        });
+
         // Outputs and Inputs
         let mut operand_renames = HashMap::new();
         for operand in args {
+            if operand.constraints.contains('"') {
+                tokens.extend(goto_operand.take().into_iter().flatten());
+            }
             tokens.push(TokenTree::Punct(Punct::new(',', Alone)));
 
             // First, convert output expr if present
@@ -932,6 +1101,8 @@ impl<'c> Translation<'c> {
             }
         }
 
+        tokens.extend(goto_operand.take().into_iter().flatten());
+
         let mut preserves_flags = true;
         let mut read_only = true;
 
@@ -975,7 +1146,7 @@ impl<'c> Translation<'c> {
             if preserves_flags {
                 options.push(mk().ident_expr("preserves_flags"));
             }
-            if !is_volatile {
+            if !is_volatile && goto_index.is_none() {
                 // Pure cannot be applied if we have no outputs
                 if read_only && (outputs.len() + clobbers.len()) > 0 {
                     options.push(mk().ident_expr("pure"));
@@ -1011,6 +1182,9 @@ impl<'c> Translation<'c> {
         // Push the post-macro statements
         stmts.extend(post_stmts.into_iter());
 
-        Ok(stmts)
+        Ok(WithStmts::new(
+            stmts,
+            goto_index.map(|goto_index| mk().ident_expr(goto_index)),
+        ))
     }
 }
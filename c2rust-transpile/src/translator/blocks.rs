@@ -0,0 +1,357 @@
+//! This module implements the translation of Clang blocks (`^{ ... }`, enabled with `-fblocks`).
+//!
+//! Blocks are laid out according to the Blocks ABI using the `c2rust-blocks` crate, so they can be
+//! passed to and called from C code. A block literal becomes a `#[repr(C)]` struct holding a
+//! `c2rust_blocks::Block` header followed by copies of the captured variables, a function
+//! implementing the block, and a static descriptor, all declared in the enclosing function. The
+//! literal itself is built on the stack, like in C, and the expression evaluates to a pointer to
+//! it. Blocks capturing other blocks get copy and dispose helpers so `Block_copy` retains them.
+//!
+//! `__block` variables, which blocks capture by reference, are not supported.
+
+use super::*;
+use failure::format_err;
+
+impl<'c> Translation<'c> {
+    /// Convert a block literal to a pointer to a `#[repr(C)]` struct built on the stack.
+    pub fn convert_block_literal(
+        &self,
+        ctx: ExprContext,
+        typ: CQualTypeId,
+        parameters: &[CParamId],
+        body: CStmtId,
+        captures: &[CBlockCapture],
+    ) -> TranslationResult<WithStmts<Box<Expr>>> {
+        if ctx.is_static {
+            return Err(TranslationError::generic(
+                "Block literals in static initializers are not supported",
+            ));
+        }
+        if captures.iter().any(|capture| capture.by_ref) {
+            return Err(TranslationError::generic(
+                "Blocks capturing __block variables are not supported",
+            ));
+        }
+
+        let ret_ty = match self.ast_context.resolve_type(typ.ctype).kind {
+            CTypeKind::BlockPointer(fn_ty) => match self.ast_context.resolve_type(fn_ty.ctype).kind
+            {
+                CTypeKind::Function(ret_ty, ..) => ret_ty,
+                ref kind => return Err(format_err!("Block of non-function type {:?}", kind).into()),
+            },
+            ref kind => return Err(format_err!("Block literal of type {:?}", kind).into()),
+        };
+
+        // `*mut c2rust_blocks::Block<F>`
+        self.use_crate(ExternCrate::C2RustBlocks);
+        let block_ty = self.convert_type(typ.ctype)?;
+        let header_ty = match *block_ty {
+            Type::Ptr(TypePtr { ref elem, .. }) => elem.clone(),
+            _ => panic!("Block pointers should be translated to raw pointers"),
+        };
+
+        let (literal_name, invoke_name, descriptor_name, var_name) = {
+            let mut renamer = self.renamer.borrow_mut();
+            (
+                renamer.pick_name("__block_literal"),
+                renamer.pick_name("__block_invoke"),
+                renamer.pick_name("__block_descriptor"),
+                renamer.pick_name("block"),
+            )
+        };
+        let literal_ptr_ty = || mk().mutbl().ptr_ty(mk().path_ty(vec![&literal_name]));
+        let void_ptr_ty = || mk().mutbl().ptr_ty(mk().path_ty(vec!["libc", "c_void"]));
+
+        // The captured variables keep their names as fields of the literal and as locals in the
+        // function implementing the block, so its body can refer to them unchanged.
+        let mut captured = vec![];
+        for capture in captures {
+            let name =
+                self.renamer.borrow().get(&capture.var).ok_or_else(|| {
+                    format_err!("Captured variable {:?} has no name", capture.var)
+                })?;
+            let typ = match self.ast_context.index(capture.var).kind {
+                CDeclKind::Variable { typ, .. } => typ,
+                ref kind => return Err(format_err!("Captured non-variable {:?}", kind).into()),
+            };
+            let is_block = matches!(
+                self.ast_context.resolve_type(typ.ctype).kind,
+                CTypeKind::BlockPointer(..)
+            );
+            captured.push((name, self.convert_type(typ.ctype)?, is_block));
+        }
+        let mut header_name = "header".to_string();
+        while captured.iter().any(|(name, ..)| *name == header_name) {
+            header_name.push('_');
+        }
+
+        let mut fields = vec![mk().struct_field(&header_name, header_ty)];
+        fields.extend(
+            captured
+                .iter()
+                .map(|(name, ty, _)| mk().struct_field(name, ty.clone())),
+        );
+        let literal_item =
+            mk().call_attr("repr", vec!["C"])
+                .struct_item(&literal_name, fields, false);
+
+        let invoke_item = self.with_scope(|| -> TranslationResult<Box<Item>> {
+            let block_arg = self.renamer.borrow_mut().pick_name("block");
+            let block_pat = if captured.is_empty() {
+                mk().wild_pat()
+            } else {
+                mk().ident_pat(&block_arg)
+            };
+            let mut args = vec![mk().arg(void_ptr_ty(), block_pat)];
+            for &param in parameters {
+                let (ident, typ) = match self.ast_context.index(param).kind {
+                    CDeclKind::Variable { ref ident, typ, .. } => (ident, typ),
+                    ref kind => return Err(format_err!("Block parameter {:?}", kind).into()),
+                };
+                let ConvertedVariable { ty, mutbl, init: _ } =
+                    self.convert_variable(ctx, None, typ)?;
+                let pat = if ident.is_empty() {
+                    mk().wild_pat()
+                } else {
+                    let name = self
+                        .renamer
+                        .borrow_mut()
+                        .insert(param, &self.tcfg.naming_policy.value_name(ident))
+                        .ok_or_else(|| {
+                            format_err!("Failed to insert block parameter '{}'", ident)
+                        })?;
+                    mk().set_mutbl(mutbl).ident_pat(name)
+                };
+                args.push(mk().arg(ty, pat));
+            }
+
+            let is_void_ret = matches!(
+                self.ast_context.resolve_type(ret_ty.ctype).kind,
+                CTypeKind::Void
+            );
+            let (ret, implicit_ret) = if is_void_ret {
+                (ReturnType::Default, cfg::ImplicitReturnType::Void)
+            } else {
+                (
+                    ReturnType::Type(Default::default(), self.convert_type(ret_ty.ctype)?),
+                    cfg::ImplicitReturnType::NoImplicitReturnType,
+                )
+            };
+
+            let mut stmts = vec![];
+            if !captured.is_empty() {
+                let block = mk().cast_expr(mk().ident_expr(&block_arg), literal_ptr_ty());
+                stmts.push(mk().local_stmt(Box::new(mk().local(
+                    mk().ident_pat(&block_arg),
+                    None,
+                    Some(block),
+                ))));
+                for (name, _, _) in &captured {
+                    let field = mk().field_expr(
+                        mk().unary_expr(
+                            UnOp::Deref(Default::default()),
+                            mk().ident_expr(&block_arg),
+                        ),
+                        name,
+                    );
+                    stmts.push(mk().local_stmt(Box::new(mk().local(
+                        mk().ident_pat(name),
+                        None,
+                        Some(field),
+                    ))));
+                }
+            }
+
+            let body_ids = match self.ast_context.index(body).kind {
+                CStmtKind::Compound(ref stmts) => stmts,
+                _ => panic!("block body expects to be a compound statement"),
+            };
+            let body_ctx = ExprContext {
+                used: true,
+                is_const: false,
+                decay_ref: DecayRef::Default,
+                is_bitfield_write: false,
+                needs_address: false,
+                expecting_valistimpl: false,
+                ternary_needs_parens: false,
+                expanding_macro: None,
+                ..ctx
+            };
+            stmts.append(&mut self.convert_function_body(
+                body_ctx,
+                &invoke_name,
+                body_ids,
+                implicit_ret,
+            )?);
+
+            let decl = mk().fn_decl(&invoke_name, args, None, ret);
+            Ok(mk()
+                .unsafe_()
+                .extern_("C")
+                .fn_item(decl, stmts_block(stmts)))
+        })?;
+
+        let mut stmts = vec![mk().item_stmt(literal_item), mk().item_stmt(invoke_item)];
+
+        // Captured blocks are copied and released along with the block capturing them
+        let (copy_helper, dispose_helper) = if captured.iter().any(|&(_, _, is_block)| is_block) {
+            let (copy_name, dispose_name) = {
+                let mut renamer = self.renamer.borrow_mut();
+                (
+                    renamer.pick_name("__block_copy"),
+                    renamer.pick_name("__block_dispose"),
+                )
+            };
+            let literal_field = |ptr: &str, mutbl: bool, name: &str| {
+                let ptr_ty = if mutbl {
+                    literal_ptr_ty()
+                } else {
+                    mk().ptr_ty(mk().path_ty(vec![&literal_name]))
+                };
+                let literal = mk().cast_expr(mk().ident_expr(ptr), ptr_ty);
+                mk().field_expr(
+                    mk().unary_expr(UnOp::Deref(Default::default()), mk().paren_expr(literal)),
+                    name,
+                )
+            };
+            let const_void_ptr_ty = || mk().ptr_ty(mk().path_ty(vec!["libc", "c_void"]));
+
+            let mut copies = vec![];
+            let mut disposes = vec![];
+            for (name, _, _) in captured.iter().filter(|&&(_, _, is_block)| is_block) {
+                copies.push(mk().semi_stmt(mk().call_expr(
+                    mk().path_expr(vec!["c2rust_blocks", "assign_block"]),
+                    vec![
+                        mk().mutbl().addr_of_expr(literal_field("dst", true, name)),
+                        literal_field("src", false, name),
+                    ],
+                )));
+                disposes.push(mk().semi_stmt(mk().call_expr(
+                    mk().path_expr(vec!["c2rust_blocks", "dispose_block"]),
+                    vec![literal_field("src", false, name)],
+                )));
+            }
+
+            let copy_decl = mk().fn_decl(
+                &copy_name,
+                vec![
+                    mk().arg(void_ptr_ty(), mk().ident_pat("dst")),
+                    mk().arg(const_void_ptr_ty(), mk().ident_pat("src")),
+                ],
+                None,
+                ReturnType::Default,
+            );
+            let dispose_decl = mk().fn_decl(
+                &dispose_name,
+                vec![mk().arg(const_void_ptr_ty(), mk().ident_pat("src"))],
+                None,
+                ReturnType::Default,
+            );
+            stmts.push(
+                mk().item_stmt(
+                    mk().unsafe_()
+                        .extern_("C")
+                        .fn_item(copy_decl, mk().block(copies)),
+                ),
+            );
+            stmts.push(
+                mk().item_stmt(
+                    mk().unsafe_()
+                        .extern_("C")
+                        .fn_item(dispose_decl, mk().block(disposes)),
+                ),
+            );
+
+            let some =
+                |name: &str| mk().call_expr(mk().ident_expr("Some"), vec![mk().ident_expr(name)]);
+            (some(&copy_name), some(&dispose_name))
+        } else {
+            (mk().ident_expr("None"), mk().ident_expr("None"))
+        };
+
+        let descriptor_ty = mk().path_ty(vec!["c2rust_blocks", "BlockDescriptor"]);
+        let descriptor_new = mk().path_expr(vec![
+            mk().path_segment("c2rust_blocks"),
+            mk().path_segment("BlockDescriptor"),
+            mk().path_segment_with_args(
+                "new",
+                mk().angle_bracketed_args(vec![mk().path_ty(vec![&literal_name])]),
+            ),
+        ]);
+        stmts.push(mk().item_stmt(mk().static_item(
+            &descriptor_name,
+            descriptor_ty,
+            mk().call_expr(descriptor_new, vec![copy_helper, dispose_helper]),
+        )));
+
+        let header = mk().call_expr(
+            mk().path_expr(vec!["c2rust_blocks", "Block", "new"]),
+            vec![
+                mk().ident_expr(&invoke_name),
+                mk().addr_of_expr(mk().ident_expr(&descriptor_name)),
+            ],
+        );
+        let mut inits = vec![mk().field(&header_name, header)];
+        inits.extend(
+            captured
+                .iter()
+                .map(|(name, _, _)| mk().field(name, mk().ident_expr(name))),
+        );
+        stmts.push(mk().local_stmt(Box::new(mk().local(
+            mk().mutbl().ident_pat(&var_name),
+            None,
+            Some(mk().struct_expr(vec![&literal_name], inits)),
+        ))));
+
+        let ptr = mk().cast_expr(
+            mk().cast_expr(
+                mk().mutbl().addr_of_expr(mk().ident_expr(&var_name)),
+                literal_ptr_ty(),
+            ),
+            block_ty,
+        );
+        Ok(WithStmts::new(stmts, ptr))
+    }
+
+    /// Convert a call through a block pointer to a call of its `invoke` function, which takes the
+    /// block itself as its first argument.
+    pub fn convert_block_call(
+        &self,
+        ctx: ExprContext,
+        func: CExprId,
+        args: &[CExprId],
+    ) -> TranslationResult<WithStmts<Box<Expr>>> {
+        let block = self.convert_expr(ctx.used(), func)?;
+        block.and_then(|block| {
+            // Evaluate the callee once, since it is used twice
+            let (mut stmts, block) = if self.ast_context.is_expr_pure(func) {
+                (vec![], block)
+            } else {
+                let name = self.renamer.borrow_mut().pick_name("block");
+                let local = mk().local(mk().ident_pat(&name), None, Some(block));
+                (
+                    vec![mk().local_stmt(Box::new(local))],
+                    mk().ident_expr(&name),
+                )
+            };
+
+            let invoke = mk().paren_expr(mk().field_expr(
+                mk().unary_expr(UnOp::Deref(Default::default()), block.clone()),
+                "invoke",
+            ));
+            let block_arg = mk().cast_expr(
+                block,
+                mk().mutbl().ptr_ty(mk().path_ty(vec!["libc", "c_void"])),
+            );
+
+            let args = self.convert_exprs(ctx.used(), args)?;
+            let mut call = args.map(|mut args| {
+                args.insert(0, block_arg);
+                mk().call_expr(invoke, args)
+            });
+            call.prepend_stmts(stmts);
+            call.set_unsafe();
+            Ok(call)
+        })
+    }
+}
@@ -37,13 +37,16 @@ impl<'c> Translation<'c> {
             "__builtin_huge_valf" => Ok(WithStmts::new_val(
                 mk().abs_path_expr(vec!["core", "f32", "INFINITY"]),
             )),
-            "__builtin_huge_val" | "__builtin_huge_vall" => Ok(WithStmts::new_val(
+            "__builtin_huge_val" => Ok(WithStmts::new_val(
                 mk().abs_path_expr(vec!["core", "f64", "INFINITY"]),
             )),
+            "__builtin_huge_vall" | "__builtin_infl" => {
+                Ok(WithStmts::new_val(self.long_double_const("INFINITY")))
+            }
             "__builtin_inff" => Ok(WithStmts::new_val(
                 mk().abs_path_expr(vec!["core", "f32", "INFINITY"]),
             )),
-            "__builtin_inf" | "__builtin_infl" => Ok(WithStmts::new_val(
+            "__builtin_inf" => Ok(WithStmts::new_val(
                 mk().abs_path_expr(vec!["core", "f64", "INFINITY"]),
             )),
             "__builtin_nanf" => Ok(WithStmts::new_val(
@@ -52,16 +55,12 @@ impl<'c> Translation<'c> {
             "__builtin_nan" => Ok(WithStmts::new_val(
                 mk().abs_path_expr(vec!["core", "f64", "NAN"]),
             )),
-            "__builtin_nanl" => {
-                self.use_crate(ExternCrate::F128);
-
-                Ok(WithStmts::new_val(
-                    mk().path_expr(vec!["f128", "f128", "NAN"]),
-                ))
-            }
+            "__builtin_nanl" => Ok(WithStmts::new_val(self.long_double_const("NAN"))),
             "__builtin_signbit" | "__builtin_signbitf" | "__builtin_signbitl" => {
-                // Long doubles require the Float trait from num_traits to call this method
-                if builtin_name == "__builtin_signbitl" {
+                // f128 requires the Float trait from num_traits to call this method
+                if builtin_name == "__builtin_signbitl"
+                    && self.tcfg.long_double == LongDoubleRepr::F128
+                {
                     self.with_cur_file_item_store(|item_store| {
                         item_store.add_use(vec!["num_traits".into()], "Float");
                     });
@@ -0,0 +1,983 @@
+//! This module reconstructs a C header from the translated items, so that C
+//! code can keep using a translated library in place of the original one.
+//!
+//! The header declares the functions and statics the crate exports (those with
+//! `#[no_mangle]` or `#[export_name]`) and defines the `#[repr(C)]` types they
+//! use. It is built from the Rust items rather than the C declarations, so it
+//! describes what the crate actually exports. Compiling it together with the
+//! original headers turns any drift in signatures or layouts into C compile
+//! errors; see [`CHeader::render_check`].
+//!
+//! For that, types defined in project headers are spelled with `C2RUST_NAME`,
+//! which moves them out of the way of the original definitions when
+//! `C2RUST_CHECK` is defined:
+//!
+//! ```c
+//! struct C2RUST_NAME(point) { int x; int y; };
+//! int norm(struct point *p);
+//! ```
+//!
+//! Prototypes and typedefs use the original spellings, so the C compiler
+//! checks them against the original declarations, and record layouts are
+//! compared by `_Static_assert`s. Record fields use the header's own spellings
+//! instead, so the renamed definitions don't depend on the original ones.
+//! Enums are only defined when not checking, and types from system headers are
+//! never defined: the header includes the system headers instead.
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::slice;
+
+use itertools::Itertools;
+
+use super::*;
+
+/// A translated type declaration, as the header needs it.
+enum HeaderType {
+    Record {
+        decl_id: CRecordId,
+        /// The struct or union holding the fields; `None` for opaque records
+        item: Option<Box<Item>>,
+        /// `align(N)` of the wrapper struct of an aligned record
+        align: Option<u64>,
+        /// C names of the fields, by Rust name
+        field_names: HashMap<String, String>,
+    },
+    Typedef {
+        decl_id: CTypedefId,
+        ty: Box<Type>,
+    },
+    Enum {
+        decl_id: CEnumId,
+        ty: Box<Type>,
+    },
+}
+
+/// The translated declarations that go into the C header, collected as they
+/// are converted.
+#[derive(Default)]
+pub struct HeaderItems {
+    /// Type declarations by Rust name
+    types: IndexMap<String, HeaderType>,
+    /// C names and values of the constants of each enum
+    enum_constants: IndexMap<CEnumId, Vec<(String, Box<Expr>)>>,
+    /// Exported functions and statics
+    values: Vec<Box<Item>>,
+}
+
+/// A C header declaring what a translated crate exports; see the module
+/// documentation.
+#[derive(Debug, Default)]
+pub struct CHeader {
+    /// `#include`s of the system headers the declarations use
+    includes: IndexSet<String>,
+    /// The declarations, by the C name they declare
+    forward: IndexMap<String, String>,
+    types: IndexMap<String, String>,
+    values: IndexMap<String, String>,
+    /// `_Static_assert`s comparing the header's types to the original ones,
+    /// along with the header defining the original type
+    checks: Vec<(PathBuf, String)>,
+}
+
+impl CHeader {
+    /// Add the declarations of another translation unit of the same crate.
+    /// Where both declare the same name, the declaration in `self` is kept.
+    pub fn merge(&mut self, other: CHeader) {
+        self.includes.extend(other.includes);
+        for (decls, other_decls) in [
+            (&mut self.forward, other.forward),
+            (&mut self.types, other.types),
+            (&mut self.values, other.values),
+        ] {
+            for (name, text) in other_decls {
+                match decls.get(&name) {
+                    Some(existing) if *existing != text => {
+                        warn!("Conflicting declarations of {} for the C header", name)
+                    }
+                    Some(_) => {}
+                    None => {
+                        decls.insert(name, text);
+                    }
+                }
+            }
+        }
+        for check in other.checks {
+            if !self.checks.contains(&check) {
+                self.checks.push(check);
+            }
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.types.is_empty() && self.values.is_empty()
+    }
+
+    /// The text of the header, using `guard` as its include guard.
+    pub fn render(&self, guard: &str) -> String {
+        let mut sections = vec![
+            format!("#ifndef {}\n#define {}", guard, guard),
+            "#include <stdarg.h>\n#include <stddef.h>\n#include <stdint.h>".to_owned(),
+        ];
+        if !self.includes.is_empty() {
+            sections.push(self.includes.iter().join("\n"));
+        }
+        sections.push(
+            "#ifdef C2RUST_CHECK\n\
+             #define C2RUST_NAME(name) c2rust_check_##name\n\
+             #else\n\
+             #define C2RUST_NAME(name) name\n\
+             #endif"
+                .to_owned(),
+        );
+        if !self.forward.is_empty() {
+            sections.push(self.forward.values().join("\n"));
+        }
+        sections.extend(self.types.values().cloned());
+        if !self.values.is_empty() {
+            sections.push(self.values.values().join("\n"));
+        }
+        sections.push(format!("#endif /* {} */", guard));
+        sections.join("\n\n") + "\n"
+    }
+
+    /// A C file that includes the `originals` and then the header `header_name`
+    /// from the same directory in check mode, followed by the layout checks of
+    /// the types the originals define.
+    pub fn render_check(&self, header_name: &str, originals: &[PathBuf]) -> String {
+        let canonical = |path: &Path| fs::canonicalize(path).unwrap_or_else(|_| path.to_owned());
+        let originals = originals
+            .iter()
+            .map(|path| canonical(path))
+            .collect::<Vec<_>>();
+
+        let mut lines = originals
+            .iter()
+            .map(|path| format!("#include \"{}\"", path.display()))
+            .collect::<Vec<_>>();
+        lines.push("#define C2RUST_CHECK".to_owned());
+        lines.push(format!("#include \"{}\"", header_name));
+        for (path, check) in &self.checks {
+            if originals.contains(&canonical(path)) {
+                lines.push(check.clone());
+            }
+        }
+        lines.join("\n") + "\n"
+    }
+}
+
+/// Where a type declaration comes from, which decides how the header treats
+/// it.
+#[derive(Debug, Clone)]
+enum Origin {
+    /// The file being translated; no original header defines the type, so the
+    /// header defines it under its own name.
+    Main,
+    /// A project header; the header defines the type under `C2RUST_NAME`.
+    Header(PathBuf),
+    /// A system header that the header includes instead of defining the type,
+    /// or `None` for compiler builtins.
+    System(Option<String>),
+}
+
+/// How to spell types that the header defines itself.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum Spelling {
+    /// As in the original C code, for prototypes and typedefs
+    Original,
+    /// As the header's own definitions, for record fields
+    Own,
+}
+
+/// The symbol an item is exported under, if it is exported.
+fn exported_symbol(item: &Item) -> Option<String> {
+    let (attrs, ident) = match item {
+        Item::Fn(f) => (&f.attrs, &f.sig.ident),
+        Item::Static(s) => (&s.attrs, &s.ident),
+        _ => return None,
+    };
+    attrs.iter().find_map(|attr| {
+        if attr.path.is_ident("no_mangle") {
+            return Some(ident.to_string());
+        }
+        match attr.parse_meta() {
+            Ok(Meta::NameValue(MetaNameValue {
+                path,
+                lit: Lit::Str(name),
+                ..
+            })) if path.is_ident("export_name") => Some(name.value()),
+            _ => None,
+        }
+    })
+}
+
+/// `packed(N)` and `align(N)` from the `#[repr]` attributes of a record.
+fn repr_of(attrs: &[syn::Attribute]) -> (Option<u64>, Option<u64>) {
+    let (mut packed, mut align) = (None, None);
+    for attr in attrs.iter().filter(|attr| attr.path.is_ident("repr")) {
+        let list = match attr.parse_meta() {
+            Ok(Meta::List(list)) => list,
+            _ => continue,
+        };
+        for nested in list.nested {
+            match nested {
+                NestedMeta::Meta(Meta::Path(path)) if path.is_ident("packed") => packed = Some(1),
+                NestedMeta::Meta(Meta::List(arg)) => {
+                    let n = match arg.nested.first() {
+                        Some(NestedMeta::Lit(Lit::Int(n))) => n.base10_parse().ok(),
+                        _ => None,
+                    };
+                    if arg.path.is_ident("packed") {
+                        packed = n;
+                    } else if arg.path.is_ident("align") {
+                        align = n;
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+    (packed, align)
+}
+
+/// The `#[bitfield(name = "..", ty = "..", bits = "A..=B")]` attributes of a
+/// bitfield group, as `(name, ty, A, B)` in bit order.
+fn bitfields_of(attrs: &[syn::Attribute]) -> Vec<(String, String, u64, u64)> {
+    let mut bitfields = attrs
+        .iter()
+        .filter(|attr| attr.path.is_ident("bitfield"))
+        .filter_map(|attr| {
+            let list = match attr.parse_meta() {
+                Ok(Meta::List(list)) => list,
+                _ => return None,
+            };
+            let mut args = HashMap::new();
+            for nested in list.nested {
+                if let NestedMeta::Meta(Meta::NameValue(MetaNameValue {
+                    path,
+                    lit: Lit::Str(value),
+                    ..
+                })) = nested
+                {
+                    args.insert(path.get_ident()?.to_string(), value.value());
+                }
+            }
+            let (start, end) = args.get("bits")?.split_once("..=")?;
+            let (start, end) = (start.parse().ok()?, end.parse().ok()?);
+            Some((args.remove("name")?, args.remove("ty")?, start, end))
+        })
+        .collect::<Vec<_>>();
+    bitfields.sort_by_key(|&(_, _, start, _)| start);
+    bitfields
+}
+
+/// The `#include <...>` on line `line` of `path`, if there is one.
+fn angle_include(path: &Path, line: u64) -> Option<String> {
+    let source = fs::read_to_string(path).ok()?;
+    let directive = source.lines().nth(line.checked_sub(1)? as usize)?.trim();
+    let target = directive
+        .strip_prefix('#')?
+        .trim_start()
+        .strip_prefix("include")?
+        .trim_start();
+    let end = target.find('>')?;
+    target
+        .starts_with('<')
+        .then(|| format!("#include {}", &target[..=end]))
+}
+
+/// A header is a system header if any `#include` on the way to it uses angle
+/// brackets; this returns the outermost one, which is what users include.
+pub(super) fn system_include(ast_context: &TypedAstContext, file: FileId) -> Option<String> {
+    let mut include = None;
+    let mut cur = file;
+    while let Some((includer, line)) = ast_context.get_includer(cur) {
+        if let Some(path) = ast_context.get_file_path(includer) {
+            include = angle_include(path, line).or(include);
+        }
+        cur = includer;
+    }
+    include
+}
+
+/// Declare `declarator` to have a type named `name`.
+fn base(name: &str, declarator: &str, is_const: bool) -> String {
+    let qualifier = if is_const { "const " } else { "" };
+    format!("{}{} {}", qualifier, name, declarator)
+        .trim_end()
+        .to_owned()
+}
+
+/// The C spelling of a Rust primitive type.
+fn primitive_name(name: &str) -> Option<&'static str> {
+    Some(match name {
+        "bool" => "_Bool",
+        "i8" => "int8_t",
+        "i16" => "int16_t",
+        "i32" => "int32_t",
+        "i64" => "int64_t",
+        "i128" => "__int128",
+        "u8" => "uint8_t",
+        "u16" => "uint16_t",
+        "u32" => "uint32_t",
+        "u64" => "uint64_t",
+        "u128" => "unsigned __int128",
+        "isize" => "ptrdiff_t",
+        "usize" => "size_t",
+        "f32" => "float",
+        "f64" => "double",
+        "bf16" => "__bf16",
+        _ => return None,
+    })
+}
+
+/// The C spelling of a type from the `libc` crate.
+fn libc_name(name: &str) -> Option<&str> {
+    Some(match name {
+        "c_void" => "void",
+        "c_char" => "char",
+        "c_schar" => "signed char",
+        "c_uchar" => "unsigned char",
+        "c_short" => "short",
+        "c_ushort" => "unsigned short",
+        "c_int" => "int",
+        "c_uint" => "unsigned int",
+        "c_long" => "long",
+        "c_ulong" => "unsigned long",
+        "c_longlong" => "long long",
+        "c_ulonglong" => "unsigned long long",
+        "c_float" => "float",
+        "c_double" => "double",
+        // `size_t` and friends are spelled the same in C
+        _ if name.ends_with("_t") => name,
+        _ => return None,
+    })
+}
+
+/// Spells the collected declarations in C; see [`Translation::c_header`].
+struct HeaderWriter<'a, 'c> {
+    t: &'a Translation<'c>,
+    items: &'a HeaderItems,
+    /// Prefix for the names of anonymous records
+    module: String,
+    /// The system headers the declarations use
+    includes: IndexSet<String>,
+    origins: HashMap<FileId, Origin>,
+}
+
+impl<'a, 'c> HeaderWriter<'a, 'c> {
+    fn origin(&mut self, decl_id: CDeclId) -> Origin {
+        let ast_context = &self.t.ast_context;
+        let file = match ast_context.file_id(&ast_context[decl_id]) {
+            Some(file) => file,
+            None => return Origin::System(None),
+        };
+        if let Some(origin) = self.origins.get(&file) {
+            return origin.clone();
+        }
+
+        let origin = if file == self.t.main_file {
+            Origin::Main
+        } else {
+            match (
+                system_include(ast_context, file),
+                ast_context.get_file_path(file),
+            ) {
+                (None, Some(path)) => Origin::Header(path.to_owned()),
+                (include, _) => Origin::System(include),
+            }
+        };
+        self.origins.insert(file, origin.clone());
+        origin
+    }
+
+    /// Note that the header refers to a type declaration.
+    fn note_use(&mut self, decl_id: CDeclId) {
+        if let Origin::System(Some(include)) = self.origin(decl_id) {
+            self.includes.insert(include);
+        }
+    }
+
+    /// The typedef naming a record or enum, if the two were translated as one
+    /// Rust type.
+    fn prenamed_typedef(&self, decl_id: CDeclId) -> Option<String> {
+        let ast_context = &self.t.ast_context;
+        let (&typedef_id, _) = ast_context
+            .prenamed_decls
+            .iter()
+            .find(|&(_, &record_id)| record_id == decl_id)?;
+        ast_context[typedef_id].kind.get_name().cloned()
+    }
+
+    fn record_keyword(&self, decl_id: CRecordId) -> &'static str {
+        match self.t.ast_context[decl_id].kind {
+            CDeclKind::Union { .. } => "union",
+            _ => "struct",
+        }
+    }
+
+    /// The original spelling of a record, and the header's own spelling of it.
+    /// For records named by a typedef, these are the typedef names.
+    fn record_names(&mut self, decl_id: CRecordId, rust_name: &str) -> (String, String) {
+        let keyword = self.record_keyword(decl_id);
+        let renamed = matches!(self.origin(decl_id), Origin::Header(_));
+        let own = |name: &str| {
+            if renamed {
+                format!("C2RUST_NAME({})", name)
+            } else {
+                name.to_owned()
+            }
+        };
+        if let Some(typedef) = self.prenamed_typedef(decl_id) {
+            let own = own(&typedef);
+            return (typedef, own);
+        }
+        match self.t.ast_context[decl_id].kind.get_name() {
+            Some(tag) => (
+                format!("{} {}", keyword, tag),
+                format!("{} {}", keyword, own(tag)),
+            ),
+            None => {
+                let own = format!(
+                    "{} {}",
+                    keyword,
+                    own(&format!("{}_{}", self.module, rust_name))
+                );
+                (own.clone(), own)
+            }
+        }
+    }
+
+    /// The original spelling of an enum, if it has one the header can use.
+    fn enum_name(&mut self, decl_id: CEnumId) -> Option<String> {
+        // The header can't define enums without constants
+        if !matches!(self.origin(decl_id), Origin::System(_))
+            && !self.items.enum_constants.contains_key(&decl_id)
+        {
+            return None;
+        }
+        self.prenamed_typedef(decl_id).or_else(|| {
+            let tag = self.t.ast_context[decl_id].kind.get_name()?;
+            Some(format!("enum {}", tag))
+        })
+    }
+
+    /// Declare `declarator` to have type `ty`, which is `const` if `is_const`.
+    fn declare(
+        &mut self,
+        ty: &Type,
+        declarator: &str,
+        is_const: bool,
+        spelling: Spelling,
+    ) -> Result<String, String> {
+        let qualifier = if is_const { "const " } else { "" };
+        match ty {
+            Type::Ptr(ptr) => {
+                let mut inner = format!("*{}{}", qualifier, declarator);
+                if let Type::Array(_) = *ptr.elem {
+                    inner = format!("({})", inner);
+                }
+                self.declare(&ptr.elem, &inner, ptr.const_token.is_some(), spelling)
+            }
+            Type::Array(array) => {
+                let len = match &array.len {
+                    Expr::Lit(ExprLit {
+                        lit: Lit::Int(len), ..
+                    }) => len.base10_digits().to_owned(),
+                    len => return Err(format!("array length `{}`", pprust::expr_to_string(len))),
+                };
+                let inner = format!("{}[{}]", declarator, len);
+                self.declare(&array.elem, &inner, is_const, spelling)
+            }
+            Type::BareFn(f) => self.declare_fn_ptr(f, declarator, is_const, spelling),
+            Type::Paren(paren) => self.declare(&paren.elem, declarator, is_const, spelling),
+            Type::Tuple(tuple) if tuple.elems.is_empty() => Ok(base("void", declarator, false)),
+            Type::Never(_) => Ok(base("void", declarator, false)),
+            Type::Path(path) => self.declare_path(&path.path, declarator, is_const, spelling),
+            _ => Err("a type with no C equivalent".to_owned()),
+        }
+    }
+
+    fn declare_path(
+        &mut self,
+        path: &syn::Path,
+        declarator: &str,
+        is_const: bool,
+        spelling: Spelling,
+    ) -> Result<String, String> {
+        let last = path.segments.last().ok_or("an empty path")?;
+        if last.ident == "Option" {
+            if let PathArguments::AngleBracketed(args) = &last.arguments {
+                if let Some(GenericArgument::Type(Type::BareFn(f))) = args.args.first() {
+                    return self.declare_fn_ptr(f, declarator, is_const, spelling);
+                }
+            }
+        }
+
+        let segments = path
+            .segments
+            .iter()
+            .map(|segment| segment.ident.to_string())
+            .collect::<Vec<_>>();
+        let segments = segments.iter().map(String::as_str).collect::<Vec<_>>();
+        let unknown = || format!("`{}`", pprust::path_to_string(path));
+        let name = match segments[..] {
+            ["libc", name] => libc_name(name).ok_or_else(unknown)?,
+            ["core", "ffi", "VaList" | "VaListImpl"] => "va_list",
+            ["c2rust_x87", "f80"] | ["f128", "f128"] => "long double",
+            [name] => match primitive_name(name) {
+                Some(name) => name,
+                None => return self.declare_named(name, declarator, is_const, spelling),
+            },
+            _ => return Err(unknown()),
+        };
+        Ok(base(name, declarator, is_const))
+    }
+
+    /// Declare `declarator` to have a translated type named `name`.
+    fn declare_named(
+        &mut self,
+        name: &str,
+        declarator: &str,
+        is_const: bool,
+        spelling: Spelling,
+    ) -> Result<String, String> {
+        let items = self.items;
+        match items.types.get(name) {
+            Some(&HeaderType::Record { decl_id, .. }) => {
+                self.note_use(decl_id);
+                let (original, own) = self.record_names(decl_id, name);
+                let name = match spelling {
+                    Spelling::Original => original,
+                    Spelling::Own => own,
+                };
+                Ok(base(&name, declarator, is_const))
+            }
+            Some(HeaderType::Typedef { decl_id, ty }) => {
+                self.note_use(*decl_id);
+                let system = matches!(self.origin(*decl_id), Origin::System(_));
+                match self.t.ast_context[*decl_id].kind.get_name() {
+                    Some(name) if spelling == Spelling::Original || system => {
+                        Ok(base(name, declarator, is_const))
+                    }
+                    _ => self.declare(ty, declarator, is_const, spelling),
+                }
+            }
+            Some(HeaderType::Enum { decl_id, ty }) => {
+                self.note_use(*decl_id);
+                match self.enum_name(*decl_id) {
+                    Some(name) if spelling == Spelling::Original => {
+                        Ok(base(&name, declarator, is_const))
+                    }
+                    _ => self.declare(ty, declarator, is_const, spelling),
+                }
+            }
+            None => Err(format!("`{}`", name)),
+        }
+    }
+
+    fn declare_fn_ptr(
+        &mut self,
+        f: &TypeBareFn,
+        declarator: &str,
+        is_const: bool,
+        spelling: Spelling,
+    ) -> Result<String, String> {
+        let qualifier = if is_const { "const " } else { "" };
+        let params = f.inputs.iter().map(|arg| {
+            let name = arg.name.as_ref().map(|(name, _)| name.to_string());
+            (name, &arg.ty)
+        });
+        let params = self.params(params, f.variadic.is_some(), spelling)?;
+        let inner = format!("(*{}{})({})", qualifier, declarator, params);
+        self.declare_return(&f.output, &inner, spelling)
+    }
+
+    fn declare_return(
+        &mut self,
+        output: &ReturnType,
+        declarator: &str,
+        spelling: Spelling,
+    ) -> Result<String, String> {
+        match output {
+            ReturnType::Default => Ok(base("void", declarator, false)),
+            ReturnType::Type(_, ty) => self.declare(ty, declarator, false, spelling),
+        }
+    }
+
+    /// A parameter list; the `...` parameter of variadic definitions is left
+    /// out in favor of `variadic`.
+    fn params<'t>(
+        &mut self,
+        params: impl Iterator<Item = (Option<String>, &'t Type)>,
+        variadic: bool,
+        spelling: Spelling,
+    ) -> Result<String, String> {
+        let mut decls = params
+            .filter(|(_, ty)| !matches!(ty, Type::Verbatim(_)))
+            .map(|(name, ty)| self.declare(ty, name.as_deref().unwrap_or(""), false, spelling))
+            .collect::<Result<Vec<_>, _>>()?;
+        if variadic {
+            decls.push("...".to_owned());
+        } else if decls.is_empty() {
+            decls.push("void".to_owned());
+        }
+        Ok(decls.join(", "))
+    }
+
+    /// Add the definition of a type to `header`.
+    fn define(
+        &mut self,
+        rust_name: &str,
+        ty: &HeaderType,
+        header: &mut CHeader,
+    ) -> Result<(), String> {
+        match *ty {
+            HeaderType::Record {
+                decl_id,
+                ref item,
+                align,
+                ref field_names,
+            } => {
+                let path = match self.origin(decl_id) {
+                    Origin::System(_) => return Ok(()),
+                    Origin::Main => None,
+                    Origin::Header(path) => Some(path),
+                };
+                let keyword = self.record_keyword(decl_id);
+                let (original, own) = self.record_names(decl_id, rust_name);
+                let (forward, head, tail) = if self.prenamed_typedef(decl_id).is_some() {
+                    (
+                        format!("typedef {} {} {};", keyword, own, own),
+                        format!("typedef {} {}", keyword, own),
+                        format!(" {}", own),
+                    )
+                } else {
+                    (format!("{};", own), own.clone(), String::new())
+                };
+                header.forward.insert(original.clone(), forward);
+
+                let (attrs, fields) = match item.as_deref() {
+                    Some(Item::Struct(s)) => (&s.attrs, s.fields.iter().collect::<Vec<_>>()),
+                    Some(Item::Union(u)) => (&u.attrs, u.fields.named.iter().collect()),
+                    _ => return Ok(()),
+                };
+                let mut lines = vec![];
+                let mut checked_fields = vec![];
+                for field in fields {
+                    let rust_name = field
+                        .ident
+                        .as_ref()
+                        .map(ToString::to_string)
+                        .unwrap_or_default();
+                    let bitfields = bitfields_of(&field.attrs);
+                    if bitfields.is_empty() {
+                        let name = field_names.get(&rust_name).unwrap_or(&rust_name);
+                        lines.push(self.declare(&field.ty, name, false, Spelling::Own)? + ";");
+                        if field_names.contains_key(&rust_name) {
+                            checked_fields.push(name.clone());
+                        }
+                        continue;
+                    }
+                    // Rebuild the bitfields of the group, with unnamed ones
+                    // for the unused bits between them
+                    let mut next_bit = 0;
+                    for (name, ty, start, end) in bitfields {
+                        let ty = syn::parse_str::<Type>(&ty).map_err(|e| e.to_string())?;
+                        let ty = self.declare(&ty, "", false, Spelling::Own)?;
+                        if start > next_bit {
+                            lines.push(format!("{} : {};", ty, start - next_bit));
+                        }
+                        let name = field_names.get(&name).unwrap_or(&name);
+                        lines.push(format!("{} {} : {};", ty, name, end - start + 1));
+                        next_bit = end + 1;
+                    }
+                }
+
+                let (packed, repr_align) = repr_of(attrs);
+                let mut attributes = vec![];
+                if packed == Some(1) {
+                    attributes.push("packed".to_owned());
+                }
+                if let Some(align) = align.or(repr_align) {
+                    attributes.push(format!("aligned({})", align));
+                }
+                let attributes = if attributes.is_empty() {
+                    String::new()
+                } else {
+                    format!(" __attribute__(({}))", attributes.join(", "))
+                };
+                let mut text = vec![format!("{} {{", head)];
+                text.extend(lines.iter().map(|line| format!("    {}", line)));
+                text.push(format!("}}{}{};", attributes, tail));
+                if let Some(pack) = packed.filter(|&pack| pack > 1) {
+                    text.insert(0, format!("#pragma pack(push, {})", pack));
+                    text.push("#pragma pack(pop)".to_owned());
+                }
+                header.types.insert(original.clone(), text.join("\n"));
+
+                if let Some(path) = path {
+                    let mut checks = vec![
+                        format!(
+                            "_Static_assert(sizeof({}) == sizeof({}), \"size of {}\");",
+                            original, own, original
+                        ),
+                        format!(
+                            "_Static_assert(_Alignof({}) == _Alignof({}), \"alignment of {}\");",
+                            original, own, original
+                        ),
+                    ];
+                    checks.extend(checked_fields.iter().map(|field| {
+                        format!(
+                            "_Static_assert(offsetof({}, {}) == offsetof({}, {}), \"offset of {}.{}\");",
+                            original, field, own, field, original, field
+                        )
+                    }));
+                    header
+                        .checks
+                        .extend(checks.into_iter().map(|check| (path.clone(), check)));
+                }
+            }
+
+            HeaderType::Typedef { decl_id, ref ty } => {
+                if let Origin::System(_) = self.origin(decl_id) {
+                    return Ok(());
+                }
+                let name = match self.t.ast_context[decl_id].kind.get_name() {
+                    Some(name) => name.clone(),
+                    None => return Ok(()),
+                };
+                let text = self.declare(ty, &name, false, Spelling::Original)?;
+                header.types.insert(name, format!("typedef {};", text));
+            }
+
+            HeaderType::Enum { decl_id, ref ty } => {
+                let path = match self.origin(decl_id) {
+                    Origin::System(_) => return Ok(()),
+                    Origin::Main => None,
+                    Origin::Header(path) => Some(path),
+                };
+                let items = self.items;
+                let constants = match items.enum_constants.get(&decl_id) {
+                    Some(constants) => constants,
+                    None => return Ok(()),
+                };
+                let typedef = self.prenamed_typedef(decl_id);
+                let tag = self.t.ast_context[decl_id].kind.get_name().cloned();
+                let key = self
+                    .enum_name(decl_id)
+                    .unwrap_or_else(|| format!("enum {}_{}", self.module, rust_name));
+
+                let mut head = match &tag {
+                    Some(tag) => format!("enum {}", tag),
+                    None => "enum".to_owned(),
+                };
+                if typedef.is_some() {
+                    head = format!("typedef {}", head);
+                }
+                let mut text = vec![format!("{} {{", head)];
+                let last = constants.len() - 1;
+                for (i, (name, value)) in constants.iter().enumerate() {
+                    let comma = if i == last { "" } else { "," };
+                    let value = pprust::expr_to_string(value);
+                    text.push(format!("    {} = {}{}", name, value, comma));
+                }
+                match &typedef {
+                    Some(typedef) => text.push(format!("}} {};", typedef)),
+                    None => text.push("};".to_owned()),
+                }
+                if path.is_some() {
+                    // The original header defines it when checking
+                    text.insert(0, "#ifndef C2RUST_CHECK".to_owned());
+                    text.push("#endif".to_owned());
+                }
+                header.types.insert(key.clone(), text.join("\n"));
+
+                if let (Some(path), Some(name)) = (path, self.enum_name(decl_id)) {
+                    let repr = self.declare(ty, "", false, Spelling::Own)?;
+                    let check = format!(
+                        "_Static_assert(sizeof({}) == sizeof({}), \"size of {}\");",
+                        name, repr, name
+                    );
+                    header.checks.push((path, check));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Add the declaration of an exported function or static to `header`.
+    fn declare_value(&mut self, item: &Item, header: &mut CHeader) -> Result<(), String> {
+        let symbol = match exported_symbol(item) {
+            Some(symbol) => symbol,
+            None => return Ok(()),
+        };
+        let text = match item {
+            Item::Fn(f) => {
+                let params = f.sig.inputs.iter().filter_map(|arg| match arg {
+                    FnArg::Typed(arg) => {
+                        let name = match &*arg.pat {
+                            Pat::Ident(pat) => Some(pat.ident.to_string()),
+                            _ => None,
+                        };
+                        Some((name, &*arg.ty))
+                    }
+                    FnArg::Receiver(_) => None,
+                });
+                let params = self.params(params, f.sig.variadic.is_some(), Spelling::Original)?;
+                let declarator = format!("{}({})", symbol, params);
+                self.declare_return(&f.sig.output, &declarator, Spelling::Original)? + ";"
+            }
+            Item::Static(s) => {
+                let is_const = s.mutability.is_none();
+                let text = self.declare(&s.ty, &symbol, is_const, Spelling::Original)?;
+                format!("extern {};", text)
+            }
+            _ => return Ok(()),
+        };
+        header.values.insert(symbol, text);
+        Ok(())
+    }
+}
+
+impl<'c> Translation<'c> {
+    /// Keep what the C header needs from a converted declaration.
+    pub fn add_to_c_header(&self, decl_id: CDeclId, converted: &ConvertedDecl) {
+        if !self.tcfg.emit_c_header {
+            return;
+        }
+        let items = match converted {
+            ConvertedDecl::Item(item) => slice::from_ref(item),
+            ConvertedDecl::Items(items) => items.as_slice(),
+            ConvertedDecl::ForeignItem(_) | ConvertedDecl::NoItem => &[],
+        };
+        let rust_name = || self.type_converter.borrow().resolve_decl_name(decl_id);
+        let type_item = || {
+            items.iter().find_map(|item| match &**item {
+                Item::Type(item) => Some(item.ty.clone()),
+                _ => None,
+            })
+        };
+
+        let mut header = self.c_header_items.borrow_mut();
+        match self.ast_context[decl_id].kind {
+            CDeclKind::Struct { ref fields, .. } | CDeclKind::Union { ref fields, .. } => {
+                let name = match rust_name() {
+                    Some(name) => name,
+                    None => return,
+                };
+                // Aligned records are split into a wrapper struct with the
+                // alignment and an inner struct with the fields
+                let mut records = items
+                    .iter()
+                    .map(|item| &**item)
+                    .filter(|item| matches!(item, Item::Struct(_) | Item::Union(_)));
+                let (item, align) = match (records.next(), records.next()) {
+                    (Some(Item::Struct(outer)), Some(inner)) => {
+                        (Some(inner), repr_of(&outer.attrs).1)
+                    }
+                    (record, _) => (record, None),
+                };
+                let item = item.cloned().map(Box::new);
+                let field_names = fields
+                    .iter()
+                    .flatten()
+                    .filter_map(|&field_id| {
+                        let c_name = match self.ast_context[field_id].kind {
+                            CDeclKind::Field { ref name, .. } if !name.is_empty() => name.clone(),
+                            _ => return None,
+                        };
+                        let rust_name = self
+                            .type_converter
+                            .borrow()
+                            .resolve_field_name(Some(decl_id), field_id)?;
+                        Some((rust_name, c_name))
+                    })
+                    .collect();
+                header.types.insert(
+                    name,
+                    HeaderType::Record {
+                        decl_id,
+                        item,
+                        align,
+                        field_names,
+                    },
+                );
+            }
+            CDeclKind::Typedef { .. } => {
+                if let (Some(name), Some(ty)) = (rust_name(), type_item()) {
+                    header
+                        .types
+                        .insert(name, HeaderType::Typedef { decl_id, ty });
+                }
+            }
+            CDeclKind::Enum { .. } => {
+                if let (Some(name), Some(ty)) = (rust_name(), type_item()) {
+                    header.types.insert(name, HeaderType::Enum { decl_id, ty });
+                }
+            }
+            CDeclKind::EnumConstant { ref name, .. } => {
+                let value = items.iter().find_map(|item| match &**item {
+                    Item::Const(item) => Some(item.expr.clone()),
+                    _ => None,
+                });
+                if let Some(value) = value {
+                    let enum_id = self.ast_context.parents[&decl_id];
+                    header
+                        .enum_constants
+                        .entry(enum_id)
+                        .or_default()
+                        .push((name.clone(), value));
+                }
+            }
+            CDeclKind::Function { .. } | CDeclKind::Variable { .. } => {
+                let exported = items.iter().filter(|item| exported_symbol(item).is_some());
+                header.values.extend(exported.cloned());
+            }
+            _ => {}
+        }
+    }
+
+    /// The C header for the declarations collected by
+    /// [`Translation::add_to_c_header`]. Declarations that can't be spelled
+    /// in C are left out with a warning.
+    pub fn c_header(&self) -> CHeader {
+        let items = self.c_header_items.borrow();
+        let module = self
+            .ast_context
+            .get_file_path(self.main_file)
+            .and_then(Path::file_stem)
+            .map_or("c2rust".into(), |stem| {
+                stem.to_string_lossy().replace('-', "_")
+            });
+        let mut writer = HeaderWriter {
+            t: self,
+            items: &items,
+            module,
+            includes: IndexSet::new(),
+            origins: HashMap::new(),
+        };
+
+        let mut header = CHeader::default();
+        for (name, ty) in &items.types {
+            if let Err(e) = writer.define(name, ty, &mut header) {
+                warn!(
+                    "Leaving {} out of the C header: cannot spell {} in C",
+                    name, e
+                );
+            }
+        }
+        for item in &items.values {
+            if let Err(e) = writer.declare_value(item, &mut header) {
+                let name = item_ident(item).map_or(String::new(), ToString::to_string);
+                warn!(
+                    "Leaving {} out of the C header: cannot spell {} in C",
+                    name, e
+                );
+            }
+        }
+        header.includes = writer.includes;
+        header
+    }
+}
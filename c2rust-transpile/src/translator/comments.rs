@@ -1,22 +1,70 @@
-use super::Translation;
+use super::{foreign_item_attrs, item_attrs, ConvertedDecl, Translation};
 use crate::c_ast::iterators::{NodeVisitor, SomeId};
-use crate::c_ast::{CDeclId, CDeclKind, CommentContext, SrcLoc, TypedAstContext};
+use crate::c_ast::{CDeclId, CDeclKind, CommentContext, Located, SrcLoc, TypedAstContext};
 use crate::rust_ast::comment_store::CommentStore;
 use crate::rust_ast::{pos_to_span, SpanExt};
+use c2rust_ast_builder::{mk, Builder};
 use log::debug;
+use once_cell::sync::Lazy;
 use proc_macro2::Span;
+use regex::Regex;
 use std::collections::{HashMap, HashSet};
+use std::mem;
 
 struct CommentLocator<'c> {
     ast_context: &'c TypedAstContext,
     comment_context: &'c CommentContext,
     comment_store: &'c mut CommentStore,
     spans: &'c mut HashMap<SomeId, Span>,
+    docs: &'c mut HashMap<CDeclId, Vec<String>>,
     top_decls: &'c HashSet<CDeclId>,
     last_id: Option<SomeId>,
 }
 
 impl<'c> CommentLocator<'c> {
+    /// Is `decl_id` translated to an item or field that can carry doc comments?
+    fn is_documented_decl(&self, decl_id: CDeclId) -> bool {
+        use CDeclKind::*;
+        match self.ast_context[decl_id].kind {
+            Function { .. }
+            | Typedef { .. }
+            | Struct { .. }
+            | Union { .. }
+            | Enum { .. }
+            | EnumConstant { .. }
+            | Field { .. }
+            | MacroObject { .. } => true,
+            Variable {
+                has_static_duration,
+                has_thread_duration,
+                ..
+            } => has_static_duration || has_thread_duration,
+            _ => false,
+        }
+    }
+
+    /// Is `decl_id` a struct field or enum constant, which may be documented by
+    /// a comment following it on the same line?
+    fn is_member_decl(&self, decl_id: CDeclId) -> bool {
+        matches!(
+            self.ast_context[decl_id].kind,
+            CDeclKind::Field { .. } | CDeclKind::EnumConstant { .. }
+        )
+    }
+
+    fn add_docs(&mut self, decl_id: CDeclId, comments: &[String]) {
+        let lines = doxygen_to_rustdoc(comments);
+        if lines.is_empty() {
+            return;
+        }
+        debug!("Attaching doc comment {:?} to {:?}", lines, decl_id);
+        let docs = self.docs.entry(decl_id).or_default();
+        if !docs.is_empty() {
+            docs.push(String::new());
+        }
+        docs.extend(lines);
+    }
+
     /// Check for comments starting on the same line but after the end of the
     /// last node and before the end of the current node.
     fn check_last_for_trailing(&mut self, cur_loc: SrcLoc) {
@@ -26,12 +74,14 @@ impl<'c> CommentLocator<'c> {
             // statements and comma-separated exprs, but we don't support
             // comments after comma-separated exprs yet.
             Some(SomeId::Stmt(id)) => SomeId::Stmt(id),
+            // Trailing comments on fields and enum constants become their docs.
+            Some(SomeId::Decl(id)) if self.is_member_decl(id) => SomeId::Decl(id),
             _ => return,
         };
         if let Some(last_loc) = self.ast_context.get_src_loc(last_id) {
-            // TODO: handle Mixed comments (code before and after the
-            // comment on the same line
-            if cur_loc.line == last_loc.end_line {
+            // Mixed comments (code before and after the comment on the same
+            // line) can only be attached to members, as doc attributes.
+            if cur_loc.line == last_loc.end_line && matches!(last_id, SomeId::Stmt(_)) {
                 return;
             }
 
@@ -40,10 +90,11 @@ impl<'c> CommentLocator<'c> {
                 .peek_next_comment_on_line(last_loc.end(), self.ast_context)
             {
                 if comment.loc.unwrap().end() < cur_loc {
-                    let existing_pos = self.spans.get(&last_id).map(|span| span.lo());
-                    if let Some(pos) = self.comment_store.extend_existing_comments(
+                    if let SomeId::Decl(decl_id) = last_id {
+                        self.add_docs(decl_id, &[comment.kind.clone()]);
+                    } else if let Some(pos) = self.comment_store.extend_existing_comments(
                         &[comment.kind.clone()],
-                        existing_pos,
+                        self.spans.get(&last_id).map(|span| span.lo()),
                         //CommentStyle::Trailing,
                     ) {
                         debug!(
@@ -85,7 +136,7 @@ impl<'c> NodeVisitor for CommentLocator<'c> {
 
             let comments = self
                 .comment_context
-                .get_comments_before(loc.begin(), self.ast_context);
+                .get_located_comments_before(loc.begin(), self.ast_context);
             if let SomeId::Decl(decl_id) = id {
                 let decl_kind = &self.ast_context[decl_id].kind;
                 if let CDeclKind::NonCanonicalDecl { canonical_decl } = decl_kind {
@@ -94,6 +145,14 @@ impl<'c> NodeVisitor for CommentLocator<'c> {
                     id = SomeId::Decl(*canonical_decl);
                 }
             }
+            let comments = match id {
+                SomeId::Decl(decl_id) if self.is_documented_decl(decl_id) => {
+                    let (comments, docs) = split_doc_comments(comments, loc.begin_line);
+                    self.add_docs(decl_id, &docs);
+                    comments
+                }
+                _ => comments.into_iter().map(|c| c.kind).collect(),
+            };
             if let Some(existing) = self.spans.get(&id) {
                 let new_pos = self.comment_store.extend_existing_comments(
                     &comments,
@@ -134,6 +193,10 @@ impl<'c> NodeVisitor for CommentLocator<'c> {
             }
         }
         if let Some(loc) = self.ast_context.get_src_loc(id) {
+            // Check if we have a comment before the end of this node that we
+            // need to attach to the end of the last node.
+            self.check_last_for_trailing(loc.end());
+
             let comments = self
                 .comment_context
                 .get_comments_before(loc.end(), self.ast_context);
@@ -145,10 +208,6 @@ impl<'c> NodeVisitor for CommentLocator<'c> {
                 let span = self.spans.entry(id).or_insert_with(Span::call_site);
                 *span = span.with_hi(pos);
             }
-
-            // Check if we have a comment before the end of this node that we
-            // need to attach to the end of the last node.
-            self.check_last_for_trailing(loc.end());
         }
 
         self.last_id = Some(id);
@@ -161,6 +220,7 @@ impl<'c> Translation<'c> {
         let mut top_decls: HashSet<CDeclId> =
             self.ast_context.c_decls_top.iter().copied().collect();
         let mut spans: HashMap<SomeId, Span> = HashMap::new();
+        let mut docs: HashMap<CDeclId, Vec<String>> = HashMap::new();
         for decl_id in &self.ast_context.c_decls_top {
             top_decls.remove(decl_id);
             let mut visitor = CommentLocator {
@@ -168,15 +228,351 @@ impl<'c> Translation<'c> {
                 comment_context: &self.comment_context,
                 comment_store: &mut self.comment_store.borrow_mut(),
                 spans: &mut spans,
+                docs: &mut docs,
                 top_decls: &top_decls,
                 last_id: None,
             };
             visitor.visit_tree(&self.ast_context, SomeId::Decl(*decl_id));
         }
         self.spans = spans;
+        self.docs = docs;
     }
 
     pub fn get_span(&self, id: SomeId) -> Option<Span> {
         self.spans.get(&id).copied()
     }
+
+    /// Fold the doc comments written on typedefs that were merged into the
+    /// declaration they name (see `TypedAstContext::prenamed_decls`) into the
+    /// docs of that declaration, ahead of its own.
+    pub fn merge_typedef_docs(&mut self) {
+        let mut merged: HashMap<CDeclId, Vec<String>> = HashMap::new();
+        for (typedef_id, subdecl_id) in &self.ast_context.prenamed_decls {
+            if let Some(docs) = self.docs.get(typedef_id) {
+                let lines = merged.entry(*subdecl_id).or_default();
+                if !lines.is_empty() {
+                    lines.push(String::new());
+                }
+                lines.extend(docs.iter().cloned());
+            }
+        }
+        for (decl_id, mut lines) in merged {
+            if let Some(docs) = self.docs.remove(&decl_id) {
+                lines.push(String::new());
+                lines.extend(docs);
+            }
+            self.docs.insert(decl_id, lines);
+        }
+    }
+
+    /// Get the rustdoc lines for `decl_id`, including those written on a
+    /// typedef that was merged into it (see [`Self::merge_typedef_docs`]).
+    fn doc_lines(&self, decl_id: CDeclId) -> Vec<&str> {
+        self.docs
+            .get(&decl_id)
+            .map_or_else(Vec::new, |docs| docs.iter().map(String::as_str).collect())
+    }
+
+    /// Start a builder carrying the doc comments of `decl_id` as `#[doc]`
+    /// attributes, which are printed as `///` comments.
+    pub fn mk_with_docs(&self, decl_id: CDeclId) -> Builder {
+        self.doc_lines(decl_id)
+            .into_iter()
+            .fold(mk(), |builder, line| {
+                builder.str_attr("doc", doc_attr_text(line))
+            })
+    }
+
+    /// Prepend the doc comments of `decl_id` to the attributes of its
+    /// translation.
+    pub fn add_doc_attrs(&self, decl_id: CDeclId, converted: &mut ConvertedDecl) {
+        let attrs = match converted {
+            ConvertedDecl::Item(item) => item_attrs(item),
+            // The first item is the one named after the declaration
+            ConvertedDecl::Items(items) => items.first_mut().and_then(|item| item_attrs(item)),
+            ConvertedDecl::ForeignItem(item) => foreign_item_attrs(item),
+            ConvertedDecl::NoItem => None,
+        };
+        if let Some(attrs) = attrs {
+            let other_attrs = mem::replace(attrs, self.mk_with_docs(decl_id).into_attrs());
+            attrs.extend(other_attrs);
+        }
+    }
+}
+
+/// Text of the `#[doc]` attribute for a rustdoc line, which the printer emits
+/// directly after `///`.
+fn doc_attr_text(line: &str) -> String {
+    if line.is_empty() {
+        String::new()
+    } else {
+        format!(" {}", line)
+    }
+}
+
+/// Split `comments` preceding a declaration starting on `decl_line` into
+/// plain comments and the run of comments directly above the declaration,
+/// which document it. A blank line or other code ends the documenting run.
+fn split_doc_comments(
+    comments: Vec<Located<String>>,
+    decl_line: u64,
+) -> (Vec<String>, Vec<String>) {
+    let mut next_line = decl_line;
+    let mut split = comments.len();
+    for (i, comment) in comments.iter().enumerate().rev() {
+        let loc = match comment.loc {
+            Some(loc) => loc,
+            None => break,
+        };
+        if loc.end_line + 1 < next_line {
+            break;
+        }
+        next_line = loc.begin_line;
+        split = i;
+    }
+    let mut comments: Vec<String> = comments.into_iter().map(|c| c.kind).collect();
+    let docs = comments.split_off(split);
+    (comments, docs)
+}
+
+/// Strip the comment markers from a C comment, returning its lines of text.
+fn comment_text(comment: &str) -> Vec<String> {
+    let comment = comment.trim();
+    let lines: Vec<&str> = if let Some(body) = comment.strip_prefix("/*") {
+        let body = body.strip_suffix("*/").unwrap_or(body);
+        // Doxygen markers: `/**`, `/*!` and `/**<`
+        let body = body
+            .strip_prefix(|c: char| c == '*' || c == '!')
+            .unwrap_or(body);
+        body.strip_prefix('<').unwrap_or(body).lines().collect()
+    } else {
+        comment
+            .lines()
+            .map(|line| {
+                let line = line.trim_start();
+                let line = line.strip_prefix("//").unwrap_or(line);
+                // Doxygen markers: `///`, `//!` and `///<`
+                let line = line
+                    .strip_prefix(|c: char| c == '/' || c == '!')
+                    .unwrap_or(line);
+                line.strip_prefix('<').unwrap_or(line)
+            })
+            .collect()
+    };
+
+    let mut lines: Vec<String> = lines
+        .into_iter()
+        .map(|line| {
+            let line = line.trim_end();
+            let trimmed = line.trim_start();
+            if trimmed.chars().all(|c| c == '*') {
+                // Blank continuation line or a banner of asterisks
+                String::new()
+            } else if let Some(rest) = trimmed.strip_prefix('*') {
+                rest.strip_prefix(' ').unwrap_or(rest).to_owned()
+            } else {
+                line.strip_prefix(' ').unwrap_or(line).to_owned()
+            }
+        })
+        .collect();
+    while lines.last().map_or(false, String::is_empty) {
+        lines.pop();
+    }
+    let leading_blanks = lines.iter().take_while(|line| line.is_empty()).count();
+    lines.drain(..leading_blanks);
+    lines
+}
+
+/// A Doxygen section that is gathered up and emitted after the description.
+enum DocSection {
+    Description,
+    Argument,
+    Returns,
+}
+
+/// Convert C comments, possibly using Doxygen commands, into rustdoc
+/// markdown lines.
+///
+/// `@brief` and `@details` become the plain description, `@param` and
+/// `@return`/`@retval` become `# Arguments` and `# Returns` sections, and
+/// `@code`/`@endcode` become fenced `c` code blocks, which rustdoc does not try
+/// to compile. Other text is kept, minus indentation that markdown would
+/// otherwise treat as a (Rust) code block.
+fn doxygen_to_rustdoc(comments: &[String]) -> Vec<String> {
+    static COMMAND: Lazy<Regex> =
+        Lazy::new(|| Regex::new(r"^[@\\]([A-Za-z]+)(\[[^\]]*\])?(\{[^}]*\})?\s*(.*)$").unwrap());
+    static INLINE_COMMAND: Lazy<Regex> =
+        Lazy::new(|| Regex::new(r"[@\\](p|c|a|e|em|b)\s+([^\s,.;:)]+)").unwrap());
+    let command = &*COMMAND;
+    let inline_command = &*INLINE_COMMAND;
+    let inline = |text: &str| -> String {
+        inline_command
+            .replace_all(text, |caps: &regex::Captures| match &caps[1] {
+                "p" | "c" => format!("`{}`", &caps[2]),
+                "b" => format!("**{}**", &caps[2]),
+                _ => format!("*{}*", &caps[2]),
+            })
+            .into_owned()
+    };
+
+    let mut description: Vec<String> = vec![];
+    let mut arguments: Vec<String> = vec![];
+    let mut returns: Vec<String> = vec![];
+    let mut in_code = false;
+
+    for (i, comment) in comments.iter().enumerate() {
+        if i > 0 && !description.is_empty() {
+            description.push(String::new());
+        }
+        let mut section = DocSection::Description;
+        for line in comment_text(comment) {
+            let trimmed = line.trim();
+            let caps = command.captures(trimmed);
+            let cmd = caps.as_ref().map(|caps| caps.get(1).unwrap().as_str());
+
+            if in_code {
+                if matches!(cmd, Some("endcode") | Some("endverbatim")) {
+                    description.push("```".into());
+                    in_code = false;
+                } else {
+                    description.push(line);
+                }
+                continue;
+            }
+
+            let (cmd, rest) = match (cmd, &caps) {
+                (Some(cmd), Some(caps)) => (cmd, inline(caps.get(4).unwrap().as_str())),
+                _ => {
+                    // Plain text continues the current section; a blank line
+                    // ends it.
+                    let text = inline(trimmed);
+                    let target = match section {
+                        DocSection::Argument if !text.is_empty() => arguments.last_mut(),
+                        DocSection::Returns if !text.is_empty() => returns.last_mut(),
+                        _ => None,
+                    };
+                    match target {
+                        Some(entry) => {
+                            entry.push(' ');
+                            entry.push_str(&text);
+                        }
+                        None => {
+                            section = DocSection::Description;
+                            description.push(text);
+                        }
+                    }
+                    continue;
+                }
+            };
+
+            section = DocSection::Description;
+            match cmd {
+                "brief" | "short" | "details" => description.push(rest),
+                "param" | "tparam" => {
+                    let (name, text) = rest
+                        .split_once(char::is_whitespace)
+                        .unwrap_or((rest.as_str(), ""));
+                    arguments.push(format!("* `{}` - {}", name, text.trim_start()));
+                    section = DocSection::Argument;
+                }
+                "return" | "returns" | "result" => {
+                    returns.push(rest);
+                    section = DocSection::Returns;
+                }
+                "retval" => {
+                    let (value, text) = rest
+                        .split_once(char::is_whitespace)
+                        .unwrap_or((rest.as_str(), ""));
+                    returns.push(format!("* `{}` - {}", value, text.trim_start()));
+                    section = DocSection::Returns;
+                }
+                "note" | "remark" | "remarks" => description.push(format!("**Note:** {}", rest)),
+                "warning" | "attention" => description.push(format!("**Warning:** {}", rest)),
+                "deprecated" => description.push(format!("**Deprecated:** {}", rest)),
+                "see" | "sa" => description.push(format!("See also: {}", rest)),
+                "code" | "verbatim" => {
+                    description.push("```c".into());
+                    in_code = true;
+                }
+                // Grouping and structural commands have no rustdoc equivalent
+                "file" | "defgroup" | "ingroup" | "addtogroup" | "name" | "fn" | "struct"
+                | "union" | "enum" | "var" | "def" | "typedef" => {}
+                _ => description.push(inline(trimmed)),
+            }
+        }
+    }
+    if in_code {
+        description.push("```".into());
+    }
+
+    let mut lines = description;
+    for (heading, entries) in [("# Arguments", arguments), ("# Returns", returns)] {
+        if entries.is_empty() {
+            continue;
+        }
+        lines.extend([String::new(), heading.to_owned(), String::new()]);
+        lines.extend(entries);
+    }
+
+    // Collapse runs of blank lines left behind by dropped commands
+    lines.dedup_by(|a, b| a.is_empty() && b.is_empty());
+    while lines.first().map_or(false, String::is_empty) {
+        lines.remove(0);
+    }
+    while lines.last().map_or(false, String::is_empty) {
+        lines.pop();
+    }
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn convert(comments: &[&str]) -> Vec<String> {
+        let comments = comments.iter().map(|c| c.to_string()).collect::<Vec<_>>();
+        doxygen_to_rustdoc(&comments)
+    }
+
+    #[test]
+    fn plain_comments() {
+        assert_eq!(convert(&["// a line comment"]), ["a line comment"]);
+        assert_eq!(convert(&["/* first\n * second\n */"]), ["first", "second"]);
+        assert_eq!(convert(&["///< trailing"]), ["trailing"]);
+        assert_eq!(convert(&["/**< trailing */"]), ["trailing"]);
+    }
+
+    #[test]
+    fn doxygen_sections() {
+        let doc = convert(&["/**\n\
+             * @brief Adds two numbers.\n\
+             *\n\
+             * @param a the first\n\
+             *        number\n\
+             * @param[out] b the \\p second\n\
+             * @return the sum\n\
+             * @code\n\
+             *   add(1, 2);\n\
+             * @endcode\n\
+             */"]);
+        assert_eq!(
+            doc,
+            [
+                "Adds two numbers.",
+                "",
+                "```c",
+                "  add(1, 2);",
+                "```",
+                "",
+                "# Arguments",
+                "",
+                "* `a` - the first number",
+                "* `b` - the `second`",
+                "",
+                "# Returns",
+                "",
+                "the sum",
+            ]
+        );
+    }
 }
@@ -0,0 +1,277 @@
+//! This module provides the API generated for structs that end in a flexible
+//! array member, and the recognizer that rewrites `malloc` calls sized for
+//! such a struct to go through that API.
+//!
+//! A struct like `struct S { size_t len; T data[]; }` is translated with a
+//! trailing `data: [T; 0]` field, so the Rust struct is exactly the C header.
+//! On top of that we emit:
+//!
+//! ```ignore
+//! impl S {
+//!     pub unsafe fn new_with_len(len: usize) -> *mut S;
+//!     pub unsafe fn tail<'a>(this: *const S, len: usize) -> &'a [T];
+//!     pub unsafe fn tail_mut<'a>(this: *mut S, len: usize) -> &'a mut [T];
+//! }
+//! ```
+//!
+//! `new_with_len` allocates with `calloc`, so the result can still be passed
+//! to `free` and `realloc` by the rest of the translated program. `tail` and
+//! `tail_mut` take the struct by raw pointer and reach the array through
+//! `addr_of!`, since a reference to the struct only covers its header. In a
+//! packed struct, they panic if the array isn't aligned for `T`.
+
+use super::*;
+
+impl<'c> Translation<'c> {
+    /// Get the flexible array member of a struct, along with its element type.
+    ///
+    /// Only `T data[]` and `T data[0]` count here; the older `T data[1]`
+    /// idiom changes `sizeof` of the struct and is left alone.
+    pub fn flexible_array_member(&self, decl_id: CRecordId) -> Option<(CFieldId, CTypeId)> {
+        let fields = match self.ast_context[decl_id].kind {
+            CDeclKind::Struct {
+                fields: Some(ref fields),
+                ..
+            } => fields,
+            _ => return None,
+        };
+        let field_id = *fields.last()?;
+        let typ = match self.ast_context[field_id].kind {
+            CDeclKind::Field { typ, .. } => typ,
+            _ => return None,
+        };
+        match self.ast_context.resolve_type(typ.ctype).kind {
+            CTypeKind::IncompleteArray(elt) | CTypeKind::ConstantArray(elt, 0) => {
+                Some((field_id, elt))
+            }
+            _ => None,
+        }
+    }
+
+    /// Build the `impl` block holding `new_with_len`, `tail` and `tail_mut` for a
+    /// struct with a flexible array member.
+    pub fn convert_flexible_array_api(
+        &self,
+        decl_id: CRecordId,
+        name: &str,
+    ) -> TranslationResult<Option<Box<Item>>> {
+        let (field_id, elt) = match self.flexible_array_member(decl_id) {
+            Some(member) => member,
+            None => return Ok(None),
+        };
+        let field_name = self
+            .type_converter
+            .borrow()
+            .resolve_field_name(Some(decl_id), field_id)
+            .ok_or_else(|| format_err!("Missing name for flexible array member"))?;
+        let self_ty = mk().path_ty(vec![name]);
+        let elt_ty = self.convert_type(elt)?;
+        let len_arg = || mk().arg(mk().path_ty(vec!["usize"]), mk().ident_pat("len"));
+        let len = || mk().path_expr(vec!["len"]);
+
+        // calloc(1, size_of::<S>() + len * size_of::<T>()) as *mut S
+        let header_size = self.compute_size_of_ty(self_ty.clone())?.to_expr();
+        let elt_size = self.compute_size_of_ty(elt_ty.clone())?.to_expr();
+        let size = mk().binary_expr(
+            BinOp::Add(Default::default()),
+            header_size,
+            mk().binary_expr(BinOp::Mul(Default::default()), len(), elt_size),
+        );
+        let alloc = mk().call_expr(
+            mk().path_expr(vec!["libc", "calloc"]),
+            vec![mk().lit_expr(mk().int_unsuffixed_lit(1)), size],
+        );
+        let self_ptr_ty = mk().mutbl().ptr_ty(self_ty.clone());
+        let new_with_len = mk().pub_().unsafe_().method_impl_item(
+            mk().fn_decl(
+                "new_with_len",
+                vec![len_arg()],
+                None,
+                ReturnType::Type(Default::default(), self_ptr_ty.clone()),
+            ),
+            mk().block(vec![mk().expr_stmt(mk().cast_expr(alloc, self_ptr_ty))]),
+        );
+
+        // A reference to the struct would only grant access to its header, so
+        // the array is reached from the raw pointer. A field of a packed struct
+        // may be misaligned, so the slice needs checking.
+        let is_packed = self.ast_context.is_packed_struct_decl(decl_id);
+        let elt_align = self.compute_align_of_type(elt, false)?.to_expr();
+        let tail_fn = |fn_name: &str, mutbl: Mutability| {
+            let (addr_of, from_raw_parts) = match mutbl {
+                Mutability::Immutable => ("addr_of", "from_raw_parts"),
+                Mutability::Mutable => ("addr_of_mut", "from_raw_parts_mut"),
+            };
+            // core::ptr::addr_of!((*this).data) as *const T
+            let field = mk().field_expr(
+                mk().paren_expr(mk().unary_expr(
+                    UnOp::Deref(Default::default()),
+                    mk().path_expr(vec!["this"]),
+                )),
+                field_name.as_str(),
+            );
+            let field_ptr = {
+                use syn::__private::ToTokens;
+                mk().mac_expr(mk().mac(
+                    mk().abs_path(vec!["core", "ptr", addr_of]),
+                    field.into_token_stream(),
+                    MacroDelimiter::Paren(Default::default()),
+                ))
+            };
+            let ptr_ty = mk().set_mutbl(mutbl).ptr_ty(elt_ty.clone());
+            let mut stmts = vec![mk().local_stmt(Box::new(mk().local(
+                mk().ident_pat("ptr"),
+                None,
+                Some(mk().cast_expr(field_ptr, ptr_ty)),
+            )))];
+            if is_packed {
+                // if ptr.align_offset(align_of::<T>()) != 0 { panic!(..) }
+                let misaligned = mk().binary_expr(
+                    BinOp::Ne(Default::default()),
+                    mk().method_call_expr(
+                        mk().path_expr(vec!["ptr"]),
+                        "align_offset",
+                        vec![elt_align.clone()],
+                    ),
+                    mk().lit_expr(mk().int_unsuffixed_lit(0)),
+                );
+                let panic = self.panic("misaligned flexible array member");
+                stmts.push(mk().expr_stmt(mk().ifte_expr(
+                    misaligned,
+                    mk().block(vec![mk().semi_stmt(panic)]),
+                    None,
+                )));
+            }
+            let slice = mk().call_expr(
+                mk().abs_path_expr(vec!["core", "slice", from_raw_parts]),
+                vec![mk().path_expr(vec!["ptr"]), len()],
+            );
+            stmts.push(mk().expr_stmt(slice));
+            let this_arg = mk().arg(
+                mk().set_mutbl(mutbl).ptr_ty(mk().path_ty(vec!["Self"])),
+                mk().ident_pat("this"),
+            );
+            let ret_ty = mk()
+                .set_mutbl(mutbl)
+                .ref_lt_ty("a", mk().slice_ty(elt_ty.clone()));
+            mk().pub_()
+                .unsafe_()
+                .generic_over(mk().lt_param("a"))
+                .method_impl_item(
+                    mk().fn_decl(
+                        fn_name,
+                        vec![this_arg, len_arg()],
+                        None,
+                        ReturnType::Type(Default::default(), ret_ty),
+                    ),
+                    mk().block(stmts),
+                )
+        };
+        let tail = tail_fn("tail", Mutability::Immutable);
+        let tail_mut = tail_fn("tail_mut", Mutability::Mutable);
+
+        Ok(Some(
+            mk().impl_item(self_ty, vec![new_with_len, tail, tail_mut]),
+        ))
+    }
+
+    /// Recognize `malloc(sizeof(S) + n * sizeof(T))`, where `S` ends in a flexible
+    /// array of `T`, and translate it to `S::new_with_len(n)`.
+    ///
+    /// Returns `None` for every other call so that it is translated as usual.
+    pub fn convert_flexible_array_alloc(
+        &self,
+        ctx: ExprContext,
+        func: CExprId,
+        args: &[CExprId],
+        call_ty: CQualTypeId,
+    ) -> TranslationResult<Option<WithStmts<Box<Expr>>>> {
+        let is_malloc = match self.ast_context[func].kind {
+            CExprKind::DeclRef(_, decl_id, _) => matches!(
+                self.ast_context[decl_id].kind,
+                CDeclKind::Function { ref name, .. } if name == "malloc"
+            ),
+            _ => false,
+        };
+        let size = match *args {
+            [size] if is_malloc => size,
+            _ => return Ok(None),
+        };
+
+        let (decl_id, len) = match self.match_flexible_array_size(size) {
+            Some(matched) => matched,
+            None => return Ok(None),
+        };
+        // Aligned structs are split into an outer and inner struct, and only
+        // get the plain translation
+        if self.ast_context.has_inner_struct_decl(decl_id) {
+            return Ok(None);
+        }
+        let name = self
+            .type_converter
+            .borrow()
+            .resolve_decl_name(decl_id)
+            .ok_or_else(|| format_err!("Missing name for flexible array struct"))?;
+        if let Some(cur_file) = *self.cur_file.borrow() {
+            self.add_import(cur_file, decl_id, &name);
+        }
+        let call_ty = self.convert_type(call_ty.ctype)?;
+
+        let len = self.convert_expr(ctx.used(), len)?;
+        Ok(Some(len.map(|len| {
+            let alloc = mk().call_expr(
+                mk().path_expr(vec![name, "new_with_len".to_owned()]),
+                vec![cast_int(len, "usize", false)],
+            );
+            mk().cast_expr(alloc, call_ty)
+        })))
+    }
+
+    /// Match `sizeof(S) + n * sizeof(T)` in any operand order, returning `S`
+    /// and the expression for `n`.
+    fn match_flexible_array_size(&self, size: CExprId) -> Option<(CRecordId, CExprId)> {
+        let (lhs, rhs) = match self.ast_context.resolve_expr(size).1 {
+            CExprKind::Binary(_, c_ast::BinOp::Add, lhs, rhs, _, _) => (*lhs, *rhs),
+            _ => return None,
+        };
+        let sizeof_struct = |expr_id| match self.sizeof_type(expr_id) {
+            Some(ty) => match self.ast_context.resolve_type(ty).kind {
+                CTypeKind::Struct(decl_id) => self
+                    .flexible_array_member(decl_id)
+                    .map(|(_, elt)| (decl_id, elt)),
+                _ => None,
+            },
+            None => None,
+        };
+        let ((decl_id, elt), tail) = match (sizeof_struct(lhs), sizeof_struct(rhs)) {
+            (Some(s), _) => (s, rhs),
+            (None, Some(s)) => (s, lhs),
+            (None, None) => return None,
+        };
+
+        let (lhs, rhs) = match self.ast_context.resolve_expr(tail).1 {
+            CExprKind::Binary(_, c_ast::BinOp::Multiply, lhs, rhs, _, _) => (*lhs, *rhs),
+            _ => return None,
+        };
+        let is_sizeof_elt = |expr_id| {
+            self.sizeof_type(expr_id).map_or(false, |ty| {
+                self.ast_context.resolve_type_id(ty) == self.ast_context.resolve_type_id(elt)
+            })
+        };
+        if is_sizeof_elt(rhs) {
+            Some((decl_id, lhs))
+        } else if is_sizeof_elt(lhs) {
+            Some((decl_id, rhs))
+        } else {
+            None
+        }
+    }
+
+    /// The type measured by a `sizeof` expression, looking through casts.
+    fn sizeof_type(&self, expr_id: CExprId) -> Option<CTypeId> {
+        match self.ast_context.resolve_expr(expr_id).1 {
+            CExprKind::UnaryType(_, UnTypeOp::SizeOf, _, arg_ty) => Some(arg_ty.ctype),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,424 @@
+//! This module rewrites calls to libc string and memory functions into safe
+//! Rust, driven by the [`LibcMapping`](crate::LibcMapping) table.
+//!
+//! A call is only rewritten when the translator can see what its pointer
+//! arguments point to: a known array such as `buf` or `s->buf`, or a string
+//! literal. Lengths must be provably in bounds, e.g. `sizeof(buf)`,
+//! `n * sizeof(buf[0])` for a constant `n`, or a constant byte count for byte
+//! arrays. Every other call is translated as a plain `libc::` call.
+
+use super::*;
+use crate::libc_mapping::LibcRewrite;
+
+/// An argument of a mapped libc call whose pointee the translator knows.
+#[derive(Debug, Clone, Copy)]
+enum KnownBuffer<'a> {
+    /// A decayed array lvalue with no side effects, along with its element
+    /// type and length.
+    Array {
+        expr: CExprId,
+        elem: CTypeId,
+        len: usize,
+    },
+    /// A string literal's bytes, up to its first NUL.
+    Str(&'a [u8]),
+}
+
+impl<'c> Translation<'c> {
+    /// Translate a call through the libc mapping table, if it is enabled, the
+    /// callee is in it, and the arguments allow it.
+    ///
+    /// Returns `None` for every other call so that it is translated as usual.
+    pub fn convert_mapped_libc_call(
+        &self,
+        ctx: ExprContext,
+        func: CExprId,
+        args: &[CExprId],
+        call_ty: CQualTypeId,
+    ) -> TranslationResult<Option<WithStmts<Box<Expr>>>> {
+        let mapping = match self.tcfg.libc_mapping {
+            Some(ref mapping) if !ctx.is_static => mapping,
+            _ => return Ok(None),
+        };
+        // Only calls to external functions; a program may define its own `strlen`
+        let name = match self.ast_context[func].kind {
+            CExprKind::DeclRef(_, decl_id, _) => match self.ast_context[decl_id].kind {
+                CDeclKind::Function {
+                    ref name,
+                    body: None,
+                    ..
+                } => name.strip_prefix("__builtin_").unwrap_or(name),
+                _ => return Ok(None),
+            },
+            _ => return Ok(None),
+        };
+        let rewrite = match mapping.get(name) {
+            Some(rewrite) if rewrite.min_args() <= args.len() => rewrite,
+            _ => return Ok(None),
+        };
+
+        let result = match rewrite {
+            // `memcpy` and `memset` return their destination, so only
+            // statement calls are rewritten
+            LibcRewrite::Copy { .. } | LibcRewrite::Fill { .. } if ctx.is_used() => None,
+            LibcRewrite::Copy {
+                dst,
+                src,
+                len,
+                overlapping,
+            } => self.convert_mapped_copy(ctx, args[dst], args[src], args[len], overlapping)?,
+            LibcRewrite::Fill { dst, value, len } => {
+                self.convert_mapped_fill(ctx, args[dst], args[value], args[len])?
+            }
+            LibcRewrite::Strlen { s } => self.convert_mapped_strlen(ctx, args[s], call_ty)?,
+            LibcRewrite::Strcmp { lhs, rhs } => {
+                self.convert_mapped_strcmp(ctx, args[lhs], args[rhs], call_ty)?
+            }
+        };
+        match result {
+            Some(result) if ctx.is_unused() => {
+                let result = result.and_then(|expr| -> TranslationResult<_> {
+                    Ok(WithStmts::new(
+                        vec![mk().semi_stmt(expr)],
+                        self.panic_or_err(&format!("{} not used", name)),
+                    ))
+                })?;
+                Ok(Some(result))
+            }
+            result => Ok(result),
+        }
+    }
+
+    /// `dst[..n].copy_from_slice(&src[..n])` between arrays, or a
+    /// `copy_nonoverlapping` of a string literal with a proven length.
+    fn convert_mapped_copy(
+        &self,
+        ctx: ExprContext,
+        dst: CExprId,
+        src: CExprId,
+        len: CExprId,
+        overlapping: bool,
+    ) -> TranslationResult<Option<WithStmts<Box<Expr>>>> {
+        let (dst, dst_elem, dst_len) = match self.known_buffer(dst) {
+            Some(KnownBuffer::Array { expr, elem, len }) => (expr, elem, len),
+            _ => return Ok(None),
+        };
+        let n = match self.proven_elem_count(len, dst_elem) {
+            Some(n) if n <= dst_len => n,
+            _ => return Ok(None),
+        };
+
+        match self.known_buffer(src) {
+            Some(KnownBuffer::Array { expr, elem, len })
+                if n <= len
+                    && self.ast_context.resolve_type_id(elem)
+                        == self.ast_context.resolve_type_id(dst_elem)
+                    && !self.is_same_lvalue(dst, expr)
+                    && !(overlapping && !self.are_distinct_variables(dst, expr)) =>
+            {
+                let dst_val = self.convert_expr(ctx.used(), dst)?;
+                let src_val = self.convert_expr(ctx.used(), expr)?;
+                let n = || mk().lit_expr(mk().int_unsuffixed_lit(n as u128));
+                let copy = dst_val.and_then(|dst| -> TranslationResult<_> {
+                    Ok(src_val.map(|src| {
+                        let dst = mk().index_expr(dst, mk().range_expr(None, Some(n())));
+                        let src = mk().index_expr(src, mk().range_expr(None, Some(n())));
+                        mk().method_call_expr(dst, "copy_from_slice", vec![mk().addr_of_expr(src)])
+                    }))
+                })?;
+                Ok(Some(copy))
+            }
+            // A literal's bytes are `u8`, so copy through pointers when `dst`
+            // holds `c_char`s
+            Some(KnownBuffer::Str(bytes))
+                if self.is_byte_type(dst_elem) && n <= bytes.len() + 1 =>
+            {
+                let elem_ty = self.convert_type(dst_elem)?;
+                let mut bytes = bytes.to_owned();
+                bytes.push(0);
+                let src = mk().cast_expr(
+                    mk().method_call_expr(mk().lit_expr(bytes), "as_ptr", vec![]),
+                    mk().ptr_ty(elem_ty),
+                );
+                let mut copy = self.convert_expr(ctx.used(), dst)?.map(|dst| {
+                    mk().call_expr(
+                        mk().abs_path_expr(vec!["core", "ptr", "copy_nonoverlapping"]),
+                        vec![
+                            src,
+                            mk().method_call_expr(dst, "as_mut_ptr", vec![]),
+                            mk().lit_expr(mk().int_unsuffixed_lit(n as u128)),
+                        ],
+                    )
+                });
+                copy.set_unsafe();
+                Ok(Some(copy))
+            }
+            _ => Ok(None),
+        }
+    }
+
+    /// `dst[..n].fill(value)`, for any byte value into a byte array, or for
+    /// zero into an array of numbers.
+    fn convert_mapped_fill(
+        &self,
+        ctx: ExprContext,
+        dst: CExprId,
+        value: CExprId,
+        len: CExprId,
+    ) -> TranslationResult<Option<WithStmts<Box<Expr>>>> {
+        let (dst, elem, dst_len) = match self.known_buffer(dst) {
+            Some(KnownBuffer::Array { expr, elem, len }) => (expr, elem, len),
+            _ => return Ok(None),
+        };
+        let n = match self.proven_elem_count(len, elem) {
+            Some(n) if n <= dst_len => n,
+            _ => return Ok(None),
+        };
+        let value = match self.const_int(value) {
+            Some(value) => value,
+            None => return Ok(None),
+        };
+        let elem_kind = &self.ast_context.resolve_type(elem).kind;
+        let value = if self.is_byte_type(elem) {
+            mk().cast_expr(
+                mk().lit_expr(mk().int_unsuffixed_lit((value & 0xff) as u128)),
+                self.convert_type(elem)?,
+            )
+        } else if value == 0 && elem_kind.is_integral_type() && !elem_kind.is_bool() {
+            mk().lit_expr(mk().int_unsuffixed_lit(0))
+        } else if value == 0 && elem_kind.is_floating_type() && !self.is_soft_long_double(elem) {
+            mk().lit_expr(mk().float_unsuffixed_lit("0."))
+        } else {
+            return Ok(None);
+        };
+
+        let dst_val = self.convert_expr(ctx.used(), dst)?;
+        Ok(Some(dst_val.map(|dst| {
+            let n = mk().lit_expr(mk().int_unsuffixed_lit(n as u128));
+            let dst = mk().index_expr(dst, mk().range_expr(None, Some(n)));
+            mk().method_call_expr(dst, "fill", vec![value])
+        })))
+    }
+
+    /// `CStr::to_bytes().len()` of a string literal or byte array.
+    fn convert_mapped_strlen(
+        &self,
+        ctx: ExprContext,
+        s: CExprId,
+        call_ty: CQualTypeId,
+    ) -> TranslationResult<Option<WithStmts<Box<Expr>>>> {
+        let s = match self.convert_known_cstr(ctx, s)? {
+            Some(s) => s,
+            None => return Ok(None),
+        };
+        let ty = self.convert_type(call_ty.ctype)?;
+        Ok(Some(s.map(|s| {
+            let bytes = mk().method_call_expr(s, "to_bytes", vec![]);
+            mk().cast_expr(mk().method_call_expr(bytes, "len", vec![]), ty)
+        })))
+    }
+
+    /// Compare two `CStr`s, which like `strcmp` compares bytes as unsigned.
+    fn convert_mapped_strcmp(
+        &self,
+        ctx: ExprContext,
+        lhs: CExprId,
+        rhs: CExprId,
+        call_ty: CQualTypeId,
+    ) -> TranslationResult<Option<WithStmts<Box<Expr>>>> {
+        let (lhs, rhs) = match (
+            self.convert_known_cstr(ctx, lhs)?,
+            self.convert_known_cstr(ctx, rhs)?,
+        ) {
+            (Some(lhs), Some(rhs)) => (lhs, rhs),
+            _ => return Ok(None),
+        };
+        let ty = self.convert_type(call_ty.ctype)?;
+        let cmp = lhs.and_then(|lhs| -> TranslationResult<_> {
+            Ok(rhs.map(|rhs| mk().cast_expr(mk().method_call_expr(lhs, "cmp", vec![rhs]), ty)))
+        })?;
+        Ok(Some(cmp))
+    }
+
+    /// A `&CStr` for a string literal, or for a byte array (which, as in C,
+    /// must hold a NUL).
+    fn convert_known_cstr(
+        &self,
+        ctx: ExprContext,
+        expr: CExprId,
+    ) -> TranslationResult<Option<WithStmts<Box<Expr>>>> {
+        let cstr = |method| mk().abs_path_expr(vec!["core", "ffi", "CStr", method]);
+        match self.known_buffer(expr) {
+            Some(KnownBuffer::Str(bytes)) => {
+                let mut bytes = bytes.to_owned();
+                bytes.push(0);
+                let cstr = mk().call_expr(cstr("from_bytes_with_nul"), vec![mk().lit_expr(bytes)]);
+                Ok(Some(WithStmts::new_val(mk().method_call_expr(
+                    cstr,
+                    "unwrap",
+                    vec![],
+                ))))
+            }
+            Some(KnownBuffer::Array { expr, elem, .. }) if self.is_byte_type(elem) => {
+                let c_char = mk().path_ty(vec!["libc", "c_char"]);
+                let mut cstr = self.convert_expr(ctx.used(), expr)?.map(|array| {
+                    let ptr = mk().method_call_expr(array, "as_ptr", vec![]);
+                    let ptr = mk().cast_expr(ptr, mk().ptr_ty(c_char));
+                    mk().call_expr(cstr("from_ptr"), vec![ptr])
+                });
+                cstr.set_unsafe();
+                Ok(Some(cstr))
+            }
+            _ => Ok(None),
+        }
+    }
+
+    /// Look through the casts and decay of a pointer argument to the array or
+    /// string literal it points to.
+    fn known_buffer(&self, expr_id: CExprId) -> Option<KnownBuffer> {
+        let mut expr_id = expr_id;
+        let mut decayed = false;
+        loop {
+            match self.ast_context[expr_id].kind {
+                CExprKind::ImplicitCast(_, e, CastKind::ArrayToPointerDecay, _, _) => {
+                    decayed = true;
+                    expr_id = e;
+                }
+                CExprKind::ImplicitCast(_, e, CastKind::BitCast | CastKind::NoOp, _, _)
+                | CExprKind::ExplicitCast(_, e, CastKind::BitCast | CastKind::NoOp, _, _)
+                | CExprKind::Paren(_, e) => expr_id = e,
+                _ => break,
+            }
+        }
+        if !decayed {
+            return None;
+        }
+
+        if let CExprKind::Literal(_, CLiteral::String(ref bytes, 1)) =
+            self.ast_context[expr_id].kind
+        {
+            let len = bytes.iter().position(|&b| b == 0).unwrap_or(bytes.len());
+            return Some(KnownBuffer::Str(&bytes[..len]));
+        }
+        if !self.is_pure_lvalue(expr_id) {
+            return None;
+        }
+        let ty = self.ast_context[expr_id].kind.get_type()?;
+        match self.ast_context.resolve_type(ty).kind {
+            CTypeKind::ConstantArray(elem, len) => Some(KnownBuffer::Array {
+                expr: expr_id,
+                elem,
+                len,
+            }),
+            _ => None,
+        }
+    }
+
+    /// Whether an lvalue is a variable or a chain of field accesses, which we
+    /// can translate without side effects.
+    fn is_pure_lvalue(&self, expr_id: CExprId) -> bool {
+        match self.ast_context[expr_id].kind {
+            CExprKind::DeclRef(..) => true,
+            CExprKind::Member(_, base, _, _, _)
+            | CExprKind::Paren(_, base)
+            | CExprKind::ImplicitCast(_, base, CastKind::LValueToRValue, _, _) => {
+                self.is_pure_lvalue(base)
+            }
+            _ => false,
+        }
+    }
+
+    /// Whether two lvalues are spelled the same, in which case Rust won't let
+    /// us borrow one mutably while borrowing the other.
+    fn is_same_lvalue(&self, a: CExprId, b: CExprId) -> bool {
+        let strip = |mut expr_id: CExprId| loop {
+            match self.ast_context[expr_id].kind {
+                CExprKind::Paren(_, e)
+                | CExprKind::ImplicitCast(_, e, CastKind::LValueToRValue, _, _) => expr_id = e,
+                _ => return expr_id,
+            }
+        };
+        match (
+            &self.ast_context[strip(a)].kind,
+            &self.ast_context[strip(b)].kind,
+        ) {
+            (&CExprKind::DeclRef(_, a, _), &CExprKind::DeclRef(_, b, _)) => a == b,
+            (
+                &CExprKind::Member(_, base_a, field_a, _, _),
+                &CExprKind::Member(_, base_b, field_b, _, _),
+            ) => field_a == field_b && self.is_same_lvalue(base_a, base_b),
+            _ => false,
+        }
+    }
+
+    /// Whether two array lvalues name different variables, so they can't overlap.
+    fn are_distinct_variables(&self, a: CExprId, b: CExprId) -> bool {
+        match (&self.ast_context[a].kind, &self.ast_context[b].kind) {
+            (&CExprKind::DeclRef(_, a, _), &CExprKind::DeclRef(_, b, _)) => a != b,
+            _ => false,
+        }
+    }
+
+    /// The number of `elem`s covered by a byte count, if it is a constant we
+    /// can see through: `sizeof(array of elem)`, `n * sizeof(elem)`, or any
+    /// constant for byte-sized elements.
+    fn proven_elem_count(&self, len: CExprId, elem: CTypeId) -> Option<usize> {
+        let elem = self.ast_context.resolve_type_id(elem);
+        let sizeof_elems = |expr_id: CExprId| -> Option<usize> {
+            match *self.ast_context.resolve_expr(expr_id).1 {
+                CExprKind::UnaryType(_, UnTypeOp::SizeOf, _, arg_ty) => {
+                    let arg_ty = self.ast_context.resolve_type_id(arg_ty.ctype);
+                    if arg_ty == elem {
+                        return Some(1);
+                    }
+                    match self.ast_context.resolve_type(arg_ty).kind {
+                        CTypeKind::ConstantArray(e, n)
+                            if self.ast_context.resolve_type_id(e) == elem =>
+                        {
+                            Some(n)
+                        }
+                        _ => None,
+                    }
+                }
+                _ => None,
+            }
+        };
+
+        if let Some(n) = sizeof_elems(len) {
+            return Some(n);
+        }
+        if let CExprKind::Binary(_, c_ast::BinOp::Multiply, lhs, rhs, _, _) =
+            *self.ast_context.resolve_expr(len).1
+        {
+            let count = |expr_id| usize::try_from(self.const_int(expr_id)?).ok();
+            return match (sizeof_elems(lhs), sizeof_elems(rhs)) {
+                (Some(1), _) => count(rhs),
+                (_, Some(1)) => count(lhs),
+                _ => None,
+            };
+        }
+        if self.is_byte_type(elem) {
+            return usize::try_from(self.const_int(len)?).ok();
+        }
+        None
+    }
+
+    /// The value of a non-negative integer constant expression.
+    fn const_int(&self, expr_id: CExprId) -> Option<u64> {
+        match *self.ast_context.resolve_expr(expr_id).1 {
+            CExprKind::Literal(_, CLiteral::Integer(value, _)) => Some(value),
+            CExprKind::ConstantExpr(_, _, Some(ConstIntExpr::U(value))) => Some(value),
+            CExprKind::ConstantExpr(_, _, Some(ConstIntExpr::I(value))) => {
+                u64::try_from(value).ok()
+            }
+            _ => None,
+        }
+    }
+
+    fn is_byte_type(&self, ty: CTypeId) -> bool {
+        matches!(
+            self.ast_context.resolve_type(ty).kind,
+            CTypeKind::Char | CTypeKind::SChar | CTypeKind::UChar
+        )
+    }
+}
@@ -0,0 +1,276 @@
+//! This module handles C's `long double`, which has no native Rust equivalent.
+//!
+//! Depending on [`LongDoubleRepr`], `long double` becomes `f64`, `c2rust_x87::f80`
+//! or `f128::f128`. The latter two are library types: they can't be `as`-cast,
+//! written as literals, or passed through C varargs, so conversions go through
+//! their constructors and `to_*` methods instead.
+
+use super::*;
+
+/// `printf`-like functions, along with the index of their format argument.
+const PRINTF_FUNCTIONS: &[(&str, usize)] = &[
+    ("printf", 0),
+    ("fprintf", 1),
+    ("dprintf", 1),
+    ("sprintf", 1),
+    ("asprintf", 1),
+    ("snprintf", 2),
+];
+
+impl<'c> Translation<'c> {
+    /// Whether `long double` is translated to a library type rather than `f64`.
+    pub fn has_soft_long_double(&self) -> bool {
+        self.tcfg.long_double != LongDoubleRepr::F64
+    }
+
+    /// Whether `ty` is a `long double` that is translated to a library type.
+    pub fn is_soft_long_double(&self, ty: CTypeId) -> bool {
+        self.has_soft_long_double()
+            && matches!(
+                self.ast_context.resolve_type(ty).kind,
+                CTypeKind::LongDouble
+            )
+    }
+
+    /// Record the dependency on the crate providing `long double`, if any.
+    pub fn use_long_double_crate(&self) {
+        match self.tcfg.long_double {
+            LongDoubleRepr::F64 => {}
+            LongDoubleRepr::X87 => self.use_crate(ExternCrate::C2RustX87),
+            LongDoubleRepr::F128 => self.use_crate(ExternCrate::F128),
+        }
+    }
+
+    /// Path to an associated item of the `long double` type, e.g. `f128::f128::new`.
+    fn long_double_item(&self, name: &str) -> Box<Expr> {
+        self.use_long_double_crate();
+        match self.tcfg.long_double {
+            LongDoubleRepr::F64 => mk().abs_path_expr(vec!["core", "f64", name]),
+            LongDoubleRepr::X87 => mk().path_expr(vec!["c2rust_x87", "f80", name]),
+            LongDoubleRepr::F128 => mk().path_expr(vec!["f128", "f128", name]),
+        }
+    }
+
+    /// A constant of the `long double` type, such as `ZERO` or `NAN`.
+    pub fn long_double_const(&self, name: &str) -> Box<Expr> {
+        match (self.tcfg.long_double, name) {
+            (LongDoubleRepr::F64, "ZERO") => mk().lit_expr(mk().float_unsuffixed_lit("0.")),
+            _ => self.long_double_item(name),
+        }
+    }
+
+    /// Convert a primitive number to `long double` by calling `method`, which is
+    /// `new` or `from`.
+    pub fn long_double_from(&self, method: &str, val: Box<Expr>) -> Box<Expr> {
+        match self.tcfg.long_double {
+            LongDoubleRepr::F64 => mk().cast_expr(val, mk().path_ty(vec!["f64"])),
+            _ => mk().call_expr(self.long_double_item(method), vec![val]),
+        }
+    }
+
+    /// A `long double` literal, given its decimal spelling.
+    pub fn long_double_lit(&self, lit: &str) -> Box<Expr> {
+        match self.tcfg.long_double {
+            LongDoubleRepr::F64 => mk().lit_expr(mk().float_lit(lit, "f64")),
+            // Parse the literal at full precision rather than rounding it to f64 first
+            LongDoubleRepr::X87 => {
+                mk().call_expr(self.long_double_item("from_lit"), vec![mk().lit_expr(lit)])
+            }
+            LongDoubleRepr::F128 => mk().call_expr(
+                self.long_double_item("new"),
+                vec![mk().lit_expr(mk().float_unsuffixed_lit(lit))],
+            ),
+        }
+    }
+
+    /// Cast a `long double` translated to a library type to some other int or
+    /// float type.
+    pub fn long_double_cast_to(
+        &self,
+        val: WithStmts<Box<Expr>>,
+        target_ty_ctype: &CTypeKind,
+    ) -> TranslationResult<WithStmts<Box<Expr>>> {
+        // f80 has inherent conversion methods, f128 uses num_traits
+        if self.tcfg.long_double == LongDoubleRepr::F128 {
+            self.use_crate(ExternCrate::NumTraits);
+
+            self.with_cur_file_item_store(|item_store| {
+                item_store.add_use(vec!["num_traits".into()], "ToPrimitive");
+            });
+        }
+        let to_method_name = match target_ty_ctype {
+            CTypeKind::Float => "to_f32",
+            CTypeKind::Double => "to_f64",
+            CTypeKind::Char | CTypeKind::SChar => "to_i8",
+            CTypeKind::UChar => "to_u8",
+            CTypeKind::Short => "to_i16",
+            CTypeKind::UShort => "to_u16",
+            CTypeKind::Int => "to_i32",
+            CTypeKind::UInt => "to_u32",
+            CTypeKind::Long => "to_i64",
+            CTypeKind::ULong => "to_u64",
+            CTypeKind::LongLong => "to_i64",
+            CTypeKind::ULongLong => "to_u64",
+            CTypeKind::Int128 => "to_i128",
+            CTypeKind::UInt128 => "to_u128",
+            _ => {
+                return Err(format_err!(
+                    "Tried casting long double to unsupported type: {:?}",
+                    target_ty_ctype
+                )
+                .into());
+            }
+        };
+
+        Ok(val.map(|val| {
+            let to_call = mk().method_call_expr(val, to_method_name, Vec::new());
+
+            mk().method_call_expr(to_call, "unwrap", Vec::new())
+        }))
+    }
+
+    /// Translate a call to a `printf`-like function that passes `long double`
+    /// arguments.
+    ///
+    /// C passes variadic `long double`s in a way Rust can't reproduce, so these
+    /// arguments are converted to `double` and any `L` length modifiers on
+    /// floating point conversions are dropped from the format string. This only
+    /// works for literal format strings without positional arguments; `None` is
+    /// returned for every other call so that it is translated as usual.
+    pub fn convert_long_double_printf(
+        &self,
+        ctx: ExprContext,
+        func: CExprId,
+        args: &[CExprId],
+    ) -> TranslationResult<Option<WithStmts<Box<Expr>>>> {
+        let format_index = match self.ast_context[func].kind {
+            CExprKind::DeclRef(_, decl_id, _) => match self.ast_context[decl_id].kind {
+                CDeclKind::Function { ref name, .. } => PRINTF_FUNCTIONS
+                    .iter()
+                    .find(|(printf, _)| printf == name)
+                    .map(|&(_, index)| index),
+                _ => None,
+            },
+            _ => None,
+        };
+        let format_index = match format_index {
+            Some(index) if index < args.len() => index,
+            _ => return Ok(None),
+        };
+
+        let is_long_double = |arg: CExprId| {
+            self.ast_context[arg].kind.get_type().map_or(false, |ty| {
+                matches!(
+                    self.ast_context.resolve_type(ty).kind,
+                    CTypeKind::LongDouble
+                )
+            })
+        };
+        if !args[format_index + 1..]
+            .iter()
+            .any(|&arg| is_long_double(arg))
+        {
+            return Ok(None);
+        }
+
+        let format = match self.ast_context.resolve_expr(args[format_index]).1 {
+            CExprKind::Literal(_, CLiteral::String(ref bytes, 1)) => bytes,
+            _ => return Ok(None),
+        };
+        let format = &format[..format.iter().position(|&b| b == 0).unwrap_or(format.len())];
+        let mut format = match strip_long_double_conversions(format) {
+            Some(format) => format,
+            None => return Ok(None),
+        };
+        format.push(0);
+
+        let func = self.convert_expr(ctx.used(), func)?;
+        let mut arg_ctx = ctx.used();
+        arg_ctx.decay_ref = DecayRef::Yes;
+        let args = args
+            .iter()
+            .enumerate()
+            .map(|(i, &arg)| {
+                if i == format_index {
+                    let format_ty = self.ast_context[arg]
+                        .kind
+                        .get_type()
+                        .ok_or_else(|| format_err!("bad format string type"))?;
+                    let format =
+                        mk().method_call_expr(mk().lit_expr(format.clone()), "as_ptr", vec![]);
+                    Ok(WithStmts::new_val(
+                        mk().cast_expr(format, self.convert_type(format_ty)?),
+                    ))
+                } else if i > format_index && self.has_soft_long_double() && is_long_double(arg) {
+                    let val = self.convert_expr(arg_ctx, arg)?;
+                    self.long_double_cast_to(val, &CTypeKind::Double)
+                } else {
+                    self.convert_expr(arg_ctx, arg)
+                }
+            })
+            .collect::<TranslationResult<WithStmts<Vec<_>>>>()?;
+
+        Ok(Some(func.and_then(|func| {
+            let call: TranslationResult<_> = Ok(args.map(|args| mk().call_expr(func, args)));
+            call
+        })?))
+    }
+}
+
+/// Drop the `L` length modifier from floating point conversions in a `printf`
+/// format string, e.g. turning `%.3Lf` into `%.3f`.
+///
+/// Returns `None` if the format uses positional arguments such as `%1$Lf`.
+fn strip_long_double_conversions(format: &[u8]) -> Option<Vec<u8>> {
+    let mut out = Vec::with_capacity(format.len());
+    let mut i = 0;
+    while i < format.len() {
+        out.push(format[i]);
+        i += 1;
+        if format[i - 1] != b'%' {
+            continue;
+        }
+
+        // Flags, field width and precision
+        let start = i;
+        while i < format.len() && b"-+ #0'123456789.*$".contains(&format[i]) {
+            i += 1;
+        }
+        if format[start..i].contains(&b'$') {
+            return None;
+        }
+        out.extend_from_slice(&format[start..i]);
+
+        let is_float = |c: Option<&u8>| c.map_or(false, |c| b"aAeEfFgG".contains(c));
+        if format.get(i) == Some(&b'L') && is_float(format.get(i + 1)) {
+            i += 1;
+        }
+        // The conversion itself, which may be a second `%` in `%%`
+        if i < format.len() {
+            out.push(format[i]);
+            i += 1;
+        }
+    }
+    Some(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::strip_long_double_conversions;
+
+    fn strip(format: &str) -> Option<String> {
+        strip_long_double_conversions(format.as_bytes()).map(|f| String::from_utf8(f).unwrap())
+    }
+
+    #[test]
+    fn test_strip_long_double_conversions() {
+        assert_eq!(strip("%Lf\n").as_deref(), Some("%f\n"));
+        assert_eq!(
+            strip("%d: %-10.3Le %LG %%Lf %*.*La").as_deref(),
+            Some("%d: %-10.3e %G %%Lf %*.*a")
+        );
+        assert_eq!(strip("%ld %lf %Ld").as_deref(), Some("%ld %lf %Ld"));
+        assert_eq!(strip("100%").as_deref(), Some("100%"));
+        assert_eq!(strip("%1$Lf"), None);
+    }
+}
@@ -2,13 +2,23 @@
 //! Translating main requires us to wrap the C implementation to
 //! a helper that can be called from a generated main function in
 //! Rust.
+//!
+//! By default, the generated `main` builds `argv` and `envp` from
+//! `std::env::args_os` and `std::env::vars_os`, so arguments that aren't valid
+//! UTF-8 are passed through unchanged. The strings are owned by `main`, which
+//! never returns, so they live as long as the program. Their bytes come from
+//! `std::os::unix::ffi::OsStrExt`, so the generated `main` only builds on Unix:
+//! other platforms have no lossless byte view of an `OsStr`.
+//!
+//! With `--emit-no-std`, there is no `std` runtime to ask, and we export the C
+//! entry point `extern "C" fn main(argc, argv)` instead, along with a
+//! `#[panic_handler]` that aborts, since `no_std` binaries must bring their own.
 
 use super::*;
 use failure::format_err;
-use proc_macro2::{TokenStream, TokenTree};
 
 impl<'c> Translation<'c> {
-    pub fn convert_main(&self, main_id: CDeclId) -> TranslationResult<Box<Item>> {
+    pub fn convert_main(&self, main_id: CDeclId) -> TranslationResult<Vec<Box<Item>>> {
         if let CDeclKind::Function {
             ref parameters,
             typ,
@@ -35,223 +45,323 @@ impl<'c> Translation<'c> {
                 .get(&main_id)
                 .expect("Could not find main function in renamer");
 
-            let decl = mk().fn_decl("main", vec![], None, ReturnType::Default);
+            // Check `main` has the right form
+            let n = parameters.len();
+            if n != 0 && n != 2 && n != 3 {
+                return Err(format_err!(
+                    "Main function should have 0, 2, or 3 parameters, not {}.",
+                    n
+                )
+                .into());
+            };
+
+            let param_names = ["argc", "argv", "envp"];
+            let param_tys = parameters
+                .iter()
+                .zip(param_names)
+                .map(|(&param, name)| match self.ast_context.index(param).kind {
+                    CDeclKind::Variable { ref typ, .. } => self.convert_type(typ.ctype),
+                    _ => Err(TranslationError::generic(&format!(
+                        "Cannot find type of '{}' argument in main function",
+                        name
+                    ))),
+                })
+                .collect::<TranslationResult<Vec<_>>>()?;
 
             let main_fn = mk().path_expr(vec![main_fn_name]);
+            let is_void = matches!(ret, CTypeKind::Void);
 
-            let exit_fn = mk().abs_path_expr(vec!["std", "process", "exit"]);
-            let args_fn = mk().abs_path_expr(vec!["std", "env", "args"]);
-            let vars_fn = mk().abs_path_expr(vec!["std", "env", "vars"]);
+            if self.tcfg.emit_no_std {
+                Ok(vec![
+                    self.convert_main_entry_point(main_fn, &param_names[..n], param_tys, is_void),
+                    self.panic_handler(),
+                ])
+            } else {
+                Ok(vec![self.convert_main_wrapper(main_fn, param_tys, is_void)])
+            }
+        } else {
+            Err(TranslationError::generic(
+                "Cannot translate non-function main entry point",
+            ))
+        }
+    }
 
-            let no_args: Vec<Box<Expr>> = vec![];
+    /// Build a Rust `main` that passes the arguments and environment of the
+    /// process to the translated C `main`, and exits with its result.
+    fn convert_main_wrapper(
+        &self,
+        main_fn: Box<Expr>,
+        param_tys: Vec<Box<Type>>,
+        is_void: bool,
+    ) -> Box<Item> {
+        let decl = mk().fn_decl("main", vec![], None, ReturnType::Default);
+        let exit_fn = mk().abs_path_expr(vec!["std", "process", "exit"]);
+        let as_bytes =
+            || mk().abs_path_expr(vec!["std", "os", "unix", "ffi", "OsStrExt", "as_bytes"]);
+        let os_str_bytes = |name: &str| {
+            mk().call_expr(
+                as_bytes(),
+                vec![mk().method_call_expr(mk().ident_expr(name), "as_os_str", vec![])],
+            )
+        };
 
-            let mut stmts: Vec<Stmt> = vec![];
-            let mut main_args: Vec<Box<Expr>> = vec![];
+        let mut stmts: Vec<Stmt> = vec![];
+        let mut main_args: Vec<Box<Expr>> = vec![];
+        let mut param_tys = param_tys.into_iter();
 
-            let n = parameters.len();
+        if let (Some(argc_ty), Some(argv_ty)) = (param_tys.next(), param_tys.next()) {
+            // `argv` and `argc`
+            stmts.extend(c_string_array(
+                "args",
+                mk().ident_pat("arg"),
+                mk().call_expr(mk().abs_path_expr(vec!["std", "env", "args_os"]), vec![]),
+                vec![],
+                os_str_bytes("arg"),
+                "Failed to convert argument into CString.",
+            ));
 
-            if n >= 2 {
-                // `argv` and `argc`
-
-                stmts.push(mk().local_stmt(Box::new(mk().local(
-                    mk().mutbl().ident_pat("args"),
-                    Some(mk().path_ty(vec![mk().path_segment_with_args(
-                        "Vec",
-                        mk().angle_bracketed_args(vec![
-                            mk().mutbl().ptr_ty(mk().path_ty(vec!["libc", "c_char"])),
-                        ]),
-                    )])),
-                    Some(mk().call_expr(mk().path_expr(vec!["Vec", "new"]), vec![])),
-                ))));
-                stmts.push(mk().semi_stmt(mk().for_expr(
-                    mk().ident_pat("arg"),
-                    mk().call_expr(args_fn, vec![]),
-                    mk().block(vec![mk().semi_stmt(mk().method_call_expr(
-                        mk().path_expr(vec!["args"]),
-                        "push",
-                        vec![mk().method_call_expr(
-                            mk().method_call_expr(
-                                mk().call_expr(
-                                    // TODO(kkysen) change `"std"` to `"alloc"` after `#![feature(alloc_c_string)]` is stabilized in `1.63.0`
-                                    mk().abs_path_expr(vec!["std", "ffi", "CString", "new"]),
-                                    vec![mk().path_expr(vec!["arg"])],
-                                ),
-                                "expect",
-                                vec![mk().lit_expr("Failed to convert argument into CString.")],
-                            ),
-                            "into_raw",
-                            vec![],
-                        )],
-                    ))]),
-                    None::<Ident>,
-                )));
-                stmts.push(mk().semi_stmt(mk().method_call_expr(
-                    mk().path_expr(vec!["args"]),
-                    "push",
-                    vec![
-                        mk().call_expr(mk().abs_path_expr(vec!["core", "ptr", "null_mut"]), vec![]),
-                    ],
-                )));
-
-                let argc_ty: Box<Type> = match self.ast_context.index(parameters[0]).kind {
-                    CDeclKind::Variable { ref typ, .. } => self.convert_type(typ.ctype),
-                    _ => Err(TranslationError::generic(
-                        "Cannot find type of 'argc' argument in main function",
-                    )),
-                }?;
-                let argv_ty: Box<Type> = match self.ast_context.index(parameters[1]).kind {
-                    CDeclKind::Variable { ref typ, .. } => self.convert_type(typ.ctype),
-                    _ => Err(TranslationError::generic(
-                        "Cannot find type of 'argv' argument in main function",
-                    )),
-                }?;
-
-                let args = mk().ident_expr("args");
-                let argc = mk().binary_expr(
-                    BinOp::Sub(Default::default()),
-                    mk().method_call_expr(args.clone(), "len", no_args.clone()),
-                    mk().lit_expr(mk().int_lit(1, "")),
-                );
-                let argv = mk().method_call_expr(args, "as_mut_ptr", no_args.clone());
-
-                main_args.push(mk().cast_expr(argc, argc_ty));
-                main_args.push(mk().cast_expr(argv, argv_ty));
-            }
+            let args = mk().ident_expr("args");
+            let argc = mk().binary_expr(
+                BinOp::Sub(Default::default()),
+                mk().method_call_expr(args.clone(), "len", vec![]),
+                mk().lit_expr(mk().int_lit(1, "")),
+            );
+            let argv = mk().method_call_expr(args, "as_mut_ptr", vec![]);
 
-            if n >= 3 {
-                // non-standard `envp`
-
-                stmts.push(mk().local_stmt(Box::new(mk().local(
-                    mk().mutbl().ident_pat("vars"),
-                    Some(mk().path_ty(vec![mk().path_segment_with_args(
-                        "Vec",
-                        mk().angle_bracketed_args(vec![
-                            mk().mutbl().ptr_ty(mk().path_ty(vec!["libc", "c_char"])),
-                        ]),
-                    )])),
-                    Some(mk().call_expr(mk().path_expr(vec!["Vec", "new"]), vec![])),
-                ))));
-                let var_name_ident = mk().ident("var_name");
-                let var_value_ident = mk().ident("var_value");
-                stmts.push(mk().semi_stmt(mk().for_expr(
-                    mk().tuple_pat(vec![
-                        mk().ident_pat("var_name"),
-                        mk().ident_pat("var_value"),
-                    ]),
-                    mk().call_expr(vars_fn, vec![]),
-                    mk().block(vec![
-                        mk().local_stmt(Box::new(
-                            mk().local(
-                                mk().ident_pat("var"),
-                                Some(mk().path_ty(vec!["String"])),
-                                Some(
-                                    mk().mac_expr(
-                                        mk().mac(
-                                            mk().path(vec!["format"]),
-                                            vec![
-                                                TokenTree::Literal(
-                                                    proc_macro2::Literal::string("{}={}"),
-                                                ),
-                                                TokenTree::Punct(Punct::new(
-                                                    ',',
-                                                    proc_macro2::Spacing::Alone,
-                                                )),
-                                                TokenTree::Ident(var_name_ident),
-                                                TokenTree::Punct(Punct::new(
-                                                    ',',
-                                                    proc_macro2::Spacing::Alone,
-                                                )),
-                                                TokenTree::Ident(var_value_ident),
-                                            ]
-                                            .into_iter()
-                                            .collect::<TokenStream>(),
-                                            MacroDelimiter::Paren(Default::default()),
-                                        ),
-                                    ),
-                                ),
-                            ),
-                        )),
-                        mk().semi_stmt(mk().method_call_expr(
-                            mk().path_expr(vec!["vars"]),
-                            "push",
-                            vec![mk().method_call_expr(
-                                mk().method_call_expr(
-                                    mk().call_expr(
-                                        mk().abs_path_expr(vec![
-                                            // TODO(kkysen) change `"std"` to `"alloc"` after `#![feature(alloc_c_string)]` is stabilized in `1.63.0`
-                                            "std", "ffi", "CString", "new",
-                                        ]),
-                                        vec![mk().path_expr(vec!["var"])],
-                                    ),
-                                    "expect",
-                                    vec![mk().lit_expr(
-                                    "Failed to convert environment variable into CString."
-                                )],
-                                ),
-                                "into_raw",
-                                vec![],
-                            )],
-                        )),
-                    ]),
-                    None as Option<Ident>,
-                )));
-                stmts.push(mk().semi_stmt(mk().method_call_expr(
-                    mk().path_expr(vec!["vars"]),
-                    "push",
-                    vec![
-                        mk().call_expr(mk().abs_path_expr(vec!["core", "ptr", "null_mut"]), vec![]),
-                    ],
-                )));
-
-                let envp_ty: Box<Type> = match self.ast_context.index(parameters[2]).kind {
-                    CDeclKind::Variable { ref typ, .. } => self.convert_type(typ.ctype),
-                    _ => Err(TranslationError::generic(
-                        "Cannot find type of 'envp' argument in main function",
-                    )),
-                }?;
+            main_args.push(mk().cast_expr(argc, argc_ty));
+            main_args.push(mk().cast_expr(argv, argv_ty));
+        }
 
-                let envp = mk().method_call_expr(mk().ident_expr("vars"), "as_mut_ptr", no_args);
+        if let Some(envp_ty) = param_tys.next() {
+            // non-standard `envp`, whose entries are `name=value`
+            let var = || mk().ident_expr("var");
+            let extend_var = |name| {
+                mk().semi_stmt(mk().method_call_expr(
+                    var(),
+                    "extend_from_slice",
+                    vec![os_str_bytes(name)],
+                ))
+            };
+            stmts.extend(c_string_array(
+                "vars",
+                mk().tuple_pat(vec![
+                    mk().ident_pat("var_name"),
+                    mk().ident_pat("var_value"),
+                ]),
+                mk().call_expr(mk().abs_path_expr(vec!["std", "env", "vars_os"]), vec![]),
+                vec![
+                    mk().local_stmt(Box::new(mk().local(
+                        mk().mutbl().ident_pat("var"),
+                        Some(byte_vec_ty()),
+                        Some(mk().call_expr(mk().path_expr(vec!["Vec", "new"]), vec![])),
+                    ))),
+                    extend_var("var_name"),
+                    mk().semi_stmt(mk().method_call_expr(var(), "push", vec![mk().lit_expr(b'=')])),
+                    extend_var("var_value"),
+                ],
+                var(),
+                "Failed to convert environment variable into CString.",
+            ));
 
-                main_args.push(mk().cast_expr(envp, envp_ty));
-            }
+            let envp = mk().method_call_expr(mk().ident_expr("vars"), "as_mut_ptr", vec![]);
 
-            // Check `main` has the right form
-            if n != 0 && n != 2 && n != 3 {
-                return Err(format_err!(
-                    "Main function should have 0, 2, or 3 parameters, not {}.",
-                    n
-                )
-                .into());
-            };
+            main_args.push(mk().cast_expr(envp, envp_ty));
+        }
 
-            if let CTypeKind::Void = ret {
-                let call_main = mk().call_expr(main_fn, main_args);
-                let unsafe_block = mk().unsafe_block(vec![mk().expr_stmt(call_main)]);
+        if is_void {
+            let call_main = mk().call_expr(main_fn, main_args);
+            let unsafe_block = mk().unsafe_block(vec![mk().expr_stmt(call_main)]);
 
-                stmts.push(mk().expr_stmt(mk().unsafe_block_expr(unsafe_block)));
+            stmts.push(mk().expr_stmt(mk().unsafe_block_expr(unsafe_block)));
 
-                let exit_arg = mk().lit_expr(mk().int_lit(0, "i32"));
-                let call_exit = mk().call_expr(exit_fn, vec![exit_arg]);
+            let exit_arg = mk().lit_expr(mk().int_lit(0, "i32"));
+            let call_exit = mk().call_expr(exit_fn, vec![exit_arg]);
 
-                stmts.push(mk().semi_stmt(call_exit));
-            } else {
-                let call_main = mk().cast_expr(
-                    mk().call_expr(main_fn, main_args),
-                    mk().path_ty(vec!["i32"]),
-                );
+            stmts.push(mk().semi_stmt(call_exit));
+        } else {
+            let call_main = mk().cast_expr(
+                mk().call_expr(main_fn, main_args),
+                mk().path_ty(vec!["i32"]),
+            );
 
-                let call_exit = mk().call_expr(exit_fn, vec![call_main]);
-                let unsafe_block = mk().unsafe_block(vec![mk().expr_stmt(call_exit)]);
+            let call_exit = mk().call_expr(exit_fn, vec![call_main]);
+            let unsafe_block = mk().unsafe_block(vec![mk().expr_stmt(call_exit)]);
 
-                stmts.push(mk().expr_stmt(mk().unsafe_block_expr(unsafe_block)));
-            };
+            stmts.push(mk().expr_stmt(mk().unsafe_block_expr(unsafe_block)));
+        };
 
-            let block = mk().block(stmts);
-            Ok(mk().pub_().fn_item(decl, block))
-        } else {
-            Err(TranslationError::generic(
-                "Cannot translate non-function main entry point",
-            ))
+        let block = mk().block(stmts);
+        mk().pub_().fn_item(decl, block)
+    }
+
+    /// Build the C entry point `main` for a `no_std` program, passing its
+    /// arguments on to the translated C `main`. Translated `main`s without
+    /// parameters ignore them.
+    fn convert_main_entry_point(
+        &self,
+        main_fn: Box<Expr>,
+        param_names: &[&str],
+        param_tys: Vec<Box<Type>>,
+        is_void: bool,
+    ) -> Box<Item> {
+        let c_int = || mk().path_ty(vec!["libc", "c_int"]);
+        let c_str_array = || {
+            mk().mutbl()
+                .ptr_ty(mk().mutbl().ptr_ty(mk().path_ty(vec!["libc", "c_char"])))
+        };
+
+        let mut args = vec![
+            (param_names.first().copied().unwrap_or("_argc"), c_int()),
+            (
+                param_names.get(1).copied().unwrap_or("_argv"),
+                c_str_array(),
+            ),
+        ];
+        if let Some(&envp) = param_names.get(2) {
+            args.push((envp, c_str_array()));
         }
+        let main_args = param_names
+            .iter()
+            .zip(param_tys)
+            .map(|(name, ty)| mk().cast_expr(mk().ident_expr(*name), ty))
+            .collect();
+        let args = args
+            .into_iter()
+            .map(|(name, ty)| mk().arg(ty, mk().ident_pat(name)))
+            .collect();
+
+        let decl = mk().fn_decl(
+            "main",
+            args,
+            None,
+            ReturnType::Type(Default::default(), c_int()),
+        );
+        let call_main = mk().call_expr(main_fn, main_args);
+        let stmts = if is_void {
+            vec![
+                mk().semi_stmt(call_main),
+                mk().expr_stmt(mk().lit_expr(mk().int_lit(0, ""))),
+            ]
+        } else {
+            vec![mk().expr_stmt(mk().cast_expr(call_main, c_int()))]
+        };
+
+        mk().single_attr("no_mangle")
+            .pub_()
+            .unsafe_()
+            .extern_("C")
+            .fn_item(decl, mk().block(stmts))
+    }
+
+    /// Build the `#[panic_handler]` of a `no_std` program, which aborts like
+    /// C programs do when something goes wrong.
+    fn panic_handler(&self) -> Box<Item> {
+        let name = self.renamer.borrow_mut().pick_name_root("panic");
+        let info_ty = mk().ref_ty(mk().path_ty(mk().abs_path(vec!["core", "panic", "PanicInfo"])));
+        let decl = mk().fn_decl(
+            name,
+            vec![mk().arg(info_ty, mk().ident_pat("_info"))],
+            None,
+            ReturnType::Type(Default::default(), mk().never_ty()),
+        );
+        let abort = mk().call_expr(mk().path_expr(vec!["libc", "abort"]), vec![]);
+        let body = mk().unsafe_block(vec![mk().expr_stmt(abort)]);
+
+        mk().single_attr("panic_handler").fn_item(
+            decl,
+            mk().block(vec![mk().expr_stmt(mk().unsafe_block_expr(body))]),
+        )
     }
 }
+
+fn byte_vec_ty() -> Box<Type> {
+    mk().path_ty(vec![mk().path_segment_with_args(
+        "Vec",
+        mk().angle_bracketed_args(vec![mk().path_ty(vec!["u8"])]),
+    )])
+}
+
+/// Build a null-terminated array `name` of C strings, one for each `pat` in
+/// `iter`, whose bytes are given by `bytes` after running `prelude`.
+///
+/// The strings are kept in `{name}_strings`, so the array stays valid as long
+/// as that is in scope, and C code may modify them in place like it can
+/// modify `argv`.
+fn c_string_array(
+    name: &str,
+    pat: Pat,
+    iter: Box<Expr>,
+    mut prelude: Vec<Stmt>,
+    bytes: Box<Expr>,
+    error: &str,
+) -> Vec<Stmt> {
+    let strings = format!("{}_strings", name);
+    let c_char_ptr = || mk().mutbl().ptr_ty(mk().path_ty(vec!["libc", "c_char"]));
+
+    // let mut {name}_strings: Vec<Vec<u8>> = Vec::new();
+    // for {pat} in {iter} {
+    //     {prelude}
+    //     {name}_strings.push(CString::new({bytes}).expect({error}).into_bytes_with_nul());
+    // }
+    let c_string = mk().method_call_expr(
+        mk().call_expr(
+            // TODO(kkysen) change `"std"` to `"alloc"` after `#![feature(alloc_c_string)]` is stabilized in `1.63.0`
+            mk().abs_path_expr(vec!["std", "ffi", "CString", "new"]),
+            vec![bytes],
+        ),
+        "expect",
+        vec![mk().lit_expr(error)],
+    );
+    prelude.push(mk().semi_stmt(mk().method_call_expr(
+        mk().ident_expr(&strings),
+        "push",
+        vec![mk().method_call_expr(c_string, "into_bytes_with_nul", vec![])],
+    )));
+    let collect_strings = vec![
+        mk().local_stmt(Box::new(mk().local(
+            mk().mutbl().ident_pat(&strings),
+            Some(mk().path_ty(vec![mk().path_segment_with_args(
+                "Vec",
+                mk().angle_bracketed_args(vec![byte_vec_ty()]),
+            )])),
+            Some(mk().call_expr(mk().path_expr(vec!["Vec", "new"]), vec![])),
+        ))),
+        mk().semi_stmt(mk().for_expr(pat, iter, mk().block(prelude), None::<Ident>)),
+    ];
+
+    // let mut {name}: Vec<*mut libc::c_char> = Vec::new();
+    // for string in &mut {name}_strings {
+    //     {name}.push(string.as_mut_ptr() as *mut libc::c_char);
+    // }
+    // {name}.push(::core::ptr::null_mut());
+    let collect_ptrs = vec![
+        mk().local_stmt(Box::new(mk().local(
+            mk().mutbl().ident_pat(name),
+            Some(mk().path_ty(vec![mk().path_segment_with_args(
+                "Vec",
+                mk().angle_bracketed_args(vec![c_char_ptr()]),
+            )])),
+            Some(mk().call_expr(mk().path_expr(vec!["Vec", "new"]), vec![])),
+        ))),
+        mk().semi_stmt(mk().for_expr(
+            mk().ident_pat("string"),
+            mk().mutbl().addr_of_expr(mk().ident_expr(&strings)),
+            mk().block(vec![mk().semi_stmt(mk().method_call_expr(
+                mk().ident_expr(name),
+                "push",
+                vec![mk().cast_expr(
+                    mk().method_call_expr(mk().ident_expr("string"), "as_mut_ptr", vec![]),
+                    c_char_ptr(),
+                )],
+            ))]),
+            None::<Ident>,
+        )),
+        mk().semi_stmt(mk().method_call_expr(
+            mk().ident_expr(name),
+            "push",
+            vec![mk().call_expr(mk().abs_path_expr(vec!["core", "ptr", "null_mut"]), vec![])],
+        )),
+    ];
+
+    collect_strings.into_iter().chain(collect_ptrs).collect()
+}
@@ -1,3 +1,4 @@
+use std::borrow::Cow;
 use std::cell::RefCell;
 use std::char;
 use std::collections::HashMap;
@@ -17,7 +18,7 @@ use syn::spanned::Spanned as _;
 use syn::*;
 use syn::{BinOp, UnOp}; // To override c_ast::{BinOp,UnOp} from glob import
 
-use crate::diagnostics::TranslationResult;
+use crate::diagnostics::{self, diag, Diagnostic, TranslationResult};
 use crate::rust_ast::comment_store::CommentStore;
 use crate::rust_ast::item_store::ItemStore;
 use crate::rust_ast::set_span::SetSpan;
@@ -29,8 +30,8 @@ use c2rust_ast_printer::pprust::{self};
 use crate::c_ast::iterators::{DFExpr, SomeId};
 use crate::c_ast::*;
 use crate::cfg;
-use crate::convert_type::TypeConverter;
-use crate::renamer::Renamer;
+use crate::convert_type::{LongDoubleRepr, TypeConverter};
+use crate::renamer::{RenamedDecl, Renamer};
 use crate::with_stmts::WithStmts;
 use crate::{c_ast, format_translation_err};
 use crate::{ExternCrate, ExternCrateDetails, TranspilerConfig};
@@ -38,16 +39,24 @@ use c2rust_ast_exporter::clang_ast::LRValue;
 
 mod assembly;
 mod atomics;
+mod blocks;
 mod builtins;
+mod c_header;
 mod comments;
+mod flexible_arrays;
+mod libc_calls;
 mod literals;
+mod long_double;
 mod main_function;
 mod named_references;
 mod operators;
+mod shared_items;
 mod simd;
 mod structs;
 mod variadic;
 
+pub use self::c_header::CHeader;
+pub use self::shared_items::{SharedItems, SHARED_MODULE};
 pub use crate::diagnostics::{TranslationError, TranslationErrorKind};
 use crate::CrateSet;
 use crate::PragmaVec;
@@ -256,12 +265,18 @@ pub struct Translation<'c> {
     function_context: RefCell<FuncContext>,
     potential_flexible_array_members: RefCell<IndexSet<CDeclId>>,
     macro_expansions: RefCell<IndexMap<CDeclId, Option<MacroExpansion>>>,
+    c_header_items: RefCell<c_header::HeaderItems>,
+
+    // Header definitions set aside to share with other translation units
+    share_items: bool,
+    shared_items: RefCell<Vec<(shared_items::SharedKey, Vec<Box<Item>>)>>,
 
     // Comment support
     pub comment_context: CommentContext,      // Incoming comments
     pub comment_store: RefCell<CommentStore>, // Outgoing comments
 
     spans: HashMap<SomeId, Span>,
+    docs: HashMap<CDeclId, Vec<String>>,
 
     // Items indexed by file id of the source
     items: RefCell<IndexMap<FileId, ItemStore>>,
@@ -491,8 +506,17 @@ pub fn translate(
     ast_context: TypedAstContext,
     tcfg: &TranspilerConfig,
     main_file: PathBuf,
-) -> (String, PragmaVec, CrateSet) {
+    share_items: bool,
+) -> (
+    String,
+    PragmaVec,
+    CrateSet,
+    Vec<RenamedDecl>,
+    CHeader,
+    SharedItems,
+) {
     let mut t = Translation::new(ast_context, tcfg, main_file.as_path());
+    t.share_items = share_items && !tcfg.is_binary(&main_file);
     let ctx = ExprContext {
         used: true,
         is_static: false,
@@ -520,7 +544,7 @@ pub fn translate(
             .prune_unwanted_decls(tcfg.preserve_unused_functions);
 
         enum Name<'a> {
-            Var(&'a str),
+            Var(Cow<'a, str>),
             Type(&'a str),
             Anonymous,
             None,
@@ -538,6 +562,25 @@ pub fn translate(
             prefix_names(&mut t, prefix);
         }
 
+        // Types from system headers keep their C names under every naming
+        // policy, since that's how other code knows them
+        let mut system_files = HashMap::new();
+        let mut type_name = |t: &Translation, decl_id: CDeclId, name: &str| {
+            let system = match t.ast_context.file_id(&t.ast_context[decl_id]) {
+                Some(file) if file == t.main_file => false,
+                Some(file) => *system_files.entry(file).or_insert_with(|| {
+                    t.ast_context.get_file_path(file).is_none()
+                        || c_header::system_include(&t.ast_context, file).is_some()
+                }),
+                None => true,
+            };
+            if system {
+                name.to_owned()
+            } else {
+                tcfg.naming_policy.type_name(name).into_owned()
+            }
+        };
+
         // Identify typedefs that name unnamed types and collapse the two declarations
         // into a single name and declaration, eliminating the typedef altogether.
         let mut prenamed_decls: IndexMap<CDeclId, CDeclId> = IndexMap::new();
@@ -581,9 +624,10 @@ pub fn translate(
                     {
                         prenamed_decls.insert(decl_id, subdecl_id);
 
+                        let name = type_name(&t, decl_id, name);
                         t.type_converter
                             .borrow_mut()
-                            .declare_decl_name(decl_id, name);
+                            .declare_decl_name(decl_id, &name);
                         t.type_converter
                             .borrow_mut()
                             .alias_decl_name(subdecl_id, decl_id);
@@ -593,6 +637,7 @@ pub fn translate(
         }
 
         t.ast_context.prenamed_decls = prenamed_decls;
+        t.merge_typedef_docs();
 
         // Helper function that returns true if there is either a matching typedef or its
         // corresponding struct/union/enum
@@ -602,6 +647,7 @@ pub fn translate(
         }
 
         // Populate renamer with top-level names
+        let naming_policy = tcfg.naming_policy;
         for (&decl_id, decl) in t.ast_context.iter_decls() {
             use CDeclKind::*;
             let decl_name = match decl.kind {
@@ -610,12 +656,12 @@ pub fn translate(
                 Enum { ref name, .. } => some_type_name(name.as_ref().map(String::as_str)),
                 Union { ref name, .. } => some_type_name(name.as_ref().map(String::as_str)),
                 Typedef { ref name, .. } => Name::Type(name),
-                Function { ref name, .. } => Name::Var(name),
-                EnumConstant { ref name, .. } => Name::Var(name),
+                Function { ref name, .. } => Name::Var(naming_policy.value_name(name)),
+                EnumConstant { ref name, .. } => Name::Var(name.into()),
                 Variable { ref ident, .. } if t.ast_context.c_decls_top.contains(&decl_id) => {
-                    Name::Var(ident)
+                    Name::Var(naming_policy.static_name(ident))
                 }
-                MacroObject { ref name, .. } => Name::Var(name),
+                MacroObject { ref name, .. } => Name::Var(name.into()),
                 _ => Name::None,
             };
             match decl_name {
@@ -626,12 +672,13 @@ pub fn translate(
                         .declare_decl_name(decl_id, "C2RustUnnamed");
                 }
                 Name::Type(name) => {
+                    let name = type_name(&t, decl_id, name);
                     t.type_converter
                         .borrow_mut()
-                        .declare_decl_name(decl_id, name);
+                        .declare_decl_name(decl_id, &name);
                 }
                 Name::Var(name) => {
-                    t.renamer.borrow_mut().insert(decl_id, name);
+                    t.renamer.borrow_mut().insert(decl_id, &name);
                 }
             }
         }
@@ -649,19 +696,24 @@ pub fn translate(
                         translate_failure(t.tcfg, &msg);
                     }
                     Ok(converted_decl) => {
+                        t.add_to_c_header(decl_id, &converted_decl);
                         use ConvertedDecl::*;
                         match converted_decl {
-                            Item(item) => {
-                                t.insert_item(item, decl);
-                            }
+                            Item(item) => match t.shared_item_key(decl_id) {
+                                Some(key) => t.shared_items.borrow_mut().push((key, vec![item])),
+                                None => t.insert_item(item, decl),
+                            },
                             ForeignItem(item) => {
                                 t.insert_foreign_item(*item, decl);
                             }
-                            Items(items) => {
-                                for item in items {
-                                    t.insert_item(item, decl);
+                            Items(items) => match t.shared_item_key(decl_id) {
+                                Some(key) => t.shared_items.borrow_mut().push((key, items)),
+                                None => {
+                                    for item in items {
+                                        t.insert_item(item, decl);
+                                    }
                                 }
-                            }
+                            },
                             NoItem => {}
                         }
                     }
@@ -704,6 +756,7 @@ pub fn translate(
                 Variable { .. } => true,
                 MacroObject { .. } => tcfg.translate_const_macros,
                 MacroFunction { .. } => tcfg.translate_fn_macros,
+                FileScopeAsm { .. } => true,
                 _ => false,
             };
             if needs_export {
@@ -736,11 +789,13 @@ pub fn translate(
                         translate_failure(t.tcfg, &msg);
                     }
                     Ok(converted_decl) => {
+                        t.add_to_c_header(*top_id, &converted_decl);
                         use ConvertedDecl::*;
                         match converted_decl {
-                            Item(item) => {
-                                t.insert_item(item, decl);
-                            }
+                            Item(item) => match t.shared_item_key(*top_id) {
+                                Some(key) => t.shared_items.borrow_mut().push((key, vec![item])),
+                                None => t.insert_item(item, decl),
+                            },
                             ForeignItem(item) => {
                                 t.insert_foreign_item(*item, decl);
                             }
@@ -766,7 +821,11 @@ pub fn translate(
         // Add the main entry point
         if let Some(main_id) = t.ast_context.c_main {
             match t.convert_main(main_id) {
-                Ok(item) => t.items.borrow_mut()[&t.main_file].add_item(item),
+                Ok(items) => {
+                    for item in items {
+                        t.items.borrow_mut()[&t.main_file].add_item(item);
+                    }
+                }
                 Err(e) => {
                     let msg = format!("Failed to translate main: {}", e);
                     translate_failure(t.tcfg, &msg)
@@ -785,6 +844,8 @@ pub fn translate(
 
         let pragmas = t.get_pragmas();
         let crates = t.extern_crates.borrow().clone();
+        let renamed = t.renamed_decls();
+        let c_header = t.c_header();
 
         let mut mod_items: Vec<Box<Item>> = Vec::new();
 
@@ -816,6 +877,8 @@ pub fn translate(
 
         // Main file item store
         let (items, foreign_items, uses) = t.items.borrow_mut()[&t.main_file].drain();
+        let shared_items =
+            shared_items::collect(t.shared_items.take(), &items, &foreign_items, &uses);
 
         // Re-order comments
         // FIXME: We shouldn't have to replace with an empty comment store here, that's bad design
@@ -885,7 +948,14 @@ pub fn translate(
                 items: all_items.into_iter().map(|x| *x).collect(),
             }
         });
-        (translation, pragmas, crates)
+        (
+            translation,
+            pragmas,
+            crates,
+            renamed,
+            c_header,
+            shared_items,
+        )
     }
 }
 
@@ -1068,6 +1138,10 @@ fn arrange_header(t: &Translation, is_binary: bool) -> (Vec<syn::Attribute>, Vec
 
         if t.tcfg.emit_no_std {
             out_attrs.push(mk().single_attr("no_std").as_inner_attrs()[0].clone());
+            if is_binary && t.ast_context.c_main.is_some() {
+                // `main` is exported as the C entry point, see `convert_main`
+                out_attrs.push(mk().single_attr("no_main").as_inner_attrs()[0].clone());
+            }
         }
 
         if is_binary {
@@ -1206,6 +1280,8 @@ impl<'c> Translation<'c> {
         if tcfg.translate_valist {
             type_converter.translate_valist = true
         }
+        type_converter.set_naming_policy(tcfg.naming_policy);
+        type_converter.long_double = tcfg.long_double;
 
         let main_file = ast_context.find_file_id(main_file).unwrap_or(0);
         let items = indexmap! {main_file => ItemStore::new()};
@@ -1232,9 +1308,13 @@ impl<'c> Translation<'c> {
             function_context: RefCell::new(FuncContext::new()),
             potential_flexible_array_members: RefCell::new(IndexSet::new()),
             macro_expansions: RefCell::new(IndexMap::new()),
+            c_header_items: RefCell::new(Default::default()),
+            share_items: false,
+            shared_items: RefCell::new(Vec::new()),
             comment_context,
             comment_store: RefCell::new(CommentStore::new()),
             spans: HashMap::new(),
+            docs: HashMap::new(),
             sectioned_static_initializers: RefCell::new(Vec::new()),
             items: RefCell::new(items),
             mod_names: RefCell::new(IndexMap::new()),
@@ -1272,6 +1352,74 @@ impl<'c> Translation<'c> {
         self.features.borrow_mut().insert(feature);
     }
 
+    /// Collect the top-level declarations, types and fields whose Rust name
+    /// differs from their C name, in declaration order.
+    pub fn renamed_decls(&self) -> Vec<RenamedDecl> {
+        let renamer = self.renamer.borrow();
+        let type_converter = self.type_converter.borrow();
+        let mut renamed = vec![];
+        let mut push = |kind, c_name: &str, rust_name: Option<String>| {
+            if let Some(rust_name) = rust_name {
+                if rust_name != c_name {
+                    renamed.push(RenamedDecl {
+                        kind,
+                        c_name: c_name.to_owned(),
+                        rust_name,
+                    });
+                }
+            }
+        };
+
+        for &decl_id in &self.ast_context.c_decls_top {
+            use CDeclKind::*;
+            match self.ast_context[decl_id].kind {
+                Function { ref name, .. } => push("function", name, renamer.get(&decl_id)),
+                Variable { ref ident, .. } => push("variable", ident, renamer.get(&decl_id)),
+                Typedef { ref name, .. } => {
+                    push("type", name, type_converter.resolve_decl_name(decl_id))
+                }
+                Enum {
+                    name: Some(ref name),
+                    ..
+                } => push("type", name, type_converter.resolve_decl_name(decl_id)),
+                Struct {
+                    name: ref record_name,
+                    fields: Some(ref fields),
+                    ..
+                }
+                | Union {
+                    name: ref record_name,
+                    fields: Some(ref fields),
+                    ..
+                } => {
+                    let rust_record = type_converter.resolve_decl_name(decl_id);
+                    if let Some(ref name) = *record_name {
+                        push("type", name, rust_record.clone());
+                    }
+                    let c_record = record_name.as_deref().unwrap_or("<anonymous>");
+                    let rust_record = rust_record.as_deref().unwrap_or("<anonymous>");
+                    for &field_id in fields {
+                        if let Field { ref name, .. } = self.ast_context[field_id].kind {
+                            if let Some(field) =
+                                type_converter.resolve_field_name(Some(decl_id), field_id)
+                            {
+                                if &field != name {
+                                    push(
+                                        "field",
+                                        &format!("{}.{}", c_record, name),
+                                        Some(format!("{}.{}", rust_record, field)),
+                                    );
+                                }
+                            }
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+        renamed
+    }
+
     pub fn get_pragmas(&self) -> PragmaVec {
         let mut features = vec![];
         features.extend(self.features.borrow().iter());
@@ -1353,6 +1501,13 @@ impl<'c> Translation<'c> {
             use CExprKind::*;
             match self.ast_context[expr_id].kind {
                 DeclRef(_, _, LRValue::LValue) => return true,
+                // Pointer arithmetic is translated to `offset` calls
+                ArraySubscript(..) => return true,
+                Binary(typ, c_ast::BinOp::Add | c_ast::BinOp::Subtract, _, _, _, _)
+                    if self.ast_context.resolve_type(typ.ctype).kind.is_pointer() =>
+                {
+                    return true
+                }
                 ImplicitCast(_, _, cast_kind, _, _) | ExplicitCast(_, _, cast_kind, _, _) => {
                     use CastKind::*;
                     match cast_kind {
@@ -1369,28 +1524,40 @@ impl<'c> Translation<'c> {
         false
     }
 
-    /// The purpose of this function is to decide on whether or not a static initializer's
-    /// translation is able to be compiled as a valid rust static initializer
-    fn static_initializer_is_uncompilable(
+    /// Decide whether a static initializer has to be computed at runtime by
+    /// `run_static_initializers` rather than being evaluated as a Rust constant.
+    ///
+    /// Most C constant expressions, including addresses of other statics, pointer
+    /// arithmetic on static arrays, function pointer tables and casts between them,
+    /// translate to expressions that rustc can const-evaluate. This returns the
+    /// reason for the few that it can't, so that each fallback can be reported.
+    fn static_initializer_dynamic_reason(
         &self,
         expr_id: Option<CExprId>,
         qtype: CQualTypeId,
-    ) -> bool {
-        use crate::c_ast::BinOp::{Add, Divide, Modulus, Multiply, Subtract};
+    ) -> Option<&'static str> {
+        use crate::c_ast::BinOp::{
+            EqualEqual, Greater, GreaterEqual, Less, LessEqual, NotEqual, Subtract,
+        };
         use crate::c_ast::CastKind::{IntegralToPointer, PointerToIntegral};
-        use crate::c_ast::UnOp::{AddressOf, Negate};
 
-        let expr_id = match expr_id {
-            Some(expr_id) => expr_id,
-            None => return false,
-        };
+        let expr_id = expr_id?;
 
-        // The f128 crate doesn't currently provide a way to const initialize
+        // Neither f128 nor c2rust_x87 provide a way to const initialize
         // values, except for common mathematical constants
-        if let CTypeKind::LongDouble = self.ast_context[qtype.ctype].kind {
-            return true;
+        if self.is_soft_long_double(qtype.ctype) {
+            return Some("`long double` values cannot be built in a const context");
         }
 
+        let is_pointer = |expr_id: CExprId| {
+            self.ast_context[expr_id]
+                .kind
+                .get_type()
+                .map_or(false, |ty| {
+                    self.ast_context.resolve_type(ty).kind.is_pointer()
+                })
+        };
+
         let iter = DFExpr::new(&self.ast_context, expr_id.into());
 
         for i in iter {
@@ -1401,46 +1568,44 @@ impl<'c> Translation<'c> {
 
             use CExprKind::*;
             match self.ast_context[expr_id].kind {
-                // Technically we're being conservative here, but it's only the most
-                // contrived array indexing initializers that would be accepted
-                ArraySubscript(..) => return true,
-                Member(..) => return true,
-
-                Conditional(..) => return true,
-                Unary(typ, Negate, _, _) => {
-                    if self
-                        .ast_context
-                        .resolve_type(typ.ctype)
-                        .kind
-                        .is_unsigned_integral_type()
-                    {
-                        return true;
+                // Indexing is only const when it starts from an array; going through
+                // a pointer would require a raw pointer dereference
+                ArraySubscript(_, lhs, rhs, _) => {
+                    let base = if is_pointer(lhs) { lhs } else { rhs };
+                    if !matches!(
+                        self.ast_context[base].kind,
+                        ImplicitCast(_, _, CastKind::ArrayToPointerDecay, _, _)
+                    ) {
+                        return Some("subscripting a pointer is not allowed in a const context");
                     }
                 }
+                // `(&x)->field` is translated as `x.field`, anything else is a deref
+                Member(_, base, _, MemberKind::Arrow, _)
+                    if !matches!(
+                        self.ast_context[base].kind,
+                        Unary(_, c_ast::UnOp::AddressOf, _, _)
+                    ) =>
+                {
+                    return Some("`->` member access is not allowed in a const context");
+                }
 
                 // PointerToIntegral is no longer allowed, const-eval throws an
                 // error: "pointer-to-integer cast" needs an rfc before being
                 // allowed inside constants
                 ImplicitCast(_, _, PointerToIntegral, _, _)
-                | ExplicitCast(_, _, PointerToIntegral, _, _) => return true,
-
-                Binary(typ, op, _, _, _, _) => {
-                    let problematic_op = matches!(op, Add | Subtract | Multiply | Divide | Modulus);
+                | ExplicitCast(_, _, PointerToIntegral, _, _) => {
+                    return Some("pointer-to-integer casts are not allowed in a const context");
+                }
 
-                    if problematic_op {
-                        let k = &self.ast_context.resolve_type(typ.ctype).kind;
-                        if k.is_unsigned_integral_type() || k.is_pointer() {
-                            return true;
-                        }
+                Binary(_, op, lhs, rhs, _, _) if is_pointer(lhs) && is_pointer(rhs) => match op {
+                    Subtract => {
+                        return Some("pointer subtraction is not allowed in a const context")
                     }
-                }
-                Unary(_, AddressOf, expr_id, _) => {
-                    if let Member(_, expr_id, _, _, _) = self.ast_context[expr_id].kind {
-                        if let DeclRef(..) = self.ast_context[expr_id].kind {
-                            return true;
-                        }
+                    EqualEqual | NotEqual | Less | Greater | LessEqual | GreaterEqual => {
+                        return Some("pointer comparisons are not allowed in a const context")
                     }
-                }
+                    _ => {}
+                },
                 InitList(qtype, _, _, _) => {
                     let ty = &self.ast_context.resolve_type(qtype.ctype).kind;
 
@@ -1460,7 +1625,7 @@ impl<'c> Translation<'c> {
                                     ..
                                 } = field_decl
                                 {
-                                    return true;
+                                    return Some("bitfield setters are not const fns");
                                 }
                             }
                         }
@@ -1474,7 +1639,9 @@ impl<'c> Translation<'c> {
                         if let CTypeKind::Function(..) =
                             self.ast_context.resolve_type(qtype.ctype).kind
                         {
-                            return true;
+                            return Some(
+                                "integer-to-function-pointer casts are not allowed in a const context",
+                            );
                         }
                     }
                 }
@@ -1482,7 +1649,20 @@ impl<'c> Translation<'c> {
             }
         }
 
-        false
+        None
+    }
+
+    /// Report that the initializer of `ident` is deferred to `run_static_initializers`.
+    fn warn_dynamic_static_initializer(&self, decl_id: CDeclId, ident: &str, reason: &str) {
+        let loc = self.ast_context.display_loc(&self.ast_context[decl_id].loc);
+        let loc = loc.map_or_else(String::new, |loc| format!("{}: ", loc));
+        diag!(
+            Diagnostic::StaticInitializers,
+            "{}initializer of static `{}` is computed at startup: {}",
+            loc,
+            ident,
+            reason,
+        );
     }
 
     fn add_static_initializer_to_section(
@@ -1564,6 +1744,16 @@ impl<'c> Translation<'c> {
     }
 
     fn convert_decl(&self, ctx: ExprContext, decl_id: CDeclId) -> TranslationResult<ConvertedDecl> {
+        let mut converted = self.convert_decl_kind(ctx, decl_id)?;
+        self.add_doc_attrs(decl_id, &mut converted);
+        Ok(converted)
+    }
+
+    fn convert_decl_kind(
+        &self,
+        ctx: ExprContext,
+        decl_id: CDeclId,
+    ) -> TranslationResult<ConvertedDecl> {
         let decl = self
             .ast_context
             .get_decl(&decl_id)
@@ -1736,11 +1926,13 @@ impl<'c> Translation<'c> {
                         mk_ = mk_.generic_over(mk().lt_param(mk().ident("a")))
                     }
 
-                    Ok(ConvertedDecl::Item(mk_.struct_item(
-                        name,
-                        field_entries,
-                        false,
-                    )))
+                    let flexible_array_api = self.convert_flexible_array_api(decl_id, &name)?;
+                    let struct_item = mk_.struct_item(name, field_entries, false);
+
+                    match flexible_array_api {
+                        Some(api) => Ok(ConvertedDecl::Items(vec![struct_item, api])),
+                        None => Ok(ConvertedDecl::Item(struct_item)),
+                    }
                 }
             }
 
@@ -1765,7 +1957,7 @@ impl<'c> Translation<'c> {
                                 .borrow_mut()
                                 .declare_field_name(decl_id, x, name);
                             let typ = self.convert_type(typ.ctype)?;
-                            field_syns.push(mk().pub_().struct_field(name, typ))
+                            field_syns.push(self.mk_with_docs(x).pub_().struct_field(name, typ))
                         }
                         _ => {
                             return Err(TranslationError::generic(
@@ -2043,9 +2235,13 @@ impl<'c> Translation<'c> {
                     .get(&decl_id)
                     .expect("Variables should already be renamed");
 
-                // Collect problematic static initializers and offload them to sections for the linker
-                // to initialize for us
-                let (ty, init) = if self.static_initializer_is_uncompilable(initializer, typ) {
+                // Initializers that rustc can't const-evaluate are offloaded to sections for the
+                // linker to run for us
+                let (ty, init) = if let Some(reason) =
+                    self.static_initializer_dynamic_reason(initializer, typ)
+                {
+                    self.warn_dynamic_static_initializer(decl_id, ident, reason);
+
                     // Note: We don't pass has_static_duration through here. Extracted initializers
                     // are run outside of the static initializer.
                     let ConvertedVariable { ty, mutbl: _, init } =
@@ -2178,6 +2374,10 @@ impl<'c> Translation<'c> {
                 warn!("ignoring static assert during translation");
                 Ok(ConvertedDecl::NoItem)
             }
+
+            FileScopeAsm { ref asm } => {
+                Ok(ConvertedDecl::Item(self.convert_file_scope_asm(span, asm)?))
+            }
         }
     }
 
@@ -2268,7 +2468,7 @@ impl<'c> Translation<'c> {
                     let new_var = self
                         .renamer
                         .borrow_mut()
-                        .insert(decl_id, var.as_str())
+                        .insert(decl_id, &self.tcfg.naming_policy.value_name(var))
                         .unwrap_or_else(|| {
                             panic!(
                                 "Failed to insert argument '{}' while converting '{}'",
@@ -2470,9 +2670,9 @@ impl<'c> Translation<'c> {
         );
 
         if self.tcfg.dump_structures {
-            eprintln!("Relooped structures:");
+            diagnostics::eprint("Relooped structures:".to_owned());
             for s in &relooped {
-                eprintln!("  {:#?}", s);
+                diagnostics::eprint(format!("  {:#?}", s));
             }
         }
 
@@ -2638,11 +2838,12 @@ impl<'c> Translation<'c> {
             ..
         } = self.ast_context.index(decl_id).kind
         {
-            if self.static_initializer_is_uncompilable(initializer, typ) {
+            if let Some(reason) = self.static_initializer_dynamic_reason(initializer, typ) {
+                self.warn_dynamic_static_initializer(decl_id, ident, reason);
                 let ident2 = self
                     .renamer
                     .borrow_mut()
-                    .insert_root(decl_id, ident)
+                    .insert_root(decl_id, &self.tcfg.naming_policy.static_name(ident))
                     .ok_or_else(|| {
                         TranslationError::generic(
                             "Unable to rename function scoped static initializer",
@@ -2692,7 +2893,7 @@ impl<'c> Translation<'c> {
                 let rust_name = self
                     .renamer
                     .borrow_mut()
-                    .insert(decl_id, ident)
+                    .insert(decl_id, &self.tcfg.naming_policy.value_name(ident))
                     .unwrap_or_else(|| panic!("Failed to insert variable '{}'", ident));
 
                 if self.ast_context.is_va_list(typ.ctype) {
@@ -2952,6 +3153,11 @@ impl<'c> Translation<'c> {
 
         let pointee = match self.ast_context.resolve_type(type_id).kind {
             CTypeKind::Pointer(pointee) => pointee,
+            // Block pointers are always mutable
+            CTypeKind::BlockPointer(_) => {
+                let zero = mk().lit_expr(mk().int_unsuffixed_lit(0));
+                return Ok(mk().cast_expr(zero, self.convert_type(type_id)?));
+            }
             _ => return Err(TranslationError::generic("null_ptr requires a pointer")),
         };
         let ty = self.convert_type(type_id)?;
@@ -3456,9 +3662,10 @@ impl<'c> Translation<'c> {
                 // A reference must be decayed if a bitcast is required. Const casts in
                 // LLVM 8 are now NoOp casts, so we need to include it as well.
                 match kind {
-                    CastKind::BitCast | CastKind::PointerToIntegral | CastKind::NoOp => {
-                        ctx.decay_ref = DecayRef::Yes
-                    }
+                    CastKind::BitCast
+                    | CastKind::PointerToIntegral
+                    | CastKind::NoOp
+                    | CastKind::AnyPointerToBlockPointerCast => ctx.decay_ref = DecayRef::Yes,
                     CastKind::FunctionToPointerDecay | CastKind::BuiltinFnToFnPtr => {
                         ctx.needs_address = true;
                     }
@@ -3712,6 +3919,19 @@ impl<'c> Translation<'c> {
             }
 
             Call(call_expr_ty, func, ref args) => {
+                let callee_ty = self.ast_context[func]
+                    .kind
+                    .get_type()
+                    .ok_or_else(|| format_err!("Invalid callee expression {:?}", func))?;
+                if let CTypeKind::BlockPointer(..) = self.ast_context.resolve_type(callee_ty).kind {
+                    let call = self.convert_block_call(ctx, func, args)?;
+                    return self.convert_side_effects_expr(
+                        ctx,
+                        call,
+                        "Block call expression is not supposed to be used",
+                    );
+                }
+
                 let fn_ty =
                     self.ast_context
                         .get_pointee_qual_type(
@@ -3731,11 +3951,37 @@ impl<'c> Translation<'c> {
                     // callee is a declref
                     if matches!(self.ast_context[fexp].kind, CExprKind::DeclRef(..)) =>
                         {
+                            if let Some(alloc) =
+                                self.convert_flexible_array_alloc(ctx, fexp, args, call_expr_ty)?
+                            {
+                                return self.convert_side_effects_expr(
+                                    ctx,
+                                    alloc,
+                                    "Function call expression is not supposed to be used",
+                                );
+                            }
+                            if let Some(call) = self.convert_long_double_printf(ctx, fexp, args)? {
+                                return self.convert_side_effects_expr(
+                                    ctx,
+                                    call,
+                                    "Function call expression is not supposed to be used",
+                                );
+                            }
+                            if let Some(call) =
+                                self.convert_mapped_libc_call(ctx, fexp, args, call_expr_ty)?
+                            {
+                                return Ok(call);
+                            }
                             self.convert_expr(ctx.used(), fexp)?
                         }
 
                     // Builtin function call
                     CExprKind::ImplicitCast(_, fexp, CastKind::BuiltinFnToFnPtr, _, _) => {
+                        if let Some(call) =
+                            self.convert_mapped_libc_call(ctx, fexp, args, call_expr_ty)?
+                        {
+                            return Ok(call);
+                        }
                         return self.convert_builtin(ctx, fexp, args);
                     }
 
@@ -3802,7 +4048,17 @@ impl<'c> Translation<'c> {
                     self.convert_expr(ctx, expr)
                 } else {
                     let mut val = match kind {
-                        MemberKind::Dot => self.convert_expr(ctx, expr)?,
+                        MemberKind::Dot => {
+                            // Static initializers can't dereference raw pointers, so
+                            // `&arr[i].field` has to index the array rather than offset
+                            // a pointer into it
+                            let ctx = if ctx.is_static {
+                                ctx.set_needs_address(false)
+                            } else {
+                                ctx
+                            };
+                            self.convert_expr(ctx, expr)?
+                        }
                         MemberKind::Arrow => {
                             if let CExprKind::Unary(_, c_ast::UnOp::AddressOf, subexpr_id, _) =
                                 self.ast_context[expr].kind
@@ -3881,6 +4137,13 @@ impl<'c> Translation<'c> {
                 self.convert_statement_expression(ctx, compound_stmt_id)
             }
 
+            BlockLiteral {
+                typ,
+                ref parameters,
+                body,
+                ref captures,
+            } => self.convert_block_literal(ctx, typ, parameters, body, captures),
+
             VAArg(ty, val_id) => self.convert_vaarg(ctx, ty, val_id),
 
             Choose(_, _cond, lhs, rhs, is_cond_true) => {
@@ -4222,7 +4485,7 @@ impl<'c> Translation<'c> {
         });
 
         match kind {
-            CastKind::BitCast | CastKind::NoOp => {
+            CastKind::BitCast | CastKind::NoOp | CastKind::AnyPointerToBlockPointerCast => {
                 val.and_then(|x| {
                     if self.ast_context.is_function_pointer(ty.ctype)
                         || self.ast_context.is_function_pointer(source_ty.ctype)
@@ -4263,13 +4526,10 @@ impl<'c> Translation<'c> {
                 let source_ty_ctype_id = source_ty.ctype;
 
                 let source_ty = self.convert_type(source_ty_ctype_id)?;
-                if let CTypeKind::LongDouble = target_ty_ctype {
-                    self.use_crate(ExternCrate::F128);
-
-                    let fn_path = mk().path_expr(vec!["f128", "f128", "new"]);
-                    Ok(val.map(|val| mk().call_expr(fn_path, vec![val])))
-                } else if let CTypeKind::LongDouble = self.ast_context[source_ty_ctype_id].kind {
-                    self.f128_cast_to(val, target_ty_ctype)
+                if self.is_soft_long_double(ty.ctype) {
+                    Ok(val.map(|val| self.long_double_from("new", val)))
+                } else if self.is_soft_long_double(source_ty_ctype_id) {
+                    self.long_double_cast_to(val, target_ty_ctype)
                 } else if let &CTypeKind::Enum(enum_decl_id) = target_ty_ctype {
                     // Casts targeting `enum` types...
                     let expr =
@@ -4342,13 +4602,11 @@ impl<'c> Translation<'c> {
                             let call = val.map(|x| mk().method_call_expr(x, method, vec![]));
 
                             // Static arrays can now use as_ptr. Can also cast that const ptr to a
-                            // mutable pointer as we do here:
+                            // mutable pointer as we do here. The pointee type is spelled out so
+                            // that pointer arithmetic in the initializer can call methods on it.
                             if ctx.is_static && !is_const {
-                                return Ok(call.map(|val| {
-                                    let inferred_type = mk().infer_ty();
-                                    let ptr_type = mk().mutbl().ptr_ty(inferred_type);
-                                    mk().cast_expr(val, ptr_type)
-                                }));
+                                let ptr_type = self.convert_type(ty.ctype)?;
+                                return Ok(call.map(|val| mk().cast_expr(val, ptr_type)));
                             }
 
                             Ok(call)
@@ -4415,48 +4673,6 @@ impl<'c> Translation<'c> {
         }
     }
 
-    /// Cast a f128 to some other int or float type
-    fn f128_cast_to(
-        &self,
-        val: WithStmts<Box<Expr>>,
-        target_ty_ctype: &CTypeKind,
-    ) -> TranslationResult<WithStmts<Box<Expr>>> {
-        self.use_crate(ExternCrate::NumTraits);
-
-        self.with_cur_file_item_store(|item_store| {
-            item_store.add_use(vec!["num_traits".into()], "ToPrimitive");
-        });
-        let to_method_name = match target_ty_ctype {
-            CTypeKind::Float => "to_f32",
-            CTypeKind::Double => "to_f64",
-            CTypeKind::Char => "to_i8",
-            CTypeKind::UChar => "to_u8",
-            CTypeKind::Short => "to_i16",
-            CTypeKind::UShort => "to_u16",
-            CTypeKind::Int => "to_i32",
-            CTypeKind::UInt => "to_u32",
-            CTypeKind::Long => "to_i64",
-            CTypeKind::ULong => "to_u64",
-            CTypeKind::LongLong => "to_i64",
-            CTypeKind::ULongLong => "to_u64",
-            CTypeKind::Int128 => "to_i128",
-            CTypeKind::UInt128 => "to_u128",
-            _ => {
-                return Err(format_err!(
-                    "Tried casting long double to unsupported type: {:?}",
-                    target_ty_ctype
-                )
-                .into());
-            }
-        };
-
-        Ok(val.map(|val| {
-            let to_call = mk().method_call_expr(val, to_method_name, Vec::new());
-
-            mk().method_call_expr(to_call, "unwrap", Vec::new())
-        }))
-    }
-
     /// This handles translating casts when the target type in an `enum` type.
     ///
     /// When translating variable references to `EnumConstant`'s, we always insert casts to the
@@ -4517,7 +4733,8 @@ impl<'c> Translation<'c> {
         let resolved_ty_id = self.ast_context.resolve_type_id(ty_id);
         let resolved_ty = &self.ast_context.index(resolved_ty_id).kind;
 
-        if self.ast_context.is_va_list(resolved_ty_id) {
+        // Pointer-based `va_list`s are only recognizable before resolving typedefs
+        if self.ast_context.is_va_list(ty_id) || self.ast_context.is_va_list(resolved_ty_id) {
             // generate MaybeUninit::uninit().assume_init()
             let path = vec!["core", "mem", "MaybeUninit", "uninit"];
             let call = mk().call_expr(mk().abs_path_expr(path), vec![]);
@@ -4533,9 +4750,7 @@ impl<'c> Translation<'c> {
             ))
         } else if resolved_ty.is_floating_type() {
             match self.ast_context[ty_id].kind {
-                CTypeKind::LongDouble => Ok(WithStmts::new_val(
-                    mk().path_expr(vec!["f128", "f128", "ZERO"]),
-                )),
+                CTypeKind::LongDouble => Ok(WithStmts::new_val(self.long_double_const("ZERO"))),
                 _ => Ok(WithStmts::new_val(
                     mk().lit_expr(mk().float_unsuffixed_lit("0.")),
                 )),
@@ -4719,7 +4934,7 @@ impl<'c> Translation<'c> {
             } else {
                 mk().method_call_expr(val, "is_none", vec![])
             }
-        } else if ty.is_pointer() {
+        } else if ty.is_pointer() || matches!(ty, CTypeKind::BlockPointer(..)) {
             let mut res = mk().method_call_expr(val, "is_null", vec![]);
             if target {
                 res = mk().unary_expr(UnOp::Not(Default::default()), res)
@@ -4769,7 +4984,9 @@ impl<'c> Translation<'c> {
             };
 
             // The backup is to just compare against zero
-            let zero = if ty.is_floating_type() {
+            let zero = if self.is_soft_long_double(ty_id) {
+                self.long_double_const("ZERO")
+            } else if ty.is_floating_type() {
                 mk().lit_expr(mk().float_unsuffixed_lit("0."))
             } else {
                 mk().lit_expr(mk().int_unsuffixed_lit(0))
@@ -4874,7 +5091,9 @@ impl<'c> Translation<'c> {
         match type_kind {
             // libc can be accessed from anywhere as of Rust 2019 by full path
             Void | Char | SChar | UChar | Short | UShort | Int | UInt | Long | ULong | LongLong
-            | ULongLong | Int128 | UInt128 | Half | BFloat16 | Float | Double | LongDouble => {}
+            | ULongLong | Int128 | UInt128 | Half | BFloat16 | Float | Double => {}
+            // long double may come from an external crate
+            LongDouble => self.use_long_double_crate(),
             // Bool uses the bool type, so no dependency on libc
             Bool => {}
             Paren(ctype)
@@ -4886,9 +5105,12 @@ impl<'c> Translation<'c> {
             | Attributed(CQualTypeId { ctype, .. }, _)
             | VariableArray(ctype, _)
             | Reference(CQualTypeId { ctype, .. })
-            | BlockPointer(CQualTypeId { ctype, .. })
             | TypeOf(ctype)
             | Complex(ctype) => self.import_type(*ctype, decl_file_id),
+            BlockPointer(CQualTypeId { ctype, .. }) => {
+                self.use_crate(ExternCrate::C2RustBlocks);
+                self.import_type(*ctype, decl_file_id)
+            }
             Enum(decl_id) | Typedef(decl_id) | Union(decl_id) | Struct(decl_id) => {
                 let mut decl_id = *decl_id;
                 // if the `decl` has been "squashed", get the corresponding `decl_id`
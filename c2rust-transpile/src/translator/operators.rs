@@ -186,17 +186,12 @@ impl<'c> Translation<'c> {
                 rhs,
             )))
         } else {
-            let resolved_computed_kind = &self.ast_context.resolve_type(compute_lhs_ty.ctype).kind;
             let lhs_type = self.convert_type(compute_lhs_ty.ctype)?;
 
             // We can't simply as-cast into a non primitive like f128
-            let lhs = if *resolved_computed_kind == CTypeKind::LongDouble {
-                self.use_crate(ExternCrate::F128);
-
-                let fn_path = mk().path_expr(vec!["f128", "f128", "from"]);
-                let args = vec![read];
-
-                mk().call_expr(fn_path, args)
+            let is_soft_long_double = self.is_soft_long_double(compute_lhs_ty.ctype);
+            let lhs = if is_soft_long_double {
+                self.long_double_from("from", read)
             } else {
                 mk().cast_expr(read, lhs_type.clone())
             };
@@ -220,11 +215,11 @@ impl<'c> Translation<'c> {
                 WithStmts::new_unsafe_val(transmute_expr(lhs_type, result_type, val))
             } else {
                 // We can't as-cast from a non primitive like f128 back to the result_type
-                if *resolved_computed_kind == CTypeKind::LongDouble {
+                if is_soft_long_double {
                     let resolved_lhs_kind = &self.ast_context.resolve_type(lhs_ty.ctype).kind;
                     let val = WithStmts::new_val(val);
 
-                    self.f128_cast_to(val, resolved_lhs_kind)?
+                    self.long_double_cast_to(val, resolved_lhs_kind)?
                 } else {
                     WithStmts::new_val(mk().cast_expr(val, result_type))
                 }
@@ -683,14 +678,11 @@ impl<'c> Translation<'c> {
         let one = match self.ast_context.resolve_type(ty.ctype).kind {
             // TODO: If rust gets f16 support:
             // CTypeKind::Half |
-            CTypeKind::Float | CTypeKind::Double => mk().lit_expr(mk().float_unsuffixed_lit("1.")),
-            CTypeKind::LongDouble => {
-                self.use_crate(ExternCrate::F128);
-
-                let fn_path = mk().path_expr(vec!["f128", "f128", "new"]);
-                let args = vec![mk().lit_expr(mk().float_unsuffixed_lit("1."))];
-
-                mk().call_expr(fn_path, args)
+            CTypeKind::LongDouble if self.has_soft_long_double() => {
+                self.long_double_from("new", mk().lit_expr(mk().float_unsuffixed_lit("1.")))
+            }
+            CTypeKind::Float | CTypeKind::Double | CTypeKind::LongDouble => {
+                mk().lit_expr(mk().float_unsuffixed_lit("1."))
             }
             _ => mk().lit_expr(mk().int_unsuffixed_lit(1)),
         };
@@ -744,16 +736,11 @@ impl<'c> Translation<'c> {
                 let mut one = match self.ast_context[ty.ctype].kind {
                     // TODO: If rust gets f16 support:
                     // CTypeKind::Half |
-                    CTypeKind::Float | CTypeKind::Double => {
-                        mk().lit_expr(mk().float_unsuffixed_lit("1."))
+                    CTypeKind::LongDouble if self.has_soft_long_double() => {
+                        self.long_double_from("new", mk().lit_expr(mk().float_unsuffixed_lit("1.")))
                     }
-                    CTypeKind::LongDouble => {
-                        self.use_crate(ExternCrate::F128);
-
-                        let fn_path = mk().path_expr(vec!["f128", "f128", "new"]);
-                        let args = vec![mk().lit_expr(mk().float_unsuffixed_lit("1."))];
-
-                        mk().call_expr(fn_path, args)
+                    CTypeKind::Float | CTypeKind::Double | CTypeKind::LongDouble => {
+                        mk().lit_expr(mk().float_unsuffixed_lit("1."))
                     }
                     _ => mk().lit_expr(mk().int_unsuffixed_lit(1)),
                 };
@@ -855,22 +842,29 @@ impl<'c> Translation<'c> {
                     };
 
                     arg.result_map(|a| {
-                        let mut addr_of_arg: Box<Expr>;
+                        let addr_of_arg: Box<Expr>;
 
                         if ctx.is_static {
-                            // static variable initializers aren't able to use &mut,
-                            // so we work around that by using & and an extra cast
-                            // through & to *const to *mut
-                            addr_of_arg = mk().addr_of_expr(a);
-                            if let Mutability::Mutable = mutbl {
-                                let mut qtype = pointee_ty;
-                                qtype.qualifiers.is_const = true;
-                                let ty_ = self
-                                    .type_converter
-                                    .borrow_mut()
-                                    .convert_pointer(&self.ast_context, qtype)?;
-                                addr_of_arg = mk().cast_expr(addr_of_arg, ty_);
-                            }
+                            // static variable initializers aren't able to use &mut
+                            // or dereference raw pointers, so `&*p` (which is how
+                            // `&arr[i]` comes out) is just `p`, and other places
+                            // get a *const pointer from addr_of! that is then cast
+                            // to *mut below
+                            addr_of_arg = match *a {
+                                Expr::Unary(ExprUnary {
+                                    op: UnOp::Deref(_),
+                                    expr,
+                                    ..
+                                }) => expr,
+                                place => {
+                                    use syn::__private::ToTokens;
+                                    mk().mac_expr(mk().mac(
+                                        mk().abs_path(vec!["core", "ptr", "addr_of"]),
+                                        place.into_token_stream(),
+                                        MacroDelimiter::Paren(Default::default()),
+                                    ))
+                                }
+                            };
                         } else {
                             // Normal case is allowed to use &mut if needed
                             addr_of_arg = mk().set_mutbl(mutbl).addr_of_expr(a);
@@ -0,0 +1,505 @@
+//! This module shares `static inline` functions defined in headers between
+//! the translation units of a crate.
+//!
+//! Every translation unit that uses such a function gets its own translation
+//! of it, so helpers like `min` or `list_add` would otherwise be duplicated in
+//! every module. Instead, the translator sets header definitions aside (see
+//! [`Translation::shared_item_key`]), and once all translation units are done,
+//! [`SharedItems::share`] emits each function that they all translated the same way once,
+//! as a `pub(crate)` function of the module [`SHARED_MODULE`], and imports it
+//! into the modules that use it.
+//!
+//! The header types such a function uses move to the shared module along with
+//! it, as long as all translation units define them the same way; the modules
+//! that use the types import them too. A function that refers to anything else
+//! the translation unit defines, like a static, stays in its module. Functions
+//! it calls through `extern "C"` declarations don't prevent sharing: the
+//! shared module declares them as well, and it imports whatever the translation
+//! units import from other crates, such as the `num_traits` traits whose
+//! methods the functions call.
+
+use std::collections::HashSet;
+
+use itertools::Itertools;
+
+use super::*;
+use crate::rust_ast::item_store::PathedMultiImports;
+use c2rust_ast_builder::Make;
+
+/// The name of the module holding the shared items
+pub const SHARED_MODULE: &str = "c2rust_shared";
+
+/// The header path, line and column of a C definition
+pub type SharedKey = (PathBuf, u64, u64);
+
+/// The translation of a header definition in one translation unit
+#[derive(Debug, Clone, PartialEq)]
+struct SharedItem {
+    key: SharedKey,
+    /// The names the translation defines
+    names: Vec<String>,
+    is_fn: bool,
+    /// The items as they are written in their own module
+    local: String,
+    /// The items as they are written in the shared module
+    shared: String,
+    /// `extern` blocks declaring the foreign items the items use
+    externs: Vec<String>,
+    /// The other header definitions of the translation unit the items use
+    deps: Vec<SharedKey>,
+    /// Whether the items use anything else the translation unit defines
+    is_local: bool,
+}
+
+impl SharedItem {
+    /// Whether `other` translates the same definition the same way
+    fn same_as(&self, other: &SharedItem) -> bool {
+        self.shared == other.shared && self.externs == other.externs && self.deps == other.deps
+    }
+}
+
+/// The header definitions a translation unit set aside for sharing
+#[derive(Debug, Default)]
+pub struct SharedItems {
+    items: Vec<SharedItem>,
+    /// The keys of those the rest of the translation unit uses
+    used: IndexSet<SharedKey>,
+    /// The `use` items of the translation unit's imports from other crates
+    uses: Vec<String>,
+}
+
+impl<'c> Translation<'c> {
+    /// The location of `decl_id` if it is a `static inline` function or a type
+    /// defined in a header, and its translation should be set aside for
+    /// sharing.
+    pub fn shared_item_key(&self, decl_id: CDeclId) -> Option<SharedKey> {
+        if !self.share_items {
+            return None;
+        }
+        let decl = &self.ast_context[decl_id];
+        match decl.kind {
+            CDeclKind::Function {
+                is_global: false,
+                is_inline: true,
+                body: Some(_),
+                ..
+            }
+            | CDeclKind::Struct { .. }
+            | CDeclKind::Union { .. }
+            | CDeclKind::Enum { .. }
+            | CDeclKind::EnumConstant { .. }
+            | CDeclKind::Typedef { .. } => {}
+            _ => return None,
+        }
+        let file = self.ast_context.file_id(decl)?;
+        if file == self.main_file {
+            return None;
+        }
+        let path = self.ast_context.get_file_path(file)?;
+        let loc = decl.loc.as_ref()?.begin();
+        Some((path.to_owned(), loc.line, loc.column))
+    }
+}
+
+fn collect_idents(tokens: TokenStream, idents: &mut HashSet<String>) {
+    for tree in tokens {
+        match tree {
+            TokenTree::Ident(ident) => {
+                idents.insert(ident.to_string());
+            }
+            TokenTree::Group(group) => collect_idents(group.stream(), idents),
+            TokenTree::Punct(_) | TokenTree::Literal(_) => {}
+        }
+    }
+}
+
+fn idents<T: syn::__private::ToTokens>(node: &T) -> HashSet<String> {
+    let mut idents = HashSet::new();
+    collect_idents(node.to_token_stream(), &mut idents);
+    idents
+}
+
+fn render(items: Vec<Box<Item>>) -> String {
+    pprust::to_string(|| syn::File {
+        shebang: None,
+        attrs: vec![],
+        items: items.into_iter().map(|item| *item).collect(),
+    })
+}
+
+/// Prepare the header definitions set aside by a translation unit for
+/// [`SharedItems::share`], given the rest of its main module.
+pub fn collect(
+    shared_items: Vec<(SharedKey, Vec<Box<Item>>)>,
+    items: &[Box<Item>],
+    foreign_items: &[ForeignItem],
+    uses: &PathedMultiImports,
+) -> SharedItems {
+    let keys = shared_items
+        .iter()
+        .flat_map(|(key, items)| {
+            items
+                .iter()
+                .filter_map(|item| item_ident(item))
+                .map(move |ident| (ident.to_string(), key.clone()))
+        })
+        .collect::<HashMap<_, _>>();
+    let keys_used_by = |idents: &HashSet<String>| {
+        keys.iter()
+            .filter(|(name, _)| idents.contains(*name))
+            .map(|(_, key)| key.clone())
+            .collect::<Vec<_>>()
+    };
+
+    let mut local_names = items
+        .iter()
+        .filter_map(|item| item_ident(item))
+        .map(Ident::to_string)
+        .collect::<HashSet<_>>();
+    local_names.extend(uses.local_leaves().map(str::to_owned));
+
+    // Foreign functions and statics can be declared again in the shared
+    // module, unless their signatures use the unit's own types. Foreign types
+    // can't: that would be a different type.
+    let mut externs = HashMap::new();
+    let mut used = IndexSet::new();
+    for foreign_item in foreign_items {
+        let mut foreign_idents = idents(foreign_item);
+        used.extend(keys_used_by(&foreign_idents));
+        if let Some((ident, _)) = foreign_item_ident_vis(foreign_item) {
+            let name = ident.to_string();
+            foreign_idents.remove(&name);
+            let is_portable = matches!(foreign_item, ForeignItem::Fn(_) | ForeignItem::Static(_))
+                && foreign_idents.is_disjoint(&local_names);
+            let decl = is_portable.then(|| {
+                let decl = mk().extern_("C").foreign_items(vec![foreign_item.clone()]);
+                (render(vec![decl]), keys_used_by(&foreign_idents))
+            });
+            externs.insert(name, decl);
+        }
+    }
+    for item in items {
+        used.extend(keys_used_by(&idents(&**item)));
+    }
+
+    let items = shared_items
+        .into_iter()
+        .map(|(key, items)| {
+            let names = items
+                .iter()
+                .filter_map(|item| item_ident(item))
+                .map(Ident::to_string)
+                .collect::<Vec<_>>();
+            let mut used = HashSet::new();
+            for item in &items {
+                used.extend(idents(&**item));
+            }
+            for name in &names {
+                used.remove(name);
+            }
+
+            let mut is_local = !used.is_disjoint(&local_names);
+            let mut deps = keys_used_by(&used);
+            let mut item_externs = vec![];
+            for (name, decl) in &externs {
+                if used.contains(name) {
+                    match decl {
+                        Some((decl, decl_deps)) => {
+                            item_externs.push(decl.clone());
+                            deps.extend(decl_deps.iter().cloned());
+                        }
+                        None => is_local = true,
+                    }
+                }
+            }
+            item_externs.sort();
+            deps.sort();
+            deps.dedup();
+
+            let is_fn = items.iter().any(|item| matches!(**item, Item::Fn(_)));
+            let mut shared = items.clone();
+            for item in &mut shared {
+                if let Item::Fn(ref mut f) = **item {
+                    f.vis = Make::<Visibility>::make("pub(crate)", &mk());
+                }
+            }
+            SharedItem {
+                key,
+                names,
+                is_fn,
+                local: render(items),
+                shared: render(shared),
+                externs: item_externs,
+                deps,
+                is_local,
+            }
+        })
+        .collect();
+
+    let uses = uses
+        .external_items()
+        .into_iter()
+        .map(|item| render(vec![item]))
+        .collect();
+
+    SharedItems { items, used, uses }
+}
+
+impl SharedItems {
+    /// Decide which of the definitions set aside by `units` to share, and return
+    /// the source of the shared module and, for each unit, the source to append to
+    /// its module: the imports of the shared items it uses and its own copies of
+    /// the rest. `module` is the path of the shared module; without it, nothing is
+    /// shared.
+    pub fn share(units: &[SharedItems], module: Option<&[String]>) -> (String, Vec<String>) {
+        let mut translations = IndexMap::<&SharedKey, Vec<&SharedItem>>::new();
+        for unit in units {
+            for item in &unit.items {
+                translations.entry(&item.key).or_default().push(item);
+            }
+        }
+
+        // Candidates are definitions all units translated the same way, without
+        // using their own items. Functions are only worth sharing if several units
+        // use them.
+        let mut shared = translations
+            .iter()
+            .filter(|(_, items)| {
+                items
+                    .iter()
+                    .all(|item| !item.is_local && item.same_as(items[0]))
+                    && (!items[0].is_fn || items.len() > 1)
+            })
+            .map(|(&key, items)| (key, items[0]))
+            .collect::<IndexMap<_, _>>();
+        if module.is_none() {
+            shared.clear();
+        }
+
+        // The shared module can't hold two items of the same name, needs all the
+        // definitions its items use, and only holds types its functions need.
+        loop {
+            let mut names = HashMap::<&str, usize>::new();
+            for item in shared.values() {
+                for name in &item.names {
+                    *names.entry(name.as_str()).or_default() += 1;
+                }
+            }
+            let unique = shared
+                .iter()
+                .filter(|(_, item)| item.names.iter().all(|name| names[name.as_str()] == 1))
+                .map(|(&key, _)| key)
+                .collect::<HashSet<_>>();
+            let needed = shared
+                .values()
+                .flat_map(|item| &item.deps)
+                .collect::<HashSet<_>>();
+            let before = shared.len();
+            shared.retain(|key, item| {
+                unique.contains(key)
+                    && item.deps.iter().all(|dep| unique.contains(dep))
+                    && (item.is_fn || needed.contains(key))
+            });
+            if shared.len() == before {
+                break;
+            }
+        }
+
+        // Not all units need every import, so some may go unused
+        let mut uses = IndexSet::new();
+        for unit in units {
+            if unit.items.iter().any(|item| shared.contains_key(&item.key)) {
+                uses.extend(unit.uses.iter().map(String::as_str));
+            }
+        }
+        let mut externs = IndexSet::new();
+        let mut shared_items = vec![];
+        for item in shared.values() {
+            externs.extend(item.externs.iter().map(String::as_str));
+            shared_items.push(item.shared.as_str());
+        }
+        let allow_unused = (!uses.is_empty()).then(|| "#![allow(unused_imports)]\n");
+        let shared_module = allow_unused
+            .into_iter()
+            .chain(uses)
+            .chain(externs)
+            .chain(shared_items)
+            .join("\n");
+
+        let appendices = units
+            .iter()
+            .map(|unit| {
+                let mut imported = unit.used.iter().collect::<IndexSet<_>>();
+                let mut copies = vec![];
+                for item in &unit.items {
+                    if !shared.contains_key(&item.key) {
+                        imported.extend(item.deps.iter());
+                        copies.push(item.local.as_str());
+                    }
+                }
+                let mut appendix = unit
+                    .items
+                    .iter()
+                    .filter(|item| shared.contains_key(&item.key) && imported.contains(&item.key))
+                    .flat_map(|item| &item.names)
+                    .map(|name| format!("use {}::{};\n", module.unwrap().join("::"), name))
+                    .collect::<String>();
+                for copy in copies {
+                    if !appendix.is_empty() {
+                        appendix.push('\n');
+                    }
+                    appendix.push_str(copy);
+                }
+                appendix
+            })
+            .collect();
+
+        (shared_module, appendices)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(line: u64) -> SharedKey {
+        (PathBuf::from("list.h"), line, 1)
+    }
+
+    fn shared_item(line: u64, name: &str, body: &str, deps: &[u64]) -> SharedItem {
+        let is_fn = body != "struct";
+        let (local, shared) = if is_fn {
+            (
+                format!("unsafe extern \"C\" fn {}() {{ {} }}\n", name, body),
+                format!(
+                    "pub(crate) unsafe extern \"C\" fn {}() {{ {} }}\n",
+                    name, body
+                ),
+            )
+        } else {
+            let item = format!("pub struct {};\n", name);
+            (item.clone(), item)
+        };
+        SharedItem {
+            key: key(line),
+            names: vec![name.to_owned()],
+            is_fn,
+            local,
+            shared,
+            externs: vec![],
+            deps: deps.iter().copied().map(key).collect(),
+            is_local: false,
+        }
+    }
+
+    fn unit(items: Vec<SharedItem>) -> SharedItems {
+        let used = items.iter().map(|item| item.key.clone()).collect();
+        SharedItems {
+            items,
+            used,
+            uses: vec![],
+        }
+    }
+
+    fn module() -> Vec<String> {
+        vec!["crate".to_owned(), SHARED_MODULE.to_owned()]
+    }
+
+    #[test]
+    fn shares_identical_translations() {
+        let units = [
+            unit(vec![shared_item(1, "min", "", &[])]),
+            unit(vec![shared_item(1, "min", "", &[])]),
+        ];
+        let (shared, appendices) = SharedItems::share(&units, Some(&module()));
+        assert_eq!(shared, units[0].items[0].shared);
+        assert_eq!(appendices, ["use crate::c2rust_shared::min;\n"; 2]);
+
+        let (shared, appendices) = SharedItems::share(&units, None);
+        assert!(shared.is_empty());
+        assert_eq!(appendices, [units[0].items[0].local.as_str(); 2]);
+    }
+
+    #[test]
+    fn shares_types_with_the_functions_using_them() {
+        let list = || {
+            unit(vec![
+                shared_item(1, "list_head", "struct", &[]),
+                shared_item(2, "list_add", "", &[1]),
+                shared_item(3, "unused", "struct", &[]),
+            ])
+        };
+        let units = [list(), list()];
+        let (shared, appendices) = SharedItems::share(&units, Some(&module()));
+        assert_eq!(
+            shared,
+            "pub struct list_head;\n\npub(crate) unsafe extern \"C\" fn list_add() {  }\n"
+        );
+        assert!(appendices[0].starts_with("use crate::c2rust_shared::list_head;\n"));
+        assert!(appendices[0].ends_with("\npub struct unused;\n"));
+
+        // If one unit defines the type differently, the function can't be shared
+        let mut units = [list(), list()];
+        units[1].items[0].shared = "pub struct list_head(i32);\n".to_owned();
+        let (shared, _) = SharedItems::share(&units, Some(&module()));
+        assert!(shared.is_empty());
+    }
+
+    #[test]
+    fn keeps_differing_translations_local() {
+        let mut local = shared_item(1, "max", "", &[]);
+        local.is_local = true;
+        let units = [
+            unit(vec![
+                shared_item(1, "min", "", &[]),
+                shared_item(2, "clamp", "", &[1]),
+                local.clone(),
+            ]),
+            unit(vec![
+                shared_item(1, "min", "a", &[]),
+                shared_item(2, "clamp", "", &[1]),
+                local,
+            ]),
+            unit(vec![shared_item(3, "swap", "", &[])]),
+        ];
+        let (shared, appendices) = SharedItems::share(&units, Some(&module()));
+        assert!(shared.is_empty());
+        assert!(appendices[0].contains("fn min() {  }"));
+        assert!(appendices[1].contains("fn min() { a }"));
+        assert_eq!(appendices[2], units[2].items[0].local);
+    }
+
+    #[test]
+    fn imports_what_the_units_import() {
+        let float = "use num_traits::Float;\n".to_owned();
+        let to_primitive = "use num_traits::ToPrimitive;\n".to_owned();
+        let mut units = [
+            unit(vec![shared_item(1, "signbit", "", &[])]),
+            unit(vec![shared_item(1, "signbit", "", &[])]),
+            unit(vec![shared_item(2, "unshared", "", &[])]),
+        ];
+        units[0].uses = vec![float.clone()];
+        units[1].uses = vec![float.clone(), to_primitive.clone()];
+        units[2].uses = vec!["use core::arch::asm;\n".to_owned()];
+        let (shared, _) = SharedItems::share(&units, Some(&module()));
+        let expected = [
+            "#![allow(unused_imports)]\n",
+            float.as_str(),
+            to_primitive.as_str(),
+            units[0].items[0].shared.as_str(),
+        ];
+        assert_eq!(shared, expected.join("\n"));
+    }
+
+    #[test]
+    fn imports_shared_functions_used_by_local_copies() {
+        let mut clamp = shared_item(2, "clamp", "", &[1]);
+        clamp.is_local = true;
+        let mut first = unit(vec![shared_item(1, "min", "", &[]), clamp]);
+        first.used.shift_remove(&key(1));
+        let units = [first, unit(vec![shared_item(1, "min", "", &[])])];
+        let (_, appendices) = SharedItems::share(&units, Some(&module()));
+        assert!(appendices[0].starts_with("use crate::c2rust_shared::min;\n"));
+        assert!(appendices[0].ends_with(&units[0].items[1].local));
+    }
+}
@@ -107,7 +107,8 @@ impl<'a> Translation<'a> {
 
                 let ctype = typ.ctype;
                 // TODO: clean up code and avoid code duplication
-                // TODO: handle or panic on structs with more than one va_list?
+                // All `va_list` fields share the struct's `'a` lifetime parameter,
+                // so any number of them can be stored in one struct.
                 let is_va_list = self.ast_context.is_va_list(ctype);
                 let mut ty = if is_va_list {
                     let path = vec![
@@ -175,7 +176,10 @@ impl<'a> Translation<'a> {
                             })
                         }
 
-                        let field = mk().pub_().struct_field(field_name.clone(), ty);
+                        let field = self
+                            .mk_with_docs(*field_id)
+                            .pub_()
+                            .struct_field(field_name.clone(), ty);
 
                         reorganized_fields.push(FieldType::Regular {
                             name: field_name,
@@ -0,0 +1,836 @@
+#![no_std]
+//! A software implementation of the x87 80-bit extended precision format, which is
+//! what C's `long double` is on x86 and x86_64 Linux and BSD.
+//!
+//! [`f80`] has the same in-memory layout as the C type, so structs, arrays and
+//! pointers containing `long double` keep their layout when translated. Passing
+//! an [`f80`] *by value* to C is not ABI compatible, since C passes `long double`
+//! on the x87 stack or in memory; C2Rust avoids doing so where it can, for example
+//! by converting `long double` arguments of `printf`-like functions to `double`.
+//!
+//! Arithmetic is correctly rounded to nearest, ties to even, like the x87 FPU with
+//! its default control word.
+
+use core::cmp::Ordering;
+use core::fmt;
+use core::ops::{Add, AddAssign, Div, DivAssign, Mul, MulAssign, Neg, Sub, SubAssign};
+use core::str::FromStr;
+
+const EXP_BIAS: i32 = 16383;
+const EXP_MAX: u16 = 0x7fff;
+const INT_BIT: u64 = 1 << 63;
+const SIGN_BIT: u16 = 0x8000;
+
+/// An x87 80-bit extended precision float.
+#[allow(non_camel_case_types)]
+#[derive(Copy, Clone, Default)]
+#[cfg_attr(target_arch = "x86_64", repr(C, align(16)))]
+#[cfg_attr(not(target_arch = "x86_64"), repr(C))]
+pub struct f80 {
+    /// The significand, including the explicit integer bit.
+    significand: u64,
+    /// The sign bit followed by the 15-bit biased exponent.
+    sign_exponent: u16,
+}
+
+/// An [`f80`] broken down for arithmetic.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum Unpacked {
+    Nan,
+    Infinite,
+    Zero,
+    /// `significand / 2^63 * 2^exponent`, with the top bit of `significand` set.
+    Finite {
+        exponent: i32,
+        significand: u64,
+    },
+}
+
+/// Shift right, OR-ing any bits shifted out into the lowest bit so that rounding
+/// can still tell the result was inexact.
+fn shr_sticky(m: u128, shift: u32) -> u128 {
+    if shift == 0 {
+        m
+    } else if shift >= 128 {
+        (m != 0) as u128
+    } else {
+        (m >> shift) | ((m & ((1 << shift) - 1) != 0) as u128)
+    }
+}
+
+impl f80 {
+    pub const ZERO: f80 = f80::from_parts(false, 0, 0);
+    pub const ONE: f80 = f80::from_parts(false, EXP_BIAS as u16, INT_BIT);
+    pub const INFINITY: f80 = f80::from_parts(false, EXP_MAX, INT_BIT);
+    pub const NEG_INFINITY: f80 = f80::from_parts(true, EXP_MAX, INT_BIT);
+    pub const NAN: f80 = f80::from_parts(false, EXP_MAX, INT_BIT | (1 << 62));
+
+    const fn from_parts(sign: bool, exponent: u16, significand: u64) -> f80 {
+        f80 {
+            significand,
+            sign_exponent: if sign { SIGN_BIT } else { 0 } | exponent,
+        }
+    }
+
+    /// Convert any number that C could implicitly convert to `long double`.
+    pub fn new<T: Into<f80>>(x: T) -> f80 {
+        x.into()
+    }
+
+    /// Parse a decimal floating literal such as `1.5`, `.25e-3` or `6.02214076e23`.
+    ///
+    /// # Panics
+    ///
+    /// If `s` is not a valid literal.
+    pub fn from_lit(s: &str) -> f80 {
+        s.parse().expect("invalid floating point literal")
+    }
+
+    /// The raw 80 bits, with the sign in bit 79.
+    pub const fn to_bits(self) -> u128 {
+        ((self.sign_exponent as u128) << 64) | self.significand as u128
+    }
+
+    /// Build an `f80` from its raw 80 bits; bits above 79 are ignored.
+    pub const fn from_bits(bits: u128) -> f80 {
+        f80 {
+            significand: bits as u64,
+            sign_exponent: (bits >> 64) as u16,
+        }
+    }
+
+    fn sign(self) -> bool {
+        self.sign_exponent & SIGN_BIT != 0
+    }
+
+    fn biased_exponent(self) -> u16 {
+        self.sign_exponent & EXP_MAX
+    }
+
+    fn unpack(self) -> Unpacked {
+        let exponent = self.biased_exponent();
+        let significand = self.significand;
+        if exponent == EXP_MAX {
+            // Pseudo-infinities and pseudo-NaNs (integer bit clear) are invalid
+            // operands on the 387 and later, so treat them as NaN too.
+            if significand == INT_BIT {
+                Unpacked::Infinite
+            } else {
+                Unpacked::Nan
+            }
+        } else if significand == 0 {
+            Unpacked::Zero
+        } else {
+            // Denormals (and pseudo-denormals) have an effective exponent of 1;
+            // unnormals are simply normalized.
+            let shift = significand.leading_zeros();
+            Unpacked::Finite {
+                exponent: (exponent as i32).max(1) - EXP_BIAS - shift as i32,
+                significand: significand << shift,
+            }
+        }
+    }
+
+    /// Round `m / 2^127 * 2^exponent` to the nearest `f80`, ties to even.
+    /// The top bit of `m` must be set.
+    fn round_pack(sign: bool, exponent: i32, m: u128) -> f80 {
+        debug_assert!(m >> 127 == 1);
+        let mut biased = exponent + EXP_BIAS;
+        if biased >= EXP_MAX as i32 {
+            return if sign {
+                f80::NEG_INFINITY
+            } else {
+                f80::INFINITY
+            };
+        }
+        let mut m = m;
+        if biased <= 0 {
+            m = shr_sticky(m, (1 - biased) as u32);
+            biased = 0;
+        }
+
+        let mut significand = (m >> 64) as u64;
+        let rest = m as u64;
+        const HALF: u64 = 1 << 63;
+        if rest > HALF || (rest == HALF && significand & 1 == 1) {
+            significand = significand.wrapping_add(1);
+            if significand == 0 {
+                // Carried out of the top: 1.111... rounded up to 10.000...
+                significand = INT_BIT;
+                biased += 1;
+            }
+        }
+        if biased == 0 && significand & INT_BIT != 0 {
+            // A denormal rounded up to the smallest normal
+            biased = 1;
+        }
+        if biased >= EXP_MAX as i32 {
+            return if sign {
+                f80::NEG_INFINITY
+            } else {
+                f80::INFINITY
+            };
+        }
+        f80::from_parts(sign, biased as u16, significand)
+    }
+
+    /// Round to an IEEE binary format with `mant_bits` explicit fraction bits
+    /// and `exp_bits` exponent bits, returning its bits.
+    fn to_ieee(self, mant_bits: u32, exp_bits: u32) -> u64 {
+        let sign = (self.sign() as u64) << (mant_bits + exp_bits);
+        let exp_max = (1u64 << exp_bits) - 1;
+        let bias = (exp_max >> 1) as i32;
+        let inf = sign | (exp_max << mant_bits);
+        let (exponent, significand) = match self.unpack() {
+            Unpacked::Nan => return inf | (1 << (mant_bits - 1)),
+            Unpacked::Infinite => return inf,
+            Unpacked::Zero => return sign,
+            Unpacked::Finite {
+                exponent,
+                significand,
+            } => (exponent, significand),
+        };
+
+        let mut biased = exponent + bias;
+        if biased >= exp_max as i32 {
+            return inf;
+        }
+        let mut m = significand as u128;
+        if biased <= 0 {
+            m = shr_sticky(m, (1 - biased) as u32);
+            biased = 0;
+        }
+        let m = m as u64;
+        let drop = 63 - mant_bits;
+        let mut kept = m >> drop;
+        let rest = m & ((1 << drop) - 1);
+        let half = 1 << (drop - 1);
+        if rest > half || (rest == half && kept & 1 == 1) {
+            kept += 1;
+            if kept == 1 << (mant_bits + 1) {
+                kept >>= 1;
+                biased += 1;
+            }
+        }
+        if biased == 0 && kept & (1 << mant_bits) != 0 {
+            biased = 1;
+        }
+        if biased >= exp_max as i32 {
+            return inf;
+        }
+        sign | ((biased as u64) << mant_bits) | (kept & ((1 << mant_bits) - 1))
+    }
+
+    fn from_ieee(bits: u64, mant_bits: u32, exp_bits: u32) -> f80 {
+        let sign = (bits >> (mant_bits + exp_bits)) & 1 == 1;
+        let exp_max = (1u64 << exp_bits) - 1;
+        let bias = (exp_max >> 1) as i32;
+        let exponent = (bits >> mant_bits) & exp_max;
+        let fraction = bits & ((1 << mant_bits) - 1);
+        if exponent == exp_max {
+            return if fraction != 0 {
+                f80::NAN
+            } else if sign {
+                f80::NEG_INFINITY
+            } else {
+                f80::INFINITY
+            };
+        }
+        if exponent == 0 && fraction == 0 {
+            return f80::from_parts(sign, 0, 0);
+        }
+        let (exponent, significand) = if exponent == 0 {
+            let shift = fraction.leading_zeros();
+            (
+                1 - bias - (shift as i32 - (63 - mant_bits as i32)),
+                fraction << shift,
+            )
+        } else {
+            (
+                exponent as i32 - bias,
+                INT_BIT | (fraction << (63 - mant_bits)),
+            )
+        };
+        // Always exactly representable
+        f80::round_pack(sign, exponent, (significand as u128) << 64)
+    }
+
+    fn from_u128(sign: bool, x: u128) -> f80 {
+        if x == 0 {
+            return f80::from_parts(sign, 0, 0);
+        }
+        let shift = x.leading_zeros();
+        f80::round_pack(sign, 127 - shift as i32, x << shift)
+    }
+
+    /// Truncate towards zero, returning the sign and magnitude, or `None` for
+    /// NaN, infinities and values too large for a `u128`.
+    fn trunc_u128(self) -> Option<(bool, u128)> {
+        match self.unpack() {
+            Unpacked::Nan | Unpacked::Infinite => None,
+            Unpacked::Zero => Some((self.sign(), 0)),
+            Unpacked::Finite {
+                exponent,
+                significand,
+            } => {
+                let magnitude = if exponent < 0 {
+                    0
+                } else if exponent <= 63 {
+                    (significand >> (63 - exponent)) as u128
+                } else if exponent <= 127 {
+                    (significand as u128) << (exponent - 63)
+                } else {
+                    return None;
+                };
+                Some((self.sign(), magnitude))
+            }
+        }
+    }
+
+    pub fn is_nan(self) -> bool {
+        self.unpack() == Unpacked::Nan
+    }
+
+    pub fn is_infinite(self) -> bool {
+        self.unpack() == Unpacked::Infinite
+    }
+
+    pub fn is_finite(self) -> bool {
+        !matches!(self.unpack(), Unpacked::Nan | Unpacked::Infinite)
+    }
+
+    pub fn is_sign_negative(self) -> bool {
+        self.sign()
+    }
+
+    pub fn is_sign_positive(self) -> bool {
+        !self.sign()
+    }
+
+    pub fn abs(self) -> f80 {
+        f80::from_bits(self.to_bits() & !((SIGN_BIT as u128) << 64))
+    }
+
+    pub fn to_f64(self) -> Option<f64> {
+        Some(f64::from_bits(self.to_ieee(52, 11)))
+    }
+
+    pub fn to_f32(self) -> Option<f32> {
+        Some(f32::from_bits(self.to_ieee(23, 8) as u32))
+    }
+
+    pub fn to_u128(self) -> Option<u128> {
+        match self.trunc_u128()? {
+            (true, m) if m != 0 => None,
+            (_, m) => Some(m),
+        }
+    }
+
+    pub fn to_i128(self) -> Option<i128> {
+        match self.trunc_u128()? {
+            (true, m) if m <= 1 << 127 => Some((m as i128).wrapping_neg()),
+            (false, m) if m < 1 << 127 => Some(m as i128),
+            _ => None,
+        }
+    }
+}
+
+macro_rules! impl_to_int {
+    ($($method:ident: $ty:ty = $via:ident),*) => {
+        impl f80 {
+            $(
+                /// Truncate towards zero, or `None` if the result doesn't fit.
+                pub fn $method(self) -> Option<$ty> {
+                    self.$via().and_then(|x| <$ty>::try_from(x).ok())
+                }
+            )*
+        }
+    };
+}
+
+impl_to_int! {
+    to_i8: i8 = to_i128,
+    to_i16: i16 = to_i128,
+    to_i32: i32 = to_i128,
+    to_i64: i64 = to_i128,
+    to_isize: isize = to_i128,
+    to_u8: u8 = to_u128,
+    to_u16: u16 = to_u128,
+    to_u32: u32 = to_u128,
+    to_u64: u64 = to_u128,
+    to_usize: usize = to_u128
+}
+
+macro_rules! impl_from_int {
+    ($($ty:ty),*) => {
+        $(
+            impl From<$ty> for f80 {
+                #[allow(unused_comparisons)]
+                fn from(x: $ty) -> f80 {
+                    let sign = x < 0;
+                    f80::from_u128(sign, (x as i128).unsigned_abs())
+                }
+            }
+        )*
+    };
+}
+
+impl_from_int!(i8, i16, i32, i64, isize, u8, u16, u32, u64, usize);
+
+impl From<i128> for f80 {
+    fn from(x: i128) -> f80 {
+        f80::from_u128(x < 0, x.unsigned_abs())
+    }
+}
+
+impl From<u128> for f80 {
+    fn from(x: u128) -> f80 {
+        f80::from_u128(false, x)
+    }
+}
+
+impl From<f64> for f80 {
+    fn from(x: f64) -> f80 {
+        f80::from_ieee(x.to_bits(), 52, 11)
+    }
+}
+
+impl From<f32> for f80 {
+    fn from(x: f32) -> f80 {
+        f80::from_ieee(x.to_bits() as u64, 23, 8)
+    }
+}
+
+impl From<bool> for f80 {
+    fn from(x: bool) -> f80 {
+        f80::from(x as u8)
+    }
+}
+
+impl Neg for f80 {
+    type Output = f80;
+    fn neg(self) -> f80 {
+        f80::from_bits(self.to_bits() ^ ((SIGN_BIT as u128) << 64))
+    }
+}
+
+impl Add for f80 {
+    type Output = f80;
+    fn add(self, rhs: f80) -> f80 {
+        let (a, b) = (self.unpack(), rhs.unpack());
+        let (sa, sb) = (self.sign(), rhs.sign());
+        match (a, b) {
+            (Unpacked::Nan, _) | (_, Unpacked::Nan) => f80::NAN,
+            (Unpacked::Infinite, Unpacked::Infinite) if sa != sb => f80::NAN,
+            (Unpacked::Infinite, _) => self,
+            (_, Unpacked::Infinite) => rhs,
+            (Unpacked::Zero, Unpacked::Zero) => f80::from_parts(sa && sb, 0, 0),
+            (Unpacked::Zero, _) => rhs,
+            (_, Unpacked::Zero) => self,
+            (
+                Unpacked::Finite {
+                    exponent: ea,
+                    significand: ma,
+                },
+                Unpacked::Finite {
+                    exponent: eb,
+                    significand: mb,
+                },
+            ) => {
+                // Make `a` the operand with the larger exponent
+                let ((ea, ma, sa), (eb, mb, sb)) = if ea >= eb {
+                    ((ea, ma, sa), (eb, mb, sb))
+                } else {
+                    ((eb, mb, sb), (ea, ma, sa))
+                };
+                // Leave a spare top bit for the carry
+                let a = (ma as u128) << 63;
+                let b = shr_sticky((mb as u128) << 63, (ea - eb) as u32);
+                let exponent = ea + 1;
+                let (sign, m) = if sa == sb {
+                    (sa, a + b)
+                } else if a >= b {
+                    (sa, a - b)
+                } else {
+                    (sb, b - a)
+                };
+                if m == 0 {
+                    return f80::ZERO;
+                }
+                let shift = m.leading_zeros();
+                f80::round_pack(sign, exponent - shift as i32, m << shift)
+            }
+        }
+    }
+}
+
+impl Sub for f80 {
+    type Output = f80;
+    fn sub(self, rhs: f80) -> f80 {
+        self + -rhs
+    }
+}
+
+impl Mul for f80 {
+    type Output = f80;
+    fn mul(self, rhs: f80) -> f80 {
+        let sign = self.sign() != rhs.sign();
+        match (self.unpack(), rhs.unpack()) {
+            (Unpacked::Nan, _) | (_, Unpacked::Nan) => f80::NAN,
+            (Unpacked::Infinite, Unpacked::Zero) | (Unpacked::Zero, Unpacked::Infinite) => f80::NAN,
+            (Unpacked::Infinite, _) | (_, Unpacked::Infinite) => {
+                f80::from_parts(sign, EXP_MAX, INT_BIT)
+            }
+            (Unpacked::Zero, _) | (_, Unpacked::Zero) => f80::from_parts(sign, 0, 0),
+            (
+                Unpacked::Finite {
+                    exponent: ea,
+                    significand: ma,
+                },
+                Unpacked::Finite {
+                    exponent: eb,
+                    significand: mb,
+                },
+            ) => {
+                let m = ma as u128 * mb as u128;
+                let shift = m.leading_zeros();
+                f80::round_pack(sign, ea + eb + 1 - shift as i32, m << shift)
+            }
+        }
+    }
+}
+
+impl Div for f80 {
+    type Output = f80;
+    fn div(self, rhs: f80) -> f80 {
+        let sign = self.sign() != rhs.sign();
+        match (self.unpack(), rhs.unpack()) {
+            (Unpacked::Nan, _) | (_, Unpacked::Nan) => f80::NAN,
+            (Unpacked::Infinite, Unpacked::Infinite) | (Unpacked::Zero, Unpacked::Zero) => f80::NAN,
+            (Unpacked::Infinite, _) | (_, Unpacked::Zero) => {
+                f80::from_parts(sign, EXP_MAX, INT_BIT)
+            }
+            (_, Unpacked::Infinite) | (Unpacked::Zero, _) => f80::from_parts(sign, 0, 0),
+            (
+                Unpacked::Finite {
+                    exponent: ea,
+                    significand: ma,
+                },
+                Unpacked::Finite {
+                    exponent: eb,
+                    significand: mb,
+                },
+            ) => {
+                // Scale the dividend so the first quotient digit has its top bit set
+                let (shift, exponent) = if ma >= mb {
+                    (63, ea - eb)
+                } else {
+                    (64, ea - eb - 1)
+                };
+                let n = (ma as u128) << shift;
+                let (q1, r1) = (n / mb as u128, n % mb as u128);
+                let n = r1 << 64;
+                let (q2, r2) = (n / mb as u128, n % mb as u128);
+                let m = (q1 << 64) | q2 | (r2 != 0) as u128;
+                f80::round_pack(sign, exponent, m)
+            }
+        }
+    }
+}
+
+macro_rules! impl_assign_op {
+    ($($trait:ident::$method:ident => $op:tt),*) => {
+        $(
+            impl $trait for f80 {
+                fn $method(&mut self, rhs: f80) {
+                    *self = *self $op rhs;
+                }
+            }
+        )*
+    };
+}
+
+impl_assign_op! {
+    AddAssign::add_assign => +,
+    SubAssign::sub_assign => -,
+    MulAssign::mul_assign => *,
+    DivAssign::div_assign => /
+}
+
+impl PartialEq for f80 {
+    fn eq(&self, other: &f80) -> bool {
+        self.partial_cmp(other) == Some(Ordering::Equal)
+    }
+}
+
+impl PartialOrd for f80 {
+    fn partial_cmp(&self, other: &f80) -> Option<Ordering> {
+        // Map each value to a key that orders like the real number it represents
+        let key = |x: f80| -> Option<(i8, i32, u64)> {
+            let (exponent, significand) = match x.unpack() {
+                Unpacked::Nan => return None,
+                Unpacked::Zero => return Some((0, 0, 0)),
+                Unpacked::Infinite => (i32::MAX, 0),
+                Unpacked::Finite {
+                    exponent,
+                    significand,
+                } => (exponent, significand),
+            };
+            Some(if x.sign() {
+                (-1, -exponent, !significand)
+            } else {
+                (1, exponent, significand)
+            })
+        };
+        Some(key(*self)?.cmp(&key(*other)?))
+    }
+}
+
+/// The error from parsing an invalid decimal literal.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseF80Error;
+
+impl fmt::Display for ParseF80Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("invalid floating point literal")
+    }
+}
+
+/// An intermediate value `m / 2^127 * 2^exponent` carried with 128 bits of
+/// precision while scaling a decimal literal by powers of ten.
+struct Wide {
+    exponent: i32,
+    m: u128,
+}
+
+impl Wide {
+    fn mul10(&mut self) {
+        // 10 * m needs up to 132 bits, so split m into 64-bit halves
+        let hi = (self.m >> 64) * 10;
+        let lo = (self.m as u64 as u128) * 10;
+        let top = (hi >> 64) + ((hi as u64 as u128 + (lo >> 64)) >> 64);
+        let low = (hi << 64).wrapping_add(lo);
+        // `top` holds the 3 or 4 bits that overflowed
+        let shift = 128 - top.leading_zeros();
+        self.m = (top << (128 - shift)) | shr_sticky(low, shift);
+        self.exponent += shift as i32;
+    }
+
+    fn div10(&mut self) {
+        let (q, r) = (self.m / 10, self.m % 10);
+        let shift = q.leading_zeros();
+        let n = r << shift;
+        self.m = (q << shift) | (n / 10) | (n % 10 != 0) as u128;
+        self.exponent -= shift as i32;
+    }
+}
+
+impl FromStr for f80 {
+    type Err = ParseF80Error;
+
+    fn from_str(s: &str) -> Result<f80, ParseF80Error> {
+        let (sign, s) = match s.as_bytes().first() {
+            Some(b'-') => (true, &s[1..]),
+            Some(b'+') => (false, &s[1..]),
+            _ => (false, s),
+        };
+        let (mantissa, exp10) = match s.find(|c| c == 'e' || c == 'E') {
+            Some(i) => {
+                let exp = s[i + 1..].parse::<i32>().map_err(|_| ParseF80Error)?;
+                (&s[..i], exp)
+            }
+            None => (s, 0),
+        };
+
+        // Collect up to 38 significant digits, which always fit in a u128, and
+        // remember whether any nonzero digits were dropped after that
+        let mut digits: u128 = 0;
+        let mut num_digits = 0;
+        let mut exp10 = exp10 as i64;
+        let mut inexact = false;
+        let mut seen_point = false;
+        let mut seen_digit = false;
+        for c in mantissa.bytes() {
+            match c {
+                b'.' if !seen_point => seen_point = true,
+                b'0'..=b'9' => {
+                    seen_digit = true;
+                    let d = (c - b'0') as u128;
+                    if num_digits < 38 {
+                        if digits != 0 || d != 0 {
+                            num_digits += 1;
+                        }
+                        digits = digits * 10 + d;
+                        if seen_point {
+                            exp10 -= 1;
+                        }
+                    } else {
+                        inexact |= d != 0;
+                        if !seen_point {
+                            exp10 += 1;
+                        }
+                    }
+                }
+                _ => return Err(ParseF80Error),
+            }
+        }
+        if !seen_digit {
+            return Err(ParseF80Error);
+        }
+        if digits == 0 {
+            return Ok(f80::from_parts(sign, 0, 0));
+        }
+
+        let shift = digits.leading_zeros();
+        let mut wide = Wide {
+            exponent: 127 - shift as i32,
+            m: (digits << shift) | inexact as u128,
+        };
+        // Anything beyond these bounds has long since overflowed or underflowed
+        let exp10 = exp10.clamp(-6000, 6000);
+        for _ in 0..exp10.max(0) {
+            wide.mul10();
+            if wide.exponent > 20000 {
+                break;
+            }
+        }
+        for _ in 0..(-exp10).max(0) {
+            wide.div10();
+            if wide.exponent < -20000 {
+                break;
+            }
+        }
+        Ok(f80::round_pack(sign, wide.exponent, wide.m))
+    }
+}
+
+impl fmt::Debug for f80 {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Debug::fmt(&self.to_f64().unwrap(), f)
+    }
+}
+
+/// Formats through `f64`, so only about 17 significant digits are exact.
+impl fmt::Display for f80 {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Display::fmt(&self.to_f64().unwrap(), f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::f80;
+
+    fn bits(x: f80) -> u128 {
+        x.to_bits()
+    }
+
+    #[test]
+    fn test_layout() {
+        #[cfg(target_arch = "x86_64")]
+        assert_eq!(core::mem::size_of::<f80>(), 16);
+        assert_eq!(bits(f80::ONE), 0x3fff_8000_0000_0000_0000);
+        assert_eq!(bits(f80::from(-2.0f64)), 0xc000_8000_0000_0000_0000);
+    }
+
+    #[test]
+    fn test_round_trip_f64() {
+        for &x in &[
+            0.0,
+            -0.0,
+            1.0,
+            0.1,
+            -3.75,
+            1e300,
+            5e-324,
+            2.2250738585072014e-308,
+            f64::MAX,
+            f64::INFINITY,
+            f64::NEG_INFINITY,
+        ] {
+            let y = f80::from(x).to_f64().unwrap();
+            assert_eq!(x.to_bits(), y.to_bits(), "{}", x);
+        }
+        assert!(f80::from(f64::NAN).is_nan());
+        assert!(f80::from(f64::NAN).to_f64().unwrap().is_nan());
+    }
+
+    #[test]
+    fn test_arithmetic_matches_f64_when_exact() {
+        let cases = [(1.5, 2.25), (-7.0, 3.0), (1e10, -1e-10), (0.5, 0.5)];
+        for &(a, b) in &cases {
+            let (fa, fb) = (f80::from(a), f80::from(b));
+            assert_eq!((fa * fb).to_f64(), Some(a * b));
+            assert_eq!((fa + fb).to_f64(), Some(a + b));
+            assert_eq!((fa - fb).to_f64(), Some(a - b));
+        }
+        assert_eq!((f80::from(1) / f80::from(4)).to_f64(), Some(0.25));
+        assert_eq!((f80::from(-9) / f80::from(3)).to_i32(), Some(-3));
+    }
+
+    #[test]
+    fn test_extended_precision() {
+        // 1 + 2^-63 is representable in 80 bits but not in a double
+        let tiny = f80::from_bits(0x3fc0_8000_0000_0000_0000);
+        let x = f80::ONE + tiny;
+        assert_eq!(bits(x), 0x3fff_8000_0000_0000_0001);
+        assert!(x > f80::ONE);
+        assert_eq!(x - f80::ONE, tiny);
+        // ... while 1 + 2^-64 rounds back down to 1 (ties to even)
+        let tinier = f80::from_bits(0x3fbf_8000_0000_0000_0000);
+        assert_eq!(f80::ONE + tinier, f80::ONE);
+
+        // 1/3 rounded to 64 bits
+        let third = f80::ONE / f80::from(3);
+        assert_eq!(bits(third), 0x3ffd_aaaa_aaaa_aaaa_aaab);
+        assert_eq!(f80::from_lit("0.333333333333333333333333333333"), third);
+    }
+
+    #[test]
+    fn test_literals() {
+        assert_eq!(bits(f80::from_lit("0.1")), 0x3ffb_cccc_cccc_cccc_cccd);
+        assert_eq!(bits(f80::from_lit("1e4932")), 0x7ffe_d72c_b2a9_5c7e_f6cd);
+        assert_eq!(f80::from_lit("1e5000"), f80::INFINITY);
+        assert_eq!(f80::from_lit(".5"), f80::from(0.5));
+        assert_eq!(f80::from_lit("12.5e-1"), f80::from(1.25));
+        assert_eq!(f80::from_lit("0e10"), f80::ZERO);
+        assert!("1.2.3".parse::<f80>().is_err());
+        assert!("e5".parse::<f80>().is_err());
+    }
+
+    #[test]
+    fn test_specials() {
+        assert!((f80::INFINITY - f80::INFINITY).is_nan());
+        assert!((f80::ZERO / f80::ZERO).is_nan());
+        assert_eq!(f80::ONE / f80::ZERO, f80::INFINITY);
+        assert_eq!(-f80::ONE / f80::ZERO, f80::NEG_INFINITY);
+        assert!(f80::NAN != f80::NAN);
+        assert_eq!(f80::ZERO, -f80::ZERO);
+        assert!((-f80::ZERO).is_sign_negative());
+        assert!(f80::NEG_INFINITY < f80::from(-1e300));
+        assert!(f80::from(-2) < f80::from(-1));
+    }
+
+    #[test]
+    fn test_int_conversions() {
+        assert_eq!(f80::from(u64::MAX).to_u64(), Some(u64::MAX));
+        assert_eq!(f80::from(i64::MIN).to_i64(), Some(i64::MIN));
+        assert_eq!(f80::from(-1.9).to_i32(), Some(-1));
+        assert_eq!(f80::from(-1.9).to_u32(), None);
+        assert_eq!(f80::from(-0.5).to_u32(), Some(0));
+        assert_eq!(f80::from(300).to_u8(), None);
+        assert_eq!(f80::NAN.to_i32(), None);
+        assert_eq!(f80::from(i128::MIN).to_i128(), Some(i128::MIN));
+    }
+
+    #[test]
+    fn test_denormals() {
+        let min_normal = f80::from_bits(0x0001_8000_0000_0000_0000);
+        let half = min_normal / f80::from(2);
+        assert_eq!(bits(half), 0x0000_4000_0000_0000_0000);
+        assert_eq!(half * f80::from(2), min_normal);
+        let min_denormal = f80::from_bits(1);
+        assert_eq!(min_denormal / f80::from(2), f80::ZERO);
+        assert_eq!(min_denormal.to_f64(), Some(0.0));
+    }
+}
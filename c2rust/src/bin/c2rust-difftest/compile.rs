@@ -0,0 +1,192 @@
+//! Building the C program and its translation.
+
+use anyhow::{anyhow, bail, Context};
+use serde::Deserialize;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// An entry of a `compile_commands.json` file.
+#[derive(Debug, Deserialize)]
+pub struct CompileCommand {
+    directory: PathBuf,
+    file: PathBuf,
+    #[serde(default)]
+    arguments: Vec<String>,
+    #[serde(default)]
+    command: Option<String>,
+}
+
+impl CompileCommand {
+    /// The compiler followed by its arguments.
+    fn arguments(&self) -> anyhow::Result<Vec<String>> {
+        if !self.arguments.is_empty() {
+            return Ok(self.arguments.clone());
+        }
+        let command = self
+            .command
+            .as_deref()
+            .ok_or_else(|| anyhow!("no command for {}", self.file.display()))?;
+        shlex::split(command).ok_or_else(|| anyhow!("bad quoting in command: {command}"))
+    }
+
+    fn source_path(&self) -> PathBuf {
+        self.directory.join(&self.file)
+    }
+
+    /// The module the translation of this file ends up in, which is what
+    /// `--binary` refers to.
+    pub fn module_name(&self) -> anyhow::Result<String> {
+        self.file
+            .file_stem()
+            .and_then(|stem| stem.to_str())
+            .map(|stem| stem.replace('-', "_"))
+            .ok_or_else(|| anyhow!("bad file name: {}", self.file.display()))
+    }
+}
+
+pub fn read_compile_commands(path: &Path) -> anyhow::Result<Vec<CompileCommand>> {
+    let json = fs::read_to_string(path).with_context(|| format!("reading {}", path.display()))?;
+    serde_json::from_str(&json).with_context(|| format!("parsing {}", path.display()))
+}
+
+/// Run a build step, saving its output to `log` and failing with a pointer
+/// to it if the step fails.
+fn run_logged(cmd: &mut Command, log: &Path) -> anyhow::Result<()> {
+    let output = cmd
+        .output()
+        .with_context(|| format!("running {:?}", cmd.get_program()))?;
+    fs::write(log, [output.stdout, output.stderr].concat())?;
+    if !output.status.success() {
+        bail!(
+            "{:?} failed with {}; see {}",
+            cmd.get_program(),
+            output.status,
+            log.display()
+        );
+    }
+    Ok(())
+}
+
+/// Compile each file in the compile database as it was compiled originally
+/// and link them into the program `binary` in `out_dir`.
+///
+/// This mirrors the crate `c2rust transpile --binary` makes: the `main` of the
+/// file named `binary` is the entry point, and all other files are library
+/// code. Those may define a `main` of their own, for other programs built
+/// from the same compile database, so it is renamed out of the way.
+pub fn build_c(
+    commands: &[CompileCommand],
+    binary: &str,
+    link_args: &[String],
+    out_dir: &Path,
+) -> anyhow::Result<PathBuf> {
+    fs::create_dir_all(out_dir)?;
+    let mut linker = None;
+    let mut objects = Vec::new();
+    let mut found_binary = false;
+    for (i, command) in commands.iter().enumerate() {
+        let is_binary = command.module_name()? == binary;
+        found_binary |= is_binary;
+        let arguments = command.arguments()?;
+        let (compiler, arguments) = arguments
+            .split_first()
+            .ok_or_else(|| anyhow!("empty command for {}", command.file.display()))?;
+        let source = command.source_path();
+
+        // Drop the parts of the command that we replace: the source file,
+        // the output file and whether to link.
+        let mut flags = Vec::new();
+        let mut arguments = arguments.iter();
+        while let Some(arg) = arguments.next() {
+            if arg == "-o" {
+                arguments.next();
+            } else if arg != "-c" && !arg.starts_with("-o") && command.directory.join(arg) != source
+            {
+                flags.push(arg);
+            }
+        }
+
+        // Only the binary's `main` is the program's entry point
+        let other_main = format!("-Dmain=c2rust_difftest_main_{i}");
+        if !is_binary {
+            flags.push(&other_main);
+        }
+
+        let object = out_dir.join(format!("{i}.o"));
+        run_logged(
+            Command::new(compiler)
+                .args(flags)
+                .arg("-c")
+                .arg(&source)
+                .arg("-o")
+                .arg(&object)
+                .current_dir(&command.directory),
+            &out_dir.join(format!("{i}.log")),
+        )?;
+        objects.push(object);
+        linker.get_or_insert(compiler.clone());
+    }
+
+    if !found_binary {
+        bail!("no file in the compile database is named after the binary {binary}");
+    }
+    let linker = linker.ok_or_else(|| anyhow!("the compile database is empty"))?;
+    let exe = out_dir.join(binary);
+    run_logged(
+        Command::new(linker)
+            .args(&objects)
+            .arg("-o")
+            .arg(&exe)
+            .args(link_args),
+        &out_dir.join("link.log"),
+    )?;
+    Ok(exe)
+}
+
+/// Translate the program with `c2rust transpile` into `work_dir/rust` and
+/// build it in `target_dir`, returning the path to the executable.
+pub fn translate(
+    cc_db: &Path,
+    binary: &str,
+    transpile_args: &[String],
+    link_args: &[String],
+    work_dir: &Path,
+    target_dir: &Path,
+) -> anyhow::Result<PathBuf> {
+    // `c2rust` runs us from the directory it lives in, next to the other
+    // subcommands
+    let transpile =
+        env::current_exe()?.with_file_name(format!("c2rust-transpile{}", env::consts::EXE_SUFFIX));
+    let crate_dir = work_dir.join("rust");
+    run_logged(
+        Command::new(transpile)
+            .arg("--output-dir")
+            .arg(&crate_dir)
+            .arg("--binary")
+            .arg(binary)
+            .arg("--overwrite-existing")
+            .args(transpile_args)
+            .arg(cc_db),
+        &work_dir.join("transpile.log"),
+    )?;
+
+    run_logged(
+        Command::new("cargo")
+            .arg("rustc")
+            .arg("--release")
+            .arg("--bin")
+            .arg(binary)
+            .arg("--target-dir")
+            .arg(target_dir)
+            .arg("--")
+            .arg("-Awarnings")
+            .args(link_args.iter().map(|arg| format!("-Clink-arg={arg}")))
+            .current_dir(&crate_dir),
+        &work_dir.join("cargo.log"),
+    )?;
+    Ok(target_dir
+        .join("release")
+        .join(format!("{binary}{}", env::consts::EXE_SUFFIX)))
+}
@@ -0,0 +1,190 @@
+//! Testing the translator on random programs generated by Csmith.
+
+use anyhow::Context;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use crate::exec::{load_corpus, Exit, TestCase};
+use crate::{check_translation, run_c, CommonArgs, CsmithArgs, Verdict};
+
+const DEFAULT_CSMITH_HOME: &str = "/usr/local/opt/csmith/include/csmith-2.3.0/runtime";
+
+/// Csmith output we can't translate, and so don't let it generate.
+const CSMITH_FLAGS: &[&str] = &["--no-bitfields", "--no-builtins"];
+
+/// A generated program and what it needs to be built.
+struct Program<'a> {
+    dir: PathBuf,
+    cc: &'a str,
+    include: &'a Path,
+}
+
+impl Program<'_> {
+    const SOURCE: &'static str = "prog.c";
+    const BINARY: &'static str = "prog";
+
+    /// Write the source and a compile database for it.
+    fn write(&self, source: &[u8]) -> anyhow::Result<PathBuf> {
+        fs::create_dir_all(&self.dir)?;
+        fs::write(self.dir.join(Self::SOURCE), source)?;
+        let commands = serde_json::json!([{
+            "directory": self.dir,
+            "arguments": [self.cc, "-I", self.include, Self::SOURCE],
+            "file": Self::SOURCE,
+        }]);
+        let cc_db = self.dir.join("compile_commands.json");
+        fs::write(&cc_db, serde_json::to_string_pretty(&commands)?)?;
+        Ok(cc_db)
+    }
+
+    /// Test the program, or return `None` if the C program itself is not
+    /// usable as a test because it doesn't build or doesn't terminate.
+    fn test(
+        &self,
+        source: &[u8],
+        cases: &[TestCase],
+        common: &CommonArgs,
+    ) -> anyhow::Result<Option<Verdict>> {
+        let cc_db = self.write(source)?;
+        let c_outcomes = match run_c(&cc_db, Self::BINARY, cases, &self.dir, common, &self.dir) {
+            Ok(outcomes) => outcomes,
+            Err(_) => return Ok(None),
+        };
+        if c_outcomes.iter().any(|o| o.exit == Exit::Timeout) {
+            return Ok(None);
+        }
+        check_translation(
+            &cc_db,
+            Self::BINARY,
+            cases,
+            &c_outcomes,
+            &self.dir,
+            common,
+            &self.dir,
+        )
+        .map(Some)
+    }
+}
+
+/// Remove ever smaller chunks of lines from `lines` as long as the program
+/// made of the remaining lines `still_fails`.
+fn minimize(
+    mut lines: Vec<String>,
+    mut still_fails: impl FnMut(&[String]) -> anyhow::Result<bool>,
+) -> anyhow::Result<Vec<String>> {
+    let mut chunk = lines.len() / 2;
+    while chunk > 0 {
+        let mut removed_any = false;
+        let mut start = 0;
+        while start < lines.len() {
+            let end = (start + chunk).min(lines.len());
+            let candidate = [&lines[..start], &lines[end..]].concat();
+            if still_fails(&candidate)? {
+                lines = candidate;
+                removed_any = true;
+            } else {
+                start = end;
+            }
+        }
+        if !removed_any {
+            chunk /= 2;
+        }
+    }
+    Ok(lines)
+}
+
+pub fn difftest_csmith(args: CsmithArgs) -> anyhow::Result<bool> {
+    let include = match args.csmith_include {
+        Some(include) => include,
+        None => env::var_os("CSMITH_HOME")
+            .map(PathBuf::from)
+            .unwrap_or_else(|| DEFAULT_CSMITH_HOME.into()),
+    };
+    let include = include
+        .canonicalize()
+        .with_context(|| format!("Csmith runtime headers not found at {}", include.display()))?;
+    let work_dir = args.common.work_dir()?;
+    let cases = load_corpus(None)?;
+
+    let (mut passed, mut skipped, mut failed) = (0, 0, 0);
+    for seed in args.seed..args.seed + args.count {
+        let program = Program {
+            dir: work_dir.join(format!("seed-{seed}")),
+            cc: &args.cc,
+            include: &include,
+        };
+        let output = Command::new(&args.csmith)
+            .args(CSMITH_FLAGS)
+            .args(&args.csmith_args)
+            .arg("--seed")
+            .arg(seed.to_string())
+            .output()
+            .with_context(|| format!("running {}", args.csmith.display()))?;
+        if !output.status.success() {
+            anyhow::bail!("Csmith failed on seed {seed}: {}", output.status);
+        }
+        let source = output.stdout;
+
+        let verdict = match program.test(&source, &cases, &args.common)? {
+            Some(verdict) => verdict,
+            None => {
+                skipped += 1;
+                fs::remove_dir_all(&program.dir)?;
+                continue;
+            }
+        };
+        if let Verdict::Pass = verdict {
+            passed += 1;
+            fs::remove_dir_all(&program.dir)?;
+            continue;
+        }
+
+        failed += 1;
+        println!("seed {seed} FAILED, see {}", program.dir.display());
+        verdict.print();
+
+        if args.minimize {
+            // Only keep reductions that fail the same way
+            let translation_failed = matches!(verdict, Verdict::TranslationFailed(_));
+            let reducer = Program {
+                dir: program.dir.join("minimize"),
+                ..program
+            };
+            let lines = String::from_utf8_lossy(&source)
+                .lines()
+                .map(|line| format!("{line}\n"))
+                .collect();
+            let minimized = minimize(lines, |lines| {
+                let verdict = reducer.test(lines.concat().as_bytes(), &cases, &args.common)?;
+                Ok(match verdict {
+                    Some(Verdict::Pass) | None => false,
+                    Some(Verdict::Mismatch(_)) => !translation_failed,
+                    Some(Verdict::TranslationFailed(_)) => translation_failed,
+                })
+            })?;
+            let path = reducer.dir.join("minimized.c");
+            fs::write(&path, minimized.concat())?;
+            println!("seed {seed} minimized to {}", path.display());
+        }
+    }
+
+    println!("{passed} passed, {failed} failed, {skipped} skipped (C program unusable)");
+    Ok(failed == 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::minimize;
+
+    #[test]
+    fn test_minimize() {
+        let lines = (0..20).map(|i| i.to_string()).collect();
+        let minimized = minimize(lines, |lines| {
+            Ok(lines.contains(&"3".to_owned()) && lines.contains(&"17".to_owned()))
+        })
+        .unwrap();
+        assert_eq!(minimized, ["3", "17"]);
+    }
+}
@@ -0,0 +1,290 @@
+//! Running programs on test cases and comparing what they did.
+
+use anyhow::{anyhow, Context};
+use std::collections::BTreeSet;
+use std::fmt::{self, Display, Formatter};
+use std::fs;
+use std::io::{ErrorKind, Read, Write};
+use std::mem;
+use std::os::unix::process::{CommandExt, ExitStatusExt};
+use std::path::Path;
+use std::process::{Command, ExitStatus, Stdio};
+use std::sync::mpsc::{self, Receiver};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// How long to wait for the output pipes of a program to close once it has
+/// ended. Processes it started may keep them open for longer.
+const PIPE_GRACE: Duration = Duration::from_secs(1);
+
+/// The inputs for one run of a program.
+pub struct TestCase {
+    pub name: String,
+    pub args: Vec<String>,
+    pub stdin: Vec<u8>,
+}
+
+/// Read the test cases in a corpus directory, or make a single one without
+/// any input if there is no corpus.
+pub fn load_corpus(dir: Option<&Path>) -> anyhow::Result<Vec<TestCase>> {
+    let dir = match dir {
+        Some(dir) => dir,
+        None => {
+            return Ok(vec![TestCase {
+                name: "default".into(),
+                args: Vec::new(),
+                stdin: Vec::new(),
+            }])
+        }
+    };
+
+    let mut names = BTreeSet::new();
+    for entry in fs::read_dir(dir).with_context(|| format!("reading {}", dir.display()))? {
+        let path = entry?.path();
+        let is_case = path
+            .extension()
+            .map_or(false, |ext| ext == "args" || ext == "stdin");
+        if let (true, Some(name)) = (is_case, path.file_stem().and_then(|s| s.to_str())) {
+            names.insert(name.to_owned());
+        }
+    }
+    if names.is_empty() {
+        return Err(anyhow!("no test cases in {}", dir.display()));
+    }
+
+    names
+        .into_iter()
+        .map(|name| {
+            let args_path = dir.join(format!("{name}.args"));
+            let args = if args_path.exists() {
+                let args = fs::read_to_string(&args_path)?;
+                shlex::split(args.trim())
+                    .ok_or_else(|| anyhow!("bad quoting in {}", args_path.display()))?
+            } else {
+                Vec::new()
+            };
+            let stdin_path = dir.join(format!("{name}.stdin"));
+            let stdin = if stdin_path.exists() {
+                fs::read(&stdin_path)?
+            } else {
+                Vec::new()
+            };
+            Ok(TestCase { name, args, stdin })
+        })
+        .collect()
+}
+
+/// How a program run ended.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Exit {
+    Code(i32),
+    Signal(i32),
+    Timeout,
+}
+
+impl From<ExitStatus> for Exit {
+    fn from(status: ExitStatus) -> Self {
+        match (status.code(), status.signal()) {
+            (Some(code), _) => Exit::Code(code),
+            (None, Some(signal)) => Exit::Signal(signal),
+            (None, None) => unreachable!("process neither exited nor was signaled"),
+        }
+    }
+}
+
+impl Display for Exit {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            Exit::Code(code) => write!(f, "exit code {code}"),
+            Exit::Signal(signal) => write!(f, "signal {signal}"),
+            Exit::Timeout => write!(f, "timeout"),
+        }
+    }
+}
+
+/// Everything a program run did that the translation has to reproduce.
+pub struct Outcome {
+    pub exit: Exit,
+    pub stdout: Vec<u8>,
+    pub stderr: Vec<u8>,
+}
+
+/// The output of a pipe, which a background thread collects.
+struct PipeReader {
+    buf: Arc<Mutex<Vec<u8>>>,
+    closed: Receiver<()>,
+}
+
+impl PipeReader {
+    /// Everything read from the pipe once it closes, or after `PIPE_GRACE`
+    /// if it doesn't.
+    fn finish(self) -> Vec<u8> {
+        let _ = self.closed.recv_timeout(PIPE_GRACE);
+        let mut buf = self.buf.lock().unwrap();
+        mem::take(&mut *buf)
+    }
+}
+
+fn read_in_background(mut reader: impl Read + Send + 'static) -> PipeReader {
+    let buf = Arc::new(Mutex::new(Vec::new()));
+    let (sender, closed) = mpsc::channel();
+    let thread_buf = Arc::clone(&buf);
+    thread::spawn(move || {
+        let mut chunk = [0; 4096];
+        // A program killed on timeout may leave its output unfinished
+        loop {
+            match reader.read(&mut chunk) {
+                Ok(0) => break,
+                Ok(n) => thread_buf.lock().unwrap().extend_from_slice(&chunk[..n]),
+                Err(e) if e.kind() == ErrorKind::Interrupted => {}
+                Err(_) => break,
+            }
+        }
+        let _ = sender.send(());
+    });
+    PipeReader { buf, closed }
+}
+
+/// Run `exe` on a test case in `cwd`, killing it after `timeout`.
+pub fn run(exe: &Path, case: &TestCase, cwd: &Path, timeout: Duration) -> anyhow::Result<Outcome> {
+    let mut command = Command::new(exe);
+    command
+        .args(&case.args)
+        .current_dir(cwd)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+    // Put the program in a process group of its own, so that on timeout we
+    // can kill whatever it started along with it.
+    unsafe {
+        command.pre_exec(|| {
+            if libc::setpgid(0, 0) == 0 {
+                Ok(())
+            } else {
+                Err(std::io::Error::last_os_error())
+            }
+        });
+    }
+    let mut child = command
+        .spawn()
+        .with_context(|| format!("running {}", exe.display()))?;
+
+    let mut stdin = child.stdin.take().unwrap();
+    let input = case.stdin.clone();
+    // Not joined: the write blocks for as long as someone holds the pipe open
+    // without reading from it.
+    thread::spawn(move || {
+        // The program doesn't have to read all of its input
+        let _ = stdin.write_all(&input);
+    });
+    let stdout = read_in_background(child.stdout.take().unwrap());
+    let stderr = read_in_background(child.stderr.take().unwrap());
+
+    let deadline = Instant::now() + timeout;
+    let exit = loop {
+        if let Some(status) = child.try_wait()? {
+            break Exit::from(status);
+        }
+        if Instant::now() >= deadline {
+            // The process group has the same ID as the program
+            unsafe { libc::kill(-(child.id() as libc::pid_t), libc::SIGKILL) };
+            child.wait()?;
+            break Exit::Timeout;
+        }
+        thread::sleep(Duration::from_millis(10));
+    };
+
+    Ok(Outcome {
+        exit,
+        stdout: stdout.finish(),
+        stderr: stderr.finish(),
+    })
+}
+
+/// Describe the first line where two outputs differ.
+fn first_difference(c: &[u8], rust: &[u8]) -> Option<String> {
+    if c == rust {
+        return None;
+    }
+    let mut c_lines = c.split(|&b| b == b'\n');
+    let mut rust_lines = rust.split(|&b| b == b'\n');
+    let mut line = 1;
+    loop {
+        let (c_line, rust_line) = (c_lines.next(), rust_lines.next());
+        if c_line != rust_line {
+            let show = |l: Option<&[u8]>| {
+                l.map_or("<end of output>".into(), |l| {
+                    format!("{:?}", String::from_utf8_lossy(l))
+                })
+            };
+            return Some(format!(
+                "line {line} differs\n    C:    {}\n    Rust: {}",
+                show(c_line),
+                show(rust_line)
+            ));
+        }
+        line += 1;
+    }
+}
+
+/// Compare the runs of the C program and its translation, describing the
+/// differences if there are any.
+pub fn compare(c: &Outcome, rust: &Outcome) -> Option<String> {
+    let mut diffs = Vec::new();
+    if c.exit != rust.exit {
+        diffs.push(format!("C ended with {}, Rust with {}", c.exit, rust.exit));
+    }
+    if let Some(diff) = first_difference(&c.stdout, &rust.stdout) {
+        diffs.push(format!("stdout {diff}"));
+    }
+    if let Some(diff) = first_difference(&c.stderr, &rust.stderr) {
+        diffs.push(format!("stderr {diff}"));
+    }
+    if diffs.is_empty() {
+        None
+    } else {
+        Some(diffs.join("\n  "))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{first_difference, run, Exit, TestCase};
+    use std::path::Path;
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn test_first_difference() {
+        assert_eq!(first_difference(b"a\nb\n", b"a\nb\n"), None);
+        assert_eq!(
+            first_difference(b"a\nb\n", b"a\nc\n").unwrap(),
+            "line 2 differs\n    C:    \"b\"\n    Rust: \"c\""
+        );
+        assert_eq!(
+            first_difference(b"a\n", b"a").unwrap(),
+            "line 2 differs\n    C:    \"\"\n    Rust: <end of output>"
+        );
+    }
+
+    #[test]
+    fn test_run_timeout_with_lingering_child() {
+        // The background `sleep` keeps stdout open after the shell is killed
+        let case = TestCase {
+            name: "lingering".into(),
+            args: vec!["-c".into(), "echo hi; (sleep 30 &); sleep 30".into()],
+            stdin: Vec::new(),
+        };
+        let start = Instant::now();
+        let outcome = run(
+            Path::new("/bin/sh"),
+            &case,
+            Path::new("."),
+            Duration::from_secs(1),
+        )
+        .unwrap();
+        assert_eq!(outcome.exit, Exit::Timeout);
+        assert_eq!(outcome.stdout, b"hi\n");
+        assert!(start.elapsed() < Duration::from_secs(10));
+    }
+}
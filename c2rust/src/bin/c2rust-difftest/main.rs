@@ -0,0 +1,268 @@
+//! Differential testing of translations: build a C program and its Rust
+//! translation, run both on the same inputs, and compare what they do.
+
+mod compile;
+mod csmith;
+mod exec;
+
+use anyhow::bail;
+use clap::{Parser, Subcommand};
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::time::Duration;
+
+use crate::compile::{build_c, read_compile_commands, translate};
+use crate::exec::{compare, load_corpus, run, Outcome, TestCase};
+
+#[derive(Debug, Parser)]
+#[clap(
+    name = "difftest",
+    author = "- The C2Rust Project Developers <c2rust@immunant.com>",
+    version,
+    about = "Check that translated programs behave like the original C",
+    long_about = None
+)]
+struct Args {
+    #[clap(subcommand)]
+    mode: Mode,
+}
+
+#[derive(Debug, Subcommand)]
+enum Mode {
+    /// Test a C program described by a compile database on a corpus of inputs
+    Run(RunArgs),
+    /// Test random programs generated by Csmith
+    Csmith(CsmithArgs),
+}
+
+#[derive(Debug, clap::Args)]
+struct RunArgs {
+    /// Path to the `compile_commands.json` of the C program
+    compile_commands: PathBuf,
+
+    /// Translation unit whose `main` function the program uses. Can be left
+    /// out if the compile database has a single entry
+    #[clap(short = 'b', long)]
+    binary: Option<String>,
+
+    /// Directory of test cases. `NAME.args` holds the shell-quoted arguments
+    /// and `NAME.stdin` the standard input of test case `NAME`; either may be
+    /// missing. Without a corpus, the programs are run once without input
+    #[clap(long)]
+    corpus: Option<PathBuf>,
+
+    #[clap(flatten)]
+    common: CommonArgs,
+}
+
+#[derive(Debug, clap::Args)]
+struct CsmithArgs {
+    /// The Csmith executable
+    #[clap(long, default_value = "csmith")]
+    csmith: PathBuf,
+
+    /// Directory containing `csmith.h` [default: $CSMITH_HOME]
+    #[clap(long)]
+    csmith_include: Option<PathBuf>,
+
+    /// Extra flag to pass to Csmith
+    #[clap(long = "csmith-arg", multiple = true, number_of_values = 1)]
+    csmith_args: Vec<String>,
+
+    /// C compiler for the generated programs
+    #[clap(long, default_value = "clang")]
+    cc: String,
+
+    /// Number of programs to generate
+    #[clap(long, default_value_t = 100)]
+    count: u64,
+
+    /// Csmith seed of the first program; program `i` uses `seed + i`
+    #[clap(long, default_value_t = 1)]
+    seed: u64,
+
+    /// Shrink each failing program to a smaller one that still fails
+    #[clap(long)]
+    minimize: bool,
+
+    #[clap(flatten)]
+    common: CommonArgs,
+}
+
+#[derive(Debug, clap::Args)]
+struct CommonArgs {
+    /// Directory for build products and failing programs [default: a new
+    /// temporary directory]
+    #[clap(long)]
+    work_dir: Option<PathBuf>,
+
+    /// Cargo target directory for the translated programs, which can be kept
+    /// between runs to avoid rebuilding dependencies [default: WORK_DIR/target]
+    #[clap(long)]
+    target_dir: Option<PathBuf>,
+
+    /// Seconds each program may run before it counts as hung
+    #[clap(long, default_value_t = 10)]
+    timeout: u64,
+
+    /// Extra flag to pass to `c2rust transpile`, e.g.
+    /// `--transpile-arg=--translate-const-macros`
+    #[clap(long = "transpile-arg", multiple = true, number_of_values = 1)]
+    transpile_args: Vec<String>,
+
+    /// Extra flag to pass to the linker of both programs, e.g. `-lm`
+    #[clap(long = "link-arg", multiple = true, number_of_values = 1)]
+    link_args: Vec<String>,
+}
+
+impl CommonArgs {
+    fn timeout(&self) -> Duration {
+        Duration::from_secs(self.timeout)
+    }
+
+    fn work_dir(&self) -> anyhow::Result<PathBuf> {
+        let dir = self
+            .work_dir
+            .clone()
+            .unwrap_or_else(|| env::temp_dir().join(format!("c2rust-difftest-{}", process::id())));
+        fs::create_dir_all(&dir)?;
+        Ok(dir.canonicalize()?)
+    }
+
+    fn target_dir(&self) -> anyhow::Result<PathBuf> {
+        let dir = match &self.target_dir {
+            Some(dir) => dir.clone(),
+            None => self.work_dir()?.join("target"),
+        };
+        fs::create_dir_all(&dir)?;
+        Ok(dir.canonicalize()?)
+    }
+}
+
+/// The result of testing the translation of one program.
+enum Verdict {
+    Pass,
+    /// The programs behaved differently; one report per differing test case.
+    Mismatch(Vec<String>),
+    /// The program could not be translated, or its translation did not build.
+    TranslationFailed(anyhow::Error),
+}
+
+impl Verdict {
+    fn print(&self) {
+        match self {
+            Verdict::Pass => {}
+            Verdict::Mismatch(reports) => {
+                for report in reports {
+                    println!("{report}");
+                }
+            }
+            Verdict::TranslationFailed(e) => println!("{e:#}"),
+        }
+    }
+}
+
+/// Build the C program and run it on every test case.
+fn run_c(
+    cc_db: &Path,
+    binary: &str,
+    cases: &[TestCase],
+    cwd: &Path,
+    common: &CommonArgs,
+    work_dir: &Path,
+) -> anyhow::Result<Vec<Outcome>> {
+    let commands = read_compile_commands(cc_db)?;
+    let exe = build_c(&commands, binary, &common.link_args, &work_dir.join("c"))?;
+    cases
+        .iter()
+        .map(|case| run(&exe, case, cwd, common.timeout()))
+        .collect()
+}
+
+/// Translate and build the program, run it on every test case and compare
+/// the results to those of the C program.
+fn check_translation(
+    cc_db: &Path,
+    binary: &str,
+    cases: &[TestCase],
+    c_outcomes: &[Outcome],
+    cwd: &Path,
+    common: &CommonArgs,
+    work_dir: &Path,
+) -> anyhow::Result<Verdict> {
+    let exe = match translate(
+        cc_db,
+        binary,
+        &common.transpile_args,
+        &common.link_args,
+        work_dir,
+        &common.target_dir()?,
+    ) {
+        Ok(exe) => exe,
+        Err(e) => return Ok(Verdict::TranslationFailed(e)),
+    };
+    let mut reports = Vec::new();
+    for (case, c_outcome) in cases.iter().zip(c_outcomes) {
+        let rust_outcome = run(&exe, case, cwd, common.timeout())?;
+        if let Some(report) = compare(c_outcome, &rust_outcome) {
+            reports.push(format!("{}: {}", case.name, report));
+        }
+    }
+    Ok(if reports.is_empty() {
+        Verdict::Pass
+    } else {
+        Verdict::Mismatch(reports)
+    })
+}
+
+fn difftest_run(args: RunArgs) -> anyhow::Result<bool> {
+    let cc_db = args.compile_commands.canonicalize()?;
+    let binary = match args.binary {
+        Some(binary) => binary,
+        None => match read_compile_commands(&cc_db)?.as_slice() {
+            [command] => command.module_name()?,
+            _ => bail!("the compile database has several entries; pick one with --binary"),
+        },
+    };
+    let cases = load_corpus(args.corpus.as_deref())?;
+    let cwd = env::current_dir()?;
+    let work_dir = args.common.work_dir()?;
+
+    let c_outcomes = run_c(&cc_db, &binary, &cases, &cwd, &args.common, &work_dir)?;
+    let verdict = check_translation(
+        &cc_db,
+        &binary,
+        &cases,
+        &c_outcomes,
+        &cwd,
+        &args.common,
+        &work_dir,
+    )?;
+    verdict.print();
+    let failed = match &verdict {
+        Verdict::Pass => 0,
+        Verdict::Mismatch(reports) => reports.len(),
+        Verdict::TranslationFailed(_) => cases.len(),
+    };
+    println!(
+        "{} passed, {} failed; build products are in {}",
+        cases.len() - failed,
+        failed,
+        work_dir.display()
+    );
+    Ok(failed == 0)
+}
+
+fn main() -> anyhow::Result<()> {
+    let args = Args::parse();
+    let passed = match args.mode {
+        Mode::Run(args) => difftest_run(args)?,
+        Mode::Csmith(args) => csmith::difftest_csmith(args)?,
+    };
+    if !passed {
+        process::exit(1);
+    }
+    Ok(())
+}
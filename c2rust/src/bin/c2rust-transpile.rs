@@ -2,8 +2,11 @@ use clap::{Parser, ValueEnum};
 use log::LevelFilter;
 use regex::Regex;
 use std::path::{Path, PathBuf};
+use std::process;
 
-use c2rust_transpile::{Diagnostic, ReplaceMode, TranspilerConfig};
+use c2rust_transpile::{
+    Diagnostic, LibcMapping, LongDoubleRepr, NamingPolicy, ReplaceMode, TranspilerConfig,
+};
 
 #[derive(Debug, Parser)]
 #[clap(
@@ -116,6 +119,23 @@ struct Args {
     #[clap(short = 'b', long = "binary", multiple = true, number_of_values = 1)]
     binary: Option<Vec<String>>,
 
+    /// Emit a C header NAME.h next to the crate, declaring the functions and types it exports
+    #[clap(long)]
+    emit_c_header: bool,
+
+    /// Check the emitted C header against an original header of the library by compiling them together with $CC (default: clang); implies --emit-c-header
+    #[clap(
+        long = "check-c-header",
+        value_name = "HEADER",
+        multiple = true,
+        number_of_values = 1
+    )]
+    check_c_headers: Vec<PathBuf>,
+
+    /// Emit static inline header functions that several translation units translate the same way only once, in a shared module (requires -e/--emit-build-files)
+    #[clap(long)]
+    share_header_items: bool,
+
     /// Emit files even if it causes existing files to be overwritten
     #[clap(long)]
     overwrite_existing: bool,
@@ -136,7 +156,7 @@ struct Args {
     #[clap(short = 'W')]
     warn: Option<Diagnostic>,
 
-    /// Emit code using core rather than std
+    /// Emit code using core rather than std; binaries export a C `main` entry point
     #[clap(long)]
     emit_no_std: bool,
 
@@ -155,6 +175,29 @@ struct Args {
     /// Fail when the control-flow graph generates branching constructs
     #[clap(long)]
     fail_on_multiple: bool,
+
+    /// Number of translation units to transpile in parallel (0 uses all available cores)
+    #[clap(short = 'j', long, default_value_t = 1)]
+    jobs: usize,
+
+    /// How to name translated items: keep C names, or use Rust naming conventions
+    /// (the C symbol names are kept via `export_name`/`link_name`)
+    #[clap(long, value_enum, default_value_t = NamingPolicyArg::Preserve)]
+    naming_policy: NamingPolicyArg,
+
+    /// How to represent `long double`: `f64` (lossy), a software x87 80-bit
+    /// float from `c2rust-x87`, or `f128::f128`
+    #[clap(long, value_enum, default_value_t = LongDoubleArg::F128)]
+    long_double: LongDoubleArg,
+
+    /// Translate calls to libc string and memory functions on known arrays and
+    /// string literals into safe slice operations
+    #[clap(long)]
+    map_libc_calls: bool,
+
+    /// TOML file of additional libc functions to map (implies --map-libc-calls)
+    #[clap(long, value_name = "FILE")]
+    libc_mapping: Option<PathBuf>,
 }
 
 #[derive(Debug, PartialEq, Eq, ValueEnum, Clone)]
@@ -164,6 +207,21 @@ enum InvalidCodes {
     CompileError,
 }
 
+#[derive(Debug, PartialEq, Eq, ValueEnum, Clone)]
+#[clap(rename_all = "snake_case")]
+enum NamingPolicyArg {
+    Preserve,
+    Idiomatic,
+}
+
+#[derive(Debug, PartialEq, Eq, ValueEnum, Clone)]
+#[clap(rename_all = "snake_case")]
+enum LongDoubleArg {
+    F64,
+    X87,
+    F128,
+}
+
 fn main() {
     let args = Args::parse();
 
@@ -185,6 +243,22 @@ fn main() {
         filter: args.filter,
         debug_relooper_labels: args.debug_labels,
         prefix_function_names: args.prefix_function_names,
+        naming_policy: match args.naming_policy {
+            NamingPolicyArg::Preserve => NamingPolicy::Preserve,
+            NamingPolicyArg::Idiomatic => NamingPolicy::Idiomatic,
+        },
+        long_double: match args.long_double {
+            LongDoubleArg::F64 => LongDoubleRepr::F64,
+            LongDoubleArg::X87 => LongDoubleRepr::X87,
+            LongDoubleArg::F128 => LongDoubleRepr::F128,
+        },
+        libc_mapping: match args.libc_mapping {
+            Some(ref path) => Some(LibcMapping::load(path).unwrap_or_else(|e| {
+                panic!("Could not load libc mapping {}: {}", path.display(), e)
+            })),
+            None if args.map_libc_calls => Some(LibcMapping::default()),
+            None => None,
+        },
 
         // We used to guard asm translation with a command-line
         // option. Defaulting to enabled now, can add an option to disable if
@@ -211,11 +285,15 @@ fn main() {
         emit_build_files: args.emit_build_files,
         output_dir: args.output_dir,
         binaries: args.binary.unwrap_or_default(),
+        emit_c_header: args.emit_c_header || !args.check_c_headers.is_empty(),
+        check_c_headers: args.check_c_headers,
+        share_header_items: args.share_header_items,
         panic_on_translator_failure: args.invalid_code == InvalidCodes::Panic,
         replace_unsupported_decls: ReplaceMode::Extern,
         emit_no_std: args.emit_no_std,
         enabled_warnings: args.warn.into_iter().collect(),
         log_level: args.log_level,
+        jobs: args.jobs,
     };
     // binaries imply emit-build-files
     if !tcfg.binaries.is_empty() {
@@ -240,5 +318,8 @@ fn main() {
         .map(AsRef::as_ref)
         .collect::<Vec<_>>();
 
-    c2rust_transpile::transpile(tcfg, &cc_json_path, &extra_args);
+    if let Err(e) = c2rust_transpile::transpile(tcfg, &cc_json_path, &extra_args) {
+        eprintln!("error: {}", e);
+        process::exit(1);
+    }
 }
@@ -35,6 +35,7 @@ type AddressTaken = IndexSet<(FuncId, Local)>;
 
 pub trait EventKindExt {
     fn ptr(&self, metadata: &EventMetadata) -> Option<Pointer>;
+    fn result_ptr(&self, metadata: &EventMetadata) -> Option<Pointer>;
     fn to_node_kind(&self, func: FuncId, address_taken: &mut AddressTaken) -> Option<NodeKind>;
 }
 
@@ -63,6 +64,17 @@ impl EventKindExt for EventKind {
         })
     }
 
+    /// return the ptr produced by a particular event, which differs from [`Self::ptr`] for events
+    /// that derive a new pointer from an old one
+    fn result_ptr(&self, metadata: &EventMetadata) -> Option<Pointer> {
+        use EventKind::*;
+        match *self {
+            Realloc { new_ptr, .. } => Some(new_ptr),
+            Offset(_, _, new_ptr) => Some(new_ptr),
+            _ => self.ptr(metadata),
+        }
+    }
+
     fn to_node_kind(&self, func: FuncId, address_taken: &mut AddressTaken) -> Option<NodeKind> {
         use EventKind::*;
         Some(match *self {
@@ -215,6 +227,7 @@ pub fn add_node(
         block: basic_block_idx.into(),
         statement_idx,
         kind: node_kind,
+        null: event.kind.result_ptr(event_metadata) == Some(0),
         source: source
             .and_then(|p| parent(&node_kind, p))
             .map(|(_, nid)| nid),
@@ -0,0 +1,151 @@
+//! Export of the dynamically observed facts in a PDG, for use as permission evidence by
+//! `c2rust-analyze --pdg`.
+//!
+//! The facts are written as a JSON object whose `nodes` field lists one entry per [`Node`]:
+//!
+//! * `function`: the [`DefPathHash`] of [`Node::function`], as a pair of integers.
+//! * `function_name`: the name of [`Node::function`], for debugging.
+//! * `block`, `statement_idx`: the MIR location of the operation.
+//! * `dest`: the index of the MIR local the operation assigns to, or `null` if the operation
+//!   doesn't assign to a whole local.
+//! * `kind`: the [`NodeKind`](crate::graph::NodeKind), for debugging.
+//! * `load`, `store`, `pos_offset`, `neg_offset`: whether the [`Node`] flows to a node of the
+//!   corresponding kind (see [`FlowInfo`]).
+//! * `unique`: see [`NodeInfo::unique`].
+//! * `null`: see [`Node::null`].
+//!
+//! [`DefPathHash`]: c2rust_analysis_rt::mir_loc::DefPathHash
+//! [`FlowInfo`]: crate::info::FlowInfo
+//! [`NodeInfo::unique`]: crate::info::NodeInfo::unique
+
+use crate::graph::{Graphs, Node};
+use c2rust_analysis_rt::mir_loc::{DefPathHash, Fingerprint, FuncId};
+use serde::Serialize;
+use std::io::Write;
+
+#[derive(Debug, Serialize)]
+struct NodeFacts<'a> {
+    function: (u64, u64),
+    function_name: &'a str,
+    block: usize,
+    statement_idx: usize,
+    dest: Option<u32>,
+    kind: String,
+    load: bool,
+    store: bool,
+    pos_offset: bool,
+    neg_offset: bool,
+    unique: bool,
+    null: bool,
+}
+
+impl<'a> NodeFacts<'a> {
+    /// Collect the facts for `node`, or return `None` if [`add_info`](crate::info::add_info)
+    /// hasn't been run on it.
+    fn new(node: &'a Node) -> Option<Self> {
+        let info = node.info.as_ref()?;
+        let FuncId(DefPathHash(Fingerprint(hash0, hash1))) = node.function.id;
+        let dest = node
+            .dest
+            .as_ref()
+            .filter(|dest| dest.projection.is_empty())
+            .map(|dest| dest.local.as_u32());
+        Some(NodeFacts {
+            function: (hash0, hash1),
+            function_name: &node.function.name,
+            block: node.block.as_usize(),
+            statement_idx: node.statement_idx,
+            dest,
+            kind: node.kind.to_string(),
+            load: info.flows_to.load.is_some(),
+            store: info.flows_to.store.is_some(),
+            pos_offset: info.flows_to.pos_offset.is_some(),
+            neg_offset: info.flows_to.neg_offset.is_some(),
+            unique: info.unique,
+            null: node.null,
+        })
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct Facts<'a> {
+    nodes: Vec<NodeFacts<'a>>,
+}
+
+/// Write the facts for all [`Node`]s in `graphs` to `w` as JSON.
+pub fn write_facts(graphs: &Graphs, w: impl Write) -> serde_json::Result<()> {
+    let nodes = graphs
+        .graphs
+        .iter()
+        .flat_map(|graph| graph.nodes.iter())
+        .filter_map(NodeFacts::new)
+        .collect();
+    serde_json::to_writer(w, &Facts { nodes })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::graph::{Graph, NodeKind};
+    use crate::info::add_info;
+    use c2rust_analysis_rt::mir_loc::{Func, MirPlace};
+    use serde_json::Value;
+
+    fn mk_node(g: &mut Graph, kind: NodeKind, source: Option<usize>, dest: Option<u32>) {
+        g.nodes.push(Node {
+            function: Func {
+                id: FuncId((1, 2).into()),
+                name: "fake_function".into(),
+            },
+            block: 0_u32.into(),
+            statement_idx: g.nodes.len(),
+            dest: dest.map(|local| MirPlace {
+                local: local.into(),
+                projection: vec![],
+            }),
+            kind,
+            null: false,
+            source: source.map(Into::into),
+            info: None,
+            debug_info: "".into(),
+        });
+    }
+
+    /// ```rust
+    /// let p = malloc(..);     // _1
+    /// let q = p.offset(1);    // _2
+    /// *q = 0;
+    /// ```
+    #[test]
+    fn flows_to_facts() {
+        let mut g = Graph::default();
+        mk_node(&mut g, NodeKind::Alloc(1), None, Some(1));
+        mk_node(&mut g, NodeKind::Offset(1), Some(0), Some(2));
+        mk_node(&mut g, NodeKind::StoreAddr, Some(1), None);
+
+        let mut graphs = Graphs::default();
+        graphs.graphs.push(g);
+        add_info(&mut graphs);
+
+        let mut buf = Vec::new();
+        write_facts(&graphs, &mut buf).unwrap();
+        let facts: Value = serde_json::from_slice(&buf).unwrap();
+        let nodes = facts["nodes"].as_array().unwrap();
+        assert_eq!(nodes.len(), 3);
+
+        let p = &nodes[0];
+        assert_eq!(p["function"], serde_json::json!([1, 2]));
+        assert_eq!(p["dest"], 1);
+        assert_eq!(p["store"], true);
+        assert_eq!(p["pos_offset"], true);
+        assert_eq!(p["neg_offset"], false);
+
+        let q = &nodes[1];
+        assert_eq!(q["dest"], 2);
+        assert_eq!(q["statement_idx"], 1);
+        assert_eq!(q["store"], true);
+        assert_eq!(q["pos_offset"], false);
+
+        assert_eq!(nodes[2]["dest"], Value::Null);
+    }
+}
@@ -174,6 +174,9 @@ pub struct Node {
     pub dest: Option<MirPlace>,
     /// The kind of operation that was performed.
     pub kind: NodeKind,
+    /// Whether the pointer this operation produced was null.  For operations that don't produce a
+    /// pointer, such as [`NodeKind::StoreAddr`], this is whether the pointer they used was null.
+    pub null: bool,
     /// The `Node` that produced the input to this operation.
     pub source: Option<NodeId>,
     /// Any string useful for debugging.
@@ -205,6 +208,7 @@ impl Node {
             statement_idx,
             dest,
             kind,
+            null: _,
             source,
             debug_info,
             info,
@@ -19,6 +19,7 @@ extern crate rustc_target;
 
 mod assert;
 mod builder;
+mod facts;
 mod graph;
 mod info;
 mod query;
@@ -32,6 +33,7 @@ use graph::Graphs;
 use info::add_info;
 use std::{
     fmt::{self, Display, Formatter},
+    io::BufWriter,
     path::{Path, PathBuf},
     sync::Once,
 };
@@ -165,6 +167,11 @@ pub struct Args {
     /// What to print.
     #[clap(long, value_parser, default_value = "graphs")]
     print: Vec<ToPrint>,
+
+    /// Path to write the dynamically observed facts about each PDG node to,
+    /// for use as permission evidence by `c2rust-analyze --pdg`.
+    #[clap(long, value_parser)]
+    facts: Option<PathBuf>,
 }
 
 static INIT: Once = Once::new();
@@ -191,6 +198,10 @@ fn main() -> eyre::Result<()> {
     pdg.graphs.assert_all_tests();
     let repr = pdg.repr(&args.print);
     println!("{repr}");
+    if let Some(facts_path) = &args.facts {
+        let file = fs_err::File::create(facts_path)?;
+        facts::write_facts(&pdg.graphs, BufWriter::new(file))?;
+    }
     Ok(())
 }
 
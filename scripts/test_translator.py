@@ -31,7 +31,7 @@ from rust_file import (
     RustMod,
     RustVisibility,
 )
-from typing import Any, Dict, Generator, List, Optional, Set, Iterable
+from typing import Any, Dict, Generator, List, Optional, Set, Iterable, Tuple
 
 # Tools we will need
 clang = get_cmd_or_die("clang")
@@ -72,13 +72,21 @@ class CFile:
         self.translate_const_macros = "translate_const_macros" in flags
         self.reorganize_definitions = "reorganize_definitions" in flags
         self.emit_build_files = "emit_build_files" in flags
-
-    def translate(self, cc_db: str, ld_lib_path: str, extra_args: List[str] = []) -> RustFile:
-        extensionless_file, _ = os.path.splitext(self.path)
-
-        # run the transpiler
-        transpiler = get_cmd_or_die(c.TRANSPILER)
-
+        self.long_double_x87 = "long_double_x87" in flags
+        self.map_libc_calls = "map_libc_calls" in flags
+        self.translate_together = "translate_together" in flags
+        self.check_c_header = "check_c_header" in flags
+        self.share_header_items = "share_header_items" in flags
+        self.emit_no_std = "emit_no_std" in flags
+        self.binary = "binary" in flags
+        # extra arguments for compiling the C file, both natively and for translation
+        self.cflags = sorted("-D" + flag[len("define_"):] for flag in flags
+                             if flag.startswith("define_"))
+        if "blocks" in flags:
+            self.cflags.append("-fblocks")
+        self.idiomatic_names = "idiomatic_names" in flags
+
+    def transpiler_args(self, cc_db: str) -> List[str]:
         args = [
             cc_db,
             "--prefix-function-names",
@@ -96,28 +104,97 @@ class CFile:
             args.append("--reorganize-definitions")
         if self.emit_build_files:
             args.append("--emit-build-files")
+        if self.long_double_x87:
+            args.append("--long-double=x87")
+        if self.map_libc_calls:
+            args.append("--map-libc-calls")
+        if self.share_header_items:
+            args.append("--share-header-items")
+        if self.emit_no_std:
+            args.append("--emit-no-std")
+        if self.idiomatic_names:
+            args.append("--naming-policy=idiomatic")
+        if self.binary:
+            extensionless_file, _ = os.path.splitext(self.path)
+            args.extend(["--binary", os.path.basename(extensionless_file)])
+        if self.check_c_header:
+            extensionless_file, _ = os.path.splitext(self.path)
+            args.extend(["--check-c-header", extensionless_file + ".h"])
 
         if self.log_level == 'DEBUG':
             args.append("--log-level=debug")
 
+        return args
+
+    def translate(self, cc_db: str, ld_lib_path: str, extra_args: List[str] = []) -> RustFile:
+        extensionless_file, _ = os.path.splitext(self.path)
+
+        args = self.transpiler_args(cc_db)
         args.append("--")
         args.extend(extra_args)
+        # The transpiler exits with an error if the emitted C header doesn't
+        # match the original one, which `run_transpiler` reports
+        run_transpiler(args, ld_lib_path)
 
-        with pb.local.env(RUST_BACKTRACE='1', LD_LIBRARY_PATH=ld_lib_path):
-            # log the command in a format that's easy to re-run
-            translation_cmd = "LD_LIBRARY_PATH=" + ld_lib_path + " \\\n"
-            translation_cmd += str(transpiler[args])
-            logging.debug("translation command:\n %s", translation_cmd)
-            retcode, stdout, stderr = (transpiler[args]).run(
-                retcode=None)
+        if self.check_c_header:
+            check_c_header(os.path.dirname(self.path))
 
-            logging.debug("stdout:\n%s", stdout)
-            logging.debug("stderr:\n%s", stderr)
+        return RustFile(extensionless_file + ".rs")
 
-        if retcode != 0:
-            raise NonZeroReturn(stderr)
 
-        return RustFile(extensionless_file + ".rs")
+def translate_together(c_files: List[CFile], cc_db: str, ld_lib_path: str,
+                       extra_args: List[str] = []) -> List[RustFile]:
+    """
+    Translate `c_files`, which must all be in `cc_db`, in one transpiler run
+    with a worker per file. The first file's flags apply to all of them.
+    """
+    args = c_files[0].transpiler_args(cc_db)
+    args.append("--jobs={}".format(len(c_files)))
+    args.append("--")
+    args.extend(extra_args)
+    stdout, _ = run_transpiler(args, ld_lib_path)
+
+    # Parallel translation must still report in compile_commands.json order
+    transpiled = [line[len("Transpiling "):] for line in stdout.splitlines()
+                  if line.startswith("Transpiling ")]
+    expected = [os.path.basename(c_file.path) for c_file in c_files]
+    if transpiled != expected:
+        raise NonZeroReturn("files were reported out of order: {}".format(transpiled))
+
+    return [RustFile(os.path.splitext(c_file.path)[0] + ".rs") for c_file in c_files]
+
+
+def run_transpiler(args: List[str], ld_lib_path: str) -> Tuple[str, str]:
+    transpiler = get_cmd_or_die(c.TRANSPILER)
+
+    with pb.local.env(RUST_BACKTRACE='1', LD_LIBRARY_PATH=ld_lib_path):
+        # log the command in a format that's easy to re-run
+        translation_cmd = "LD_LIBRARY_PATH=" + ld_lib_path + " \\\n"
+        translation_cmd += str(transpiler[args])
+        logging.debug("translation command:\n %s", translation_cmd)
+        retcode, stdout, stderr = (transpiler[args]).run(
+            retcode=None)
+
+        logging.debug("stdout:\n%s", stdout)
+        logging.debug("stderr:\n%s", stderr)
+
+    if retcode != 0:
+        raise NonZeroReturn(stderr)
+
+    return stdout, stderr
+
+
+def check_c_header(output_dir: str) -> None:
+    """
+    Compile the C header the transpiler emitted into `output_dir` on its own to
+    make sure it is self-contained.
+    """
+    header = os.path.join(output_dir, "c2rust_out.h")
+    args = ["-fsyntax-only", "-x", "c", header]
+    logging.debug("header compilation command:\n %s", str(clang[args]))
+    retcode, _, stderr = clang[args].run(retcode=None)
+    if retcode != 0:
+        raise NonZeroReturn(stderr)
 
 
 def get_native_arch() -> str:
@@ -129,6 +206,13 @@ def get_native_arch() -> str:
     raise KeyError
 
 
+def get_native_target() -> str:
+    for line in rustc["-vV"]().splitlines():
+        if line.startswith("host: "):
+            return line[len("host: "):]
+    raise KeyError
+
+
 def rustc_has_target(target: str) -> bool:
     args = ["--target", target, "--print", "target-libdir"]
     stdout = rustc[args]()
@@ -153,6 +237,7 @@ def build_static_library(c_files: Iterable[CFile],
     # create .o files
     args = ["-c", "-fPIC", "-Wno-error=int-conversion"]
     args += target_args(target)
+    args += sorted({cflag for c_file in c_files for cflag in c_file.cflags})
     paths = [c_file.path for c_file in c_files]
 
     if len(paths) == 0:
@@ -213,7 +298,8 @@ class TestFile(RustFile):
 
 
 class TestDirectory:
-    def __init__(self, full_path: str, files: 're.Pattern', keep: List[str], log_level: str) -> None:
+    def __init__(self, full_path: str, files: 're.Pattern', keep: List[str], log_level: str,
+                 cross_target: Optional[str] = None) -> None:
         self.c_files = []
         self.rs_test_files = []
         self.full_path = full_path
@@ -231,7 +317,11 @@ class TestDirectory:
 
         # if the test is arch-specific, check if we can run it natively; if not,
         # set self.target to a known-working target tuple for it
-        self.target = None
+        self.target = cross_target
+
+        # tests listed in `cross-targets` are only translated and built for
+        # those targets, since we can't run them without the target hardware
+        self.build_only = cross_target is not None
 
         # include the compiler resource directory in compile_commands.json
         _, stdout, _ = clang["-print-resource-dir"].run(retcode=None)
@@ -239,7 +329,7 @@ class TestDirectory:
 
         # parse target arch from directory name if it includes a dot
         split_by_dots = self.name.split('.')
-        if len(split_by_dots) > 1:
+        if len(split_by_dots) > 1 and not self.target:
             target_arch = split_by_dots[-1]
             # if native and target arch differ, cross-compile to specific target
             if target_arch != get_native_arch():
@@ -315,22 +405,27 @@ class TestDirectory:
         if message:
             sys.stdout.write(message)
 
-    def _generate_cc_db(self, c_file_path: str) -> None:
-        directory, cfile = os.path.split(c_file_path)
-
+    def _generate_cc_db(self, c_files: List[CFile]) -> None:
         target_args = '"-target", "{}", '.format(self.target) if self.target else ""
 
-        compile_commands = """ \
-        [
+        entries = []
+        for c_file in c_files:
+            directory, cfile = os.path.split(c_file.path)
+            cflag_args = "".join(' "{}",'.format(cflag) for cflag in c_file.cflags)
+            entries.append("""
           {{
-            "arguments": [ "cc", "-D_FORTIFY_SOURCE=0",{3} "-c", {2}"{0}" ],
+            "arguments": [ "cc", "-D_FORTIFY_SOURCE=0",{3}{4} "-c", {2}"{0}" ],
             "directory": "{1}",
             "file": "{0}"
-          }}
+          }}""".format(cfile, directory, target_args, self.clang_resource_dir,
+                       cflag_args))
+
+        compile_commands = """ \
+        [{}
         ]
-        """.format(cfile, directory, target_args, self.clang_resource_dir)
+        """.format(",".join(entries))
 
-        cc_db = os.path.join(directory, "compile_commands.json")
+        cc_db = os.path.join(os.path.abspath(self.full_path_src), "compile_commands.json")
 
         self.generated_files["cc_db"] = [cc_db]
 
@@ -407,8 +502,54 @@ class TestDirectory:
         if 'LD_LIBRARY_PATH' in pb.local.env:
             ld_lib_path += ':' + pb.local.env['LD_LIBRARY_PATH']
 
-        # .c -> .rs
+        # .c -> .rs, first the files that are translated together
+        together = [c_file for c_file in self.c_files if c_file.translate_together]
+        if together:
+            c_files_short = ", ".join(os.path.basename(c_file.path) for c_file in together)
+            description = "{}: translating the C files into Rust in parallel...".format(
+                c_files_short)
+            self.print_status(Colors.WARNING, "RUNNING", description)
+
+            self._generate_cc_db(together)
+
+            try:
+                logging.debug("translating %s", c_files_short)
+                translated_rust_files = translate_together(together,
+                                                           self.generated_files["cc_db"][0],
+                                                           ld_lib_path,
+                                                           extra_args=target_args(self.target))
+            except NonZeroReturn as exception:
+                self.print_status(Colors.FAIL, "FAILED", "translate " +
+                                  c_files_short)
+                sys.stdout.write('\n')
+                sys.stdout.write(str(exception))
+
+                outcomes.append(TestOutcome.UnexpectedFailure)
+                translated_rust_files = []
+
+            # Definitions shared between the files live in a module of their own
+            shared_rust_file = RustFile(os.path.join(self.full_path_src, "c2rust_shared.rs"))
+            if translated_rust_files and os.path.isfile(shared_rust_file.path):
+                translated_rust_files.append(shared_rust_file)
+
+            for translated_rust_file in translated_rust_files:
+                self.generated_files["rust_src"].append(translated_rust_file)
+                _, rust_file_short = os.path.split(translated_rust_file.path)
+                extensionless_rust_file, _ = os.path.splitext(rust_file_short)
+                rust_file_builder.add_mod(RustMod(extensionless_rust_file,
+                                                  RustVisibility.Public))
+            if translated_rust_files and together[0].idiomatic_names:
+                self.generated_files["rust_src"].append(self.full_path + "/src/c2rust-names.json")
+            if translated_rust_files and together[0].emit_build_files:
+                self.generated_files["rust_src"].append(self.full_path + "/src/Cargo.toml")
+                self.generated_files["rust_src"].append(self.full_path + "/src/build.rs")
+                self.generated_files["rust_src"].append(self.full_path + "/src/c2rust-lib.rs")
+                self.generated_files["rust_src"].append(self.full_path + "/src/rust-toolchain.toml")
+
         for c_file in self.c_files:
+            if c_file.translate_together:
+                continue
+
             _, c_file_short = os.path.split(c_file.path)
             description = "{}: translating the C file into Rust...".format(
                 c_file_short)
@@ -416,7 +557,7 @@ class TestDirectory:
             # Run the step
             self.print_status(Colors.WARNING, "RUNNING", description)
 
-            self._generate_cc_db(c_file.path)
+            self._generate_cc_db([c_file])
 
             try:
                 logging.debug("translating %s", c_file_short)
@@ -433,12 +574,22 @@ class TestDirectory:
                 continue
 
             self.generated_files["rust_src"].append(translated_rust_file)
-            if c_file.emit_build_files:
+            if c_file.check_c_header:
+                self.generated_files["rust_src"].append(self.full_path + "/src/c2rust_out.h")
+                self.generated_files["rust_src"].append(
+                    self.full_path + "/src/c2rust_out_check.c")
+            if c_file.idiomatic_names:
+                self.generated_files["rust_src"].append(self.full_path + "/src/c2rust-names.json")
+            if c_file.emit_build_files or c_file.binary:
                 self.generated_files["rust_src"].append(self.full_path + "/src/Cargo.toml")
                 self.generated_files["rust_src"].append(self.full_path + "/src/build.rs")
                 self.generated_files["rust_src"].append(self.full_path + "/src/c2rust-lib.rs")
                 self.generated_files["rust_src"].append(self.full_path + "/src/rust-toolchain.toml")
 
+            # A binary is the root of a crate of its own, which its tests build
+            if c_file.binary:
+                continue
+
             _, rust_file_short = os.path.split(translated_rust_file.path)
             extensionless_rust_file, _ = os.path.splitext(rust_file_short)
 
@@ -526,6 +677,13 @@ class TestDirectory:
 
             return outcomes
 
+        if self.build_only:
+            self.print_status(Colors.OKGREEN, "OK",
+                              "    built {} for {}".format(self.name, self.target))
+            sys.stdout.write('\n')
+            outcomes.append(TestOutcome.Success)
+            return outcomes
+
         for test_file in self.rs_test_files:
             if not test_file.pass_expected:
                 continue
@@ -623,6 +781,17 @@ def get_testdirectories(
                 continue
             yield TestDirectory(str(path.absolute()), files, keep, log_level)
 
+            # Also build the test for each of the targets listed in
+            # `cross-targets`, except the one we just ran it on. Targets of the
+            # same architecture can still differ in ABI (e.g. `va_list` on
+            # aarch64 Linux and macOS), so compare whole target tuples.
+            cross_targets = path / "cross-targets"
+            if cross_targets.is_file():
+                for target in cross_targets.read_text(encoding="utf-8").split():
+                    if target != get_native_target():
+                        yield TestDirectory(str(path.absolute()), files, keep, log_level,
+                                            cross_target=target)
+
 
 def main() -> None:
     desc = 'run regression / unit / feature tests.'
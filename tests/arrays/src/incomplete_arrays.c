@@ -23,6 +23,23 @@ struct sized_array *new_sized_array(size_t n) {
         return res;
 }
 
+struct sized_array *new_sized_array_direct(size_t n) {
+        struct sized_array *res = malloc(sizeof(struct sized_array) + sizeof(unsigned) * n);
+        res->n = n;
+        return res;
+}
+
+// Packed, so `data` isn't aligned for its elements unless they are bytes
+struct __attribute__((packed)) packed_bytes {
+        char tag;
+        unsigned char data[];
+};
+
+struct __attribute__((packed)) packed_ints {
+        char tag;
+        int data[];
+};
+
 // This test ensures that `struct sized_array *` isn't a fat
 // pointer, which would cause the following argument `n` to
 // be garbage
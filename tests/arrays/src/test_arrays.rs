@@ -1,5 +1,8 @@
 use crate::arrays::rust_entry;
-use crate::incomplete_arrays::{rust_check_some_ints, rust_entry2, rust_test_sized_array};
+use crate::incomplete_arrays::{
+    packed_bytes, packed_ints, rust_check_some_ints, rust_entry2, rust_new_sized_array_direct,
+    rust_sized_array_sum_last_n, rust_test_sized_array, sized_array,
+};
 use crate::variable_arrays::{rust_alloca_arrays, rust_variable_arrays};
 use libc::{c_int, c_uint};
 
@@ -33,6 +36,39 @@ pub fn test_sized_array_impls() {
     }
 }
 
+pub fn test_flexible_array_api() {
+    unsafe {
+        let a = rust_new_sized_array_direct(4);
+        sized_array::tail_mut(a, 4).copy_from_slice(&[1, 2, 3, 4]);
+        assert_eq!(sized_array::tail(a, 4), &[1, 2, 3, 4]);
+        assert_eq!(rust_sized_array_sum_last_n(a, 2), 7);
+        libc::free(a.cast());
+
+        let b = sized_array::new_with_len(2);
+        assert_eq!((*b).n, 0);
+        assert_eq!(sized_array::tail(b, 2), &[0, 0]);
+        libc::free(b.cast());
+    }
+
+    // The malloc call sized for the struct goes through the generated allocator
+    let src = include_str!("incomplete_arrays.rs");
+    assert!(src.contains("sized_array::new_with_len("));
+}
+
+pub fn test_packed_flexible_array_api() {
+    unsafe {
+        let a = packed_bytes::new_with_len(3);
+        packed_bytes::tail_mut(a, 3).copy_from_slice(&[1, 2, 3]);
+        assert_eq!(packed_bytes::tail(a, 3), &[1, 2, 3]);
+        libc::free(a.cast());
+
+        // The ints start at offset 1, so they can't be viewed as a slice
+        let b = packed_ints::new_with_len(2);
+        assert!(std::panic::catch_unwind(|| packed_ints::tail(b, 2).len()).is_err());
+        libc::free(b.cast());
+    }
+}
+
 pub fn test_global_incomplete_array() {
     unsafe {
         assert_eq!(rust_check_some_ints(), check_some_ints());
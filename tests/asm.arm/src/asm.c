@@ -10,6 +10,11 @@ uint32_t __STREXB(uint8_t value, volatile uint8_t *addr)
    return(result);
 }
 
+// File-scope assembly has no syntax options outside of x86
+__asm__(".pushsection .comment\n\t"
+        ".asciz \"c2rust arm asm test\"\n\t"
+        ".popsection");
+
 void entry(const unsigned int buffer_size, int buffer[const])
 {
     int i = 0;
@@ -68,6 +68,35 @@ int six(void) {
     return out;
 }
 
+// File-scope assembly is translated to `global_asm!`, in AT&T syntax unless it
+// switches to Intel. Both the C library and the translation define
+// `asm_inc`, so it's weak.
+__asm__(".pushsection .comment\n\t"
+        ".asciz \"c2rust asm test\"\n\t"
+        ".popsection\n\t"
+        ".pushsection .text\n\t"
+        ".weak asm_inc\n\t"
+        ".type asm_inc, @function\n"
+        "asm_inc:\n\t"
+        "leal 1(%rdi), %eax\n\t"
+        "ret\n\t"
+        ".popsection");
+
+int asm_inc(int x);
+
+// `asm goto` jumps to a C label from inside the assembly
+int is_nonzero(int x) {
+    asm goto("testl %k0, %k0\n\t"
+             "jz %l1"
+             :
+             : "r"(x)
+             : "cc"
+             : zero);
+    return 1;
+zero:
+    return 0;
+}
+
 void entry(const unsigned int buffer_size, int buffer[const])
 {
     int i = 0;
@@ -78,4 +107,7 @@ void entry(const unsigned int buffer_size, int buffer[const])
     buffer[i++] = mul2(6);
     buffer[i++] = mul2_2(6);
     buffer[i++] = six();
+    buffer[i++] = is_nonzero(0);
+    buffer[i++] = is_nonzero(42);
+    buffer[i++] = asm_inc(41);
 }
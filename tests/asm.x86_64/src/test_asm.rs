@@ -8,12 +8,12 @@ extern "C" {
     fn entry(_: c_uint, _: *mut c_int);
 }
 
-const BUFFER_SIZE: usize = 6;
+const BUFFER_SIZE: usize = 9;
 
 pub fn test_buffer() {
     let mut buffer = [0; BUFFER_SIZE];
     let mut rust_buffer = [0; BUFFER_SIZE];
-    let expected_buffer = [243025, 65070, 51450, 12, 12, 6];
+    let expected_buffer = [243025, 65070, 51450, 12, 12, 6, 0, 1, 42];
 
     unsafe {
         entry(BUFFER_SIZE as u32, buffer.as_mut_ptr());
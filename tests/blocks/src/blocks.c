@@ -0,0 +1,42 @@
+//! blocks
+
+#include <Block.h>
+
+typedef int (^adder_t)(int);
+
+static int apply(adder_t f, int x) {
+    return f(x);
+}
+
+// A literal on the stack, capturing `n`
+int add_with_block(int n, int x) {
+    adder_t add = ^(int y) { return y + n; };
+    return apply(add, x);
+}
+
+// A copy on the heap, called after the literal is gone
+adder_t make_adder(int n) {
+    adder_t add = ^(int y) { return y + n; };
+    return Block_copy(add);
+}
+
+int copied_block(int n, int x) {
+    adder_t add = make_adder(n);
+    int result = add(x);
+    Block_release(add);
+    return result;
+}
+
+// Blocks capturing blocks need copy and dispose helpers
+int nested_blocks(int n, int x) {
+    adder_t inner = ^(int y) { return y * n; };
+    adder_t outer = ^(int y) { return inner(y) + 1; };
+    adder_t copy = Block_copy(outer);
+    int result = copy(x);
+    Block_release(copy);
+    return result;
+}
+
+int call_block(adder_t f, int x) {
+    return f(x);
+}
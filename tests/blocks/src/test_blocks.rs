@@ -0,0 +1,50 @@
+use crate::blocks::{
+    adder_t, rust_add_with_block, rust_call_block, rust_copied_block, rust_make_adder,
+    rust_nested_blocks,
+};
+use c2rust_blocks::block_release;
+use libc::c_int;
+
+#[link(name = "test")]
+extern "C" {
+    fn add_with_block(_: c_int, _: c_int) -> c_int;
+
+    fn make_adder(_: c_int) -> adder_t;
+
+    fn copied_block(_: c_int, _: c_int) -> c_int;
+
+    fn nested_blocks(_: c_int, _: c_int) -> c_int;
+
+    fn call_block(_: adder_t, _: c_int) -> c_int;
+}
+
+pub fn test_block_literals() {
+    unsafe {
+        assert_eq!(add_with_block(3, 4), 7);
+        assert_eq!(rust_add_with_block(3, 4), 7);
+        assert_eq!(nested_blocks(3, 4), 13);
+        assert_eq!(rust_nested_blocks(3, 4), 13);
+    }
+}
+
+pub fn test_block_copy() {
+    unsafe {
+        assert_eq!(copied_block(5, 6), 11);
+        assert_eq!(rust_copied_block(5, 6), 11);
+    }
+}
+
+pub fn test_block_interop() {
+    unsafe {
+        // Rust calls a block C copied to the heap
+        let c_block = make_adder(10);
+        assert_eq!(rust_call_block(c_block, 1), 11);
+        block_release(c_block);
+
+        // C calls a translated block the blocks runtime copied
+        let rust_block = rust_make_adder(20);
+        assert_eq!(call_block(rust_block, 1), 21);
+        assert_eq!(rust_call_block(rust_block, 2), 22);
+        block_release(rust_block);
+    }
+}
@@ -0,0 +1,33 @@
+//! map_libc_calls
+
+#include <string.h>
+
+struct record {
+    int ids[4];
+    char name[8];
+};
+
+void libc_mapping(unsigned buffer_size, int buffer[]) {
+    int src[4] = {1, 2, 3, 4};
+    int dst[4];
+    char name[8];
+    struct record r;
+    int i = 0;
+
+    memcpy(dst, src, sizeof(dst));
+    memset(r.ids, 0, 3 * sizeof(int));
+    // `src` and `r.ids` are distinct, but we only prove that for variables
+    memmove(r.ids, src, 2 * sizeof(src[0]));
+    memcpy(name, "abc", 4);
+    memcpy(r.name, name, sizeof(name));
+    __builtin_memset(name, 'x', 2);
+
+    buffer[i++] = dst[0] + dst[3];
+    buffer[i++] = r.ids[0] + r.ids[1] + r.ids[2];
+    buffer[i++] = strlen("hello");
+    buffer[i++] = strlen(r.name);
+    buffer[i++] = strcmp(name, "xxc") == 0;
+    buffer[i++] = strcmp("abc", "abd") < 0;
+    buffer[i++] = strcmp(r.name, name) < 0;
+    buffer[i++] = name[0];
+}
@@ -0,0 +1,44 @@
+use crate::libc_mapping::rust_libc_mapping;
+use libc::{c_int, c_uint};
+
+#[link(name = "test")]
+extern "C" {
+    fn libc_mapping(_: c_uint, _: *mut c_int);
+}
+
+const BUFFER_SIZE: usize = 8;
+
+pub fn test_libc_mapping() {
+    let mut buffer = [0; BUFFER_SIZE];
+    let mut rust_buffer = [0; BUFFER_SIZE];
+    let expected_buffer = [5, 3, 5, 3, 1, 1, 1, b'x' as c_int];
+
+    unsafe {
+        libc_mapping(BUFFER_SIZE as u32, buffer.as_mut_ptr());
+        rust_libc_mapping(BUFFER_SIZE as u32, rust_buffer.as_mut_ptr());
+    }
+
+    assert_eq!(buffer, expected_buffer);
+    assert_eq!(rust_buffer, expected_buffer);
+}
+
+/// The number of calls to `name` in `src`, not counting its declaration.
+fn count_calls(src: &str, name: &str) -> usize {
+    let call = format!("{}(", name);
+    let decl = format!("fn {}(", name);
+    src.matches(&call).count() - src.matches(&decl).count()
+}
+
+pub fn test_libc_mapping_is_safe() {
+    let src = include_str!("libc_mapping.rs");
+
+    assert!(src.contains(".copy_from_slice("));
+    assert!(src.contains(".fill("));
+    assert!(src.contains(".to_bytes().len()"));
+    assert_eq!(count_calls(src, "memcpy"), 0);
+    assert_eq!(count_calls(src, "memset"), 0);
+    assert_eq!(count_calls(src, "strlen"), 0);
+    assert_eq!(count_calls(src, "strcmp"), 0);
+    // Not provably distinct, so left alone
+    assert_eq!(count_calls(src, "memmove"), 1);
+}
@@ -0,0 +1,18 @@
+//! check_c_header, define_COUNTER_WIDE=1
+
+#include "counter.h"
+
+void counter_init(struct counter *c) {
+    c->total = 0;
+    c->steps = 0;
+}
+
+count_t counter_add(struct counter *c, count_t n) {
+    c->total += n;
+    c->steps++;
+    return c->total;
+}
+
+count_t counter_total(const struct counter *c) {
+    return c->total;
+}
@@ -0,0 +1,29 @@
+#ifndef COUNTER_H
+#define COUNTER_H
+
+// The build has to pass the width, so checking the emitted header against
+// this one only works with the flags from compile_commands.json.
+#ifndef COUNTER_WIDE
+#error "counter.h needs COUNTER_WIDE from the build flags"
+#endif
+
+#if COUNTER_WIDE
+typedef long long count_t;
+#else
+typedef int count_t;
+#endif
+
+struct counter {
+    count_t total;
+    unsigned char steps;
+};
+
+void counter_init(struct counter *c);
+count_t counter_add(struct counter *c, count_t n);
+count_t counter_total(const struct counter *c);
+
+// The tests prefix translated functions with `rust_`, so this is how C callers
+// see the translation of `counter_total`. The emitted header has to agree.
+count_t rust_counter_total(const struct counter *c);
+
+#endif
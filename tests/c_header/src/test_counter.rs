@@ -0,0 +1,51 @@
+use crate::counter::{counter, rust_counter_add, rust_counter_init, rust_counter_total};
+use libc::c_longlong;
+
+#[link(name = "test")]
+extern "C" {
+    fn counter_init(_: *mut counter);
+
+    fn counter_add(_: *mut counter, _: c_longlong) -> c_longlong;
+
+    fn counter_total(_: *const counter) -> c_longlong;
+}
+
+pub fn test_counter() {
+    let mut c = counter { total: 1, steps: 1 };
+    let mut rust_c = counter { total: 1, steps: 1 };
+
+    unsafe {
+        counter_init(&mut c);
+        rust_counter_init(&mut rust_c);
+        for n in [1 << 40, 2, 3] {
+            assert_eq!(counter_add(&mut c, n), rust_counter_add(&mut rust_c, n));
+        }
+        assert_eq!(counter_total(&c), rust_counter_total(&rust_c));
+    }
+
+    assert_eq!(c.total, rust_c.total);
+    assert_eq!(c.total, (1 << 40) + 5);
+    assert_eq!(c.steps, 3);
+}
+
+pub fn test_emitted_header() {
+    // The harness compiles this header on its own and checks it against
+    // `counter.h`, which only compiles with `-DCOUNTER_WIDE=1`
+    let header = include_str!("c2rust_out.h");
+
+    assert!(header.contains("typedef long long count_t;"));
+    assert!(header.contains("struct C2RUST_NAME(counter) {"));
+    for name in [
+        "rust_counter_init(",
+        "rust_counter_add(",
+        "rust_counter_total(",
+    ] {
+        assert!(header.lines().any(|line| line.contains(name)), "{}", name);
+    }
+    let add = header
+        .lines()
+        .find(|line| line.contains("rust_counter_add("))
+        .unwrap();
+    assert!(add.starts_with("count_t "), "{}", add);
+    assert!(add.contains("count_t n"), "{}", add);
+}
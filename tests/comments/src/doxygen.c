@@ -0,0 +1,36 @@
+/**
+ * @brief Add two numbers.
+ *
+ * Uses @p a and @p b unchanged.
+ *
+ * @param a the first addend
+ * @param b the second addend,
+ *          continued on another line
+ * @return their @b sum
+ */
+int add(int a, int b) {
+    return a + b;
+}
+
+/// A point in the plane.
+typedef struct {
+    int x; ///< The horizontal coordinate
+    int y; /**< The vertical coordinate */
+} point;
+
+/** Documented on the typedef. */
+typedef struct named_pair named_pair;
+
+/// Documented on the struct.
+struct named_pair {
+    int first;
+    int second;
+};
+
+int sum_point(point p) {
+    return add(p.x, p.y);
+}
+
+int sum_pair(named_pair p) {
+    return add(p.first, p.second);
+}
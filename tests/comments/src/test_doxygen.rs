@@ -0,0 +1,40 @@
+use crate::doxygen::{named_pair, point, rust_add, rust_sum_pair, rust_sum_point};
+use libc::c_int;
+
+#[link(name = "test")]
+extern "C" {
+    fn add(_: c_int, _: c_int) -> c_int;
+}
+
+pub fn test_add() {
+    assert_eq!(unsafe { add(2, 3) }, 5);
+    assert_eq!(unsafe { rust_add(2, 3) }, 5);
+    assert_eq!(unsafe { rust_sum_point(point { x: 1, y: 2 }) }, 3);
+    assert_eq!(
+        unsafe {
+            rust_sum_pair(named_pair {
+                first: 3,
+                second: 4,
+            })
+        },
+        7
+    );
+}
+
+pub fn test_doxygen_docs() {
+    let src = include_str!("doxygen.rs");
+
+    // `@brief`, `@param` and `@return`, with inline commands
+    assert!(src.contains("/// Add two numbers.\n///\n/// Uses `a` and `b` unchanged.\n"));
+    assert!(src.contains("/// # Arguments\n///\n/// * `a` - the first addend\n"));
+    assert!(src.contains("/// * `b` - the second addend, continued on another line\n"));
+    assert!(src.contains("/// # Returns\n///\n/// their **sum**\n"));
+
+    // Trailing member comments document the fields
+    assert!(src.contains("/// A point in the plane."));
+    assert!(src.contains("/// The horizontal coordinate\n"));
+    assert!(src.contains("/// The vertical coordinate\n"));
+
+    // Docs on a typedef merged into its struct come first
+    assert!(src.contains("/// Documented on the typedef.\n///\n/// Documented on the struct.\n"));
+}
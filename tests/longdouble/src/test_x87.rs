@@ -0,0 +1,41 @@
+//! extern_crate_c2rust_x87
+
+use crate::x87::{
+    rust_x87_cast2uint, rust_x87_format, rust_x87_is_zero, rust_x87_ops, rust_x87_third,
+};
+use c2rust_x87::f80;
+use std::ffi::CStr;
+
+pub fn test_x87_ops() {
+    let mut input = f80::new(2);
+    let rust_ret = unsafe { rust_x87_ops(&mut input) };
+
+    assert_eq!(input, f80::from_lit("2.75"));
+    assert_eq!(rust_ret, f80::from_lit("1.125"));
+}
+
+pub fn test_x87_precision() {
+    let third = unsafe { rust_x87_third() };
+
+    assert_eq!(third, f80::ONE / f80::new(3));
+    assert_ne!(third, f80::new(1.0f64 / 3.0));
+}
+
+pub fn test_x87_casts() {
+    unsafe {
+        assert_eq!(rust_x87_cast2uint(f80::from_lit("1000.9")), 1000);
+        assert_eq!(rust_x87_is_zero(f80::ZERO), 1);
+        assert_eq!(rust_x87_is_zero(f80::from_lit("0.5")), 0);
+    }
+}
+
+pub fn test_x87_printf() {
+    let mut buf = [0 as libc::c_char; 64];
+    unsafe {
+        rust_x87_format(buf.as_mut_ptr(), buf.len() as _, f80::from_lit("0.125"));
+        assert_eq!(
+            CStr::from_ptr(buf.as_ptr()).to_str().unwrap(),
+            "0.125|1.250000e-01|7"
+        );
+    }
+}
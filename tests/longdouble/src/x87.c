@@ -0,0 +1,30 @@
+//! long_double_x87
+
+#include <stdio.h>
+
+long double x87_ops(long double *a) {
+    long double b = 1.5L;
+
+    (*a)++;
+    b *= *a;
+    *a -= 0.25L;
+    b /= 4;
+
+    return b;
+}
+
+long double x87_third(void) {
+    return 1.0L / 3;
+}
+
+unsigned int x87_cast2uint(long double a) {
+    return a;
+}
+
+int x87_is_zero(long double a) {
+    return !a;
+}
+
+int x87_format(char *buf, size_t len, long double x) {
+    return snprintf(buf, len, "%.3Lf|%Le|%d", x, x, 7);
+}
@@ -0,0 +1,15 @@
+#include <string.h>
+
+// The test runs the translated `main` with an argument and an environment
+// variable that aren't valid UTF-8, which must arrive byte for byte.
+int main(int argc, char *argv[], char *envp[]) {
+    if (argc != 3 || strcmp(argv[2], "caf\xe9") != 0 || argv[3] != NULL) {
+        return 1;
+    }
+    for (char **var = envp; *var != NULL; var++) {
+        if (strcmp(*var, "C2RUST_TEST_BYTES=\xff\xfe") == 0) {
+            return 0;
+        }
+    }
+    return 2;
+}
@@ -0,0 +1,9 @@
+//! binary, emit_no_std
+
+// Translated to a `no_std` binary exporting the C entry point `main`
+int main(int argc, char *argv[]) {
+    if (argc != 2 || argv[2] != 0) {
+        return 1;
+    }
+    return argv[1][0] == '\xe9' && argv[1][1] == '\0' ? 42 : 2;
+}
@@ -0,0 +1,45 @@
+use std::env;
+use std::ffi::OsStr;
+use std::os::unix::ffi::OsStrExt;
+use std::process::Command;
+
+const RUN_MAIN: &str = "C2RUST_TEST_RUN_MAIN";
+
+pub fn test_non_utf8_args() {
+    // Run again as a child that calls the translated `main`, which exits
+    if env::var_os(RUN_MAIN).is_some() {
+        crate::args::main();
+    }
+
+    let status = Command::new(env::current_exe().unwrap())
+        .arg("test_main::test_non_utf8_args")
+        .arg(OsStr::from_bytes(b"caf\xe9"))
+        .env(RUN_MAIN, "1")
+        .env("C2RUST_TEST_BYTES", OsStr::from_bytes(b"\xff\xfe"))
+        .status()
+        .unwrap();
+    assert_eq!(status.code(), Some(0));
+}
+
+pub fn test_no_std_main() {
+    let src = include_str!("no_std.rs");
+    assert!(src.contains("#![no_std]"));
+    assert!(src.contains("#![no_main]"));
+    assert!(src.contains("#[no_mangle]\npub unsafe extern \"C\" fn main("));
+    assert!(src.contains("#[panic_handler]\nfn panic(_info: &::core::panic::PanicInfo) -> ! {"));
+
+    // The binary is a crate of its own, see `src/Cargo.toml`
+    let manifest_dir = env!("CARGO_MANIFEST_DIR");
+    let cargo = env::var_os("CARGO").unwrap_or_else(|| "cargo".into());
+    let status = Command::new(cargo)
+        .arg("run")
+        .arg("--manifest-path")
+        .arg(format!("{}/src/Cargo.toml", manifest_dir))
+        .arg("--target-dir")
+        .arg(format!("{}/target/no_std", manifest_dir))
+        .arg("--")
+        .arg(OsStr::from_bytes(b"\xe9"))
+        .status()
+        .unwrap();
+    assert_eq!(status.code(), Some(42));
+}
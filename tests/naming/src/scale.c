@@ -0,0 +1,6 @@
+//! translate_together, idiomatic_names
+#include <stdint.h>
+
+int32_t scaleBy(int32_t v, int32_t factor) {
+    return v * factor;
+}
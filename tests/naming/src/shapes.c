@@ -0,0 +1,20 @@
+//! translate_together, idiomatic_names
+#include <stdint.h>
+
+struct point {
+    int32_t xPos;
+    int32_t yPos;
+};
+
+// Also `Point` in Rust, so it gets a suffix
+typedef struct point point_t;
+
+// Defined in scale.c, so it's imported under the C name
+int32_t scaleBy(int32_t v, int32_t factor);
+
+uint32_t callCount = 0;
+
+int32_t sumXY(const point_t *p, int32_t factor) {
+    callCount += 1;
+    return scaleBy(p->xPos + p->yPos, factor);
+}
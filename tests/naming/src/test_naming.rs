@@ -0,0 +1,64 @@
+use crate::scale::rust_scale_by;
+use crate::shapes::{rust_sum_xy, Point2, RUST_CALL_COUNT};
+use libc::c_int;
+
+#[link(name = "test")]
+extern "C" {
+    fn scaleBy(v: c_int, factor: c_int) -> c_int;
+
+    fn sumXY(p: *const Point2, factor: c_int) -> c_int;
+
+    // The Rust definitions, by their C symbol names
+    fn rust_scaleBy(v: c_int, factor: c_int) -> c_int;
+
+    fn rust_sumXY(p: *const Point2, factor: c_int) -> c_int;
+}
+
+pub fn test_idiomatic_names() {
+    let p = Point2 { x_pos: 2, y_pos: 5 };
+
+    unsafe {
+        assert_eq!(scaleBy(7, 3), rust_scale_by(7, 3));
+        assert_eq!(scaleBy(7, 3), rust_scaleBy(7, 3));
+        assert_eq!(sumXY(&p, 3), rust_sum_xy(&p, 3));
+        assert_eq!(sumXY(&p, 3), rust_sumXY(&p, 3));
+        assert_eq!(RUST_CALL_COUNT, 2);
+    }
+}
+
+pub fn test_link_attributes() {
+    let shapes = include_str!("shapes.rs");
+    let scale = include_str!("scale.rs");
+
+    assert!(scale.contains(r#"#[export_name = "rust_scaleBy"]"#));
+    assert!(shapes.contains(r#"#[export_name = "rust_sumXY"]"#));
+    assert!(shapes.contains(r#"#[export_name = "rust_callCount"]"#));
+    assert!(shapes.contains(r#"#[link_name = "scaleBy"]"#));
+    // Standard typedefs keep their C names
+    assert!(shapes.contains("pub type uint32_t = "));
+    assert!(shapes.contains("pub x_pos: int32_t,"));
+    assert!(!shapes.contains("Int32"));
+}
+
+pub fn test_name_mapping() {
+    let names: String = include_str!("c2rust-names.json")
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .collect();
+
+    for (kind, c_name, rust_name) in [
+        ("function", "rust_sumXY", "rust_sum_xy"),
+        ("function", "rust_scaleBy", "rust_scale_by"),
+        ("variable", "rust_callCount", "RUST_CALL_COUNT"),
+        ("type", "point", "Point"),
+        ("type", "point_t", "Point2"),
+        ("field", "point.xPos", "Point.x_pos"),
+    ] {
+        let entry = format!(
+            r#"{{"kind":"{}","c_name":"{}","rust_name":"{}"}}"#,
+            kind, c_name, rust_name
+        );
+        assert!(names.contains(&entry), "{}", entry);
+    }
+    assert!(!names.contains("uint32_t"));
+}
@@ -0,0 +1,13 @@
+//! translate_together
+
+static int step(int x) {
+    return x % 2 == 0 ? x / 2 : 3 * x + 1;
+}
+
+void collatz(unsigned n, int buffer[]) {
+    int x = 27;
+    for (unsigned i = 0; i < n; i++) {
+        buffer[i] = x;
+        x = x == 1 ? 1 : step(x);
+    }
+}
@@ -0,0 +1,17 @@
+//! translate_together
+
+// Each file has its own `step`, so the translation units must not leak
+// definitions into each other when they are translated concurrently.
+static int step(int a, int b) {
+    return a + b;
+}
+
+void fib(unsigned n, int buffer[]) {
+    int a = 0, b = 1;
+    for (unsigned i = 0; i < n; i++) {
+        buffer[i] = a;
+        int next = step(a, b);
+        a = b;
+        b = next;
+    }
+}
@@ -0,0 +1,11 @@
+//! translate_together
+
+static int step(int i) {
+    return i * i;
+}
+
+void squares(unsigned n, int buffer[]) {
+    for (unsigned i = 0; i < n; i++) {
+        buffer[i] = step(i);
+    }
+}
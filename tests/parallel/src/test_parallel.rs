@@ -0,0 +1,46 @@
+use crate::collatz::rust_collatz;
+use crate::fib::rust_fib;
+use crate::squares::rust_squares;
+use libc::{c_int, c_uint};
+
+#[link(name = "test")]
+extern "C" {
+    fn fib(_: c_uint, _: *mut c_int);
+
+    fn collatz(_: c_uint, _: *mut c_int);
+
+    fn squares(_: c_uint, _: *mut c_int);
+}
+
+const BUFFER_SIZE: usize = 10;
+
+type Entry = unsafe extern "C" fn(c_uint, *mut c_int);
+
+fn check(c_fn: Entry, rust_fn: Entry, expected: [c_int; BUFFER_SIZE]) {
+    let mut buffer = [0; BUFFER_SIZE];
+    let mut rust_buffer = [0; BUFFER_SIZE];
+
+    unsafe {
+        c_fn(BUFFER_SIZE as u32, buffer.as_mut_ptr());
+        rust_fn(BUFFER_SIZE as u32, rust_buffer.as_mut_ptr());
+    }
+
+    assert_eq!(buffer, rust_buffer);
+    assert_eq!(buffer, expected);
+}
+
+pub fn test_fib() {
+    check(fib, rust_fib, [0, 1, 1, 2, 3, 5, 8, 13, 21, 34]);
+}
+
+pub fn test_collatz() {
+    check(
+        collatz,
+        rust_collatz,
+        [27, 82, 41, 124, 62, 31, 94, 47, 142, 71],
+    );
+}
+
+pub fn test_squares() {
+    check(squares, rust_squares, [0, 1, 4, 9, 16, 25, 36, 49, 64, 81]);
+}
@@ -0,0 +1,8 @@
+//! translate_together, emit_build_files, share_header_items
+
+#include "scale.h"
+
+int first(int x) {
+    struct range r = {0, 10};
+    return clamp(r, x) + to_int(x * 0.5L);
+}
@@ -0,0 +1,19 @@
+#ifndef SCALE_H
+#define SCALE_H
+
+struct range {
+    int lo;
+    int hi;
+};
+
+static inline int clamp(struct range r, int x) {
+    return x < r.lo ? r.lo : x > r.hi ? r.hi : x;
+}
+
+// The translation calls a `num_traits` method, so the shared module needs
+// the units' imports
+static inline int to_int(long double x) {
+    return (int)x;
+}
+
+#endif
@@ -0,0 +1,8 @@
+//! translate_together
+
+#include "scale.h"
+
+int second(int x) {
+    struct range r = {-5, 5};
+    return clamp(r, to_int(x * 1.5L));
+}
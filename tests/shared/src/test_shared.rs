@@ -0,0 +1,35 @@
+use crate::first::rust_first;
+use crate::second::rust_second;
+use libc::c_int;
+
+#[link(name = "test")]
+extern "C" {
+    fn first(_: c_int) -> c_int;
+
+    fn second(_: c_int) -> c_int;
+}
+
+pub fn test_shared_functions() {
+    for x in [-20, -3, 0, 4, 7, 15] {
+        assert_eq!(unsafe { first(x) }, unsafe { rust_first(x) });
+        assert_eq!(unsafe { second(x) }, unsafe { rust_second(x) });
+    }
+    assert_eq!(unsafe { rust_first(15) }, 17);
+    assert_eq!(unsafe { rust_second(-20) }, -5);
+}
+
+pub fn test_shared_module() {
+    let shared = include_str!("c2rust_shared.rs");
+
+    assert!(shared.contains("pub(crate) unsafe extern \"C\" fn clamp("));
+    assert!(shared.contains("pub(crate) unsafe extern \"C\" fn to_int("));
+    assert!(shared.contains("pub struct range {"));
+    assert!(shared.contains("use num_traits::ToPrimitive;"));
+
+    for unit in [include_str!("first.rs"), include_str!("second.rs")] {
+        assert!(unit.contains("use crate::c2rust_shared::clamp;"));
+        assert!(unit.contains("use crate::c2rust_shared::to_int;"));
+        assert!(!unit.contains("fn clamp("));
+        assert!(!unit.contains("fn to_int("));
+    }
+}
@@ -37,9 +37,8 @@ Bar bar;
 void *int_to_pointer = -1;
 uintptr_t null_to_ptr_ty = (uintptr_t) NULL;
 
-// These should be initialized via sections:
+// These are const-evaluated rather than initialized via sections:
 unsigned section_me = -1U;
-int section_me2 = NULL;
 unsigned section_me3 = 1U + 2U;
 unsigned section_me4 = 1 + 1U;
 unsigned section_me5 = 1U + 1;
@@ -49,6 +48,16 @@ const size_t if_expr = sizeof(size_t) == 4 ? 30 : 31;
 stat s = {&bar.bar};
 stat2 s2 = {&bar.bar};
 static stat3 selfref = { NULL, &selfref.first };
+int const_arr[4] = {1, 2, 3, 4};
+int *arr_elem = &const_arr[2];
+int *arr_end = const_arr + 4;
+unsigned *param_field = &params[1].a;
+static int add(int a, int b) { return a + b; }
+static int sub(int a, int b) { return a - b; }
+int (*fn_table[])(int, int) = { add, sub, NULL };
+
+// These still have to be initialized via sections:
+int section_me2 = NULL;
 void (*int_to_fn_ptr)(void) = -1;
 void (*int_to_fn_ptr2)(int, ...) = -1;
 void_fn_ptr int_to_fn_ptr3 = -1;
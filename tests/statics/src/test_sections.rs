@@ -24,6 +24,59 @@ pub fn test_sectioned_statics() {
         assert_eq!(rust_section_me, c_uint::max_value() - 1);
 
         rust_use_sectioned_array();
+
+        assert_eq!(*rust_arr_elem, 3);
+        assert_eq!(rust_arr_end, rust_const_arr.as_mut_ptr().offset(4));
+        assert_eq!(*rust_param_field, 1);
+        assert_eq!(rust_fn_table[0].unwrap()(5, 3), 8);
+        assert_eq!(rust_fn_table[1].unwrap()(5, 3), 2);
+        assert!(rust_fn_table[2].is_none());
+    }
+}
+
+pub fn test_const_evaluated_statics() {
+    let src = include_str!("sections.rs");
+    let init_fn = src
+        .split("fn run_static_initializers() {")
+        .nth(1)
+        .expect("Did not find run_static_initializers");
+    let init_body = &init_fn[..init_fn.find("\n}").unwrap()];
+
+    // Initializers rustc can't evaluate are still assigned at runtime
+    assert!(init_body.contains("rust_section_me2 ="));
+
+    for name in [
+        "section_me",
+        "section_foo_b_field",
+        "s",
+        "arr_elem",
+        "arr_end",
+        "param_field",
+        "fn_table",
+    ] {
+        let decl = format!("pub static mut rust_{}:", name);
+        assert!(
+            src.lines().any(|line| line.starts_with(&decl)),
+            "Did not find static {}",
+            name
+        );
+        assert!(
+            !init_body.contains(&format!("rust_{} =", name)),
+            "{} is initialized at runtime",
+            name
+        );
+    }
+
+    // The values come from the static initializers themselves
+    unsafe {
+        assert_eq!(rust_section_me, c_uint::max_value());
+        assert_eq!(rust_section_foo_b_field.b, -1);
+        assert!(std::ptr::eq(rust_s.f, &rust_bar.bar));
+        assert_eq!(*rust_arr_elem, 3);
+        assert_eq!(rust_arr_end, rust_const_arr.as_mut_ptr().offset(4));
+        assert!(std::ptr::eq(rust_param_field, &rust_params[1].a));
+        assert_eq!(rust_fn_table[0].unwrap()(5, 3), 8);
+        assert!(rust_fn_table[2].is_none());
     }
 }
 
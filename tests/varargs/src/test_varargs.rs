@@ -0,0 +1,60 @@
+//! feature_c_variadic,
+
+use crate::varargs::{rust_format_to, rust_sum_struct, rust_sum_twice};
+
+use libc::{c_char, c_int, c_long, size_t};
+
+#[link(name = "test")]
+extern "C" {
+    fn format_to(_: *mut c_char, _: size_t, _: *const c_char, ...) -> c_int;
+
+    fn sum_twice(_: c_int, ...) -> c_int;
+
+    fn sum_struct(_: c_int, ...) -> c_long;
+}
+
+const BUFFER_SIZE: usize = 32;
+
+pub fn test_format_to() {
+    let fmt = b"%d, %s, %f\0";
+    let arg = b"test\0";
+    let mut buffer = [0 as c_char; BUFFER_SIZE];
+    let mut rust_buffer = [0 as c_char; BUFFER_SIZE];
+
+    unsafe {
+        let len = format_to(
+            buffer.as_mut_ptr(),
+            BUFFER_SIZE,
+            fmt.as_ptr() as *const c_char,
+            10,
+            arg.as_ptr(),
+            1.5,
+        );
+        let rust_len = rust_format_to(
+            rust_buffer.as_mut_ptr(),
+            BUFFER_SIZE,
+            fmt.as_ptr() as *const c_char,
+            10,
+            arg.as_ptr(),
+            1.5,
+        );
+        assert_eq!(len, rust_len);
+    }
+
+    assert_eq!(buffer, rust_buffer);
+}
+
+pub fn test_sum_twice() {
+    unsafe {
+        assert_eq!(sum_twice(3, 1, 2, 3), 12);
+        assert_eq!(rust_sum_twice(3, 1, 2, 3), 12);
+    }
+}
+
+pub fn test_sum_struct() {
+    let (a, b, c): (c_long, c_long, c_long) = (1, 2, 3);
+    unsafe {
+        assert_eq!(sum_struct(3, a, b, c), 18);
+        assert_eq!(rust_sum_struct(3, a, b, c), 18);
+    }
+}
@@ -0,0 +1,55 @@
+#include <stdarg.h>
+#include <stddef.h>
+
+// Declared by hand so that cross-target builds only need Clang's own headers
+int vsnprintf(char *str, size_t size, const char *format, va_list ap);
+
+// Forward a `va_list` to another function
+int forward(char *buf, size_t len, const char *fmt, va_list ap) {
+    return vsnprintf(buf, len, fmt, ap);
+}
+
+int format_to(char *buf, size_t len, const char *fmt, ...) {
+    va_list ap;
+    va_start(ap, fmt);
+    int n = forward(buf, len, fmt, ap);
+    va_end(ap);
+    return n;
+}
+
+// `va_arg` on a `va_list` and its `va_copy`
+int sum_twice(int count, ...) {
+    va_list ap, aq;
+    int sum = 0;
+
+    va_start(ap, count);
+    va_copy(aq, ap);
+    for (int i = 0; i < count; i++)
+        sum += va_arg(ap, int);
+    for (int i = 0; i < count; i++)
+        sum += va_arg(aq, int);
+    va_end(aq);
+    va_end(ap);
+    return sum;
+}
+
+// A struct holding more than one `va_list`
+struct two_lists {
+    va_list first;
+    va_list second;
+};
+
+long sum_struct(int count, ...) {
+    struct two_lists lists;
+    long sum = 0;
+
+    va_start(lists.first, count);
+    va_copy(lists.second, lists.first);
+    for (int i = 0; i < count; i++)
+        sum += va_arg(lists.first, long);
+    for (int i = 0; i < count; i++)
+        sum += 2 * va_arg(lists.second, long);
+    va_end(lists.second);
+    va_end(lists.first);
+    return sum;
+}